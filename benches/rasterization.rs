@@ -0,0 +1,92 @@
+//! time of drawing a frame of 100 to 100000 random particles as circles with every rasterizer, the canvas being that
+//! of the default run, of single circles from a pixel to well past the canvas across with [`IntegerRasterizer`], of
+//! supersampling the canvas, and of clearing it for the next frame
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use image::Rgba;
+use newtonian_gravity::render::cpu::{AreaIntersectionRasterizer, HorizontalLineImage, IntegerRasterizer, Rasterizer, RgbScalar};
+use newtonian_gravity::world::generate::random_particles;
+
+const PARTICLE_COUNTS: [usize; 4] = [100, 1_000, 10_000, 100_000];
+const SEED: u64 = 23;
+/// pixels per unit of distance, that of the default run
+const SCALE: f32 = 500.0;
+const SIZE: u32 = 1000;
+
+type Canvas = HorizontalLineImage<Rgba<u8>, Vec<u8>>;
+
+/// the circles of the random particles in pixels, at the scale of the default run and centered on the canvas
+fn circles(particle_count: usize) -> Vec<(f32, f32, f32)> {
+    random_particles(SEED, particle_count, SCALE)
+        .into_iter()
+        .map(|particle| {
+            let (x, y) = particle.position.to_cartesian();
+            let center = SIZE as f32 / 2.0;
+            (center + x * SCALE, center + y * SCALE, particle.radius * SCALE)
+        })
+        .collect()
+}
+
+fn draw<R: Rasterizer<Canvas, Rgba<u8>, RgbScalar>>(canvas: &mut Canvas, circles: &[(f32, f32, f32)]) {
+    canvas.as_raw_mut().fill(0);
+    for &(x, y, r) in circles {
+        R::draw_filled_circle(canvas, x, y, r, Rgba([255, 255, 255, 255]));
+    }
+}
+
+fn rasterization(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rasterization");
+    let mut canvas = Canvas::new(SIZE, SIZE, |len| vec![0; len]);
+    for particle_count in PARTICLE_COUNTS {
+        let circles = circles(particle_count);
+        group.throughput(Throughput::Elements(particle_count as u64));
+        group.bench_with_input(BenchmarkId::new("integer", particle_count), &circles, |b, circles| {
+            b.iter(|| draw::<IntegerRasterizer>(&mut canvas, circles))
+        });
+        group.bench_with_input(BenchmarkId::new("area_intersection", particle_count), &circles, |b, circles| {
+            b.iter(|| draw::<AreaIntersectionRasterizer>(&mut canvas, circles))
+        });
+    }
+    group.finish();
+}
+
+/// a circle in the center of the canvas, the largest of which only has the middle of it on the canvas
+fn integer_radii(c: &mut Criterion) {
+    let mut group = c.benchmark_group("integer_radii");
+    let mut canvas = Canvas::new(SIZE, SIZE, |len| vec![0; len]);
+    let center = SIZE as f32 / 2.0;
+    for r in [1.0, 10.0, 100.0, 10_000.0] {
+        group.bench_with_input(BenchmarkId::from_parameter(r), &r, |b, &r| {
+            b.iter(|| <IntegerRasterizer as Rasterizer<_, _, RgbScalar>>::draw_filled_circle(&mut canvas, center, center, r, Rgba([255, 255, 255, 255])))
+        });
+    }
+    group.finish();
+}
+
+/// the cost of supersampling a frame, drawing onto it upscaled and averaging that back down, without the circles
+fn supersampling(c: &mut Criterion) {
+    let mut group = c.benchmark_group("supersampling");
+    let canvas = Canvas::new(SIZE, SIZE, |len| vec![0; len]);
+    for factor in [2, 4] {
+        let large = canvas.upscale(factor);
+        group.bench_with_input(BenchmarkId::new("upscale", factor), &factor, |b, &factor| b.iter(|| canvas.upscale(factor)));
+        group.bench_with_input(BenchmarkId::new("downscale", factor), &factor, |b, &factor| b.iter(|| large.downscale(factor)));
+    }
+    group.finish();
+}
+
+/// a blank frame as the gifs got every frame, a copy of a blank canvas, against clearing the canvas of the last frame
+fn blank_frames(c: &mut Criterion) {
+    let mut group = c.benchmark_group("blank_frames");
+    let background = [0, 0, 0, 255];
+    let blank = Canvas::new(SIZE, SIZE, |len| background.into_iter().cycle().take(len).collect());
+    group.bench_function("copied", |b| b.iter(|| blank.clone()));
+    let mut canvas = blank.clone();
+    group.bench_function("filled", |b| b.iter(|| {
+        bytemuck::cast_slice_mut::<u8, [u8; 4]>(canvas.as_raw_mut()).fill(background);
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, rasterization, integer_radii, supersampling, blank_frames);
+criterion_main!(benches);
@@ -0,0 +1,142 @@
+//! time of a tick of every backend for 100 to 100000 random particles, in 1 and 20 substeps, the gpu is left out when
+//! it can't be initialized
+//!
+//! the cpu takes minutes per sample for 100000 particles, `cargo bench --bench worlds -- '/1000/'` runs a single count
+
+use std::num::NonZeroU16;
+use criterion::{criterion_group, criterion_main, BenchmarkGroup, BenchmarkId, Criterion, Throughput};
+use criterion::measurement::WallTime;
+use newtonian_gravity::Particle;
+use newtonian_gravity::world::{World, G};
+use newtonian_gravity::world::cpu::CPUWorld;
+use newtonian_gravity::world::generate::{plummer_sphere, random_particles};
+use newtonian_gravity::world::gpu::GPUWorld;
+use newtonian_gravity::world::par::ParWorld;
+
+const PARTICLE_COUNTS: [usize; 4] = [100, 1_000, 10_000, 100_000];
+const TIME_STEPS: [u16; 2] = [1, 20];
+/// time simulated per tick, that of a frame of the default run
+const TIME_PER_FRAME: f32 = 20.0;
+const SEED: u64 = 23;
+/// pixels per unit of distance the particles are generated for, that of the default run
+const SCALE: f32 = 500.0;
+
+/// ticks a world made by `world` out of the particles of every count in every number of substeps
+fn bench_ticks<W: World>(group: &mut BenchmarkGroup<WallTime>, mut world: impl FnMut(Vec<Particle>) -> W) {
+    for particle_count in PARTICLE_COUNTS {
+        group.throughput(Throughput::Elements(particle_count as u64));
+        for steps in TIME_STEPS {
+            let steps = NonZeroU16::new(steps).unwrap();
+            let mut world = world(random_particles(SEED, particle_count, SCALE));
+            group.bench_with_input(BenchmarkId::new(particle_count.to_string(), steps), &steps, |b, &steps| {
+                b.iter(|| world.tick(TIME_PER_FRAME, steps))
+            });
+        }
+    }
+}
+
+fn cpu(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cpu");
+    group.sample_size(10);
+    bench_ticks(&mut group, CPUWorld::new);
+    group.finish();
+}
+
+fn par(c: &mut Criterion) {
+    let mut group = c.benchmark_group("par");
+    group.sample_size(10);
+    bench_ticks(&mut group, ParWorld::new);
+    group.finish();
+}
+
+fn gpu(c: &mut Criterion) {
+    if GPUWorld::list_devices().is_empty() {
+        eprintln!("skipping the gpu, there is no device vulkan can run on");
+        return
+    }
+    let mut group = c.benchmark_group("gpu");
+    group.sample_size(10);
+    bench_ticks(&mut group, |particles| GPUWorld::new(particles).unwrap());
+    group.finish();
+}
+
+/// the summations of [`CPUWorld`] for 1000 random particles and a Plummer sphere of 10000, deterministic summation
+/// visits every ordered pair rather than every unordered one, so it does twice the pairwise work of the others, and
+/// neither it nor compensated summation is vectorized, on a single thread compensated summation took ~45 times as long as
+/// the vectorized default for both (91ms against 2ms, and 7.1s against 0.16s)
+fn summation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("summation");
+    group.sample_size(10);
+    let steps = NonZeroU16::new(1).unwrap();
+    let particle_sets = [
+        ("random", random_particles(SEED, 1_000, SCALE)),
+        ("plummer", plummer_sphere(SEED, 10_000, 1.0, 1e-2, G))
+    ];
+    for (set, particles) in particle_sets {
+        group.throughput(Throughput::Elements(particles.len() as u64));
+        let worlds = [
+            ("vectorized", CPUWorld::new(particles.clone())),
+            ("compensated", CPUWorld::new(particles.clone()).with_compensated_summation(true)),
+            ("deterministic", CPUWorld::new(particles).with_deterministic_summation(true))
+        ];
+        for (name, mut world) in worlds {
+            group.bench_function(BenchmarkId::new(name, set), |b| b.iter(|| world.tick(TIME_PER_FRAME, steps)));
+        }
+    }
+    group.finish();
+}
+
+/// a tick of [`CPUWorld`] stepping the columns of its store in place, against taking the particles out of the store
+/// and putting them back, as a tick with an observer does
+fn store(c: &mut Criterion) {
+    let mut group = c.benchmark_group("store");
+    let steps = NonZeroU16::new(20).unwrap();
+    for particle_count in [100, 1_000] {
+        let mut columns = CPUWorld::new(random_particles(SEED, particle_count, SCALE));
+        group.bench_function(BenchmarkId::new("columns", particle_count), |b| b.iter(|| columns.tick(TIME_PER_FRAME, steps)));
+        let mut particles = CPUWorld::new(random_particles(SEED, particle_count, SCALE));
+        group.bench_function(BenchmarkId::new("particles", particle_count), |b| {
+            b.iter(|| particles.tick_with_observer(TIME_PER_FRAME, steps, |_| {}))
+        });
+    }
+    group.finish();
+}
+
+/// a tick of [`ParWorld`] computing every unordered pair once into a buffer per task, vectorized as by default and
+/// with compensated summation, against summing up every particle on its own, which computes every pair twice but
+/// needs no buffers, as deterministic summation does, on a single thread the compensated ticks took ~2/3 of the time
+/// of the particle by particle ones for 1000 and 10000 particles (68ms against 109ms, and 6.3s against 9.2s)
+fn pair_symmetry(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pair_symmetry");
+    group.sample_size(10);
+    let steps = NonZeroU16::new(1).unwrap();
+    for particle_count in [1_000, 10_000] {
+        group.throughput(Throughput::Elements(particle_count as u64));
+        let mut vectorized = ParWorld::new(random_particles(SEED, particle_count, SCALE));
+        group.bench_function(BenchmarkId::new("vectorized", particle_count), |b| b.iter(|| vectorized.tick(TIME_PER_FRAME, steps)));
+        let mut compensated = ParWorld::new(random_particles(SEED, particle_count, SCALE)).with_compensated_summation(true);
+        group.bench_function(BenchmarkId::new("compensated", particle_count), |b| b.iter(|| compensated.tick(TIME_PER_FRAME, steps)));
+        let mut every_particle = ParWorld::new(random_particles(SEED, particle_count, SCALE)).with_deterministic_summation(true);
+        group.bench_function(BenchmarkId::new("every_particle", particle_count), |b| b.iter(|| every_particle.tick(TIME_PER_FRAME, steps)));
+    }
+    group.finish();
+}
+
+/// a tick of the pairwise [`ParWorld`] against a Barnes-Hut one with a theta of 0.5, for counts around where the tree
+/// starts paying for itself, on a single thread it overtakes the pairwise tick between 3000 and 10000 particles
+fn barnes_hut(c: &mut Criterion) {
+    let mut group = c.benchmark_group("barnes_hut");
+    group.sample_size(10);
+    let steps = NonZeroU16::new(1).unwrap();
+    for particle_count in [100, 300, 1_000, 3_000, 10_000] {
+        group.throughput(Throughput::Elements(particle_count as u64));
+        let mut pairwise = ParWorld::new(random_particles(SEED, particle_count, SCALE));
+        group.bench_function(BenchmarkId::new("pairwise", particle_count), |b| b.iter(|| pairwise.tick(TIME_PER_FRAME, steps)));
+        let mut barnes_hut = ParWorld::new(random_particles(SEED, particle_count, SCALE)).with_barnes_hut(Some(0.5));
+        group.bench_function(BenchmarkId::new("tree", particle_count), |b| b.iter(|| barnes_hut.tick(TIME_PER_FRAME, steps)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, cpu, par, gpu, summation, store, pair_symmetry, barnes_hut);
+criterion_main!(benches);
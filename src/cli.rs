@@ -0,0 +1,391 @@
+use std::fmt::{self, Display, Formatter};
+use std::num::{NonZeroU16, NonZeroUsize};
+use std::str::FromStr;
+use clap::{Args, Parser, Subcommand};
+use newtonian_gravity::world::Integrator;
+use newtonian_gravity::world::gpu::DeviceSelection;
+use newtonian_gravity::world::particle_file::read_particle_file;
+use crate::{Backend, Comparison, ParticlesFile, Preset, SimParams};
+
+/// simulates gravity between particles into gifs, comparing every backend when no command is given
+#[derive(Parser, Debug)]
+#[clap(version)]
+pub struct Cli {
+    #[clap(subcommand)]
+    pub command: Option<Command>
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// simulates a single world into a gif
+    Simulate {
+        /// world to simulate with
+        #[clap(long, value_enum, default_value_t = Backend::Par)]
+        backend: Backend,
+        /// path of the gif, its checkpoint is written next to it
+        #[clap(long, default_value = "output/run.gif", value_parser = parse_gif_path)]
+        out: String,
+        #[clap(flatten)]
+        params: ParamArgs,
+        /// simulates a heavy body with two lighter ones orbiting it in differently tilted planes in three dimensions
+        /// with the par backend, looking down the z axis, rather than the preset
+        #[clap(long, conflicts_with_all = &["backend", "preset", "particles-file"])]
+        three_d: bool,
+        /// simulates the particles of the preset in double precision with the par backend, without any of the
+        /// options the par backend only has in single precision
+        #[clap(long, conflicts_with_all = &["backend", "three-d"])]
+        f64: bool,
+        /// shows the frames in a window as they are drawn, space pauses and escape or closing the window stops
+        /// early, keeping the frames so far
+        #[cfg(feature = "preview")]
+        #[clap(long)]
+        preview: bool
+    },
+    /// simulates the same particles on every backend into output/cpu.gif, output/par.gif and output/gpu.gif,
+    /// and puts them together as --comparison says, without a usable gpu the gpu gif is simulated with the par
+    /// backend instead
+    Compare {
+        #[clap(flatten)]
+        params: ParamArgs,
+        /// how the gifs are put together
+        #[clap(long, value_enum, default_value_t = Comparison::Merged)]
+        comparison: Comparison
+    },
+    /// simulates the random particles of every seed into <out-dir>/run_<seed>.gif, and the final energy and
+    /// fraction of bound particles of every run into <out-dir>/ensemble.csv
+    Ensemble {
+        /// seeds to simulate, separated by commas, every other setting is the same for every run
+        #[clap(long, required = true, value_delimiter = ',')]
+        seeds: Vec<u64>,
+        /// world to simulate with
+        #[clap(long, value_enum, default_value_t = Backend::Par)]
+        backend: Backend,
+        /// directory the gifs and the summary are written to
+        #[clap(long, default_value = "output")]
+        out_dir: String,
+        #[clap(flatten)]
+        params: ParamArgs
+    },
+    /// lists the devices the gpu backend can run on
+    Devices,
+    /// prints the settings of the run a gif written by `simulate` or `compare` is of
+    Inspect {
+        /// path of the gif
+        gif: String
+    },
+    /// simulates the scenario described by a TOML file, such as scenarios/orbit.toml
+    #[cfg(feature = "serde")]
+    Run {
+        /// path of the scenario file
+        scenario: String
+    }
+}
+
+/// [`SimParams`] as arguments, every one defaults to [`SimParams::default`]
+#[derive(Args, Debug)]
+pub struct ParamArgs {
+    /// particles to start with
+    #[clap(long, value_enum, default_value_t = SimParams::default().preset)]
+    preset: Preset,
+    /// random particles to generate, other presets have a fixed number of particles
+    #[clap(long, default_value_t = SimParams::default().particle_count)]
+    particles: usize,
+    /// particles to start with rather than a preset, a .csv file of the columns mass,x,y,vx,vy,
+    /// or a .json array of objects with those keys
+    #[clap(long, value_parser = parse_particles_file, conflicts_with_all = &["preset", "particles"])]
+    particles_file: Option<ParticlesFile>,
+    /// frames to simulate
+    #[clap(long, default_value_t = SimParams::default().frame_count, value_parser = parse_frames)]
+    frames: usize,
+    /// seed of the random presets
+    #[clap(long, default_value_t = SimParams::default().seed)]
+    seed: u64,
+    /// pixels per unit of distance
+    #[clap(long, default_value_t = SimParams::default().scale, value_parser = parse_scale, allow_hyphen_values = true)]
+    scale: f32,
+    /// width and height of the gif in pixels, such as 1200x800, or `fit` to fit every frame
+    #[clap(long, default_value_t = Size(SimParams::default().size))]
+    size: Size,
+    /// time simulated per frame
+    #[clap(long, default_value_t = SimParams::default().time_per_frame, value_parser = parse_time_per_frame, allow_hyphen_values = true)]
+    time_per_frame: f32,
+    /// substeps per frame
+    #[clap(long, default_value_t = SimParams::default().time_steps, value_parser = parse_steps)]
+    steps: NonZeroU16,
+    /// integration scheme: euler, verlet, rk4 or yoshida
+    #[clap(long, default_value = "euler", value_parser = parse_integrator)]
+    integrator: Integrator,
+    /// gravitational constant
+    #[clap(long, default_value_t = SimParams::default().g, value_parser = parse_g, allow_hyphen_values = true)]
+    g: f32,
+    /// softening length, keeps close encounters from producing absurd accelerations
+    #[clap(long, default_value_t = SimParams::default().softening, value_parser = parse_softening)]
+    softening: f32,
+    /// device the gpu backend runs on: first, high-performance, low-power,
+    /// an index of the `devices` command or part of a device name
+    #[clap(long, default_value_t = SimParams::default().device)]
+    device: DeviceSelection,
+    /// frames per second the gif plays at
+    #[clap(long, default_value_t = SimParams::default().frame_rate, value_parser = parse_fps)]
+    fps: f32,
+    /// writes <out>.ckpt every this many frames, so an interrupted run can be resumed with --resume
+    #[clap(long, value_parser = parse_checkpoint_interval)]
+    checkpoint_interval: Option<NonZeroUsize>,
+    /// continues from <out>.ckpt when there is one, into <out>_from_<frame>.gif
+    #[clap(long)]
+    resume: bool,
+    /// draws the particles 2 or 4 times larger and averages them back down, so that those smaller than a pixel
+    /// don't flicker
+    #[clap(long, default_value_t = SimParams::default().supersampling, value_parser = parse_supersampling)]
+    supersampling: u32,
+    /// also writes the mass points of every frame to <out>.csv, a row per particle, or gzipped to <out>.csv.gz
+    /// with csv.gz
+    #[clap(long, value_parser = parse_trajectory)]
+    trajectory: Option<String>,
+    /// also writes the positions of every frame to <out>_positions.npy and the masses to <out>_masses.npy for
+    /// NumPy, with the settings of the run in <out>.json
+    #[clap(long)]
+    npy_trajectory: bool
+}
+
+impl ParamArgs {
+    pub fn params(&self) -> SimParams {
+        SimParams {
+            seed: self.seed,
+            particle_count: self.particles,
+            frame_count: self.frames,
+            scale: self.scale,
+            time_per_frame: self.time_per_frame,
+            time_steps: self.steps,
+            size: self.size.0,
+            preset: self.preset,
+            particles_file: self.particles_file.clone(),
+            integrator: self.integrator,
+            g: self.g,
+            softening: self.softening,
+            device: self.device.clone(),
+            frame_rate: self.fps,
+            checkpoint_interval: self.checkpoint_interval,
+            resume: self.resume,
+            supersampling: self.supersampling,
+            trajectory: self.trajectory.clone(),
+            npy_trajectory: self.npy_trajectory,
+            #[cfg(feature = "preview")]
+            preview: false
+        }
+    }
+}
+
+/// [`SimParams::size`] as an argument
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Size(pub Option<(f32, f32)>);
+
+impl FromStr for Size {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "fit" {
+            return Ok(Self(None))
+        }
+        let malformed = || format!("expected WIDTHxHEIGHT in pixels such as 1200x800, or `fit`, not `{}`", s);
+        let (width, height) = s.split_once('x').ok_or_else(malformed)?;
+        let width: u32 = width.parse().map_err(|_| malformed())?;
+        let height: u32 = height.parse().map_err(|_| malformed())?;
+        if width == 0 || height == 0 {
+            return Err(format!("the gif needs at least one pixel in each direction, not {}x{}", width, height))
+        }
+        Ok(Self(Some((width as f32, height as f32))))
+    }
+}
+
+impl Display for Size {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some((width, height)) => write!(f, "{}x{}", width, height),
+            None => write!(f, "fit")
+        }
+    }
+}
+
+fn parse_frames(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(0) => Err("at least one frame is needed".to_string()),
+        Ok(frames) => Ok(frames),
+        Err(_) => Err(format!("expected a number of frames, not `{}`", s))
+    }
+}
+
+fn parse_checkpoint_interval(s: &str) -> Result<NonZeroUsize, String> {
+    match s.parse::<usize>() {
+        Ok(0) => Err("checkpoints can only be written every one frame or more".to_string()),
+        Ok(interval) => Ok(NonZeroUsize::new(interval).unwrap()),
+        Err(_) => Err(format!("expected a number of frames, not `{}`", s))
+    }
+}
+
+fn parse_supersampling(s: &str) -> Result<u32, String> {
+    match s.parse::<u32>() {
+        Ok(factor @ 1..=8) => Ok(factor),
+        _ => Err(format!("expected a factor from 1 to 8, such as 2 or 4, not `{}`", s))
+    }
+}
+
+pub fn parse_trajectory(s: &str) -> Result<String, String> {
+    match s {
+        "csv" | "csv.gz" => Ok(s.to_string()),
+        _ => Err(format!("expected csv or csv.gz, not `{}`", s))
+    }
+}
+
+fn parse_steps(s: &str) -> Result<NonZeroU16, String> {
+    match s.parse::<u16>() {
+        Ok(0) => Err("every frame needs at least one substep".to_string()),
+        Ok(steps) => Ok(NonZeroU16::new(steps).unwrap()),
+        Err(_) => Err(format!("expected a number of substeps up to {}, not `{}`", u16::MAX, s))
+    }
+}
+
+fn parse_scale(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(scale) if scale > 0.0 && scale.is_finite() => Ok(scale),
+        _ => Err(format!("expected a positive number of pixels per unit, not `{}`", s))
+    }
+}
+
+fn parse_time_per_frame(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(time) if time.is_finite() => Ok(time),
+        _ => Err(format!("expected a finite time, not `{}`", s))
+    }
+}
+
+fn parse_fps(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(fps) if fps > 0.0 && fps.is_finite() => Ok(fps),
+        _ => Err(format!("expected a positive number of frames per second, not `{}`", s))
+    }
+}
+
+pub fn parse_integrator(s: &str) -> Result<Integrator, String> {
+    match s {
+        "euler" => Ok(Integrator::Euler),
+        "verlet" => Ok(Integrator::Verlet),
+        "rk4" => Ok(Integrator::RK4),
+        "yoshida" => Ok(Integrator::Yoshida),
+        _ => Err(format!("expected one of euler, verlet, rk4 or yoshida, not `{}`", s))
+    }
+}
+
+fn parse_g(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(g) if g.is_finite() => Ok(g),
+        _ => Err(format!("expected a finite gravitational constant, not `{}`", s))
+    }
+}
+
+fn parse_softening(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(softening) if softening >= 0.0 && softening.is_finite() => Ok(softening),
+        _ => Err(format!("expected a distance of at least 0, not `{}`", s))
+    }
+}
+
+fn parse_particles_file(s: &str) -> Result<ParticlesFile, String> {
+    match read_particle_file(s) {
+        Ok(particles) if particles.is_empty() => Err(format!("`{}` has no particles", s)),
+        Ok(particles) => Ok(ParticlesFile { path: s.to_string(), particles }),
+        Err(error) => Err(format!("`{}`: {}", s, error))
+    }
+}
+
+pub fn parse_gif_path(s: &str) -> Result<String, String> {
+    match s.strip_suffix(".gif") {
+        Some(path) if !path.is_empty() => Ok(s.to_string()),
+        _ => Err(format!("expected the path of a .gif file, not `{}`", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ErrorKind;
+    use super::*;
+
+    fn simulate(args: &[&str]) -> (Backend, String, SimParams) {
+        let cli = Cli::try_parse_from(["newtonian_gravity", "simulate"].iter().chain(args)).unwrap();
+        match cli.command {
+            Some(Command::Simulate { backend, out, params, .. }) => (backend, out, params.params()),
+            command => panic!("parsed {:?} rather than simulate", command)
+        }
+    }
+
+    fn error(args: &[&str]) -> clap::Error {
+        Cli::try_parse_from(["newtonian_gravity"].iter().chain(args)).unwrap_err()
+    }
+
+    #[test]
+    fn arguments_become_params() {
+        let (backend, out, params) = simulate(&[
+            "--backend", "cpu", "--particles", "5000", "--frames", "600", "--seed", "42", "--out", "output/big.gif",
+            "--size", "1200x800", "--scale", "400", "--steps", "4", "--integrator", "yoshida", "--g", "-1",
+            "--checkpoint-interval", "10", "--resume", "--trajectory", "csv.gz"
+        ]);
+        assert!(matches!(backend, Backend::CPU));
+        assert_eq!(out, "output/big.gif");
+        assert_eq!(params.particle_count, 5000);
+        assert_eq!(params.frame_count, 600);
+        assert_eq!(params.seed, 42);
+        assert_eq!(params.size, Some((1200.0, 800.0)));
+        assert_eq!(params.scale, 400.0);
+        assert_eq!(params.time_steps.get(), 4);
+        assert_eq!(params.integrator, Integrator::Yoshida);
+        assert_eq!(params.g, -1.0);
+        assert_eq!(params.checkpoint_interval, NonZeroUsize::new(10));
+        assert!(params.resume);
+        assert_eq!(params.trajectory.as_deref(), Some("csv.gz"));
+
+        let (_, out, params) = simulate(&["--size", "fit"]);
+        let default = SimParams::default();
+        assert_eq!(out, "output/run.gif");
+        assert_eq!(params.size, None);
+        assert_eq!(params.particle_count, default.particle_count);
+        assert_eq!(params.frame_count, default.frame_count);
+        assert_eq!(params.time_steps, default.time_steps);
+    }
+
+    #[test]
+    fn invalid_values_are_friendly_errors() {
+        for (args, message) in [
+            (&["simulate", "--steps", "0"][..], "every frame needs at least one substep"),
+            (&["simulate", "--frames", "0"], "at least one frame is needed"),
+            (&["simulate", "--size", "12x"], "expected WIDTHxHEIGHT in pixels such as 1200x800, or `fit`, not `12x`"),
+            (&["simulate", "--size", "0x10"], "the gif needs at least one pixel in each direction, not 0x10"),
+            (&["compare", "--scale", "-2"], "expected a positive number of pixels per unit, not `-2`"),
+            (&["simulate", "--out", "run.png"], "expected the path of a .gif file, not `run.png`")
+        ] {
+            let error = error(args);
+            assert_eq!(error.kind(), ErrorKind::ValueValidation, "{:?}", args);
+            assert!(error.to_string().contains(message), "{:?} failed with {}", args, error);
+        }
+    }
+
+    #[test]
+    fn conflicting_and_missing_arguments_are_errors() {
+        assert_eq!(error(&["simulate", "--f64", "--backend", "cpu"]).kind(), ErrorKind::ArgumentConflict);
+        assert_eq!(error(&["simulate", "--f64", "--three-d"]).kind(), ErrorKind::ArgumentConflict);
+        assert_eq!(error(&["simulate", "--three-d", "--preset", "kepler"]).kind(), ErrorKind::ArgumentConflict);
+        assert_eq!(error(&["ensemble"]).kind(), ErrorKind::MissingRequiredArgument);
+    }
+
+    #[test]
+    fn ensembles_take_seeds_separated_by_commas() {
+        let cli = Cli::try_parse_from(["newtonian_gravity", "ensemble", "--seeds", "1,2,30", "--out-dir", "runs"]).unwrap();
+        match cli.command {
+            Some(Command::Ensemble { seeds, out_dir, params, .. }) => {
+                assert_eq!(seeds, vec![1, 2, 30]);
+                assert_eq!(out_dir, "runs");
+                assert_eq!(params.params().frame_count, SimParams::default().frame_count);
+            },
+            command => panic!("parsed {:?} rather than ensemble", command)
+        }
+    }
+}
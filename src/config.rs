@@ -0,0 +1,311 @@
+use std::fs;
+use std::num::{NonZeroU16, NonZeroUsize};
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer};
+use serde::de::Error;
+use newtonian_gravity::vector::Vector;
+use newtonian_gravity::world::{Integrator, Particle};
+use newtonian_gravity::world::gpu::DeviceSelection;
+use crate::cli::{parse_gif_path, parse_integrator, parse_trajectory, Size};
+use crate::{drawn_radius, Backend, Preset, SimParams};
+
+/// a whole run described by a TOML file, such as `scenarios/orbit.toml`, read by the `run` command
+///
+/// every key left out is that of [`SimParams::default`], unknown keys are errors
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// cpu, par or gpu
+    #[serde(default = "default_backend", deserialize_with = "backend")]
+    pub backend: Backend,
+    /// path of the gif, relative to the working directory rather than to the file
+    #[serde(default = "default_out", deserialize_with = "gif_path")]
+    pub out: String,
+    #[serde(default)]
+    simulation: Simulation,
+    scenario: Scenario
+}
+
+impl Config {
+    pub fn read(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let config: Self = toml::from_str(&text).map_err(|error| error.to_string())?;
+        config.scenario.check()?;
+        Ok(config)
+    }
+
+    pub fn params(&self) -> SimParams {
+        let Simulation { seed, frames, scale, size, time_per_frame, steps, integrator, g, softening, ref device, fps, checkpoint_interval, resume, supersampling, ref trajectory, npy_trajectory } = self.simulation;
+        let count = self.scenario.count.unwrap_or(SimParams::default().particle_count);
+        let (preset, particle_count) = match self.scenario.generator {
+            Generator::Random => (Preset::Random, count),
+            Generator::ThreeBody => (Preset::ThreeBody, 3),
+            Generator::PinnedThreeBody => (Preset::PinnedThreeBody, 3),
+            Generator::Kepler => (Preset::Kepler, 2),
+            Generator::TwoClusters => (Preset::TwoClusters, count),
+            Generator::SolarSystem => (Preset::SolarSystem, 9),
+            Generator::FigureEight => (Preset::FigureEight, 3),
+            Generator::LagrangeL4 => (Preset::LagrangeL4, 3),
+            Generator::Plummer => (Preset::Plummer, count),
+            Generator::ExponentialDisk => (Preset::ExponentialDisk, count),
+            // not generated, the preset is never used
+            Generator::Particles => (SimParams::default().preset, self.scenario.particles.len())
+        };
+        SimParams {
+            seed,
+            particle_count,
+            frame_count: frames,
+            scale,
+            time_per_frame,
+            time_steps: steps,
+            size: size.0,
+            preset,
+            particles_file: None,
+            integrator,
+            g,
+            softening,
+            device: device.clone(),
+            frame_rate: fps,
+            checkpoint_interval,
+            resume,
+            supersampling,
+            trajectory: trajectory.clone(),
+            npy_trajectory,
+            #[cfg(feature = "preview")]
+            preview: false
+        }
+    }
+
+    /// the particles the run starts with, generated with `params`, which are [`params`](Self::params)
+    pub fn particles(&self, params: &SimParams) -> Vec<Particle> {
+        match self.scenario.generator {
+            Generator::Particles => self.scenario.particles.iter()
+                .map(|particle| particle.to_particle(params.scale))
+                .collect(),
+            _ => params.preset.particles(params)
+        }
+    }
+}
+
+/// `[simulation]`, the keys of [`SimParams`] that aren't about the particles, named like the arguments of the cli
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields, default)]
+struct Simulation {
+    seed: u64,
+    #[serde(deserialize_with = "frames")]
+    frames: usize,
+    /// pixels per unit of distance
+    #[serde(deserialize_with = "scale")]
+    scale: f32,
+    /// "WIDTHxHEIGHT" in pixels or "fit"
+    #[serde(deserialize_with = "size")]
+    size: Size,
+    #[serde(deserialize_with = "time_per_frame")]
+    time_per_frame: f32,
+    steps: NonZeroU16,
+    /// euler, verlet, rk4 or yoshida
+    #[serde(deserialize_with = "integrator")]
+    integrator: Integrator,
+    #[serde(deserialize_with = "g")]
+    g: f32,
+    #[serde(deserialize_with = "softening")]
+    softening: f32,
+    /// first, high-performance, low-power, an index or part of a name
+    #[serde(deserialize_with = "device")]
+    device: DeviceSelection,
+    /// frames per second the gif plays at
+    #[serde(deserialize_with = "fps")]
+    fps: f32,
+    /// frames between checkpoints, none are written when left out
+    checkpoint_interval: Option<NonZeroUsize>,
+    resume: bool,
+    /// 1, or 2 or 4 to draw the particles that many times larger and average them back down
+    #[serde(deserialize_with = "supersampling")]
+    supersampling: u32,
+    /// "csv" or "csv.gz" to also write the mass points of every frame next to the gif
+    #[serde(deserialize_with = "trajectory")]
+    trajectory: Option<String>,
+    /// also writes the positions and masses of the particles as .npy files next to the gif
+    npy_trajectory: bool
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        let params = SimParams::default();
+        Self {
+            seed: params.seed,
+            frames: params.frame_count,
+            scale: params.scale,
+            size: Size(params.size),
+            time_per_frame: params.time_per_frame,
+            steps: params.time_steps,
+            integrator: params.integrator,
+            g: params.g,
+            softening: params.softening,
+            device: params.device,
+            fps: params.frame_rate,
+            checkpoint_interval: params.checkpoint_interval,
+            resume: params.resume,
+            supersampling: params.supersampling,
+            trajectory: params.trajectory,
+            npy_trajectory: params.npy_trajectory
+        }
+    }
+}
+
+/// `[scenario]`, the particles a run starts with
+///
+/// only the random generators take a `count`, and only the `particles` generator takes `[[scenario.particles]]`
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Scenario {
+    generator: Generator,
+    /// random particles to generate, [`SimParams::default`]'s when left out
+    count: Option<usize>,
+    #[serde(default)]
+    particles: Vec<ParticleConfig>
+}
+
+impl Scenario {
+    /// the keys the [`Generator`] doesn't take, which can't be denied while deserializing
+    fn check(&self) -> Result<(), String> {
+        let random = matches!(self.generator, Generator::Random | Generator::TwoClusters | Generator::Plummer | Generator::ExponentialDisk);
+        if self.count.is_some() && !random {
+            return Err("unexpected key `count` for key `scenario`, only the random, two-clusters, plummer and exponential-disk \
+                generators take a count".to_string())
+        }
+        let listed = self.generator == Generator::Particles;
+        if !self.particles.is_empty() && !listed {
+            return Err("unexpected key `particles` for key `scenario`, only the particles generator takes a list of particles".to_string())
+        }
+        Ok(())
+    }
+}
+
+/// `scenario.generator`, a [`Preset`] or a list of particles
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum Generator {
+    /// `count` random particles from the seed of `[simulation]`
+    Random,
+    ThreeBody,
+    PinnedThreeBody,
+    Kepler,
+    /// `count` random particles in two groups from the seed of `[simulation]`
+    TwoClusters,
+    SolarSystem,
+    FigureEight,
+    LagrangeL4,
+    /// `count` particles in a Plummer sphere from the seed of `[simulation]`
+    Plummer,
+    /// `count` particles in a rotating disk from the seed of `[simulation]`
+    ExponentialDisk,
+    /// every particle listed as a `[[scenario.particles]]` table
+    Particles
+}
+
+/// a particle of [`Generator::Particles`], positions and velocities are cartesian
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct ParticleConfig {
+    mass: f32,
+    position: (f32, f32),
+    #[serde(default)]
+    velocity: (f32, f32),
+    /// collision radius, the radius it is drawn with when left out
+    radius: Option<f32>,
+    #[serde(default)]
+    fixed: bool,
+    #[serde(default)]
+    group: u32
+}
+
+impl ParticleConfig {
+    fn to_particle(&self, scale: f32) -> Particle {
+        Particle {
+            mass: self.mass,
+            position: Vector::from_cartesian(self.position.0, self.position.1),
+            velocity: Vector::from_cartesian(self.velocity.0, self.velocity.1),
+            radius: self.radius.unwrap_or_else(|| drawn_radius(self.mass, scale)),
+            fixed: self.fixed as u32,
+            id: 0,
+            group: self.group
+        }
+    }
+}
+
+fn default_backend() -> Backend {
+    Backend::Par
+}
+
+fn default_out() -> String {
+    "output/run.gif".to_string()
+}
+
+/// deserializes a value that has to pass `check`, which the parser of the cli argument of the same name checks as well
+fn checked<'de, D: Deserializer<'de>, T: Deserialize<'de>>(deserializer: D, check: fn(&T) -> bool, expected: &str) -> Result<T, D::Error> {
+    let value = T::deserialize(deserializer)?;
+    match check(&value) {
+        true => Ok(value),
+        false => Err(D::Error::custom(format!("expected {}", expected)))
+    }
+}
+
+fn frames<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    checked(deserializer, |&frames| frames > 0, "at least one frame")
+}
+
+fn scale<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    checked(deserializer, |scale| *scale > 0.0 && scale.is_finite(), "a positive number of pixels per unit")
+}
+
+fn time_per_frame<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    checked(deserializer, |time| time.is_finite(), "a finite time")
+}
+
+fn g<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    checked(deserializer, |g| g.is_finite(), "a finite gravitational constant")
+}
+
+fn softening<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    checked(deserializer, |softening| *softening >= 0.0 && softening.is_finite(), "a distance of at least 0")
+}
+
+fn supersampling<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    checked(deserializer, |factor| (1..=8).contains(factor), "a factor from 1 to 8")
+}
+
+fn trajectory<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    parsed(deserializer, parse_trajectory).map(Some)
+}
+
+fn fps<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    checked(deserializer, |fps| *fps > 0.0 && fps.is_finite(), "a positive number of frames per second")
+}
+
+/// deserializes a string with one of the parsers of the cli, so both accept the same values
+fn parsed<'de, D: Deserializer<'de>, T>(deserializer: D, parse: fn(&str) -> Result<T, String>) -> Result<T, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    parse(&s).map_err(D::Error::custom)
+}
+
+fn backend<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Backend, D::Error> {
+    parsed(deserializer, |s| Backend::from_str(s, true).map_err(|_| format!("expected one of cpu, par or gpu, not `{}`", s)))
+}
+
+fn gif_path<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    parsed(deserializer, parse_gif_path)
+}
+
+fn size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Size, D::Error> {
+    parsed(deserializer, str::parse)
+}
+
+fn integrator<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Integrator, D::Error> {
+    parsed(deserializer, parse_integrator)
+}
+
+fn device<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DeviceSelection, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    Ok(s.parse().unwrap_or_else(|never| match never {}))
+}
@@ -0,0 +1,33 @@
+//! gravity between particles, simulated on a single thread ([`CPUWorld`](world::cpu::CPUWorld)),
+//! in parallel ([`ParWorld`](world::par::ParWorld)) or on the GPU ([`GPUWorld`](world::gpu::GPUWorld)),
+//! and rendering of the results into gifs
+//!
+//! ```
+//! use std::num::NonZeroU16;
+//! use newtonian_gravity::{Particle, Vector};
+//! use newtonian_gravity::world::cpu::CPUWorld;
+//!
+//! let particle = |x: f32, mass: f32| Particle {
+//!     mass,
+//!     position: Vector::from_cartesian(x, 0.0),
+//!     velocity: Vector::new(0.0, 0.0),
+//!     radius: 0.0,
+//!     fixed: 0,
+//!     id: 0,
+//!     group: 0
+//! };
+//! let mut world = CPUWorld::new(vec![particle(-1.0, 1.0), particle(1.0, 1.0)]).with_g(0.1);
+//! world.tick(1.0, NonZeroU16::new(10).unwrap());
+//! let mass_points = world.get_mass_points();
+//! // the two particles pull each other closer
+//! assert!(mass_points[0].position.0 > -1.0);
+//! assert!(mass_points[1].position.0 < 1.0);
+//! ```
+
+pub mod periodic_logger;
+pub mod render;
+pub mod vector;
+pub mod world;
+
+pub use vector::Vector;
+pub use world::{MassPoint, Particle};
@@ -1,52 +1,237 @@
+mod cli;
+#[cfg(feature = "serde")]
+mod config;
+
 use std::f32::consts::{FRAC_PI_2, PI, TAU};
-use std::fs::File;
+use std::fs::{self, File};
+use std::any;
+use std::iter;
+use std::io::{BufReader, BufWriter};
 use std::num::{NonZeroU16, NonZeroUsize};
 use std::ops::Range;
 use std::thread;
+use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::collections::VecDeque;
 use std::path::Path;
+use std::mem;
+use std::sync::{mpsc, Arc, Mutex};
+#[cfg(feature = "preview")]
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
 use std::thread::available_parallelism;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
-use image::{AnimationDecoder, DynamicImage, Frame, Rgba, RgbaImage, RgbImage};
+use image::{AnimationDecoder, Delay, DynamicImage, Frame, Rgba, RgbaImage, RgbImage};
 use image::io::Reader;
+use clap::{Parser, ValueEnum};
+use clap::{CommandFactory, ErrorKind};
 use rand::{Rng, SeedableRng};
-use log::{Level, LevelFilter};
+use log::{info, warn, Level, LevelFilter};
 use log4rs::append::console::ConsoleAppender;
 use log4rs::config::{Appender, Logger, Root};
 use log4rs::encode::pattern::PatternEncoder;
 use log4rs::Config;
 use rand_pcg::Pcg64Mcg;
 use rayon::ThreadPoolBuilder;
-use world::cpu::CPUWorld;
-use world::gpu::GPUWorld;
-use crate::periodic_logger::PeriodicLogger;
-use crate::render::cpu::{AreaIntersectionRasterizer, IntegerRasterizer, FrameHandler, GifHandler, GrayscaleRgbScalar, HorizontalLineImage, Rasterizer};
-use crate::vector::Vector;
-use crate::world::{MassPoint, Particle};
-use crate::world::par::ParWorld;
-
-mod vector;
-mod periodic_logger;
-mod world;
-mod render;
-
-const SEED: u64 = 23;
-const PARTICLE_COUNT: usize = 100;
-const FRAME_COUNT: usize = 240;
-const SCALE: f32 = 500.0;
-const TIME_PER_FRAME: f32 = 20.0;
-const TIME_STEPS: NonZeroU16 = match NonZeroU16::new(20) {
-    None => panic!("TIME_STEPS may not be 0"),
-    Some(steps) => steps
-};
-const SIZE: Option<(f32, f32)> = Some((1000.0, 1000.0));
-const PARTICLE_GENERATOR: fn() -> Vec<Particle> = generate_particles;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use newtonian_gravity::world;
+use newtonian_gravity::world::cpu::CPUWorld;
+use newtonian_gravity::world::gpu::{DeviceSelection, GPUInitError, GPUWorld};
+use newtonian_gravity::periodic_logger::PeriodicLogger;
+use newtonian_gravity::render::colormap::Colormap;
+use newtonian_gravity::render::ffmpeg::FfmpegPipeHandler;
+use newtonian_gravity::render::gif_comment::{read_comments, CommentWriter};
+use newtonian_gravity::render::gpu::{Circle, GPURasterizer};
+use newtonian_gravity::render::heatmap::DensityGrid;
+use newtonian_gravity::render::viewport::Viewport;
+#[cfg(feature = "preview")]
+use newtonian_gravity::render::preview::PreviewWindow;
+use newtonian_gravity::render::text::{draw_text, text_width, GLYPH_HEIGHT};
+use newtonian_gravity::render::cpu::{draw_line, fill_rectangle, Additive, ApngHandler, AreaIntersectionRasterizer, BlendingRasterizer, CPURenderer, ExposureHandler, IntegerRasterizer, FrameHandler, GifHandler, GrayscaleRgbScalar, HorizontalLineImage, PaintScalar, PngSequenceHandler, Rasterizer, RgbScalar, ToneMap};
+use newtonian_gravity::vector::{Vector, Vector3};
+use newtonian_gravity::world::{AdaptiveSteps, Boundary, Collisions, Escape, Integrator, MassPoint, Particle, Particle3, ParticleF64, World};
+use newtonian_gravity::world::checkpoint::Checkpoint;
+use newtonian_gravity::world::generate::{exponential_disk, plummer_sphere, random_particles};
+use newtonian_gravity::world::trajectory::{NpyTrajectoryWriter, TrajectoryWriter};
+use newtonian_gravity::world::diagnostics::{self, Divergence};
+use newtonian_gravity::world::field::Field;
+use newtonian_gravity::world::force::NewtonianGravity;
+use newtonian_gravity::world::par::{ParWorld, ParWorld3, ParWorldF64};
+use newtonian_gravity::world::presets;
+use newtonian_gravity::world::validation::KeplerOrbit;
+use vulkano::device::{Device, Queue};
+use crate::cli::{Cli, Command};
+
+/// settings of a run that can be changed without recompiling, unlike the constants below
+#[derive(Clone, Debug)]
+struct SimParams {
+    seed: u64,
+    particle_count: usize,
+    frame_count: usize,
+    /// pixels per unit of distance
+    scale: f32,
+    time_per_frame: f32,
+    time_steps: NonZeroU16,
+    /// width and height of the gifs in pixels, centered on the origin, or fitted to every frame when `None`
+    size: Option<(f32, f32)>,
+    preset: Preset,
+    /// particles read with `--particles-file`, started with rather than those of `preset`
+    particles_file: Option<ParticlesFile>,
+    integrator: Integrator,
+    /// gravitational constant
+    g: f32,
+    softening: f32,
+    /// device the gpu backend runs on
+    device: DeviceSelection,
+    /// frames per second the gifs play at, rounded to whole hundredths of a second per frame by the gif format
+    frame_rate: f32,
+    /// writes <name>.ckpt every this many frames, so an interrupted run can be resumed, not supported by the 3D and
+    /// f64 worlds
+    checkpoint_interval: Option<NonZeroUsize>,
+    /// continues from <name>.ckpt when there is one, into <name>_from_<frame>.gif
+    resume: bool,
+    /// 2 or 4 draws the particles that many times larger and averages them back down, so that the ones smaller than
+    /// a pixel don't flicker as they cross from one pixel to the next, 1 draws them at the size of the frames
+    supersampling: u32,
+    /// `Some("csv")` also writes the mass points of every frame to <name>.csv as they are simulated, a row per
+    /// particle, such as for analysis elsewhere, `Some("csv.gz")` gzipped, not supported by the 3D and f64 worlds
+    trajectory: Option<String>,
+    /// also writes the positions of every frame to <name>_positions.npy and the masses to <name>_masses.npy for
+    /// NumPy, with the SimParams of the run in <name>.json, faster to load than `trajectory`, not supported by the 3D
+    /// and f64 worlds
+    npy_trajectory: bool,
+    /// shows the frames in a window as they are drawn, which can pause the run or stop it early, only for runs on
+    /// the main thread
+    #[cfg(feature = "preview")]
+    preview: bool
+}
+
+impl SimParams {
+    /// the particles the run starts with, those of `particles_file` if there is one, or else of `preset`
+    fn particles(&self) -> Vec<Particle> {
+        match &self.particles_file {
+            Some(file) => file.particles.iter()
+                .map(|particle| Particle { radius: drawn_radius(particle.mass, self.scale), ..*particle })
+                .collect(),
+            None => self.preset.particles(self)
+        }
+    }
+}
+
+impl Default for SimParams {
+    fn default() -> Self {
+        Self {
+            seed: 23,
+            particle_count: 100,
+            frame_count: 240,
+            scale: 500.0,
+            time_per_frame: 20.0,
+            time_steps: NonZeroU16::new(20).unwrap(),
+            size: Some((1000.0, 1000.0)),
+            preset: Preset::Random,
+            particles_file: None,
+            integrator: Integrator::Euler,
+            g: world::G,
+            softening: 0.0,
+            device: DeviceSelection::First,
+            frame_rate: 25.0,
+            checkpoint_interval: None,
+            resume: false,
+            supersampling: 1,
+            trajectory: None,
+            npy_trajectory: false,
+            #[cfg(feature = "preview")]
+            preview: false
+        }
+    }
+}
+
+const DRAG: f32 = 0.0;
+const BARNES_HUT_THETA: Option<f32> = None;
+// not supported by GPUWorld, slower and barely more precise for clustered particles, see CompensatedSum
+const COMPENSATED_SUMMATION: bool = false;
+// sums in the same order on every backend, so that CPUWorld and ParWorld agree bit for bit and GPUWorld stays closer to them
+const DETERMINISTIC_SUMMATION: bool = false;
+// not supported by GPUWorld
+const COLLISIONS: Collisions = Collisions::None;
+// not supported by GPUWorld, when set SimParams::time_steps is ignored
+const ADAPTIVE_STEPS: Option<AdaptiveSteps> = None;
+// not supported by GPUWorld
+const FIELDS: &[Field] = &[];
+// Boundary::Periodic { width: 2.0, height: 2.0 } wraps at the edges of the default SimParams::size of 1000x1000
+const BOUNDARY: Boundary = Boundary::Open;
+// logs the total energy of each frame, which reads back the particles of a GPUWorld
+const LOG_ENERGY: bool = false;
+// Camera::CenterOfMass keeps a drifting system in view, Camera::FollowParticle(0) draws every frame around particle 0
+const CAMERA: Camera = Camera::Fixed;
+// Some(Autoscale { smoothing: 0.1, margin: 0.1 }) zooms every frame to fit its particles, not used with `--size fit`
+const AUTOSCALE: Option<Autoscale> = None;
+// Coloring::Speed { colormap: Colormap::Inferno, scale: SpeedScale::Frame } colors particles by how fast they move
+const COLORING: Coloring = Coloring::Group;
+// Rendering::Heatmap(Heatmap { bin_size: 4, splat: Some(1.0), colormap: Colormap::Inferno }) draws where the mass is
+// rather than a circle per particle, which is much faster for many particles
+const RENDERING: Rendering = Rendering::Circles;
+// Some(MassScale::Log) draws lighter particles dimmer, relative to the masses of the whole run
+const MASS_BRIGHTNESS: Option<MassScale> = None;
+// Some(Trails { length: 30, fade: 0.9 }) leaves a dot behind every particle at each of its last 30 positions
+const TRAILS: Option<Trails> = None;
+// draws every particle at each substep of its frame rather than once, each at a fraction of its brightness so that they
+// add up to a streak along its path, not supported by the 3D and f64 worlds, and reads back every substep of a GPUWorld
+const MOTION_BLUR: bool = false;
+// Some(VelocityArrows { scale: 5000.0, max_length: 40.0 }) draws a line from every particle in the direction it moves
+const VELOCITY_ARROWS: Option<VelocityArrows> = None;
+// Some(ToneMap::Log) also sums every frame into <name>_exposure.png, a long exposure of the whole run
+const LONG_EXPOSURE: Option<ToneMap> = None;
+// also writes every frame to <name>/frame_00000.png onwards, in full color, such as for ffmpeg
+const PNG_FRAMES: bool = false;
+// Some(Apng { delay: (1, 30), plays: 0 }) also writes <name>.png, an animated png keeping every color the gif quantizes
+const APNG: Option<Apng> = None;
+// Some(Video { extension: "mp4", frame_rate: 30 }) also pipes every frame into <name>.mp4 through ffmpeg, which has to
+// be on the PATH, "webm" for a webm
+const VIDEO: Option<Video> = None;
+// Some(Overlay { corner: Corner::TopLeft, scale: 2 }) writes "t = 4800, frame 240/240, n = 97" into a corner of every frame
+const OVERLAY: Option<Overlay> = None;
+// Some(Grid { spacing: 0.5 }) draws lines every 0.5 units of distance under the particles, and the axes brighter
+const GRID: Option<Grid> = None;
+// draws the circles of the particles on the gpu of --device, or on that of the GPUWorld, rather than with GifRasterizer,
+// faster once there are about 100k particles, the substeps of MOTION_BLUR are still drawn on the cpu
+const GPU_RASTERIZATION: bool = false;
+// times the passes of every GPUWorld tick and logs them every PROFILE_INTERVAL, on devices that can write timestamps
+const PROFILE_GPU: bool = false;
+const PROFILE_INTERVAL: Duration = Duration::from_secs(5);
+// only used by GPUWorld, reads the positions particles are pulled towards in half precision, faster but less accurate
+const HALF_PRECISION_SOURCES: bool = false;
+// removes particles that left the system, Some(Escape::Radius(2.0)) removes them once they are 2.0 from the center of mass
+const ESCAPE: Option<Escape> = None;
+
+// IntegerRasterizer draws hard edged circles
+type GifRasterizer = AreaIntersectionRasterizer;
+// the substeps of MOTION_BLUR are added onto each other rather than drawn over each other
+type MotionBlurRasterizer = BlendingRasterizer<GifRasterizer, Additive>;
+// device of a GPUWorld and its queue, which GPU_RASTERIZATION draws with rather than setting up the device again
+type SharedDevice = (Arc<Device>, Arc<Queue>);
 
 fn main() {
+    let cli = Cli::parse();
     initialize_logging();
 
-    compare_outputs::<IntegerRasterizer>();
+    match cli.command {
+        None => compare_outputs::<GifRasterizer>(&SimParams::default(), Comparison::Merged),
+        Some(Command::Compare { params, comparison }) => compare_outputs::<GifRasterizer>(&params.params(), comparison),
+        Some(Command::Simulate { out, params, three_d: true, .. }) => output_3d::<GifRasterizer>(&out, &params.params()),
+        Some(Command::Simulate { out, params, f64: true, .. }) => output_f64::<GifRasterizer>(&out, &params.params()),
+        #[cfg(not(feature = "preview"))]
+        Some(Command::Simulate { backend, out, params, .. }) => simulate::<GifRasterizer>(backend, &out, &params.params()),
+        #[cfg(feature = "preview")]
+        Some(Command::Simulate { backend, out, params, preview, .. }) => {
+            simulate::<GifRasterizer>(backend, &out, &SimParams { preview, ..params.params() })
+        }
+        Some(Command::Ensemble { seeds, backend, out_dir, params }) => run_ensemble::<GifRasterizer>(&seeds, backend, &out_dir, &params.params()),
+        Some(Command::Devices) => list_devices(),
+        Some(Command::Inspect { gif }) => inspect(&gif),
+        #[cfg(feature = "serde")]
+        Some(Command::Run { scenario }) => run_scenario::<GifRasterizer>(&scenario)
+    }
 }
 
 #[allow(dead_code)]
@@ -186,197 +371,2618 @@ fn sub_divisions<const SUB_DIV: usize>(v: u32) -> [f32; SUB_DIV] {
 }
 
 #[allow(dead_code)]
-fn output_gpu<Rasterizer: crate::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, GrayscaleRgbScalar>>() {
-    let world = GPUWorld::new(PARTICLE_GENERATOR());
-    tick_and_output_gif::<_, _, _, Rasterizer>(world, GPUWorld::tick, GPUWorld::get_mass_points, "gpu");
+fn output_gpu<Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(params: &SimParams) {
+    tick_and_output_gpu_gif::<Rasterizer>(gpu_world(params.particles(), params).unwrap_or_else(|error| panic!("{}", error)), "output/gpu", params);
 }
 
-#[allow(dead_code)]
-fn compare_outputs<Rasterizer: crate::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, GrayscaleRgbScalar>>() {
-    let particles = PARTICLE_GENERATOR();
+fn cpu_world(particles: Vec<Particle>, params: &SimParams) -> CPUWorld {
+    let mut world = CPUWorld::new(particles)
+        .with_integrator(params.integrator)
+        .with_g(params.g)
+        .with_softening(params.softening)
+        .with_drag(DRAG)
+        .with_boundary(BOUNDARY)
+        .with_barnes_hut(BARNES_HUT_THETA)
+        .with_compensated_summation(COMPENSATED_SUMMATION)
+        .with_deterministic_summation(DETERMINISTIC_SUMMATION)
+        .with_collisions(COLLISIONS)
+        .with_adaptive_steps(ADAPTIVE_STEPS)
+        .with_escape(ESCAPE);
+    for field in FIELDS {
+        world.add_field(*field);
+    }
+    world
+}
+
+fn par_world(particles: Vec<Particle>, params: &SimParams) -> ParWorld {
+    let mut world = ParWorld::new(particles)
+        .with_integrator(params.integrator)
+        .with_g(params.g)
+        .with_softening(params.softening)
+        .with_drag(DRAG)
+        .with_boundary(BOUNDARY)
+        .with_barnes_hut(BARNES_HUT_THETA)
+        .with_compensated_summation(COMPENSATED_SUMMATION)
+        .with_deterministic_summation(DETERMINISTIC_SUMMATION)
+        .with_collisions(COLLISIONS)
+        .with_adaptive_steps(ADAPTIVE_STEPS)
+        .with_escape(ESCAPE);
+    for field in FIELDS {
+        world.add_field(*field);
+    }
+    world
+}
+
+fn gpu_world(particles: Vec<Particle>, params: &SimParams) -> Result<GPUWorld, GPUInitError> {
+    Ok(GPUWorld::new_on(&params.device, particles)?
+        .with_integrator(params.integrator)
+        .with_g(params.g)
+        .with_softening(params.softening)
+        .with_drag(DRAG)
+        .with_boundary(BOUNDARY)
+        .with_escape(ESCAPE)
+        .with_deterministic_summation(DETERMINISTIC_SUMMATION)
+        .with_half_precision_sources(HALF_PRECISION_SOURCES)
+        .with_profiling(PROFILE_GPU))
+}
+
+/// simulates [`generate_3_body_3d`] with the par backend into the gif at `path`, projected onto the xy plane
+fn output_3d<Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(path: &str, params: &SimParams) {
+    let world = ParWorld3::new(generate_3_body_3d(params))
+        .with_integrator(params.integrator)
+        .with_g(params.g)
+        .with_softening(params.softening);
+    tick_and_output_gif_with::<_, _, _, _, Rasterizer>(world, ParWorld3::tick, |world| {
+        world.get_mass_points()
+            .iter()
+            .map(|mass_point| mass_point.orthographic())
+            .collect::<Vec<_>>()
+            .into()
+    }, |_| None, 0..params.frame_count, gif_name(path), params);
+}
+
+/// simulates the particles of `params` in double precision with the par backend into the gif at `path`
+fn output_f64<Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(path: &str, params: &SimParams) {
+    let particles = params.particles().into_iter().map(ParticleF64::from).collect();
+    let world = ParWorldF64::new(particles)
+        .with_integrator(params.integrator)
+        .with_g(params.g as f64)
+        .with_softening(params.softening as f64);
+    tick_and_output_gif_with::<_, _, _, _, Rasterizer>(world, ParWorldF64::tick, |world| world.get_mass_points().into(), |world| LOG_ENERGY.then(|| world.total_energy() as f32), 0..params.frame_count, gif_name(path), params);
+}
+
+/// simulates the particles of `params` on every backend into `output/cpu.gif`, `output/par.gif` and `output/gpu.gif`,
+/// and puts them together as `comparison` says, into the red, green and blue channels of `output/merged.gif` with
+/// [`Comparison::Merged`], with how far apart the particles of each are in every frame in `output/divergence.csv`
+///
+/// without a usable gpu `output/gpu.gif` is simulated with a [`ParWorld`] instead, so the gpu panel or the blue
+/// channel duplicates that of par
+fn compare_outputs<Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(params: &SimParams, comparison: Comparison) {
+    let particles = params.particles();
+    let particle_count = particles.len();
     let particles_a = particles.clone();
     let particles_b = particles.clone();
     let particles_c = particles;
+    let params_a = params.clone();
+    let params_b = params.clone();
+    let params_c = params.clone();
+    fs::create_dir_all("output").expect("unable to create output directory");
 
-    ThreadPoolBuilder::new()
-        .num_threads(
-            usize::max(
-                available_parallelism()
-                    .unwrap_or(NonZeroUsize::new(1).unwrap())
-                    .get() - 1,
-                1)
-        )
-        .build_global()
-        .unwrap();
+    // the cpu world takes a core of its own
+    let par_threads = usize::max(
+        available_parallelism()
+            .unwrap_or(NonZeroUsize::new(1).unwrap())
+            .get() - 1,
+        1
+    );
+    // every frame of every backend is kept for the divergence report
     let handles = [
-        thread::spawn(|| {
-            let world = CPUWorld { particles: particles_a };
-            tick_and_output_gif::<_, _, _, Rasterizer>(world, CPUWorld::tick, CPUWorld::get_mass_points, "cpu");
+        thread::spawn(move || {
+            let mut recording = Vec::new();
+            tick_and_output_gif_recording::<_, Rasterizer>(cpu_world(particles_a, &params_a), "output/cpu", &params_a, Some(&mut recording));
+            recording
         }),
-        thread::spawn(|| {
-            let world = ParWorld::new(particles_b);
-            tick_and_output_gif::<_, _, _, Rasterizer>(world, ParWorld::tick, ParWorld::get_mass_points, "par");
+        thread::spawn(move || {
+            let mut recording = Vec::new();
+            tick_and_output_gif_recording::<_, Rasterizer>(par_world(particles_b, &params_b).with_threads(par_threads), "output/par", &params_b, Some(&mut recording));
+            recording
         }),
-        thread::spawn(|| {
-            let world = GPUWorld::new(particles_c);
-            tick_and_output_gif::<_, _, _, Rasterizer>(world, GPUWorld::tick, GPUWorld::get_mass_points, "gpu");
+        thread::spawn(move || {
+            let mut recording = Vec::new();
+            match gpu_world(particles_c.clone(), &params_c) {
+                Ok(world) => { tick_and_output_gpu_gif_recording::<Rasterizer>(world, "output/gpu", &params_c, Some(&mut recording)); }
+                Err(error) => {
+                    warn!("{}, simulating output/gpu.gif with ParWorld instead", error);
+                    tick_and_output_gif_recording::<_, Rasterizer>(par_world(particles_c, &params_c), "output/gpu", &params_c, Some(&mut recording));
+                }
+            }
+            recording
         })
     ];
-    for handle in handles {
-        handle.join().unwrap();
-    }
-
-    {
-        let single = GifDecoder::new(File::open("output/cpu.gif").unwrap()).unwrap();
-        let multi = GifDecoder::new(File::open("output/par.gif").unwrap()).unwrap();
-        let gpu = GifDecoder::new(File::open("output/gpu.gif").unwrap()).unwrap();
-        let mut merged = GifEncoder::new(File::create("output/merged.gif").unwrap());
-        merged.set_repeat(Repeat::Infinite).unwrap();
-        let mut periodic_logger = PeriodicLogger::new("exporting merged", Level::Info);
-        single.into_frames()
-            .zip(multi.into_frames())
-            .zip(gpu.into_frames())
-            .map(|((single_frame_result, multi_frame_result), gpu_frame_result)| {
-                (single_frame_result.unwrap().into_buffer(), multi_frame_result.unwrap().into_buffer(), gpu_frame_result.unwrap().into_buffer())
-            })
-            .enumerate()
-            .for_each(|(frame, (single_frame, multi_frame, gpu_frame))| {
-                periodic_logger.log(format!("{} / {}", frame, FRAME_COUNT));
-                let (width, height) = (single_frame.width(), single_frame.height());
-                let mut image = RgbaImage::new(width, height);
-                for y in 0..height {
-                    for x in 0..width {
-                        let r = single_frame[(x, y)].0[0];
-                        let g = multi_frame[(x, y)].0[1];
-                        let b = gpu_frame[(x, y)].0[2];
-                        image[(x, y)].0 = [r, g, b, 255];
-                    }
+    let recordings = handles.map(|handle| handle.join().unwrap());
+    write_divergence_report(&recordings, "output/divergence.csv");
+
+    let path = match comparison {
+        Comparison::Merged => "output/merged.gif",
+        Comparison::SideBySide | Comparison::Grid => "output/compared.gif"
+    };
+    let single = GifDecoder::new(File::open("output/cpu.gif").unwrap()).unwrap();
+    let multi = GifDecoder::new(File::open("output/par.gif").unwrap()).unwrap();
+    let gpu = GifDecoder::new(File::open("output/gpu.gif").unwrap()).unwrap();
+    let comment = gif_comment("CPUWorld/ParWorld/GPUWorld", 0, particle_count, params);
+    let mut compared = GifEncoder::new(CommentWriter::new(File::create(path).unwrap(), &comment));
+    compared.set_repeat(Repeat::Infinite).unwrap();
+    let mut periodic_logger = PeriodicLogger::new(&format!("exporting {}", path), Level::Info);
+    let frames = single.into_frames()
+        .zip(multi.into_frames())
+        .zip(gpu.into_frames())
+        .map(|((single_frame_result, multi_frame_result), gpu_frame_result)| {
+            (single_frame_result.unwrap().into_buffer(), multi_frame_result.unwrap().into_buffer(), gpu_frame_result.unwrap().into_buffer())
+        });
+    for (frame, (single_frame, multi_frame, gpu_frame)) in frames.enumerate() {
+        periodic_logger.log(format!("{} / {}", frame, params.frame_count));
+        match compare_frames(comparison, [&single_frame, &multi_frame, &gpu_frame]) {
+            Ok(image) => compared.encode_frame(Frame::from_parts(image, 0, 0, frame_delay(params))).unwrap(),
+            Err(error) => {
+                warn!("stopping {} at frame {}: {}", path, frame, error);
+                break
+            }
+        }
+    }
+}
+
+/// warns about the first frame at which the positions of two backends of [`compare_outputs`] are further apart than
+/// this, in units of distance
+const DIVERGENCE_WARNING: f32 = 0.01;
+
+/// writes the [`Divergence`] of every pair of `recordings` in every frame to `path` as csv, the recordings being
+/// the mass points of every frame of the cpu, par and gpu backends
+///
+/// particles are paired by index, see [`diagnostics::position_divergence`]
+fn write_divergence_report(recordings: &[Vec<Vec<MassPoint>>; 3], path: &str) {
+    let pairs = [(0, 1), (0, 2), (1, 2)];
+    let mut warned = [false; 3];
+    let mut csv = String::from("frame");
+    for (a, b) in pairs {
+        let pair = format!("{}_vs_{}", COMPARISON_LABELS[a], COMPARISON_LABELS[b]);
+        csv.push_str(&format!(",{}_rms,{}_max", pair, pair));
+    }
+    csv.push('\n');
+    let [cpu, par, gpu] = recordings;
+    for (frame, ((cpu_frame, par_frame), gpu_frame)) in cpu.iter().zip(par).zip(gpu).enumerate() {
+        let frames = [cpu_frame, par_frame, gpu_frame];
+        csv.push_str(&frame.to_string());
+        for (i, (a, b)) in pairs.into_iter().enumerate() {
+            let Divergence { rms, max } = diagnostics::position_divergence(frames[a], frames[b]);
+            csv.push_str(&format!(",{},{}", rms, max));
+            if max > DIVERGENCE_WARNING && !warned[i] {
+                warn!("{} and {} are {} apart at frame {}", COMPARISON_LABELS[a], COMPARISON_LABELS[b], max, frame);
+                warned[i] = true;
+            }
+        }
+        csv.push('\n');
+    }
+    fs::write(path, csv).expect("unable to write divergence report");
+}
+
+/// how [`compare_outputs`] puts the gifs of every backend together
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum Comparison {
+    /// the red channel of the cpu gif, the green of the par gif and the blue of the gpu gif into output/merged.gif,
+    /// which is unreadable once they drift apart
+    Merged,
+    /// every gif as a labeled panel, next to each other, into output/compared.gif
+    SideBySide,
+    /// every gif as a labeled panel of a 2x2 grid into output/compared.gif, the fourth showing how far apart they
+    /// are at each pixel
+    Grid
+}
+
+/// labels of the panels of [`Comparison::SideBySide`] and [`Comparison::Grid`], that of the fourth panel last
+const COMPARISON_LABELS: [&str; 4] = ["cpu", "par", "gpu", "diff"];
+/// pixels per pixel of the font of [`COMPARISON_LABELS`]
+const COMPARISON_LABEL_SCALE: u32 = 2;
+
+/// puts the same frame of the cpu, par and gpu gifs together as `comparison` says, they have to be the same size
+fn compare_frames(comparison: Comparison, frames: [&RgbaImage; 3]) -> Result<RgbaImage, String> {
+    let (width, height) = frames[0].dimensions();
+    if let Some((label, frame)) = COMPARISON_LABELS.iter().zip(frames).find(|(_, frame)| frame.dimensions() != (width, height)) {
+        return Err(format!("the {} frame is {}x{}, not {}x{} as that of cpu", label, frame.width(), frame.height(), width, height))
+    }
+    let image = match comparison {
+        Comparison::Merged => RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([frames[0][(x, y)].0[0], frames[1][(x, y)].0[1], frames[2][(x, y)].0[2], 255])
+        }),
+        Comparison::SideBySide => labeled_panels(&frames, 3, 1),
+        Comparison::Grid => {
+            // the spread of each channel over the three frames, black where they agree
+            let difference = RgbaImage::from_fn(width, height, |x, y| {
+                let mut pixel = [0, 0, 0, 255];
+                for (channel, spread) in pixel.iter_mut().take(3).enumerate() {
+                    let values = frames.map(|frame| frame[(x, y)].0[channel]);
+                    *spread = values.iter().max().unwrap() - values.iter().min().unwrap();
                 }
-                merged.encode_frame(Frame::new(image)).unwrap();
+                Rgba(pixel)
             });
+            labeled_panels(&[frames[0], frames[1], frames[2], &difference], 2, 2)
+        }
+    };
+    Ok(image)
+}
+
+/// draws `panels`, which are the same size, left to right and top to bottom into `columns` by `rows` of them, each
+/// with the [`COMPARISON_LABELS`] of its place in its top left corner
+fn labeled_panels(panels: &[&RgbaImage], columns: u32, rows: u32) -> RgbaImage {
+    let (width, height) = panels[0].dimensions();
+    let mut image = RgbaImage::from_pixel(width * columns, height * rows, Rgba([0, 0, 0, 255]));
+    for (i, panel) in panels.iter().enumerate() {
+        let (x, y) = (i as u32 % columns * width, i as u32 / columns * height);
+        image::imageops::replace(&mut image, *panel, x as i64, y as i64);
     }
+    let mut image = HorizontalLineImage::from(image);
+    let scale = COMPARISON_LABEL_SCALE;
+    for (i, label) in COMPARISON_LABELS.iter().enumerate().take(panels.len()) {
+        let (x, y) = (i as u32 % columns * width, i as u32 / columns * height);
+        let strip_width = text_width(label, scale) + 2 * scale;
+        let strip_height = (GLYPH_HEIGHT + 2) * scale;
+        // rather than running into the next panel
+        if strip_width > width || strip_height > height {
+            continue
+        }
+        fill_rectangle(&mut image, x, y, x + strip_width, y + strip_height, OVERLAY_BACKGROUND.into());
+        draw_text(&mut image, x + scale, y + scale, label, scale, OVERLAY_PAINT.into());
+    }
+    image.into()
 }
 
-#[allow(dead_code)]
-fn generate_particles() -> Vec<Particle> {
-    let mut rng = Pcg64Mcg::seed_from_u64(SEED);
-    let mut particles = Vec::with_capacity(PARTICLE_COUNT);
-    for _ in 0..PARTICLE_COUNT {
+/// simulates the particles of `params` on `backend` into the gif at `path`, creating its directory if needed
+fn simulate<Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(backend: Backend, path: &str, params: &SimParams) {
+    simulate_particles::<Rasterizer>(backend, path, params.particles(), params);
+}
+
+/// simulates the scenario file at `path`, see [`config::Config`], exiting with an error when it can't be read
+#[cfg(feature = "serde")]
+fn run_scenario<Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(path: &str) {
+    let config = config::Config::read(path).unwrap_or_else(|error| {
+        Cli::command().error(ErrorKind::InvalidValue, format!("unable to read {}: {}", path, error)).exit()
+    });
+    let params = config.params();
+    simulate_particles::<Rasterizer>(config.backend, &config.out, config.particles(&params), &params);
+}
+
+/// `path` without its extension, creating the directory it is in
+fn gif_name(path: &str) -> &str {
+    if let Some(directory) = Path::new(path).parent() {
+        fs::create_dir_all(directory).expect("unable to create output directory");
+    }
+    path.strip_suffix(".gif").expect("not the path of a gif")
+}
+
+/// [`simulate`] starting from `particles` rather than from those of `params`
+fn simulate_particles<Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(backend: Backend, path: &str, particles: Vec<Particle>, params: &SimParams) {
+    let name = gif_name(path);
+    match backend {
+        Backend::CPU => { tick_and_output_gif::<_, Rasterizer>(cpu_world(particles, params), name, params); }
+        Backend::Par => { tick_and_output_gif::<_, Rasterizer>(par_world(particles, params), name, params); }
+        Backend::GPU => { tick_and_output_gpu_gif::<Rasterizer>(gpu_world(particles, params).unwrap_or_else(|error| panic!("{}", error)), name, params); }
+    }
+}
+
+/// prints the comments of the gif at `path`, the settings of the run it is of for the gifs written here, see
+/// [`gif_comment`], exiting with an error when it can't be read
+fn inspect(path: &str) {
+    let comments = File::open(path)
+        .and_then(|file| read_comments(BufReader::new(file)))
+        .unwrap_or_else(|error| Cli::command().error(ErrorKind::Io, format!("unable to read {}: {}", path, error)).exit());
+    if comments.is_empty() {
+        println!("{} has no comments", path);
+    }
+    for comment in comments {
+        println!("{}", comment);
+    }
+}
+
+/// the settings of a run written into its gifs, `world` being what simulated it, such as `CPUWorld`, and
+/// `particles` the number of particles in its first frame, which is frame `first_frame`
+fn gif_comment(world: &str, first_frame: usize, particles: usize, params: &SimParams) -> String {
+    let preset = preset_name(params);
+    let integrator = format!("{:?}", params.integrator).to_lowercase();
+    format!(
+        "newtonian_gravity={}, world={}, preset={}, seed={}, particles={}, frames={}..{}, integrator={}, time_per_frame={}, \
+        time_steps={}, g={}, softening={}, scale={}",
+        env!("CARGO_PKG_VERSION"), world, preset, params.seed, particles, first_frame, params.frame_count, integrator,
+        params.time_per_frame, params.time_steps, params.g, params.softening, params.scale
+    )
+}
+
+/// the name of the preset of `params` as given to `--preset`, or the path of its particles file if it has one
+fn preset_name(params: &SimParams) -> String {
+    match &params.particles_file {
+        Some(file) => file.path.clone(),
+        None => params.preset.to_possible_value().map_or(String::new(), |value| value.get_name().to_string())
+    }
+}
+
+/// the name of the type `W` without its path, such as `CPUWorld`
+fn world_name<W>() -> &'static str {
+    let name = any::type_name::<W>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+/// prints every device the gpu backend can run on, with the index `--device` selects it by
+fn list_devices() {
+    let devices = GPUWorld::list_devices();
+    if devices.is_empty() {
+        println!("no devices, vulkan may not be installed");
+    }
+    for (index, device) in devices.iter().enumerate() {
+        println!("{}: {} ({:?}, vulkan {})", index, device.name, device.device_type, device.api_version);
+    }
+}
+
+/// world a run is simulated with
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum Backend {
+    CPU,
+    Par,
+    GPU
+}
+
+/// simulates `params.particle_count` random particles of each of `seeds` into `<dir>/run_<seed>.gif`, the seed
+/// of `params` is ignored and every other setting is taken from it and the constants above, and summarizes
+/// the last frame of every run into `<dir>/ensemble.csv`
+///
+/// runs are scheduled on a rayon thread pool of their own, as large as the global one, which a [`ParWorld`] ticks on
+/// as well, so the runs and the particles within them share its threads rather than oversubscribing the cpu, the
+/// frames are drawn on the global pool, as a run waits for them without giving up its thread
+fn run_ensemble<Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(seeds: &[u64], backend: Backend, dir: &str, params: &SimParams) {
+    fs::create_dir_all(dir).expect("unable to create the output directory");
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(rayon::current_num_threads())
+        .build()
+        .expect("unable to create the thread pool of the runs");
+    let rows: Vec<String> = pool.install(|| seeds.par_iter()
+        .map(|&seed| {
+            let particles = with_drawn_radii(random_particles(seed, params.particle_count, params.scale), params);
+            let name = format!("{}/run_{}", dir, seed);
+            match backend {
+                Backend::CPU => ensemble_row(seed, params, &tick_and_output_gif::<_, Rasterizer>(cpu_world(particles, params), &name, params)),
+                Backend::Par => ensemble_row(seed, params, &tick_and_output_gif::<_, Rasterizer>(par_world(particles, params), &name, params)),
+                Backend::GPU => ensemble_row(seed, params, &tick_and_output_gpu_gif::<Rasterizer>(gpu_world(particles, params).unwrap_or_else(|error| panic!("{}", error)), &name, params))
+            }
+        })
+        .collect());
+    let mut csv = String::from("seed,particles,energy,bound_fraction\n");
+    for row in rows {
+        csv.push_str(&row);
+        csv.push('\n');
+    }
+    fs::write(format!("{}/ensemble.csv", dir), csv).expect("unable to write ensemble summary");
+}
+
+/// the final energy and the fraction of particles still bound of a finished run,
+/// particles removed for escaping aren't counted
+fn ensemble_row<W: World>(seed: u64, params: &SimParams, world: &W) -> String {
+    let particles = world.get_particles();
+    let bound_fraction = diagnostics::bound_fraction(&particles, &NewtonianGravity { g: params.g }, params.softening, FIELDS);
+    format!("{},{},{:e},{}", seed, particles.len(), world.total_energy(), bound_fraction)
+}
+
+/// particles a run starts with
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum Preset {
+    /// [`generate_particles`]
+    Random,
+    /// [`generate_3_body`]
+    ThreeBody,
+    /// [`generate_pinned_3_body`]
+    PinnedThreeBody,
+    /// [`generate_kepler_orbit`]
+    Kepler,
+    /// [`generate_two_clusters`]
+    TwoClusters,
+    /// [`generate_solar_system`]
+    SolarSystem,
+    /// [`generate_figure_eight`]
+    FigureEight,
+    /// [`generate_lagrange_l4`]
+    LagrangeL4,
+    /// [`generate_plummer_sphere`]
+    Plummer,
+    /// [`generate_exponential_disk`]
+    ExponentialDisk
+}
+
+/// the particles of the file at `path`, radii are left to [`SimParams::particles`] as they depend on the scale
+#[derive(Clone, Debug)]
+struct ParticlesFile {
+    path: String,
+    particles: Vec<Particle>
+}
+
+impl Preset {
+    fn particles(self, params: &SimParams) -> Vec<Particle> {
+        match self {
+            Preset::Random => generate_particles(params),
+            Preset::ThreeBody => generate_3_body(params),
+            Preset::PinnedThreeBody => generate_pinned_3_body(params),
+            Preset::Kepler => generate_kepler_orbit(params),
+            Preset::TwoClusters => generate_two_clusters(params),
+            Preset::SolarSystem => generate_solar_system(params),
+            Preset::FigureEight => generate_figure_eight(params),
+            Preset::LagrangeL4 => generate_lagrange_l4(params),
+            Preset::Plummer => generate_plummer_sphere(params),
+            Preset::ExponentialDisk => generate_exponential_disk(params)
+        }
+    }
+}
+
+fn generate_particles(params: &SimParams) -> Vec<Particle> {
+    with_drawn_radii(random_particles(params.seed, params.particle_count, params.scale), params)
+}
+
+/// two clusters of `params.particle_count` random particles in total, side by side and heading into each other,
+/// the left one in group 0 and the right one in group 1
+fn generate_two_clusters(params: &SimParams) -> Vec<Particle> {
+    let mut rng = Pcg64Mcg::seed_from_u64(params.seed);
+    let mut particles = Vec::with_capacity(params.particle_count);
+    for i in 0..params.particle_count {
+        let group = (i % 2) as u32;
+        let (center_x, speed) = match group {
+            0 => (-0.5, 0.0001),
+            _ => (0.5, -0.0001)
+        };
+        let mass = rng.gen_range(0.0..1.0);
+        let offset = Vector::new(rng.gen_range(0.0..TAU), rng.gen_range(0.0..0.25));
         particles.push(Particle {
-            mass: rng.gen_range(0.0..1.0),
-            position: Vector::new(rng.gen_range(0.0..TAU), rng.gen_range(0.5..1.0)),
-            velocity: Vector::new(0.0, 0.0)
+            mass,
+            position: Vector::from_cartesian(center_x, 0.0) + offset,
+            velocity: Vector::from_cartesian(speed, 0.0),
+            radius: drawn_radius(mass, params.scale),
+            fixed: 0,
+            id: 0,
+            group
         });
     }
     particles
 }
 
-#[allow(dead_code)]
-fn generate_3_body() -> Vec<Particle> {
-    let mut particles = Vec::with_capacity(PARTICLE_COUNT);
+fn generate_3_body(params: &SimParams) -> Vec<Particle> {
+    let mut particles = Vec::with_capacity(3);
     particles.push(Particle {
         mass: 10000.0,
         position: Vector::new(0.0, 0.0),
-        velocity: Vector::new(0.0, 0.0)
+        velocity: Vector::new(0.0, 0.0),
+        radius: drawn_radius(10000.0, params.scale),
+        fixed: 0,
+        id: 0,
+        group: 0
     });
     particles.push(Particle {
         mass: 100.0,
         position: Vector::new(0.0, 0.50),
-        velocity: Vector::new(FRAC_PI_2, 0.001)
+        velocity: Vector::new(FRAC_PI_2, 0.001),
+        radius: drawn_radius(100.0, params.scale),
+        fixed: 0,
+        id: 0,
+        group: 0
     });
     particles.push(Particle {
         mass: 10.0,
         position: Vector::new(0.0, 0.55),
-        velocity: Vector::new(FRAC_PI_2, 0.0013)
+        velocity: Vector::new(FRAC_PI_2, 0.0013),
+        radius: drawn_radius(10.0, params.scale),
+        fixed: 0,
+        id: 0,
+        group: 0
     });
     particles
 }
 
-fn tick_and_output_gif<W, TF: FnMut(&mut W, f32, NonZeroU16), MPG: FnMut(&W) -> Vec<MassPoint>, Rasterizer: crate::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, GrayscaleRgbScalar>>(mut world: W, mut tick_function: TF, mut mass_point_getter: MPG, name: &str) {
+/// [`generate_3_body`] with the heaviest body pinned in place
+fn generate_pinned_3_body(params: &SimParams) -> Vec<Particle> {
+    let mut particles = generate_3_body(params);
+    particles[0].fixed = 1;
+    particles
+}
+
+/// a heavy and a light body on an eccentric orbit, see [`KeplerOrbit`] for the exact solution
+fn generate_kepler_orbit(params: &SimParams) -> Vec<Particle> {
+    let mut particles = KeplerOrbit {
+        g: params.g,
+        masses: (10000.0, 100.0),
+        semi_major_axis: 0.5,
+        eccentricity: 0.5
+    }.particles();
+    for particle in &mut particles {
+        particle.radius = drawn_radius(particle.mass, params.scale);
+    }
+    particles
+}
+
+/// [`presets::solar_system`] with a sun of mass 100, Jupiter going around about once in the default run
+fn generate_solar_system(params: &SimParams) -> Vec<Particle> {
+    with_drawn_radii(presets::solar_system(params.g, 100.0), params)
+}
+
+/// [`presets::figure_eight`] of bodies of mass 3000 stretching 0.5 to either side, going around the eight about once
+/// in the default run
+fn generate_figure_eight(params: &SimParams) -> Vec<Particle> {
+    with_drawn_radii(presets::figure_eight(params.g, 3000.0, 0.5), params)
+}
+
+/// [`presets::lagrange_l4`] of the masses of [`generate_kepler_orbit`] 0.5 apart
+fn generate_lagrange_l4(params: &SimParams) -> Vec<Particle> {
+    with_drawn_radii(presets::lagrange_l4(params.g, 10000.0, 100.0, 0.5), params)
+}
+
+/// [`plummer_sphere`] of `params.particle_count` particles with a total mass of 1000 and a scale length of 0.2, going
+/// through about 14 crossing times in the default run, which needs a softening of about 0.01 to hold together
+fn generate_plummer_sphere(params: &SimParams) -> Vec<Particle> {
+    with_drawn_radii(plummer_sphere(params.seed, params.particle_count, 1000.0, 0.2, params.g), params)
+}
+
+/// [`exponential_disk`] of `params.particle_count` particles with a total mass of 1000 and a scale length of 0.15,
+/// turning about once at 2 scale lengths in the default run, which needs a softening of about 0.01 to hold together
+fn generate_exponential_disk(params: &SimParams) -> Vec<Particle> {
+    with_drawn_radii(exponential_disk(params.seed, params.particle_count, 1000.0, 0.15, params.g), params)
+}
+
+fn with_drawn_radii(mut particles: Vec<Particle>, params: &SimParams) -> Vec<Particle> {
+    for particle in &mut particles {
+        particle.radius = drawn_radius(particle.mass, params.scale);
+    }
+    particles
+}
+
+/// a pinned heavy body with two lighter bodies on circular orbits in differently tilted planes
+fn generate_3_body_3d(params: &SimParams) -> Vec<Particle3> {
+    let central_mass = 10000.0;
+    let orbit = |mass: f32, radius: f32, tilt: f32| {
+        let speed = f32::sqrt(params.g * central_mass / radius);
+        Particle3 {
+            mass,
+            position: Vector3::new(radius, 0.0, 0.0),
+            velocity: Vector3::new(0.0, speed * f32::cos(tilt), speed * f32::sin(tilt)),
+            radius: drawn_radius(mass, params.scale),
+            fixed: 0
+        }
+    };
+    vec![
+        Particle3 {
+            mass: central_mass,
+            position: Vector3::default(),
+            velocity: Vector3::default(),
+            radius: drawn_radius(central_mass, params.scale),
+            fixed: 1
+        },
+        orbit(100.0, 0.5, 0.5),
+        orbit(10.0, 0.3, -1.0)
+    ]
+}
+
+/// radius in world units of the circle a particle of the given mass is drawn with at `scale`,
+/// which is also the radius it collides with
+fn drawn_radius(mass: f32, scale: f32) -> f32 {
+    pixel_radius(mass) / scale
+}
+
+/// radius in pixels of the circle a particle of the given mass is drawn with, that of a sphere of density 1 / pi^2,
+/// about 2.1 times the [`world::radius`] of a sphere of density 1
+fn pixel_radius(mass: f32) -> f32 {
+    f32::cbrt(3.0 * mass / 4.0 * PI)
+}
+
+/// simulates and outputs `world` into `<name>.gif`, `name` being a path without the extension such as `output/cpu`,
+/// writing a checkpoint every [`SimParams::checkpoint_interval`] frames, and continuing from the last one into a gif of its
+/// own when [`SimParams::resume`] is set, the checkpoint is removed once every frame is simulated
+///
+/// returns the world after the last frame
+fn tick_and_output_gif<W: World, Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(world: W, name: &str, params: &SimParams) -> W {
+    tick_and_output_gif_recording::<_, Rasterizer>(world, name, params, None)
+}
+
+/// [`tick_and_output_gif`] that also pushes the mass points of every frame onto `recording` when given, where the
+/// particles are rather than where the [`CAMERA`] draws them
+fn tick_and_output_gif_recording<W: World, Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(mut world: W, name: &str, params: &SimParams, mut recording: Option<&mut Vec<Vec<MassPoint>>>) -> W {
+    let checkpoint_path = format!("{}.ckpt", name);
+    let first_frame = resume(&mut world, &checkpoint_path, name, params);
+    let gif_name = resumed_name(name, first_frame);
+    let mut frame = first_frame;
+    let mut profile_logger = None;
+    // the mass points after every substep of the frame being ticked, with MOTION_BLUR
+    let substep_mass_positions = RefCell::new(Vec::new());
+    let tick = |world: &mut W, time: f32, steps: NonZeroU16| {
+        let substeps = match MOTION_BLUR {
+            true => world.tick_with_observer(time, steps, |particles| {
+                substep_mass_positions.borrow_mut().push(particles.iter().copied().map(MassPoint::from).collect());
+            }),
+            false => world.tick(time, steps)
+        };
+        log_profile(world, &mut profile_logger, name);
+        frame += 1;
+        write_checkpoint_if_due(world, &checkpoint_path, frame, params);
+        substeps
+    };
+    let mut trajectory = TrajectoryFiles::new(&gif_name, first_frame, params);
+    let mut trajectory_frame = first_frame;
+    let mass_point_getter = |world: &W| {
+        let mass_positions = world.get_mass_points();
+        if let Some(recording) = recording.as_mut() {
+            recording.push(mass_positions.clone());
+        }
+        trajectory.write_frame(trajectory_frame, &mass_positions);
+        trajectory_frame += 1;
+        SimulatedFrame { mass_positions, substeps: substep_mass_positions.take() }
+    };
+    let world = tick_and_output_gif_with::<_, _, _, _, Rasterizer>(world, tick, mass_point_getter, |world| LOG_ENERGY.then(|| world.total_energy()), first_frame..params.frame_count, &gif_name, params);
+    trajectory.finish();
+    // a finished run has nothing to resume, one stopped early from its preview resumes from its last checkpoint
+    if frame == params.frame_count {
+        let _ = fs::remove_file(&checkpoint_path);
+    }
+    world
+}
+
+/// [`tick_and_output_gif`] for a [`GPUWorld`], which submits the tick of every frame before reading back the frame
+/// before it, so that the GPU simulates a frame while the CPU reads back, draws and encodes the last one
+///
+/// the gif is the same as that of [`tick_and_output_gif`], which it falls back to when particles can escape,
+/// as removing them replaces the buffers the frames are read back from, and with [`MOTION_BLUR`], which reads back
+/// every substep
+fn tick_and_output_gpu_gif<Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(world: GPUWorld, name: &str, params: &SimParams) -> GPUWorld {
+    tick_and_output_gpu_gif_recording::<Rasterizer>(world, name, params, None)
+}
+
+/// [`tick_and_output_gpu_gif`] that also records every frame, see [`tick_and_output_gif_recording`]
+fn tick_and_output_gpu_gif_recording<Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(mut world: GPUWorld, name: &str, params: &SimParams, mut recording: Option<&mut Vec<Vec<MassPoint>>>) -> GPUWorld {
+    if ESCAPE.is_some() || MOTION_BLUR {
+        return tick_and_output_gif_recording::<_, Rasterizer>(world, name, params, recording)
+    }
+    let checkpoint_path = format!("{}.ckpt", name);
+    let first_frame = resume(&mut world, &checkpoint_path, name, params);
+    let gif_name = resumed_name(name, first_frame);
+    let mut periodic_logger = PeriodicLogger::new(&format!("simulating {}", gif_name), Level::Info);
+    let mut profile_logger = None;
+    let mut frames = first_frame..params.frame_count;
+    let mut ticked = false;
+    let device = GPU_RASTERIZATION.then(|| world.device());
+    let mass_point_frames = iter::from_fn(|| {
+        for frame in frames.by_ref() {
+            let substeps = world.tick_pipelined(params.time_per_frame, params.time_steps);
+            ticked = true;
+            log_profile(&world, &mut profile_logger, name);
+            write_checkpoint_if_due(&world, &checkpoint_path, frame + 1, params);
+            match LOG_ENERGY {
+                true => periodic_logger.log(format!("{} / {} ({} substeps, energy {:e})", frame, params.frame_count, substeps, world.total_energy())),
+                false => periodic_logger.log(format!("{} / {} ({} substeps)", frame, params.frame_count, substeps))
+            }
+            if let Some(mass_points) = world.previous_mass_points() {
+                return Some(mass_points)
+            }
+        }
+        // no tick follows the last one, so its frame is read back once it has finished
+        mem::take(&mut ticked).then(|| world.get_mass_points())
+    });
+    let mut trajectory = TrajectoryFiles::new(&gif_name, first_frame, params);
+    let mass_point_frames = (first_frame..).zip(mass_point_frames)
+        .map(|(frame, mass_points)| {
+            if let Some(recording) = recording.as_mut() {
+                recording.push(mass_points.clone());
+            }
+            trajectory.write_frame(frame, &mass_points);
+            SimulatedFrame::from(mass_points)
+        });
+    output_frames::<Rasterizer>(mass_point_frames, first_frame, &gif_name, params, world_name::<GPUWorld>(), device);
+    trajectory.finish();
+    // see tick_and_output_gif
+    if frames.is_empty() && !ticked {
+        let _ = fs::remove_file(&checkpoint_path);
+    }
+    world
+}
+
+/// the files [`SimParams::trajectory`] and [`SimParams::npy_trajectory`] write the mass points of every frame of a run into, as
+/// they are simulated, those that couldn't be created are left out
+struct TrajectoryFiles {
+    name: String,
+    /// of the csv file
+    extension: Option<String>,
+    csv: Option<TrajectoryWriter>,
+    npy: Option<NpyTrajectoryWriter>
+}
+
+impl TrajectoryFiles {
+    /// for the run written to `<name>.gif` with `params`, the first frame of which is frame `first_frame`
+    fn new(name: &str, first_frame: usize, params: &SimParams) -> Self {
+        let csv = params.trajectory.as_ref().and_then(|extension| match TrajectoryWriter::create(format!("{}.{}", name, extension)) {
+            Ok(csv) => Some(csv),
+            Err(error) => {
+                warn!("leaving out {}.{}: {}", name, extension, error);
+                None
+            }
+        });
+        let npy = params.npy_trajectory.then(|| {
+            fs::write(format!("{}.json", name), trajectory_parameters(first_frame, params))?;
+            NpyTrajectoryWriter::create(format!("{}_positions.npy", name), format!("{}_masses.npy", name))
+        });
+        let npy = match npy {
+            Some(Ok(npy)) => Some(npy),
+            Some(Err(error)) => {
+                warn!("leaving out the npy files of {}: {}", name, error);
+                None
+            }
+            None => None
+        };
+        Self { name: name.to_string(), extension: params.trajectory.clone(), csv, npy }
+    }
+
+    fn write_frame(&mut self, frame: usize, mass_points: &[MassPoint]) {
+        if let Some(csv) = &mut self.csv {
+            csv.write_frame(frame, mass_points);
+        }
+        if let Some(npy) = &mut self.npy {
+            npy.write_frame(mass_points);
+        }
+    }
+
+    /// completes the files, logging the errors that stopped them, if any
+    fn finish(self) {
+        if let (Some(Err(error)), Some(extension)) = (self.csv.map(TrajectoryWriter::finish), self.extension) {
+            warn!("unable to write every frame of {}.{}: {}", self.name, extension, error);
+        }
+        if let Some(Err(error)) = self.npy.map(NpyTrajectoryWriter::finish) {
+            warn!("unable to write every frame of {}_positions.npy: {}", self.name, error);
+        }
+    }
+}
+
+/// `params` of a run as JSON, for the files of [`SimParams::npy_trajectory`], the first frame of which is frame `first_frame`
+fn trajectory_parameters(first_frame: usize, params: &SimParams) -> String {
+    let preset = preset_name(params).replace('\\', "\\\\").replace('"', "\\\"");
+    let integrator = format!("{:?}", params.integrator).to_lowercase();
+    format!(
+        "{{\"first_frame\": {}, \"frames\": {}, \"preset\": \"{}\", \"seed\": {}, \"particles\": {}, \"time_per_frame\": {}, \
+        \"steps\": {}, \"integrator\": \"{}\", \"g\": {}, \"softening\": {}, \"scale\": {}}}\n",
+        first_frame, params.frame_count, preset, params.seed, params.particle_count, params.time_per_frame,
+        params.time_steps, integrator, params.g, params.softening, params.scale
+    )
+}
+
+/// replaces the particles of `world` with those of the checkpoint at `checkpoint_path` when [`SimParams::resume`] is set
+/// and there is one, returns the frame it continues from
+fn resume<W: World>(world: &mut W, checkpoint_path: &str, name: &str, params: &SimParams) -> usize {
+    if !params.resume {
+        return 0
+    }
+    match read_checkpoint(checkpoint_path) {
+        Some(checkpoint) => {
+            assert!(
+                checkpoint.time == params.time_per_frame && checkpoint.steps == params.time_steps.get(),
+                "{} was written with a different time_per_frame or time_steps", checkpoint_path
+            );
+            world.resume(&checkpoint);
+            info!("resuming {} from frame {}", name, checkpoint.frame);
+            checkpoint.frame
+        }
+        None => 0
+    }
+}
+
+/// name of the gif of a run continuing from `first_frame`
+fn resumed_name(name: &str, first_frame: usize) -> String {
+    match first_frame {
+        0 => name.to_string(),
+        _ => format!("{}_from_{}", name, first_frame)
+    }
+}
+
+/// logs how long the passes of the last tick took on the GPU every [`PROFILE_INTERVAL`], if they were timed
+fn log_profile<W: World>(world: &W, profile_logger: &mut Option<PeriodicLogger>, name: &str) {
+    if let Some(profile) = world.last_tick_profile() {
+        profile_logger
+            .get_or_insert_with(|| PeriodicLogger::new_with_interval(&format!("profiling {}", name), PROFILE_INTERVAL, Level::Info))
+            .log(profile);
+    }
+}
+
+/// writes the checkpoint of `world` after `frame` frames every [`SimParams::checkpoint_interval`] frames, but not after the
+/// last
+fn write_checkpoint_if_due<W: World>(world: &W, checkpoint_path: &str, frame: usize, params: &SimParams) {
+    if let Some(interval) = params.checkpoint_interval {
+        if frame % interval.get() == 0 && frame < params.frame_count {
+            write_checkpoint(checkpoint_path, &world.checkpoint(frame, params.time_per_frame, params.time_steps));
+        }
+    }
+}
+
+fn read_checkpoint(path: &str) -> Option<Checkpoint> {
+    let file = File::open(path).ok()?;
+    Some(Checkpoint::read(BufReader::new(file)).unwrap_or_else(|error| panic!("unable to read {}: {}", path, error)))
+}
+
+/// writes to a temporary file first, so that being interrupted while writing leaves the last checkpoint intact
+fn write_checkpoint(path: &str, checkpoint: &Checkpoint) {
+    let temporary_path = format!("{}.tmp", path);
+    let file = File::create(&temporary_path).expect("unable to create checkpoint");
+    checkpoint.write(BufWriter::new(file)).expect("unable to write checkpoint");
+    fs::rename(&temporary_path, path).expect("unable to replace checkpoint");
+}
+
+/// [`tick_and_output_gif`] for worlds that aren't a [`World`], such as the three dimensional and `f64` ones
+fn tick_and_output_gif_with<W, TF: FnMut(&mut W, f32, NonZeroU16) -> u16, MPG: FnMut(&W) -> SimulatedFrame, EG: FnMut(&W) -> Option<f32>, Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(mut world: W, mut tick_function: TF, mut mass_point_getter: MPG, mut energy_getter: EG, frames: Range<usize>, name: &str, params: &SimParams) -> W {
     let mut periodic_logger = PeriodicLogger::new(&format!("simulating {}", name), Level::Info);
-    let mut mass_position_frames = Vec::with_capacity(FRAME_COUNT);
-    for frame in 0..FRAME_COUNT {
-        tick_function(&mut world, TIME_PER_FRAME, TIME_STEPS);
-        mass_position_frames.push(mass_point_getter(&world));
-        periodic_logger.log(format!("{} / {}", frame, FRAME_COUNT));
-    }
-    output_gif::<Rasterizer>(mass_position_frames, name);
-}
-
-fn output_gif<Rasterizer: crate::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, GrayscaleRgbScalar>>(mass_position_frames: Vec<Vec<MassPoint>>, name: &str) {
-    let mut bounds_x;
-    let mut bounds_y;
-    let mut bounds_mass;
-    if let Some((width, height)) = SIZE {
-        let w = (width - 1.0) / 2.0 / SCALE;
-        let h = (height - 1.0) / 2.0 / SCALE;
-        bounds_x = -w..w;
-        bounds_y = -h..h;
-        bounds_mass = 0.0..1000.0;
-    } else {
-        {
-            let MassPoint { mass, position: (x, y) } = mass_position_frames[0][0];
-            bounds_x = x..x;
-            bounds_y = y..y;
-            bounds_mass = mass..mass;
-        }
-        for mass_positions in &mass_position_frames {
-            for mass_position in mass_positions {
-                let MassPoint { mass, position: (x, y) } = *mass_position;
-                adjust_bounds(&mut bounds_mass, mass);
-                adjust_bounds(&mut bounds_x, x);
-                adjust_bounds(&mut bounds_y, y);
-            }
-        }
-    }
-
-
-    let width = ((bounds_x.end - bounds_x.start) * SCALE) as u32 + 1;
-    let height = ((bounds_y.end - bounds_y.start) * SCALE) as u32 + 1;
-    let mut gif_handler = GifHandler::new(
-        width, height,
-        [0, 0, 0, 255].into(),
-        File::create(format!("output/{}.gif", name)).expect("unable to create file")
-    );
-    let mut periodic_logger = PeriodicLogger::new(&format!("exporting {}", name), Level::Info);
-    for (frame, mass_positions) in mass_position_frames.iter().enumerate() {
-        let mut image = gif_handler.produce();
+    let tick = |frame: usize| {
+        let substeps = tick_function(&mut world, params.time_per_frame, params.time_steps);
+        let simulated_frame = mass_point_getter(&world);
+        match energy_getter(&world) {
+            Some(energy) => periodic_logger.log(format!("{} / {} ({} substeps, energy {:e})", frame, params.frame_count, substeps, energy)),
+            None => periodic_logger.log(format!("{} / {} ({} substeps)", frame, params.frame_count, substeps))
+        }
+        simulated_frame
+    };
+    let first_frame = frames.start;
+    output_frames::<Rasterizer>(frames.map(tick), first_frame, name, params, world_name::<W>(), None);
+    world
+}
+
+/// mass points of a frame of a run
+struct SimulatedFrame {
+    mass_positions: Vec<MassPoint>,
+    /// those after every substep of the frame with [`MOTION_BLUR`], oldest first, otherwise none
+    substeps: Vec<Vec<MassPoint>>
+}
+
+impl SimulatedFrame {
+    /// moves and zooms the mass points of the frame and of its substeps as `framing` says, returns how
+    fn frame(&mut self, framing: &mut Framing) -> View {
+        let view = framing.frame(&mut self.mass_positions);
+        for mass_positions in &mut self.substeps {
+            view.apply(mass_positions);
+        }
+        view
+    }
+}
+
+impl From<Vec<MassPoint>> for SimulatedFrame {
+    fn from(mass_positions: Vec<MassPoint>) -> Self {
+        Self { mass_positions, substeps: Vec::new() }
+    }
+}
+
+/// draws every frame of `mass_point_frames` into `<name>.gif`, the first of which is frame `first_frame` of the run,
+/// with [`GPU_RASTERIZATION`] on `device` when there is one
+fn output_frames<Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(mass_point_frames: impl Iterator<Item = SimulatedFrame>, first_frame: usize, name: &str, params: &SimParams, world: &str, device: Option<SharedDevice>) {
+    match params.size {
+        // the bounds are known up front, so frames are drawn and encoded on other threads as soon as they are simulated
+        Some(size) if !COLORING.needs_every_frame() => {
+            let mut mass_point_frames = mass_point_frames.peekable();
+            let first_mass_points = mass_point_frames.peek().map_or(&[][..], |simulated_frame| simulated_frame.mass_positions.as_slice());
+            let comment = gif_comment(world, first_frame, first_mass_points.len(), params);
+            let mut gif_output = GifOutput::<Rasterizer>::new(Bounds::of_size(size, params.scale, first_mass_points), first_frame, name, params, &comment, device);
+            let mut framing = Framing::new(params);
+            gif_output.draw_all(mass_point_frames.map(|mut simulated_frame| {
+                let view = simulated_frame.frame(&mut framing);
+                (simulated_frame, view)
+            }));
+        }
+        // fitting the bounds or the speeds needs every frame
+        _ => output_gif::<Rasterizer>(mass_point_frames.collect(), first_frame, name, params, world, device)
+    }
+}
+
+/// frames simulated ahead of the gif rasterizer before the simulation waits for it
+const FRAME_BUFFER: usize = 8;
+
+fn output_gif<Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(mut simulated_frames: Vec<SimulatedFrame>, first_frame: usize, name: &str, params: &SimParams, world: &str, device: Option<SharedDevice>) {
+    let mut framing = Framing::new(params);
+    let views: Vec<_> = simulated_frames.iter_mut()
+        .map(|simulated_frame| simulated_frame.frame(&mut framing))
+        .collect();
+    let mass_position_frames: Vec<&[MassPoint]> = simulated_frames.iter()
+        .map(|simulated_frame| simulated_frame.mass_positions.as_slice())
+        .collect();
+    let bounds = match params.size {
+        Some(size) => Bounds::of_size(size, params.scale, mass_position_frames.first().copied().unwrap_or(&[])),
+        None => Bounds::fitting(&mass_position_frames, params.scale)
+    };
+    let comment = gif_comment(world, first_frame, mass_position_frames.first().map_or(0, |mass_positions| mass_positions.len()), params);
+    let mut gif_output = GifOutput::<Rasterizer>::new(bounds, first_frame, name, params, &comment, device);
+    if COLORING.needs_every_frame() {
+        gif_output.frame_painter.max_speed = Some(max_speed(mass_position_frames.iter().copied().flatten()));
+    }
+    gif_output.draw_all(simulated_frames.into_iter().zip(views));
+}
+
+/// what the center of every frame of the gifs is
+#[derive(Copy, Clone, Debug)]
+#[allow(dead_code)]
+enum Camera {
+    /// the origin
+    Fixed,
+    /// the center of mass of the frame
+    CenterOfMass,
+    /// the particle with this id, see [`Particle::id`], where it was last seen once it is removed,
+    /// and the center of mass until it is first seen
+    FollowParticle(u32)
+}
+
+/// zooms every frame so that its particles fill the gif, rather than keeping [`SimParams::scale`], the edges of the
+/// particles are smoothed over the frames so that the zoom doesn't jitter, and the aspect ratio is kept
+#[derive(Copy, Clone, Debug)]
+struct Autoscale {
+    /// weight of the edges of each frame in the smoothed edges, 1 doesn't smooth at all
+    smoothing: f32,
+    /// room left around the smoothed edges, relative to their size
+    margin: f32
+}
+
+/// moves and zooms the mass points of frames as [`CAMERA`] and [`AUTOSCALE`] say, frames have to come in order
+struct Framing {
+    /// [`CAMERA`]
+    camera: Camera,
+    /// [`AUTOSCALE`]
+    autoscale: Option<Autoscale>,
+    /// see [`follow_camera`]
+    last_seen: Option<(f32, f32)>,
+    /// half the width and height of the gif in units of distance at [`SimParams::scale`], `None` for `--size fit`
+    viewport: Option<(f32, f32)>,
+    /// x and y edges of the particles of the frames so far, smoothed, after following the camera
+    edges: Option<(Range<f32>, Range<f32>)>
+}
+
+impl Framing {
+    fn new(params: &SimParams) -> Self {
+        let viewport = params.size.map(|size| {
+            let Bounds { x, y, .. } = Bounds::of_size(size, params.scale, &[]);
+            (x.end, y.end)
+        });
+        Self {
+            camera: CAMERA,
+            autoscale: AUTOSCALE,
+            last_seen: None,
+            viewport,
+            edges: None
+        }
+    }
+
+    /// moves and zooms `mass_positions`, returns where in the world they were moved from and how far they were zoomed
+    fn frame(&mut self, mass_positions: &mut [MassPoint]) -> View {
+        let camera = follow_camera(self.camera, mass_positions, &mut self.last_seen);
+        let unzoomed = View { origin: camera, zoom: 1.0 };
+        let (autoscale, (half_width, half_height)) = match (self.autoscale, self.viewport) {
+            (Some(autoscale), Some(viewport)) => (autoscale, viewport),
+            _ => return unzoomed
+        };
+        let (x, y) = match diagnostics::bounds(mass_positions, |_| 0.0) {
+            Some(edges) => edges,
+            None => return unzoomed
+        };
+        let smooth = |smoothed: &Range<f32>, edges: Range<f32>| {
+            smoothed.start + (edges.start - smoothed.start) * autoscale.smoothing..smoothed.end + (edges.end - smoothed.end) * autoscale.smoothing
+        };
+        let (x, y) = match self.edges.take() {
+            Some((smoothed_x, smoothed_y)) => (smooth(&smoothed_x, x), smooth(&smoothed_y, y)),
+            None => (x, y)
+        };
+        // the camera has already put its center at the origin
+        let (cx, cy, extent_x, extent_y) = match self.camera {
+            Camera::Fixed => ((x.start + x.end) / 2.0, (y.start + y.end) / 2.0, (x.end - x.start) / 2.0, (y.end - y.start) / 2.0),
+            _ => (0.0, 0.0, x.start.abs().max(x.end.abs()), y.start.abs().max(y.end.abs()))
+        };
+        self.edges = Some((x, y));
+        // a single particle, or particles on a line, fill the gif in the other direction
+        let zoom = f32::min(half_width / extent_x, half_height / extent_y) / (1.0 + autoscale.margin);
+        if !zoom.is_finite() {
+            return unzoomed
+        }
         for mass_position in mass_positions {
-            let MassPoint { mass, position: (x, y) } = mass_position;
-            let px = (x - bounds_x.start) * SCALE;
-            let py = (y - bounds_y.start) * SCALE;
-            //let m = ((1.0 - mass / bounds_mass.end) * 255.0) as u8;
-            Rasterizer::draw_filled_circle(
-                &mut image,
-                px, py,
-                f32::cbrt(3.0 * mass / 4.0 * PI),
-                [255, 255, 255, 255].into()
-            );
+            mass_position.position = ((mass_position.position.0 - cx) * zoom, (mass_position.position.1 - cy) * zoom);
+            mass_position.velocity = (mass_position.velocity.0 * zoom, mass_position.velocity.1 * zoom);
         }
-        gif_handler.consume(image);
-        periodic_logger.log(format!("{} / {}", frame, FRAME_COUNT));
+        View { origin: (camera.0 + cx, camera.1 + cy), zoom }
     }
 }
 
-fn adjust_bounds(bounds: &mut Range<f32>, v: f32) {
-    if v < bounds.start {
-        bounds.start = v;
-    } else if v > bounds.end {
-        bounds.end = v;
+/// how [`Framing`] moved and zoomed the mass points of a frame, a position `p` of the world is drawn at
+/// `(p - origin) * zoom`
+#[derive(Copy, Clone, Debug)]
+struct View {
+    origin: (f32, f32),
+    zoom: f32
+}
+
+impl View {
+    /// moves and zooms `mass_positions` as [`Framing`] did those of the frame
+    fn apply(&self, mass_positions: &mut [MassPoint]) {
+        let View { origin: (ox, oy), zoom } = *self;
+        for mass_position in mass_positions {
+            mass_position.position = ((mass_position.position.0 - ox) * zoom, (mass_position.position.1 - oy) * zoom);
+            mass_position.velocity = (mass_position.velocity.0 * zoom, mass_position.velocity.1 * zoom);
+        }
     }
 }
 
-#[deny(dead_code)]
-fn initialize_logging() {
-    let stdout = ConsoleAppender::builder()
-        .encoder(Box::new(PatternEncoder::new("{m}{n}")))
-        .build();
+impl Default for View {
+    fn default() -> Self {
+        Self { origin: (0.0, 0.0), zoom: 1.0 }
+    }
+}
 
-    let config = Config::builder()
-        .appender(Appender::builder().build("stdout", Box::new(stdout)))
-        .logger(Logger::builder().build("app::backend::db", LevelFilter::Info))
-        .build(Root::builder().appender("stdout").build(LevelFilter::Info))
-        .unwrap();
+/// moves `mass_positions` so that the center `camera` picks for them is at the origin, and returns that center,
+/// `last_seen` is where the followed particle was in the frames before, which have to come in order
+fn follow_camera(camera: Camera, mass_positions: &mut [MassPoint], last_seen: &mut Option<(f32, f32)>) -> (f32, f32) {
+    let (cx, cy) = match camera {
+        Camera::Fixed => return (0.0, 0.0),
+        Camera::CenterOfMass => diagnostics::center_of_mass(mass_positions.iter().copied()),
+        Camera::FollowParticle(id) => {
+            if let Some(mass_position) = mass_positions.iter().find(|mass_position| mass_position.id == id) {
+                *last_seen = Some(mass_position.position);
+            }
+            last_seen.unwrap_or_else(|| diagnostics::center_of_mass(mass_positions.iter().copied()))
+        }
+    };
+    for mass_position in mass_positions {
+        mass_position.position.0 -= cx;
+        mass_position.position.1 -= cy;
+    }
+    (cx, cy)
+}
 
-    log4rs::init_config(config).unwrap();
+/// area of the world drawn to the gif
+struct Bounds {
+    x: Range<f32>,
+    y: Range<f32>,
+    /// masses [`MASS_BRIGHTNESS`] scales the brightness of particles between
+    mass: Range<f32>
+}
+
+impl Bounds {
+    /// `width` by `height` pixels at `scale`, centered on the origin, with the masses of `first_frame`
+    ///
+    /// later frames aren't known yet, masses outside of those of the first frame, such as of merged particles,
+    /// are drawn as the lightest or heaviest of it
+    fn of_size((width, height): (f32, f32), scale: f32, first_frame: &[MassPoint]) -> Self {
+        let w = (width - 1.0) / 2.0 / scale;
+        let h = (height - 1.0) / 2.0 / scale;
+        let mass = match first_frame.first() {
+            Some(&MassPoint { mass, .. }) => first_frame.iter().fold(mass..mass, |mut masses, mass_position| {
+                adjust_bounds(&mut masses, mass_position.mass);
+                masses
+            }),
+            None => 0.0..0.0
+        };
+        Self {
+            x: -w..w,
+            y: -h..h,
+            mass
+        }
+    }
+
+    /// the smallest bounds containing the drawn circle of every mass point of every frame, so that
+    /// circles at the edge aren't cut off, `scale` is the one they are drawn at
+    fn fitting(mass_position_frames: &[&[MassPoint]], scale: f32) -> Self {
+        let MassPoint { mass, .. } = mass_position_frames[0][0];
+        let mut bounds = Self {
+            x: f32::INFINITY..f32::NEG_INFINITY,
+            y: f32::INFINITY..f32::NEG_INFINITY,
+            mass: mass..mass
+        };
+        for mass_positions in mass_position_frames {
+            if let Some((x, y)) = diagnostics::bounds(mass_positions, |mass_position| drawn_radius(mass_position.mass, scale)) {
+                bounds.x = bounds.x.start.min(x.start)..bounds.x.end.max(x.end);
+                bounds.y = bounds.y.start.min(y.start)..bounds.y.end.max(y.end);
+            }
+            for mass_position in mass_positions.iter() {
+                adjust_bounds(&mut bounds.mass, mass_position.mass);
+            }
+        }
+        bounds
+    }
+}
+
+/// how the particles of the gifs are drawn
+#[derive(Copy, Clone, Debug)]
+#[allow(dead_code)]
+enum Rendering {
+    /// a circle per particle, see [`COLORING`]
+    Circles,
+    /// the mass of every bin of pixels, without [`COLORING`], [`MASS_BRIGHTNESS`] or [`TRAILS`]
+    Heatmap(Heatmap)
+}
+
+/// mass of the particles binned into square bins of pixels and drawn in `colormap`, from its first color where there is
+/// no mass to its last at the bin with the most mass of each frame
+#[derive(Copy, Clone, Debug)]
+struct Heatmap {
+    /// width and height of a bin in pixels
+    bin_size: u32,
+    /// spreads the mass of every bin over the bins around it with a gaussian of this many bins
+    splat: Option<f32>,
+    colormap: Colormap
+}
+
+/// how the particles of the gifs are colored
+#[derive(Copy, Clone, Debug)]
+#[allow(dead_code)]
+enum Coloring {
+    /// by group, see [`PALETTE`]
+    Group,
+    /// by speed, from the first color of `colormap` when at rest to its last at the speed `scale` picks
+    Speed { colormap: Colormap, scale: SpeedScale }
+}
+
+impl Coloring {
+    /// whether every frame has to be simulated before the first is drawn
+    fn needs_every_frame(self) -> bool {
+        matches!(self, Coloring::Speed { scale: SpeedScale::Run, .. })
+    }
+}
+
+/// speed that [`Coloring::Speed`] draws in the last color of its colormap
+#[derive(Copy, Clone, Debug)]
+#[allow(dead_code)]
+enum SpeedScale {
+    /// that of the fastest particle of each frame
+    Frame,
+    /// that of the fastest particle of the whole run
+    Run
+}
+
+/// speed of the fastest of `mass_points`, 0 when there are none
+fn max_speed<'a>(mass_points: impl IntoIterator<Item = &'a MassPoint>) -> f32 {
+    mass_points.into_iter().fold(0.0, |max, mass_point| max.max(mass_point.speed()))
+}
+
+/// dots left behind every particle at each of its last `length` positions, one frame apart
+#[derive(Copy, Clone, Debug)]
+struct Trails {
+    length: usize,
+    /// brightness of a dot relative to that of the dot a frame newer, the particle itself being at 1
+    fade: f32
+}
+
+/// largest radius of the dots of [`TRAILS`] in pixels, those of smaller particles are as large as the particles
+const TRAIL_RADIUS: f32 = 1.5;
+
+/// lines from the center of every particle along its velocity, drawn over the particles in [`ARROW_PAINT`]
+#[derive(Copy, Clone, Debug)]
+struct VelocityArrows {
+    /// pixels of length per unit of speed
+    scale: f32,
+    /// longest line in pixels, longer ones are shortened to it
+    max_length: f32
+}
+
+const ARROW_PAINT: [u8; 4] = [255, 64, 64, 255];
+
+/// arrows shorter than this in pixels are left out, as they would be a pixel or no direction at all
+const MIN_ARROW_LENGTH: f32 = 1.0;
+
+/// timing of the animated png of [`APNG`]
+#[derive(Copy, Clone, Debug)]
+struct Apng {
+    /// seconds each frame is shown for, as a numerator and denominator
+    delay: (u16, u16),
+    /// times the animation is played, forever when 0
+    plays: u32
+}
+
+/// the video of [`VIDEO`]
+#[derive(Copy, Clone, Debug)]
+struct Video {
+    /// mp4 for h.264 or webm for vp9
+    extension: &'static str,
+    frame_rate: u32
+}
+
+/// lines at whole multiples of `spacing` along both axes of the world, drawn under the particles in [`GRID_PAINT`],
+/// and the axes themselves in [`GRID_AXIS_PAINT`], the heatmap of [`Rendering::Heatmap`] covers them
+#[derive(Copy, Clone, Debug)]
+struct Grid {
+    /// units of distance between lines when the frames aren't zoomed by [`AUTOSCALE`]
+    spacing: f32
+}
+
+impl Grid {
+    /// spacing of the lines of a frame zoomed by `zoom`, the one of `spacing` times 1, 2 or 5 times a power of 10
+    /// nearest to `spacing / zoom`, so that the lines stay about as far apart in pixels as they would unzoomed
+    fn spacing(self, zoom: f32) -> f32 {
+        const STEPS: [f32; 4] = [1.0, 2.0, 5.0, 10.0];
+        let decades = (1.0 / zoom).log10();
+        let decade = decades.floor();
+        // of the steps within the decade, the nearest on a logarithmic scale
+        let distance = |step: f32| (step.log10() - (decades - decade)).abs();
+        let step = STEPS.into_iter()
+            .min_by(|a, b| distance(*a).total_cmp(&distance(*b)))
+            .unwrap();
+        self.spacing * step * 10f32.powi(decade as i32)
+    }
+}
+
+const GRID_PAINT: [u8; 4] = [40, 40, 40, 255];
+const GRID_AXIS_PAINT: [u8; 4] = [96, 96, 96, 255];
+
+/// text written into a corner of every frame by [`FramePainter::draw_overlay`]
+#[derive(Copy, Clone, Debug)]
+struct Overlay {
+    corner: Corner,
+    /// pixels per pixel of the font, which is 5 pixels high
+    scale: u32
+}
+
+#[derive(Copy, Clone, Debug)]
+#[allow(dead_code)]
+enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight
+}
+
+const OVERLAY_PAINT: [u8; 4] = [255, 255, 255, 255];
+/// strip under the text of the [`OVERLAY`], so that it stays readable over particles
+const OVERLAY_BACKGROUND: [u8; 4] = [32, 32, 32, 255];
+
+/// how [`MASS_BRIGHTNESS`] maps the masses of [`Bounds::mass`] to the brightness of particles
+#[derive(Copy, Clone, Debug)]
+#[allow(dead_code)]
+enum MassScale {
+    Linear,
+    /// logarithmic, for masses spanning orders of magnitude
+    Log
+}
+
+impl MassScale {
+    /// brightness of a particle of `mass`, from [`MIN_BRIGHTNESS`] at the start of `masses` to 1 at its end,
+    /// masses outside of it are clamped, 1 when every mass is the same
+    fn brightness(self, mass: f32, masses: &Range<f32>) -> f32 {
+        if masses.end <= masses.start {
+            return 1.0
+        }
+        let t = match self {
+            MassScale::Linear => (mass - masses.start) / (masses.end - masses.start),
+            // of 1 + mass rather than mass, so that massless particles aren't at negative infinity
+            MassScale::Log => (mass.ln_1p() - masses.start.ln_1p()) / (masses.end.ln_1p() - masses.start.ln_1p())
+        };
+        MIN_BRIGHTNESS + (1.0 - MIN_BRIGHTNESS) * t.clamp(0.0, 1.0)
+    }
+}
+
+/// brightness of the lightest particles with [`MASS_BRIGHTNESS`], so that they stay visible
+const MIN_BRIGHTNESS: f32 = 0.25;
+
+/// color each group of particles is drawn in, see [`Particle::group`], groups past the end wrap around
+///
+/// every color has some of each channel, so that every group still shows up in each of the gifs
+/// [`compare_outputs`] merges a single channel of, only dimmer
+const PALETTE: &[[u8; 4]] = &[
+    [255, 255, 255, 255],
+    [255, 160, 64, 255],
+    [64, 160, 255, 255],
+    [128, 255, 128, 255]
+];
+
+/// encodes the frames of a [`GifOutput`], which draws them with `Rasterizer`
+type GifRenderer<Rasterizer> = CPURenderer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar, GifHandler<File>, Rasterizer>;
+
+/// draws frames of mass points into a gif
+struct GifOutput<Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>> {
+    frame_painter: FramePainter,
+    /// encodes the frames [`frame_painter`](Self::frame_painter) draws with `Rasterizer` into the gif
+    renderer: GifRenderer<Rasterizer>,
+    /// frames the encoder is done with, cleared and drawn over again rather than allocating every frame
+    frame_buffers: Mutex<Vec<HorizontalLineImage<Rgba<u8>, Vec<u8>>>>,
+    /// every frame drawn by [`draw_all`](Self::draw_all) summed, with [`LONG_EXPOSURE`]
+    exposure: Option<ExposureHandler>,
+    /// with [`PNG_FRAMES`], `None` if its directory couldn't be created
+    png_sequence: Option<PngSequenceHandler>,
+    /// with [`APNG`], `None` if it couldn't be created
+    apng_handler: Option<ApngHandler<BufWriter<File>>>,
+    /// with [`VIDEO`], `None` if ffmpeg couldn't be started
+    video_handler: Option<FfmpegPipeHandler>,
+    /// with [`SimParams::preview`], `None` if the window couldn't be opened or once it is closed
+    #[cfg(feature = "preview")]
+    preview: Option<PreviewWindow>,
+    name: String,
+    periodic_logger: PeriodicLogger,
+    frame: usize,
+    frame_count: usize
+}
+
+impl<Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>> GifOutput<Rasterizer> {
+    /// the first frame drawn is frame `first_frame` of the run, `comment` is written into the gif, see [`gif_comment`]
+    fn new(bounds: Bounds, first_frame: usize, name: &str, params: &SimParams, comment: &str, device: Option<SharedDevice>) -> Self {
+        let width = ((bounds.x.end - bounds.x.start) * params.scale) as u32 + 1;
+        let height = ((bounds.y.end - bounds.y.start) * params.scale) as u32 + 1;
+        let background: Rgba<u8> = [0, 0, 0, 255].into();
+        let mut renderer = CPURenderer::new(GifHandler::with_comment(
+            width, height,
+            background,
+            frame_delay(params),
+            File::create(format!("{}.gif", name)).expect("unable to create file"),
+            comment
+        ));
+        let png_sequence = match PNG_FRAMES.then(|| PngSequenceHandler::new(width, height, background, name)) {
+            Some(Ok(png_sequence)) => Some(png_sequence),
+            Some(Err(error)) => {
+                warn!("leaving out the png frames of {}: {}", name, error);
+                None
+            }
+            None => None
+        };
+        let apng_handler = APNG.and_then(|Apng { delay, plays }| {
+            let frame_count = (params.frame_count - first_frame) as u32;
+            let apng_handler = File::create(format!("{}.png", name))
+                .map_err(png::EncodingError::from)
+                .and_then(|file| ApngHandler::new(width, height, background, BufWriter::new(file), frame_count, delay, plays));
+            match apng_handler {
+                Ok(apng_handler) => Some(apng_handler),
+                Err(error) => {
+                    warn!("leaving out {}.png: {}", name, error);
+                    None
+                }
+            }
+        });
+        let video_handler = VIDEO.and_then(|Video { extension, frame_rate }| {
+            match FfmpegPipeHandler::new(width, height, background, format!("{}.{}", name, extension), frame_rate) {
+                Ok(video_handler) => Some(video_handler),
+                Err(error) => {
+                    warn!("leaving out {}.{}: {}", name, extension, error);
+                    None
+                }
+            }
+        });
+        let gpu_rasterizer = GPU_RASTERIZATION.then(|| {
+            // the circles are drawn onto the supersampled image
+            let (width, height) = (width * params.supersampling, height * params.supersampling);
+            match device {
+                Some((device, queue)) => GPURasterizer::with_device(device, queue, width, height),
+                None => GPURasterizer::new_on(&params.device, width, height)
+            }
+        });
+        let gpu_rasterizer = match gpu_rasterizer {
+            Some(Ok(gpu_rasterizer)) => Some(Mutex::new(gpu_rasterizer)),
+            Some(Err(error)) => {
+                warn!("drawing {} on the cpu: {}", name, error);
+                None
+            }
+            None => None
+        };
+        #[cfg(feature = "preview")]
+        let preview = match params.preview.then(|| PreviewWindow::new(&format!("{}.gif", name), width, height)) {
+            Some(Ok(preview)) => Some(preview),
+            Some(Err(error)) => {
+                warn!("not previewing {}: {}", name, error);
+                None
+            }
+            None => None
+        };
+        Self {
+            frame_painter: FramePainter {
+                viewport: Viewport::new((bounds.x.start, bounds.y.start), params.scale, width, height),
+                bounds,
+                image_offsets: image_offsets(BOUNDARY),
+                blank: renderer.frame_handler_mut().produce(),
+                background,
+                grid: GRID,
+                rendering: RENDERING,
+                coloring: COLORING,
+                max_speed: None,
+                mass_brightness: MASS_BRIGHTNESS,
+                trails: TRAILS,
+                velocity_arrows: VELOCITY_ARROWS,
+                overlay: OVERLAY,
+                time_per_frame: params.time_per_frame,
+                frame_count: params.frame_count,
+                supersampling: params.supersampling,
+                gpu_rasterizer
+            },
+            renderer,
+            frame_buffers: Mutex::new(Vec::new()),
+            exposure: LONG_EXPOSURE.map(|_| ExposureHandler::new(width, height)),
+            png_sequence,
+            apng_handler,
+            video_handler,
+            #[cfg(feature = "preview")]
+            preview,
+            name: name.to_string(),
+            periodic_logger: PeriodicLogger::new(&format!("exporting {}", name), Level::Info),
+            frame: first_frame,
+            frame_count: params.frame_count
+        }
+    }
+
+    /// draws every frame of `mass_point_frames` as they come, in order, up to [`FRAMES_IN_FLIGHT`] of them are
+    /// rasterized at once on the global rayon thread pool while those before them are encoded on another thread
+    ///
+    /// the frames of the [`TRAILS`] of each frame are sent along with it, as the frames are rasterized out of order
+    ///
+    /// with a [`preview`](Self::preview), the next frame isn't taken while it is paused, and none are once it is
+    /// closed, those already taken are still drawn
+    fn draw_all(&mut self, mass_point_frames: impl Iterator<Item = (SimulatedFrame, View)>) {
+        thread::scope(|scope| {
+            // the frames encoded, for the preview to show the latest of
+            #[cfg(feature = "preview")]
+            let (preview_sender, preview_receiver) = mpsc::channel();
+            #[cfg(feature = "preview")]
+            let (preview_sender, preview) = (self.preview.is_some().then_some(preview_sender), &mut self.preview);
+            let (frame_sender, frame_receiver) = mpsc::sync_channel::<(usize, View, Arc<Vec<MassPoint>>, Vec<Vec<MassPoint>>, Vec<Arc<Vec<MassPoint>>>)>(FRAME_BUFFER);
+            let (image_sender, image_receiver) = mpsc::sync_channel(FRAMES_IN_FLIGHT);
+            let (frame_painter, frame_buffers) = (&self.frame_painter, &self.frame_buffers);
+            scope.spawn(move || {
+                // waits for a frame, then rasterizes it along with every other frame that has come in by then
+                while let Ok(frame) = frame_receiver.recv() {
+                    let batch: Vec<_> = iter::once(frame)
+                        .chain(frame_receiver.try_iter().take(FRAMES_IN_FLIGHT - 1))
+                        .collect();
+                    let images: Vec<_> = batch.par_iter()
+                        .map(|(frame, view, mass_positions, substeps, trail)| {
+                            let image = frame_painter.blank_frame(frame_buffers.lock().unwrap().pop());
+                            frame_painter.paint::<Rasterizer>(image, mass_positions, substeps, trail, *frame, *view)
+                        })
+                        .collect();
+                    for image in images {
+                        image_sender.send(image).expect("gif encoder stopped");
+                    }
+                }
+            });
+            let first_frame = self.frame;
+            let (gif_handler, exposure, png_sequence, apng_handler, video_handler, periodic_logger, frame, frame_count) = (self.renderer.frame_handler_mut(), &mut self.exposure, &mut self.png_sequence, &mut self.apng_handler, &mut self.video_handler, &mut self.periodic_logger, &mut self.frame, self.frame_count);
+            scope.spawn(move || {
+                for mut image in image_receiver {
+                    if let Some(exposure) = exposure {
+                        exposure.accumulate(&image);
+                    }
+                    if let Some(png_sequence) = png_sequence {
+                        png_sequence.consume(image.clone());
+                    }
+                    if let Some(apng_handler) = apng_handler {
+                        apng_handler.consume(image.clone());
+                    }
+                    if let Some(video_handler) = video_handler {
+                        video_handler.consume(image.clone());
+                    }
+                    #[cfg(feature = "preview")]
+                    if let Some(preview_sender) = &preview_sender {
+                        // which fails once the preview is closed
+                        let _ = preview_sender.send(image.clone());
+                    }
+                    gif_handler.encode(&mut image);
+                    frame_buffers.lock().unwrap().push(image);
+                    periodic_logger.log(format!("{} / {}", frame, frame_count));
+                    *frame += 1;
+                }
+            });
+            // the frames before the current one, oldest first
+            let mut trail = VecDeque::new();
+            for (frame, (SimulatedFrame { mass_positions, substeps }, view)) in (first_frame..).zip(mass_point_frames) {
+                #[cfg(feature = "preview")]
+                if let Some(window) = preview.as_mut() {
+                    if !update_preview(window, &preview_receiver) {
+                        info!("stopping {} at frame {}, its preview was closed", self.name, frame);
+                        *preview = None;
+                        break
+                    }
+                }
+                let mass_positions = Arc::new(mass_positions);
+                frame_sender.send((frame, view, mass_positions.clone(), substeps, trail.iter().cloned().collect())).expect("gif rasterizer stopped");
+                if let Some(Trails { length, .. }) = frame_painter.trails {
+                    if trail.len() == length {
+                        trail.pop_front();
+                    }
+                    if length > 0 {
+                        trail.push_back(mass_positions);
+                    }
+                }
+            }
+            // keeps showing the frames still being drawn until the encoder is done with them
+            #[cfg(feature = "preview")]
+            if let Some(window) = preview.as_mut() {
+                drop(frame_sender);
+                loop {
+                    match preview_receiver.recv_timeout(PREVIEW_POLL) {
+                        Ok(image) => window.show(&image),
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break
+                    }
+                    if !window.update() {
+                        break
+                    }
+                }
+            }
+        });
+        if let (Some(exposure), Some(tone_map)) = (&self.exposure, LONG_EXPOSURE) {
+            exposure.to_image(tone_map).save(format!("{}_exposure.png", self.name)).expect("unable to write long exposure");
+        }
+        self.finish_png_sequence();
+        if let Some(Err(error)) = self.apng_handler.take().map(ApngHandler::finish) {
+            warn!("unable to finish {}.png: {}", self.name, error);
+        }
+        if let (Some(Err(error)), Some(Video { extension, .. })) = (self.video_handler.take().map(FfmpegPipeHandler::finish), VIDEO) {
+            warn!("unable to finish {}.{}: {}", self.name, extension, error);
+        }
+    }
+
+    /// logs the error that stopped the png frames, if any, frames drawn after this aren't written
+    fn finish_png_sequence(&mut self) {
+        if let Some(Err(error)) = self.png_sequence.take().map(PngSequenceHandler::finish) {
+            warn!("unable to write every png frame of {}: {}", self.name, error);
+        }
+    }
+}
+
+/// how long each frame of the gifs is shown for, see [`SimParams::frame_rate`]
+fn frame_delay(params: &SimParams) -> Delay {
+    // gifs truncate to hundredths of a second, so they are rounded here instead, a delay of 0 is up to the viewer
+    let hundredths = (100.0 / params.frame_rate).round().max(1.0) as u32;
+    Delay::from_numer_denom_ms(hundredths * 10, 1)
+}
+
+/// frames rasterized at once by [`GifOutput::draw_all`], at most as many again wait for the encoder
+const FRAMES_IN_FLIGHT: usize = 16;
+
+/// how often a [`PreviewWindow`] handles its events while nothing else is happening
+#[cfg(feature = "preview")]
+const PREVIEW_POLL: Duration = Duration::from_millis(16);
+
+/// shows the latest of `images` in `preview` and handles its events, waiting for as long as it is paused,
+/// returns whether it is still open
+#[cfg(feature = "preview")]
+fn update_preview(preview: &mut PreviewWindow, images: &Receiver<HorizontalLineImage<Rgba<u8>, Vec<u8>>>) -> bool {
+    loop {
+        if let Some(image) = images.try_iter().last() {
+            preview.show(&image);
+        }
+        if !preview.update() {
+            return false
+        }
+        if !preview.paused() {
+            return true
+        }
+        thread::sleep(PREVIEW_POLL);
+    }
+}
+
+/// how far the copies of every particle drawn in a world of `boundary` are moved, only periodic domains draw more
+/// than the particle itself, once in each of the eight domains around it, so that circles straddling an edge show up
+/// on the opposite side too
+fn image_offsets(boundary: Boundary) -> Vec<(f32, f32)> {
+    match boundary {
+        Boundary::Open | Boundary::Reflective { .. } => vec![(0.0, 0.0)],
+        Boundary::Periodic { width, height } => {
+            let mut offsets = Vec::with_capacity(9);
+            for oy in [-height, 0.0, height] {
+                for ox in [-width, 0.0, width] {
+                    offsets.push((ox, oy));
+                }
+            }
+            offsets
+        }
+    }
+}
+
+/// rasterizes frames of mass points, apart from the encoder so that several can be rasterized at once
+struct FramePainter {
+    /// where the particles of the frames are drawn, framed by their [`View`]
+    viewport: Viewport,
+    bounds: Bounds,
+    /// circles straddling an edge of a periodic domain are drawn again on the opposite side, these are how far
+    /// each copy is moved in the world
+    image_offsets: Vec<(f32, f32)>,
+    /// background every frame is drawn onto
+    blank: HorizontalLineImage<Rgba<u8>, Vec<u8>>,
+    /// color of every pixel of `blank`
+    background: Rgba<u8>,
+    /// [`GRID`]
+    grid: Option<Grid>,
+    /// [`RENDERING`]
+    rendering: Rendering,
+    /// [`COLORING`]
+    coloring: Coloring,
+    /// speed drawn in the last color of [`Coloring::Speed`], that of the fastest particle of each frame when `None`
+    max_speed: Option<f32>,
+    /// [`MASS_BRIGHTNESS`]
+    mass_brightness: Option<MassScale>,
+    /// [`TRAILS`]
+    trails: Option<Trails>,
+    /// [`VELOCITY_ARROWS`]
+    velocity_arrows: Option<VelocityArrows>,
+    /// [`OVERLAY`]
+    overlay: Option<Overlay>,
+    /// written by the [`overlay`](Self::overlay)
+    time_per_frame: f32,
+    frame_count: usize,
+    /// [`SimParams::supersampling`]
+    supersampling: u32,
+    /// draws the circles with [`GPU_RASTERIZATION`], shared by the frames drawn at once
+    gpu_rasterizer: Option<Mutex<GPURasterizer>>
+}
+
+impl FramePainter {
+    /// a frame of only the background, `recycled` filled with it when given rather than a new copy of `blank`
+    fn blank_frame(&self, recycled: Option<HorizontalLineImage<Rgba<u8>, Vec<u8>>>) -> HorizontalLineImage<Rgba<u8>, Vec<u8>> {
+        match recycled {
+            Some(mut image) => {
+                bytemuck::cast_slice_mut::<u8, [u8; 4]>(image.as_raw_mut()).fill(self.background.0);
+                image
+            }
+            None => self.blank.clone()
+        }
+    }
+
+    /// draws `mass_positions`, which are those of frame `frame` of the run framed as `view` says, or their `substeps`
+    /// when there are any, over the [`TRAILS`] of the frames of `trail`, which are oldest first, or the heatmap of
+    /// `mass_positions` with [`Rendering::Heatmap`], onto `image`, a [`blank_frame`](Self::blank_frame)
+    fn paint<Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(&self, mut image: HorizontalLineImage<Rgba<u8>, Vec<u8>>, mass_positions: &[MassPoint], substeps: &[Vec<MassPoint>], trail: &[Arc<Vec<MassPoint>>], frame: usize, view: View) -> HorizontalLineImage<Rgba<u8>, Vec<u8>> {
+        if let Some(grid) = self.grid {
+            self.draw_grid(&mut image, grid, view);
+        }
+        match self.rendering {
+            Rendering::Circles if self.supersampling > 1 => {
+                // the grid is made of whole pixels, which averaging copies of gives back exactly
+                let mut large = image.upscale(self.supersampling);
+                self.draw_circles::<Rasterizer>(&mut large, mass_positions, substeps, trail, self.supersampling as f32);
+                image = large.downscale(self.supersampling);
+            }
+            Rendering::Circles => self.draw_circles::<Rasterizer>(&mut image, mass_positions, substeps, trail, 1.0),
+            Rendering::Heatmap(heatmap) => self.draw_heatmap(&mut image, mass_positions, heatmap)
+        }
+        if let Some(arrows) = self.velocity_arrows {
+            self.draw_velocity_arrows(&mut image, mass_positions, arrows);
+        }
+        if let Some(overlay) = self.overlay {
+            // frame 0 is drawn after the first tick
+            let time = (frame + 1) as f32 * self.time_per_frame;
+            let text = format!("t = {}, frame {}/{}, n = {}", time, frame + 1, self.frame_count, mass_positions.len());
+            self.draw_overlay(&mut image, &text, overlay);
+        }
+        image
+    }
+
+    /// writes `text` on a strip of [`OVERLAY_BACKGROUND`] in the corner of `overlay`, cut off at the far edge of
+    /// frames too small to fit it
+    fn draw_overlay(&self, image: &mut HorizontalLineImage<Rgba<u8>, Vec<u8>>, text: &str, overlay: Overlay) {
+        let Overlay { corner, scale } = overlay;
+        let strip_width = text_width(text, scale) + 2 * scale;
+        let strip_height = (GLYPH_HEIGHT + 2) * scale;
+        let x = match corner {
+            Corner::TopLeft | Corner::BottomLeft => 0,
+            Corner::TopRight | Corner::BottomRight => self.viewport.width.saturating_sub(strip_width)
+        };
+        let y = match corner {
+            Corner::TopLeft | Corner::TopRight => 0,
+            Corner::BottomLeft | Corner::BottomRight => self.viewport.height.saturating_sub(strip_height)
+        };
+        fill_rectangle(image, x, y, x + strip_width, y + strip_height, OVERLAY_BACKGROUND.into());
+        draw_text(image, x + scale, y + scale, text, scale, OVERLAY_PAINT.into());
+    }
+
+    /// draws the lines of `grid` across the whole of `image`, at positions of the world framed as `view` says
+    fn draw_grid(&self, image: &mut HorizontalLineImage<Rgba<u8>, Vec<u8>>, grid: Grid, view: View) {
+        let spacing = grid.spacing(view.zoom);
+        let Viewport { scale, width, height, .. } = self.viewport;
+        // pixel `p` along an axis is at `p / scale + start` after framing, and so at `(p / scale + start) / zoom + origin` in the world
+        let lines = |start: f32, origin: f32, pixels: u32| {
+            let world = |pixel: f32| (pixel / scale + start) / view.zoom + origin;
+            let (first, last) = ((world(0.0) / spacing).ceil() as i64, (world(pixels as f32) / spacing).floor() as i64);
+            (first..=last).filter_map(move |i| {
+                let pixel = (((i as f32 * spacing - origin) * view.zoom - start) * scale).floor();
+                (0.0..pixels as f32).contains(&pixel).then_some((pixel as u32, i == 0))
+            })
+        };
+        // the axes last, so that the other lines don't cross them
+        for axes in [false, true] {
+            let paint = if axes { GRID_AXIS_PAINT } else { GRID_PAINT }.into();
+            for (x, axis) in lines(self.viewport.origin.0, view.origin.0, width) {
+                if axis == axes {
+                    fill_rectangle(image, x, 0, x + 1, height, paint);
+                }
+            }
+            for (y, axis) in lines(self.viewport.origin.1, view.origin.1, height) {
+                if axis == axes {
+                    fill_rectangle(image, 0, y, width, y + 1, paint);
+                }
+            }
+        }
+    }
+
+    fn draw_heatmap(&self, image: &mut HorizontalLineImage<Rgba<u8>, Vec<u8>>, mass_positions: &[MassPoint], heatmap: Heatmap) {
+        let mut grid = DensityGrid::new(self.viewport.width, self.viewport.height, heatmap.bin_size);
+        for mass_position in mass_positions {
+            let (x, y) = mass_position.position;
+            for (ox, oy) in &self.image_offsets {
+                grid.add(self.viewport.world_to_pixel(x + ox, y + oy), mass_position.mass);
+            }
+        }
+        if let Some(sigma) = heatmap.splat {
+            grid.splat(sigma);
+        }
+        grid.draw(image, heatmap.colormap, grid.max());
+    }
+
+    fn draw_velocity_arrows(&self, image: &mut HorizontalLineImage<Rgba<u8>, Vec<u8>>, mass_positions: &[MassPoint], arrows: VelocityArrows) {
+        for mass_position in mass_positions {
+            let length = (mass_position.speed() * arrows.scale).min(arrows.max_length);
+            if length < MIN_ARROW_LENGTH {
+                continue
+            }
+            let (vx, vy) = mass_position.velocity;
+            let (dx, dy) = (vx / mass_position.speed() * length, vy / mass_position.speed() * length);
+            let (x, y) = mass_position.position;
+            for (ox, oy) in &self.image_offsets {
+                let (px, py) = self.viewport.world_to_canvas(x + ox, y + oy);
+                draw_line(image, px, py, px + dx, py + dy, ARROW_PAINT.into());
+            }
+        }
+    }
+
+    /// draws `mass_positions`, or each of their `substeps` at a fraction of their brightness, over the [`TRAILS`] of the
+    /// frames of `trail`, onto an `image` `supersampling` times the size of the frame
+    fn draw_circles<Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(&self, image: &mut HorizontalLineImage<Rgba<u8>, Vec<u8>>, mass_positions: &[MassPoint], substeps: &[Vec<MassPoint>], trail: &[Arc<Vec<MassPoint>>], supersampling: f32) {
+        // the gpu draws circles over each other rather than adding them up, as the substeps have to be
+        let mut gpu_circles = self.gpu_rasterizer.as_ref().filter(|_| substeps.is_empty()).map(|_| Vec::new());
+        if let Some(Trails { fade, .. }) = self.trails {
+            for (i, past_mass_positions) in trail.iter().enumerate() {
+                let age = trail.len() - i;
+                let brightness = fade.powi(age as i32);
+                self.draw_mass_points::<Rasterizer>(image, past_mass_positions, |r| r.min(TRAIL_RADIUS), brightness, supersampling, gpu_circles.as_mut());
+            }
+        }
+        if substeps.is_empty() {
+            self.draw_mass_points::<Rasterizer>(image, mass_positions, |r| r, 1.0, supersampling, gpu_circles.as_mut());
+        } else {
+            let brightness = 1.0 / substeps.len() as f32;
+            for substep_mass_positions in substeps {
+                self.draw_mass_points::<MotionBlurRasterizer>(image, substep_mass_positions, |r| r, brightness, supersampling, None);
+            }
+        }
+        if let (Some(gpu_rasterizer), Some(circles)) = (&self.gpu_rasterizer, gpu_circles) {
+            gpu_rasterizer.lock().unwrap().draw(image, &circles);
+        }
+    }
+
+    /// draws each of `mass_positions` as a circle of `radius` of its drawn radius, at `brightness` of its color, onto
+    /// an `image` `supersampling` times the size of the frame, or adds the circles to `gpu_circles` to be drawn later
+    fn draw_mass_points<Rasterizer: newtonian_gravity::render::cpu::Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(&self, image: &mut HorizontalLineImage<Rgba<u8>, Vec<u8>>, mass_positions: &[MassPoint], radius: impl Fn(f32) -> f32, brightness: f32, supersampling: f32, mut gpu_circles: Option<&mut Vec<Circle>>) {
+        let max_speed = match (self.coloring, self.max_speed) {
+            (Coloring::Group, _) => 0.0,
+            (Coloring::Speed { .. }, Some(max_speed)) => max_speed,
+            (Coloring::Speed { .. }, None) => max_speed(mass_positions)
+        };
+        for mass_position in mass_positions {
+            let MassPoint { mass, position, group, .. } = mass_position;
+            let r = radius(pixel_radius(*mass)) * supersampling;
+            let mut paint: Rgba<u8> = match self.coloring {
+                Coloring::Group => PALETTE[*group as usize % PALETTE.len()],
+                // at rest in a frame where nothing moves, rather than dividing by 0
+                Coloring::Speed { colormap, .. } if max_speed == 0.0 => colormap.color(0.0),
+                Coloring::Speed { colormap, .. } => colormap.color(mass_position.speed() / max_speed)
+            }.into();
+            let brightness = match self.mass_brightness {
+                Some(mass_scale) => brightness * mass_scale.brightness(*mass, &self.bounds.mass),
+                None => brightness
+            };
+            if brightness != 1.0 {
+                paint = RgbScalar::scale(&paint, brightness, None);
+            }
+            for (ox, oy) in &self.image_offsets {
+                let (px, py) = self.viewport.world_to_canvas(position.0 + ox, position.1 + oy);
+                let (px, py) = (px * supersampling, py * supersampling);
+                let (width, height) = (self.viewport.width as f32 * supersampling, self.viewport.height as f32 * supersampling);
+                if px + r < 0.0 || py + r < 0.0 || px - r > width || py - r > height {
+                    continue
+                }
+                if let Some(gpu_circles) = &mut gpu_circles {
+                    gpu_circles.push(Circle::new(px, py, r, paint));
+                    continue
+                }
+                Rasterizer::draw_filled_circle(
+                    image,
+                    px, py,
+                    r,
+                    paint
+                );
+            }
+        }
+    }
+}
+
+fn adjust_bounds(bounds: &mut Range<f32>, v: f32) {
+    if v < bounds.start {
+        bounds.start = v;
+    } else if v > bounds.end {
+        bounds.end = v;
+    }
+}
+
+#[deny(dead_code)]
+fn initialize_logging() {
+    let stdout = ConsoleAppender::builder()
+        .encoder(Box::new(PatternEncoder::new("{m}{n}")))
+        .build();
+
+    let config = Config::builder()
+        .appender(Appender::builder().build("stdout", Box::new(stdout)))
+        .logger(Logger::builder().build("app::backend::db", LevelFilter::Info))
+        .build(Root::builder().appender("stdout").build(LevelFilter::Info))
+        .unwrap();
+
+    log4rs::init_config(config).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use super::*;
+    use newtonian_gravity::world::diagnostics::{SpeedStatistics, Speeds};
+
+    /// the three body preset ticked for 50 frames of the default run with `integrator`, and the world it started as
+    fn ticked_three_body(integrator: Integrator) -> (CPUWorld, CPUWorld) {
+        let params = SimParams::default();
+        let world = || CPUWorld::new(generate_3_body(&params)).with_integrator(integrator).with_g(params.g);
+        let mut ticked = world();
+        for _ in 0..50 {
+            ticked.tick(params.time_per_frame, params.time_steps);
+        }
+        (world(), ticked)
+    }
+
+    #[test]
+    fn three_body_conserves_momentum() {
+        for integrator in [Integrator::Euler, Integrator::Verlet, Integrator::RK4, Integrator::Yoshida] {
+            let (start, end) = ticked_three_body(integrator);
+            // relative to the momentum of the bodies, which mostly cancels out
+            let scale: f32 = start.get_particles().iter().map(|particle| particle.mass * particle.velocity.magnitude).sum();
+            let (x0, y0) = start.total_momentum().to_cartesian();
+            let (x1, y1) = end.total_momentum().to_cartesian();
+            let error = f32::hypot(x1 - x0, y1 - y0) / scale;
+            assert!(error < 1e-4, "{:?} changed the momentum by {} of its scale", integrator, error);
+        }
+    }
+
+    /// the energy drifts by about 3e-4 of itself with euler, the default, and by under 1e-5 with the others
+    #[test]
+    fn three_body_energy_drift() {
+        for (integrator, bound) in [(Integrator::Euler, 1e-3), (Integrator::Verlet, 1e-4), (Integrator::RK4, 1e-4), (Integrator::Yoshida, 1e-4)] {
+            let (start, end) = ticked_three_body(integrator);
+            let drift = ((end.total_energy() - start.total_energy()) / start.total_energy()).abs();
+            assert!(drift < bound, "{:?} drifted by {} of the energy", integrator, drift);
+        }
+    }
+
+    /// the statistics of every frame have to be those of the speeds of every substep the observer is shown,
+    /// which include those at the end of the frame
+    #[test]
+    fn speed_statistics_cover_every_substep_of_the_three_body_preset() {
+        let params = SimParams::default();
+        let mut world = CPUWorld::new(generate_3_body(&params)).with_g(params.g);
+        let mut statistics = SpeedStatistics::default();
+        for frame in 0..50 {
+            let mut speeds = Vec::new();
+            world.tick_with_observer(params.time_per_frame, params.time_steps, |particles| {
+                statistics.observe(particles);
+                speeds.extend(particles.iter().map(|particle| particle.velocity.magnitude));
+            });
+            statistics.end_frame();
+            assert_eq!(speeds.len(), 3 * params.time_steps.get() as usize);
+            let min = speeds.iter().copied().fold(f32::INFINITY, f32::min);
+            let max = speeds.iter().copied().fold(0.0, f32::max);
+            let mean = speeds.iter().sum::<f32>() / speeds.len() as f32;
+            assert_eq!(statistics.frames()[frame], Speeds { min, max, mean }, "frame {}", frame);
+            // mass points measure their speed from the cartesian components, which rounds a little differently
+            for mass_point in world.get_mass_points() {
+                let speed = mass_point.speed();
+                assert!(speed >= min * (1.0 - 1e-5) && speed <= max * (1.0 + 1e-5), "frame {}: {} isn't within {:?}", frame, speed, (min, max));
+            }
+        }
+        assert_eq!(statistics.frames().len(), 50);
+    }
+
+    /// relative energy error of the three body preset after each of 10 times the frames of a run with yoshida
+    fn yoshida_energy_errors<W: World>(mut world: W, params: &SimParams) -> Vec<f32> {
+        let start = world.total_energy();
+        (0..10 * params.frame_count)
+            .map(|_| {
+                world.tick(params.time_per_frame, params.time_steps);
+                (world.total_energy() - start) / start.abs()
+            })
+            .collect()
+    }
+
+    /// the error stays under ~1.2e-3 and turns around over a thousand times, a close encounter halfway through
+    /// widens the band a little but it doesn't grow from there
+    #[test]
+    fn yoshida_energy_oscillates_in_a_band() {
+        let params = SimParams::default();
+        let cpu = CPUWorld::new(generate_3_body(&params)).with_integrator(Integrator::Yoshida).with_g(params.g);
+        let par = ParWorld::new(generate_3_body(&params)).with_integrator(Integrator::Yoshida).with_g(params.g);
+        for errors in [yoshida_energy_errors(cpu, &params), yoshida_energy_errors(par, &params)] {
+            let largest = errors.iter().fold(0.0f32, |largest, error| largest.max(error.abs()));
+            assert!(largest < 2e-3, "the energy strayed by {} of itself", largest);
+            let turns = errors.windows(3).filter(|errors| (errors[1] - errors[0]) * (errors[2] - errors[1]) < 0.0).count();
+            assert!(turns > 1000, "the energy only turned around {} times", turns);
+        }
+    }
+
+    #[test]
+    fn divergence_reports_pair_every_backend() {
+        let cpu = vec![
+            vec![mass_point(1.0, (0.0, 0.0)), mass_point(1.0, (1.0, 2.0))],
+            vec![mass_point(1.0, (0.5, 0.0)), mass_point(1.0, (1.0, 2.5))]
+        ];
+        let moved = |frames: &[Vec<MassPoint>], offset: fn(usize, usize) -> (f32, f32)| -> Vec<Vec<MassPoint>> {
+            frames.iter().enumerate().map(|(frame, mass_points)| {
+                mass_points.iter().enumerate().map(|(i, mass_point)| {
+                    let (dx, dy) = offset(frame, i);
+                    MassPoint { position: (mass_point.position.0 + dx, mass_point.position.1 + dy), ..*mass_point }
+                }).collect()
+            }).collect()
+        };
+        // the first particle 5 away in the second frame, and every particle half a unit away in every frame
+        let par = moved(&cpu, |frame, i| if frame == 1 && i == 0 { (3.0, 4.0) } else { (0.0, 0.0) });
+        let gpu = moved(&cpu, |_, _| (0.3, 0.4));
+        let path = std::env::temp_dir().join(format!("newtonian_gravity_divergence_{}.csv", std::process::id()));
+        write_divergence_report(&[cpu, par, gpu], path.to_str().unwrap());
+        let csv = fs::read_to_string(&path).unwrap();
+        fs::remove_file(path).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("frame,cpu_vs_par_rms,cpu_vs_par_max,cpu_vs_gpu_rms,cpu_vs_gpu_max,par_vs_gpu_rms,par_vs_gpu_max"));
+        let rows: Vec<Vec<f32>> = lines.map(|line| line.split(',').map(|value| value.parse().unwrap()).collect()).collect();
+        let expected = [
+            [0.0, 0.0, 0.0, 0.5, 0.5, 0.5, 0.5],
+            // sqrt(5^2 / 2), and sqrt((4.5^2 + 0.5^2) / 2) with the first particle 4.5 from where the gpu put it
+            [1.0, 12.5f32.sqrt(), 5.0, 0.5, 0.5, 10.25f32.sqrt(), 4.5]
+        ];
+        assert_eq!(rows.len(), 2);
+        for (row, expected) in rows.iter().zip(expected) {
+            assert_eq!(row.len(), 7);
+            for (value, expected) in row.iter().zip(expected) {
+                assert!((value - expected).abs() < 1e-5, "{:?} rather than {:?}", row, expected);
+            }
+        }
+    }
+
+    /// a frame of the cpu, par and gpu gifs each, of a solid color, wide enough for the labels of the first three
+    /// panels but not for that of the fourth
+    fn comparison_frames() -> [RgbaImage; 3] {
+        [[200, 10, 20, 255], [10, 150, 30, 255], [40, 20, 100, 255]].map(|color| RgbaImage::from_pixel(32, 16, Rgba(color)))
+    }
+
+    #[test]
+    fn merged_frames_take_a_channel_each() {
+        let [cpu, par, gpu] = comparison_frames();
+        let merged = compare_frames(Comparison::Merged, [&cpu, &par, &gpu]).unwrap();
+        assert_eq!(merged.dimensions(), (32, 16));
+        assert!(merged.pixels().all(|pixel| pixel.0 == [200, 150, 100, 255]));
+    }
+
+    #[test]
+    fn side_by_side_frames_are_labeled_panels() {
+        let frames = comparison_frames();
+        let [cpu, par, gpu] = &frames;
+        let compared = compare_frames(Comparison::SideBySide, [cpu, par, gpu]).unwrap();
+        assert_eq!(compared.dimensions(), (3 * 32, 16));
+        for (i, frame) in frames.iter().enumerate() {
+            let x = i as u32 * 32;
+            // the label strip in the top left corner, and the frame past it
+            assert_eq!(compared[(x, 0)].0, OVERLAY_BACKGROUND);
+            assert_eq!(compared[(x + 31, 15)], frame[(31, 15)]);
+            assert_eq!(compared[(x + 16, 15)], frame[(16, 15)]);
+        }
+    }
+
+    #[test]
+    fn grid_frames_show_where_they_differ() {
+        let [cpu, par, mut gpu] = comparison_frames();
+        gpu.put_pixel(31, 15, cpu[(31, 15)]);
+        let compared = compare_frames(Comparison::Grid, [&cpu, &par, &gpu]).unwrap();
+        assert_eq!(compared.dimensions(), (2 * 32, 2 * 16));
+        assert_eq!(compared[(31, 15)], cpu[(31, 15)]);
+        assert_eq!(compared[(63, 15)], par[(31, 15)]);
+        assert_eq!(compared[(31, 31)], gpu[(31, 15)]);
+        // the spread of each channel, where the gpu frame is that of cpu it is par that differs
+        assert_eq!(compared[(62, 30)].0, [190, 140, 80, 255]);
+        assert_eq!(compared[(63, 31)].0, [190, 140, 10, 255]);
+        assert_eq!(compared[(0, 16)].0, OVERLAY_BACKGROUND);
+        assert_eq!(compared[(32, 16)].0, [190, 140, 80, 255]);
+    }
+
+    #[test]
+    fn frames_of_different_sizes_are_not_compared() {
+        let [cpu, par, _] = comparison_frames();
+        let gpu = RgbaImage::new(16, 16);
+        assert!(compare_frames(Comparison::Merged, [&cpu, &par, &gpu]).is_err());
+    }
+
+    #[test]
+    fn particles_collide_with_the_radius_they_are_drawn_with() {
+        let params = SimParams::default();
+        // the size particles have always been drawn at, larger than a sphere of density 1
+        assert!((pixel_radius(1.0) / world::radius(1.0) - f32::cbrt(PI * PI)).abs() < 1e-5);
+        for particle in generate_particles(&params) {
+            assert_eq!(particle.radius, pixel_radius(particle.mass) / params.scale);
+        }
+    }
+
+    /// checks that the pinned body of [`generate_pinned_3_body`] stays exactly where it starts for 240 frames
+    fn pinned_body_stays_put<W: World>(mut world: W, params: &SimParams) {
+        let start = world.get_mass_points()[0].position;
+        for frame in 0..240 {
+            world.tick(params.time_per_frame, params.time_steps);
+            let mass_points = world.get_mass_points();
+            assert_eq!(mass_points[0].position, start, "frame {}", frame);
+            assert_eq!(mass_points[0].velocity, (0.0, 0.0), "frame {}", frame);
+        }
+        // the others still go around it
+        assert_ne!(world.get_mass_points()[1].position, generate_pinned_3_body(params)[1].position.to_cartesian());
+    }
+
+    #[test]
+    fn pinned_bodies_never_move() {
+        let params = SimParams::default();
+        pinned_body_stays_put(cpu_world(generate_pinned_3_body(&params), &params), &params);
+        pinned_body_stays_put(par_world(generate_pinned_3_body(&params), &params), &params);
+        if GPUWorld::list_devices().is_empty() {
+            eprintln!("skipping the gpu, there is no device vulkan can run on");
+        } else {
+            pinned_body_stays_put(gpu_world(generate_pinned_3_body(&params), &params).unwrap(), &params);
+        }
+    }
+
+    /// paints frames of `width` by `height` pixels centered on the origin at `scale`, of a world of `boundary`
+    fn painter(width: u32, height: u32, scale: f32, boundary: Boundary) -> FramePainter {
+        let bounds = Bounds::of_size((width as f32, height as f32), scale, &[]);
+        let background: Rgba<u8> = [0, 0, 0, 255].into();
+        FramePainter {
+            viewport: Viewport::new((bounds.x.start, bounds.y.start), scale, width, height),
+            bounds,
+            image_offsets: image_offsets(boundary),
+            blank: RgbaImage::from_pixel(width, height, background).into(),
+            background,
+            grid: GRID,
+            rendering: RENDERING,
+            coloring: COLORING,
+            max_speed: None,
+            mass_brightness: MASS_BRIGHTNESS,
+            trails: TRAILS,
+            velocity_arrows: VELOCITY_ARROWS,
+            overlay: OVERLAY,
+            time_per_frame: 1.0,
+            frame_count: 1,
+            supersampling: 1,
+            gpu_rasterizer: None
+        }
+    }
+
+    /// a resting mass point of `mass` at `(x, y)`
+    fn mass_point(mass: f32, position: (f32, f32)) -> MassPoint {
+        MassPoint { mass, position, velocity: (0.0, 0.0), id: 0, group: 0 }
+    }
+
+    #[test]
+    fn circles_straddling_a_periodic_edge_show_up_on_both_sides() {
+        // a circle of radius ~1.3 pixels over the right edge of a 2 by 2 domain, 20 pixels across
+        let mass_points = [mass_point(1.0, (0.95, 0.0))];
+        let paint = |boundary| {
+            let painter = painter(21, 21, 10.0, boundary);
+            RgbaImage::from(painter.paint::<GifRasterizer>(painter.blank_frame(None), &mass_points, &[], &[], 0, View::default()))
+        };
+        let periodic = paint(Boundary::Periodic { width: 2.0, height: 2.0 });
+        assert_ne!(periodic.get_pixel(20, 10).0, [0, 0, 0, 255]);
+        assert_ne!(periodic.get_pixel(0, 10).0, [0, 0, 0, 255]);
+        let open = paint(Boundary::Open);
+        assert_ne!(open.get_pixel(20, 10).0, [0, 0, 0, 255]);
+        assert_eq!(open.get_pixel(0, 10).0, [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn groups_are_drawn_in_their_own_colors() {
+        // circles ~2.9 pixels across, whose centers are fully covered
+        let mass_points = [
+            MassPoint { group: 0, ..mass_point(10.0, (-0.5, 0.0)) },
+            MassPoint { group: 1, ..mass_point(10.0, (0.5, 0.0)) }
+        ];
+        let painter = painter(21, 21, 10.0, Boundary::Open);
+        let frame = RgbaImage::from(painter.paint::<GifRasterizer>(painter.blank_frame(None), &mass_points, &[], &[], 0, View::default()));
+        assert_eq!(frame.get_pixel(5, 10).0, PALETTE[0]);
+        assert_eq!(frame.get_pixel(15, 10).0, PALETTE[1]);
+        assert_ne!(PALETTE[0], PALETTE[1]);
+        let groups: HashSet<u32> = generate_two_clusters(&SimParams::default()).iter().map(|particle| particle.group).collect();
+        assert_eq!(groups, HashSet::from([0, 1]));
+    }
+
+    #[test]
+    fn the_fastest_particle_is_drawn_in_the_last_color_of_the_colormap() {
+        let mass_points = [
+            MassPoint { velocity: (0.001, 0.0), ..mass_point(10.0, (-0.5, 0.0)) },
+            MassPoint { velocity: (0.0, -0.004), ..mass_point(10.0, (0.5, 0.0)) }
+        ];
+        let frame = |mass_points: &[MassPoint], max_speed| {
+            let painter = FramePainter {
+                coloring: Coloring::Speed { colormap: Colormap::Turbo, scale: SpeedScale::Frame },
+                max_speed,
+                ..painter(21, 21, 10.0, Boundary::Open)
+            };
+            RgbaImage::from(painter.paint::<GifRasterizer>(painter.blank_frame(None), mass_points, &[], &[], 0, View::default()))
+        };
+        let per_frame = frame(&mass_points, None);
+        assert_eq!(per_frame.get_pixel(15, 10).0, Colormap::Turbo.color(1.0));
+        assert_eq!(per_frame.get_pixel(5, 10).0, Colormap::Turbo.color(mass_points[0].speed() / mass_points[1].speed()));
+        // the fastest of the run is twice as fast
+        let per_run = frame(&mass_points, Some(2.0 * mass_points[1].speed()));
+        assert_eq!(per_run.get_pixel(15, 10).0, Colormap::Turbo.color(0.5));
+        // nothing moves, which is drawn at rest rather than dividing by 0
+        let resting = frame(&[mass_point(10.0, (-0.5, 0.0)), mass_point(10.0, (0.5, 0.0))], None);
+        assert_eq!(resting.get_pixel(5, 10).0, Colormap::Turbo.color(0.0));
+        assert_eq!(resting.get_pixel(15, 10).0, Colormap::Turbo.color(0.0));
+    }
+
+    #[test]
+    fn heavier_particles_are_drawn_brighter() {
+        // circles ~5.8, ~12 and ~27 pixels across, whose centers are fully covered
+        let mass_points = [mass_point(10.0, (-2.0, 0.0)), mass_point(100.0, (0.0, 0.0)), mass_point(1000.0, (2.0, 0.0))];
+        let frame = |mass_scale| {
+            let painter = FramePainter {
+                bounds: Bounds::of_size((61.0, 61.0), 10.0, &mass_points),
+                mass_brightness: Some(mass_scale),
+                ..painter(61, 61, 10.0, Boundary::Open)
+            };
+            let frame = RgbaImage::from(painter.paint::<GifRasterizer>(painter.blank_frame(None), &mass_points, &[], &[], 0, View::default()));
+            [10, 30, 50].map(|x| frame.get_pixel(x, 30).0)
+        };
+        // the lightest at MIN_BRIGHTNESS and the heaviest at full brightness, the channels are rounded down
+        assert_eq!(frame(MassScale::Linear), [[63, 63, 63, 255], [81, 81, 81, 255], [255, 255, 255, 255]]);
+        assert_eq!(frame(MassScale::Log), [[63, 63, 63, 255], [157, 157, 157, 255], [255, 255, 255, 255]]);
+    }
+
+    #[test]
+    fn trails_fade_along_the_path_a_particle_took() {
+        // a particle ~5.8 pixels across moving 4 pixels a frame along the middle row, its dots ~3 pixels across
+        let trail: Vec<Arc<Vec<MassPoint>>> = [-1.5, -1.1, -0.7, -0.3, 0.1]
+            .map(|x| Arc::new(vec![mass_point(10.0, (x, 0.0))]))
+            .into();
+        let mass_points = [mass_point(10.0, (1.0, 0.0))];
+        let row = |trails| {
+            let painter = FramePainter { trails, ..painter(41, 21, 10.0, Boundary::Open) };
+            let frame = RgbaImage::from(painter.paint::<GifRasterizer>(painter.blank_frame(None), &mass_points, &[], &trail, 0, View::default()));
+            [5, 9, 13, 17, 21, 30].map(|x| frame.get_pixel(x, 10).0[0])
+        };
+        let faded = row(Some(Trails { length: 5, fade: 0.8 }));
+        assert_eq!(faded[5], 255);
+        // each dot dimmer than the one a frame newer
+        assert!(faded[0] > 0 && faded.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", faded);
+        assert_eq!(row(None), [0, 0, 0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn velocity_arrows_point_the_way_particles_move() {
+        let arrows = VelocityArrows { scale: 1e4, max_length: 8.0 };
+        let frame = |velocity| {
+            // a particle ~2.7 pixels across in the middle
+            let mass_points = [MassPoint { velocity, ..mass_point(1.0, (0.0, 0.0)) }];
+            let painter = FramePainter { velocity_arrows: Some(arrows), ..painter(21, 21, 10.0, Boundary::Open) };
+            RgbaImage::from(painter.paint::<GifRasterizer>(painter.blank_frame(None), &mass_points, &[], &[], 0, View::default()))
+        };
+        // 10 pixels long, shortened to 8
+        let moving = frame((0.001, 0.0));
+        for x in 12..19 {
+            assert_eq!(moving.get_pixel(x, 10).0, ARROW_PAINT, "{}", x);
+        }
+        // nothing past its end
+        assert_eq!(moving.get_pixel(19, 10).0, [0, 0, 0, 255]);
+        // nor anything behind the particle
+        assert!((0..8).all(|x| moving.get_pixel(x, 10).0 == [0, 0, 0, 255]));
+        // half a pixel long, which is left out
+        let slow = frame((0.00005, 0.0));
+        assert!(slow.pixels().all(|pixel| pixel.0 != ARROW_PAINT));
+    }
+
+    #[test]
+    fn the_heavier_of_two_clusters_is_the_hotter_blob() {
+        // 9 particles a tenth apart around each of pixels 15 and 45, those on the right 3 times as heavy
+        let cluster = |mass: f32, cx: f32| (0..9).map(move |i| mass_point(mass, (cx + (i % 3 - 1) as f32 * 0.1, (i / 3 - 1) as f32 * 0.1)));
+        let mass_points: Vec<MassPoint> = cluster(1.0, -1.5).chain(cluster(3.0, 1.5)).collect();
+        let colormap = Colormap::Inferno;
+        let painter = FramePainter {
+            rendering: Rendering::Heatmap(Heatmap { bin_size: 2, splat: Some(1.0), colormap }),
+            ..painter(61, 31, 10.0, Boundary::Open)
+        };
+        let frame = RgbaImage::from(painter.paint::<GifRasterizer>(painter.blank_frame(None), &mass_points, &[], &[], 0, View::default()));
+        let (light, heavy) = (frame.get_pixel(15, 15).0, frame.get_pixel(45, 15).0);
+        assert_eq!(heavy, colormap.color(1.0));
+        assert!(light != colormap.color(0.0) && light != heavy, "{:?}", light);
+        // the first color between and around the blobs
+        for (x, y) in [(30, 15), (0, 0), (60, 30), (15, 0)] {
+            assert_eq!(frame.get_pixel(x, y).0, colormap.color(0.0), "{}, {}", x, y);
+        }
+        // a third of the mass, so a third of the way along the colormap
+        assert_eq!(light, colormap.color(1.0 / 3.0));
+    }
+
+    #[test]
+    fn grid_lines_are_drawn_at_whole_multiples_of_their_spacing() {
+        // a particle ~5.8 pixels across in the middle, over the axes
+        let mass_points = [mass_point(10.0, (0.0, 0.0))];
+        // 4 by 2 units of the world from (-2, -1), 10 pixels a unit
+        let frame = |zoom| {
+            let painter = FramePainter { grid: Some(Grid { spacing: 0.5 }), ..painter(41, 21, 10.0, Boundary::Open) };
+            RgbaImage::from(painter.paint::<GifRasterizer>(painter.blank_frame(None), &mass_points, &[], &[], 0, View { origin: (0.0, 0.0), zoom }))
+        };
+        let columns = |frame: &RgbaImage, paint: [u8; 4]| (0..41).filter(|&x| frame.get_pixel(x, 2).0 == paint).collect::<Vec<u32>>();
+        let rows = |frame: &RgbaImage, paint: [u8; 4]| (0..21).filter(|&y| frame.get_pixel(2, y).0 == paint).collect::<Vec<u32>>();
+        let unzoomed = frame(1.0);
+        assert_eq!(columns(&unzoomed, GRID_PAINT), [0, 5, 10, 15, 25, 30, 35, 40]);
+        assert_eq!(columns(&unzoomed, GRID_AXIS_PAINT), [20]);
+        assert_eq!(rows(&unzoomed, GRID_PAINT), [0, 5, 15, 20]);
+        assert_eq!(rows(&unzoomed, GRID_AXIS_PAINT), [10]);
+        assert_eq!(unzoomed.get_pixel(20, 10).0, PALETTE[0]);
+        // zoomed out 4 times, the lines are 2.5 units apart rather than 2, and 6.25 pixels rather than 5
+        let zoomed = frame(0.25);
+        assert_eq!(columns(&zoomed, GRID_PAINT), [1, 7, 13, 26, 32, 38]);
+        assert_eq!(columns(&zoomed, GRID_AXIS_PAINT), [20]);
+    }
+
+    #[test]
+    fn the_overlay_writes_the_frame_into_its_corner() {
+        let mass_points = [mass_point(1.0, (0.0, 0.0))];
+        let frame = |width, height, corner| {
+            let painter = FramePainter {
+                overlay: Some(Overlay { corner, scale: 1 }),
+                time_per_frame: 80.0,
+                frame_count: 3,
+                ..painter(width, height, 10.0, Boundary::Open)
+            };
+            RgbaImage::from(painter.paint::<GifRasterizer>(painter.blank_frame(None), &mass_points, &[], &[], 1, View::default()))
+        };
+        // "t = 160, frame 2/3, n = 1", 25 glyphs 4 pixels apart on a strip a pixel wider on every side
+        let (paint, background) = (OVERLAY_PAINT, OVERLAY_BACKGROUND);
+        let one = [
+            [background, paint, background],
+            [paint, paint, background],
+            [background, paint, background],
+            [background, paint, background],
+            [paint, paint, paint]
+        ];
+        let glyph = |frame: &RgbaImage, x: u32, y: u32| -> [[[u8; 4]; 3]; 5] {
+            std::array::from_fn(|row| std::array::from_fn(|column| frame.get_pixel(x + column as u32, y + row as u32).0))
+        };
+        let top_left = frame(121, 41, Corner::TopLeft);
+        // the 1 of 160, the fifth glyph
+        assert_eq!(glyph(&top_left, 17, 1), one);
+        assert_eq!(top_left.get_pixel(0, 0).0, background);
+        assert_eq!(top_left.get_pixel(100, 6).0, background);
+        assert_eq!(top_left.get_pixel(101, 6).0, [0, 0, 0, 255]);
+        assert_eq!(top_left.get_pixel(0, 7).0, [0, 0, 0, 255]);
+        // the last 1, in the bottom right
+        let bottom_right = frame(121, 41, Corner::BottomRight);
+        assert_eq!(glyph(&bottom_right, 117, 35), one);
+        assert_eq!(bottom_right.get_pixel(19, 40).0, [0, 0, 0, 255]);
+        // too small for the text, which is cut off
+        let small = frame(10, 4, Corner::BottomRight);
+        assert!(small.pixels().all(|pixel| pixel.0 == background || pixel.0 == paint));
+    }
+
+    #[test]
+    fn fast_particles_are_drawn_as_streaks_with_substeps() {
+        // a particle ~2.7 pixels across moving 2 pixels a substep along the middle row, from pixel 14 to 44
+        let substeps: Vec<Vec<MassPoint>> = (0..16).map(|i| vec![mass_point(1.0, (-1.6 + 0.2 * i as f32, 0.0))]).collect();
+        let painter = painter(61, 21, 10.0, Boundary::Open);
+        let frame = |substeps: &[Vec<MassPoint>]| {
+            let mass_points = &substeps[substeps.len() - 1];
+            RgbaImage::from(painter.paint::<GifRasterizer>(painter.blank_frame(None), mass_points, substeps, &[], 0, View::default()))
+        };
+        let streak = frame(&substeps);
+        let row: Vec<u8> = (0..61).map(|x| streak.get_pixel(x, 10).0[0]).collect();
+        // contiguous, and dim, each substep is drawn at a sixteenth of the brightness
+        assert!(row[14..=44].iter().all(|&value| value > 0 && value < 64), "{:?}", row);
+        assert!(row[..12].iter().chain(&row[47..]).all(|&value| value == 0), "{:?}", row);
+        // the brightness of the particle spread along its path
+        let total = |frame: &RgbaImage| frame.pixels().map(|pixel| pixel.0[0] as f32).sum::<f32>();
+        let still = frame(&substeps[15..]);
+        // which rounding down the channels of every dim edge of every substep takes a fifth or so off
+        assert!(total(&streak) <= total(&still) && total(&streak) > 0.75 * total(&still), "{} rather than {}", total(&streak), total(&still));
+    }
+
+    #[test]
+    fn resting_particles_are_brighter_in_the_long_exposure() {
+        // two particles ~5.8 pixels across, one resting at pixel 10 and one moving 3 pixels a frame from pixel 22
+        let painter = painter(61, 21, 10.0, Boundary::Open);
+        let mut exposure = ExposureHandler::new(61, 21);
+        for frame in 0..8 {
+            let mass_points = [mass_point(10.0, (-2.0, 0.0)), mass_point(10.0, (-0.8 + 0.3 * frame as f32, 0.0))];
+            exposure.accumulate(&painter.paint::<GifRasterizer>(painter.blank_frame(None), &mass_points, &[], &[], 0, View::default()));
+        }
+        for tone_map in [ToneMap::Log, ToneMap::Gamma(2.2)] {
+            let image = exposure.to_image(tone_map);
+            let brightest = |xs: Range<u32>| xs.map(|x| image.get_pixel(x, 10).0[0]).max().unwrap();
+            // normalized to the resting one, which no pixel of the moving one comes close to
+            assert_eq!(brightest(0..16), 255);
+            let moving = brightest(18..61);
+            assert!(moving > 0 && moving < 255, "{} for {:?}", moving, tone_map);
+            // only the middle of the resting one is white
+            assert!(image.pixels().filter(|pixel| pixel.0 == [255; 3]).count() < 30);
+        }
+    }
+
+    /// total brightness of `mass_points` drawn into a gif of `bounds` at `scale`, sized the way [`GifOutput::new`] does,
+    /// which is the area they cover whatever pixels their edges fall on
+    fn brightness(bounds: Bounds, scale: f32, mass_points: &[MassPoint]) -> f32 {
+        let width = ((bounds.x.end - bounds.x.start) * scale) as u32 + 1;
+        let height = ((bounds.y.end - bounds.y.start) * scale) as u32 + 1;
+        let painter = FramePainter {
+            viewport: Viewport::new((bounds.x.start, bounds.y.start), scale, width, height),
+            ..painter(width, height, scale, Boundary::Open)
+        };
+        let frame = RgbaImage::from(painter.paint::<GifRasterizer>(painter.blank_frame(None), mass_points, &[], &[], 0, View::default()));
+        frame.pixels().map(|pixel| pixel.0[0] as f32 / 255.0).sum()
+    }
+
+    #[test]
+    fn fitted_bounds_leave_room_for_the_circles_at_the_edge() {
+        let scale = 100.0;
+        // a circle ~29 pixels across at the right edge of where the centers are
+        let frames = [vec![mass_point(1.0, (-1.0, 0.0))], vec![mass_point(1e4, (1.0, 0.0))]];
+        let frames: Vec<&[MassPoint]> = frames.iter().map(Vec::as_slice).collect();
+        let fitted = Bounds::fitting(&frames, scale);
+        let radius = drawn_radius(1e4, scale);
+        assert_eq!(fitted.x.end, 1.0 + radius);
+        assert_eq!(fitted.y, -radius..radius);
+        let whole = brightness(Bounds::of_size((1000.0, 1000.0), scale, &[]), scale, frames[1]);
+        let fitted_brightness = brightness(Bounds::fitting(&frames, scale), scale, frames[1]);
+        assert!((fitted_brightness - whole).abs() < 0.01 * whole, "{} isn't {}", fitted_brightness, whole);
+        // fitting the centers alone cuts the circle in half
+        let (x, y) = diagnostics::bounds(frames[1], |_| 0.0).unwrap();
+        let centers = Bounds { x: fitted.x.start..x.end, y, ..fitted };
+        let centers_brightness = brightness(centers, scale, frames[1]);
+        assert!(centers_brightness < 0.6 * whole, "{} of {}", centers_brightness, whole);
+    }
+
+    /// the pixels two particles drifting together are drawn at over 20 frames of the default run, following `camera`
+    fn drifting_pair_pixels(camera: Camera) -> Vec<Vec<(i32, i32)>> {
+        let params = SimParams::default();
+        let particle = |x: f32| Particle {
+            mass: 1.0,
+            position: Vector::from_cartesian(x, 0.0),
+            // 10 pixels a frame
+            velocity: Vector::from_cartesian(0.001, 0.0),
+            radius: 0.0,
+            fixed: 0,
+            id: 0,
+            group: 0
+        };
+        let mut world = CPUWorld::new(vec![particle(-0.1), particle(0.2)]);
+        let mut framing = Framing { camera, ..Framing::new(&params) };
+        let painter = painter(1000, 1000, params.scale, Boundary::Open);
+        (0..20)
+            .map(|_| {
+                world.tick(params.time_per_frame, params.time_steps);
+                let mut mass_points = world.get_mass_points();
+                framing.frame(&mut mass_points);
+                mass_points.iter().map(|mass_point| painter.viewport.world_to_pixel(mass_point.position.0, mass_point.position.1)).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_center_of_mass_camera_keeps_drifting_systems_in_place() {
+        let following = drifting_pair_pixels(Camera::CenterOfMass);
+        for pixels in &following {
+            // 0.15 on either side of the center of mass at 0.05
+            assert_eq!(pixels, &following[0]);
+            assert!((pixels[0].1 - 500).abs() <= 1, "{:?}", pixels);
+            assert!((pixels[1].0 - pixels[0].0 - 150).abs() <= 1, "{:?}", pixels);
+        }
+        let fixed = drifting_pair_pixels(Camera::Fixed);
+        assert!(fixed[19][0].0 - fixed[0][0].0 >= 189, "{:?}", fixed);
+    }
+
+    #[test]
+    fn the_followed_particle_stays_at_the_center_pixel() {
+        for pixels in drifting_pair_pixels(Camera::FollowParticle(1)) {
+            // the origin, which is half a pixel into pixel 499 of the 1000
+            assert_eq!(pixels[1], (499, 499));
+            assert!((pixels[0].0 - 349).abs() <= 1, "{:?}", pixels);
+        }
+        // once it is removed the camera stays where it was last seen
+        let mut framing = Framing { camera: Camera::FollowParticle(1), ..Framing::new(&SimParams::default()) };
+        let mut frame = [MassPoint { id: 0, ..mass_point(1.0, (0.0, 0.0)) }, MassPoint { id: 1, ..mass_point(1.0, (0.25, 0.5)) }];
+        assert_eq!(framing.frame(&mut frame).origin, (0.25, 0.5));
+        assert_eq!(frame[1].position, (0.0, 0.0));
+        let mut removed = [MassPoint { id: 0, ..mass_point(1.0, (0.75, 0.0)) }];
+        assert_eq!(framing.frame(&mut removed).origin, (0.25, 0.5));
+        assert_eq!(removed[0].position, (0.5, -0.5));
+    }
+
+    #[test]
+    fn autoscaling_zooms_out_smoothly_after_an_escaping_particle() {
+        let params = SimParams::default();
+        let mut framing = Framing { autoscale: Some(Autoscale { smoothing: 0.1, margin: 0.1 }), ..Framing::new(&params) };
+        let (half_width, _) = framing.viewport.unwrap();
+        let zooms: Vec<f32> = (0..60)
+            .map(|frame| {
+                // a bound cluster 0.2 across, and a particle escaping from it along x
+                let mut mass_points = [(-0.1, -0.1), (-0.1, 0.1), (0.1, -0.1), (0.1, 0.1), (0.1 + 0.02 * frame as f32, 0.0)]
+                    .map(|position| mass_point(1.0, position));
+                let view = framing.frame(&mut mass_points);
+                if frame == 0 {
+                    // the cluster fills the gif but for the margin
+                    let width = mass_points[2].position.0 - mass_points[0].position.0;
+                    assert!(width >= 1.8 * half_width / 1.1 - 1e-3, "{} of {}", width, 2.0 * half_width);
+                }
+                view.zoom
+            })
+            .collect();
+        for (frame, pair) in zooms.windows(2).enumerate() {
+            // out, and only a little from one frame to the next
+            assert!(pair[1] <= pair[0] && pair[1] >= 0.9 * pair[0], "frame {}: {:?}", frame + 1, pair);
+        }
+        assert!(zooms[59] < zooms[0] / 3.0, "{:?}", zooms);
+    }
+
+    /// ticks `world` 3 frames through [`tick_and_output_gif`] into a gif in the temporary directory, the mass points
+    /// it ends on have to be those of `direct` ticked 3 frames on its own, and the gif has to have 3 frames
+    fn assert_outputs_like_direct_ticks<W: World>(world: W, mut direct: W, name: &str) {
+        let params = SimParams { particle_count: 20, frame_count: 3, ..SimParams::default() };
+        let path = std::env::temp_dir().join(format!("newtonian_gravity_{}_{}", std::process::id(), name));
+        let path = path.to_str().unwrap();
+        let world = tick_and_output_gif::<_, GifRasterizer>(world, path, &params);
+        for _ in 0..3 {
+            direct.tick(params.time_per_frame, params.time_steps);
+        }
+        assert_eq!(world.get_mass_points(), direct.get_mass_points(), "{}", name);
+        let gif = format!("{}.gif", path);
+        let frames = GifDecoder::new(File::open(&gif).unwrap()).unwrap().into_frames().count();
+        assert_eq!(frames, 3, "{}", name);
+        fs::remove_file(gif).unwrap();
+    }
+
+    #[test]
+    fn every_backend_outputs_through_the_generic_path() {
+        let params = SimParams { particle_count: 20, ..SimParams::default() };
+        let particles = params.particles();
+        assert_outputs_like_direct_ticks(cpu_world(particles.clone(), &params), cpu_world(particles.clone(), &params), "generic_cpu");
+        assert_outputs_like_direct_ticks(par_world(particles.clone(), &params), par_world(particles.clone(), &params), "generic_par");
+        if GPUWorld::list_devices().is_empty() {
+            eprintln!("skipping the gpu, there is no device vulkan can run on");
+        } else {
+            assert_outputs_like_direct_ticks(gpu_world(particles.clone(), &params).unwrap(), gpu_world(particles, &params).unwrap(), "generic_gpu");
+        }
+    }
+
+    #[test]
+    fn ensembles_write_a_gif_and_a_summary_row_per_seed() {
+        let dir = std::env::temp_dir().join(format!("newtonian_gravity_ensemble_{}", std::process::id()));
+        let dir = dir.to_str().unwrap();
+        let params = SimParams { particle_count: 10, frame_count: 3, size: Some((200.0, 200.0)), ..SimParams::default() };
+        run_ensemble::<GifRasterizer>(&[1, 2], Backend::CPU, dir, &params);
+        for seed in [1, 2] {
+            let frames = GifDecoder::new(File::open(format!("{}/run_{}.gif", dir, seed)).unwrap()).unwrap().into_frames().count();
+            assert_eq!(frames, 3);
+        }
+        let summary = fs::read_to_string(format!("{}/ensemble.csv", dir)).unwrap();
+        let mut lines = summary.lines();
+        assert_eq!(lines.next(), Some("seed,particles,energy,bound_fraction"));
+        let mut seeds = Vec::new();
+        for line in lines {
+            let columns: Vec<&str> = line.split(',').collect();
+            assert_eq!(columns.len(), 4, "{}", line);
+            seeds.push(columns[0].parse::<u64>().unwrap());
+            assert_eq!(columns[1], "10");
+            // the random particles start at rest, so they are bound
+            let energy: f32 = columns[2].parse().unwrap();
+            assert!(energy.is_finite() && energy < 0.0, "{}", line);
+            let bound_fraction: f32 = columns[3].parse().unwrap();
+            assert!((0.0..=1.0).contains(&bound_fraction), "{}", line);
+        }
+        seeds.sort_unstable();
+        assert_eq!(seeds, [1, 2]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// frames of the gif of a run of `params` on the cpu, written to the temporary directory
+    fn gif_frames(params: &SimParams, name: &str) -> Vec<RgbaImage> {
+        let path = temp_gif_name(name);
+        tick_and_output_gif::<_, GifRasterizer>(cpu_world(params.particles(), params), &path, params);
+        read_gif_frames(&path)
+    }
+
+    /// a path in the temporary directory for the gif `name`, without the extension
+    fn temp_gif_name(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("newtonian_gravity_{}_{}", std::process::id(), name));
+        path.to_str().unwrap().to_string()
+    }
+
+    /// the frames of `<path>.gif`, which is removed
+    fn read_gif_frames(path: &str) -> Vec<RgbaImage> {
+        let gif = format!("{}.gif", path);
+        let frames = GifDecoder::new(File::open(&gif).unwrap()).unwrap()
+            .into_frames()
+            .map(|frame| frame.unwrap().into_buffer())
+            .collect();
+        fs::remove_file(gif).unwrap();
+        frames
+    }
+
+    #[test]
+    fn gifs_play_at_the_frame_rate() {
+        for (frame_rate, delay) in [(20.0, 50), (25.0, 40), (3.0, 330)] {
+            let params = SimParams { particle_count: 10, frame_count: 3, frame_rate, size: Some((50.0, 50.0)), ..SimParams::default() };
+            let path = temp_gif_name(&format!("frame_rate_{}", frame_rate));
+            tick_and_output_gif::<_, GifRasterizer>(cpu_world(params.particles(), &params), &path, &params);
+            let gif = format!("{}.gif", path);
+            let frames = GifDecoder::new(File::open(&gif).unwrap()).unwrap().into_frames().collect_frames().unwrap();
+            fs::remove_file(gif).unwrap();
+            assert_eq!(frames.len(), 3);
+            // gifs keep hundredths of a second, which a third of a second is rounded to
+            for frame in frames {
+                assert_eq!(frame.delay().numer_denom_ms(), (delay, 1), "at {} frames a second", frame_rate);
+            }
+        }
+    }
+
+    #[test]
+    fn pipelined_gpu_gifs_match_serial_ones() {
+        let params = SimParams { particle_count: 50, frame_count: 6, size: Some((300.0, 300.0)), ..SimParams::default() };
+        if GPUWorld::list_devices().is_empty() {
+            eprintln!("skipping the gpu, there is no device vulkan can run on");
+        } else {
+            let world = || gpu_world(params.particles(), &params).unwrap().with_deterministic_summation(true);
+            let (pipelined, serial) = (temp_gif_name("pipelined_gpu"), temp_gif_name("serial_gpu"));
+            tick_and_output_gpu_gif::<GifRasterizer>(world(), &pipelined, &params);
+            tick_and_output_gif::<_, GifRasterizer>(world(), &serial, &params);
+            let pipelined = read_gif_frames(&pipelined);
+            assert_eq!(pipelined.len(), params.frame_count);
+            assert!(pipelined == read_gif_frames(&serial));
+        }
+    }
+
+    #[test]
+    fn batched_frames_encode_like_frames_drawn_one_at_a_time() {
+        let params = SimParams {
+            particle_count: 30,
+            frame_count: 3 * FRAMES_IN_FLIGHT,
+            scale: 150.0,
+            size: Some((300.0, 300.0)),
+            ..SimParams::default()
+        };
+        let mut world = cpu_world(params.particles(), &params);
+        let mass_point_frames: Vec<Vec<MassPoint>> = (0..params.frame_count)
+            .map(|_| {
+                world.tick(params.time_per_frame, params.time_steps);
+                world.get_mass_points()
+            })
+            .collect();
+        let gif = |name: &str, delay: Duration| {
+            let path = temp_gif_name(name);
+            let frames = mass_point_frames.iter().map(|mass_points| {
+                thread::sleep(delay);
+                SimulatedFrame::from(mass_points.clone())
+            });
+            output_frames::<GifRasterizer>(frames, 0, &path, &params, world_name::<CPUWorld>(), None);
+            let gif = format!("{}.gif", path);
+            let bytes = fs::read(&gif).unwrap();
+            fs::remove_file(gif).unwrap();
+            bytes
+        };
+        // waiting between frames leaves the rasterizer a single frame to draw at a time
+        let one_at_a_time = gif("one_at_a_time", Duration::from_millis(50));
+        let batched = gif("batched", Duration::ZERO);
+        assert!(one_at_a_time == batched);
+        let first_frame = GifDecoder::new(&batched[..]).unwrap().into_frames().next().unwrap().unwrap().into_buffer();
+        assert!(first_frame.pixels().any(|pixel| pixel[0] > 0), "nothing was drawn");
+    }
+
+    #[test]
+    fn runs_in_one_process_take_their_own_params() {
+        let short = SimParams { particle_count: 10, frame_count: 2, size: Some((200.0, 100.0)), ..SimParams::default() };
+        let long = SimParams { particle_count: 20, frame_count: 5, size: Some((100.0, 300.0)), ..SimParams::default() };
+        let short_frames = gif_frames(&short, "short_params");
+        let long_frames = gif_frames(&long, "long_params");
+        assert_eq!(short_frames.len(), 2);
+        assert_eq!(long_frames.len(), 5);
+        assert_eq!(short_frames[0].dimensions(), (200, 100));
+        assert_eq!(long_frames[0].dimensions(), (100, 300));
+        assert_eq!((short.particles().len(), long.particles().len()), (10, 20));
+    }
 }
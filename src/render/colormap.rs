@@ -0,0 +1,168 @@
+use std::marker::PhantomData;
+use crate::render::cpu::PaintScalar;
+
+/// perceptual colormaps, each sampled at 11 evenly spaced points and interpolated linearly between them
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Colormap {
+    /// dark blue through green to yellow
+    Viridis,
+    /// black through red to pale yellow
+    Inferno,
+    /// blue through green and yellow to dark red
+    Turbo
+}
+
+impl Colormap {
+    /// opaque color at `t` along the colormap, `t` is clamped to `0.0..=1.0` and NaN is taken as 0
+    pub fn color(self, t: f32) -> [u8; 4] {
+        let table = match self {
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Inferno => &INFERNO,
+            Colormap::Turbo => &TURBO
+        };
+        // NaN fails the comparisons of clamp and is passed through, max turns it into 0
+        let position = t.clamp(0.0, 1.0).max(0.0) * (table.len() - 1) as f32;
+        let i = (position as usize).min(table.len() - 2);
+        let fraction = position - i as f32;
+        let (a, b) = (table[i], table[i + 1]);
+        let channel = |c: usize| (a[c] as f32 + (b[c] as f32 - a[c] as f32) * fraction).round() as u8;
+        [channel(0), channel(1), channel(2), 255]
+    }
+}
+
+/// a [`Colormap`] picked by a type rather than a value, for [`ColormapScalar`]
+pub trait ColormapType {
+    const COLORMAP: Colormap;
+}
+
+/// [`Colormap::Viridis`] as a type
+pub struct Viridis;
+
+impl ColormapType for Viridis {
+    const COLORMAP: Colormap = Colormap::Viridis;
+}
+
+/// [`Colormap::Inferno`] as a type
+pub struct Inferno;
+
+impl ColormapType for Inferno {
+    const COLORMAP: Colormap = Colormap::Inferno;
+}
+
+/// [`Colormap::Turbo`] as a type
+pub struct Turbo;
+
+impl ColormapType for Turbo {
+    const COLORMAP: Colormap = Colormap::Turbo;
+}
+
+/// colormap scaling, such as `ColormapScalar<Inferno>`
+///
+/// Paint: [RGB](image::Rgb) -> the color at `scale` along the colormap, the color of the paint is ignored
+///
+/// Paint: [RGBA](image::Rgba) -> same procedure as RGB, `A` is simply copied from the input (not scaled)
+///
+/// rasterizers draw the pixels they cover entirely with the paint itself, which should be the last color of the
+/// colormap for those to match
+pub struct ColormapScalar<C> {
+    __phantom: PhantomData<C>
+}
+
+impl <C: ColormapType> PaintScalar<image::Rgb<u8>> for ColormapScalar<C> {
+    fn scale(_: &image::Rgb<u8>, scale: f32, clamp: Option<fn(f32) -> f32>) -> image::Rgb<u8> {
+        // the colormap clamps the scale itself, `clamp` may still narrow it
+        let [r, g, b, _] = C::COLORMAP.color(clamp.map_or(scale, |clamp| clamp(scale)));
+        [r, g, b].into()
+    }
+}
+
+impl <C: ColormapType> PaintScalar<image::Rgba<u8>> for ColormapScalar<C> {
+    fn scale(paint: &image::Rgba<u8>, scale: f32, clamp: Option<fn(f32) -> f32>) -> image::Rgba<u8> {
+        // the colormap clamps the scale itself, `clamp` may still narrow it
+        let [r, g, b, _] = C::COLORMAP.color(clamp.map_or(scale, |clamp| clamp(scale)));
+        [r, g, b, paint.0[3]].into()
+    }
+}
+
+const VIRIDIS: [[u8; 3]; 11] = [
+    [68, 1, 84],
+    [72, 36, 117],
+    [65, 68, 135],
+    [53, 95, 141],
+    [42, 120, 142],
+    [33, 145, 140],
+    [34, 168, 132],
+    [68, 191, 112],
+    [122, 209, 81],
+    [189, 223, 38],
+    [253, 231, 37]
+];
+
+const INFERNO: [[u8; 3]; 11] = [
+    [0, 0, 4],
+    [22, 11, 57],
+    [66, 10, 104],
+    [106, 23, 110],
+    [147, 38, 103],
+    [188, 55, 84],
+    [221, 81, 58],
+    [243, 120, 25],
+    [252, 165, 10],
+    [246, 215, 70],
+    [252, 255, 164]
+];
+
+const TURBO: [[u8; 3]; 11] = [
+    [48, 18, 59],
+    [65, 69, 171],
+    [70, 117, 237],
+    [57, 162, 252],
+    [27, 207, 212],
+    [36, 236, 166],
+    [97, 252, 108],
+    [164, 252, 59],
+    [225, 221, 55],
+    [246, 107, 25],
+    [122, 4, 3]
+];
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, Rgba};
+    use super::*;
+
+    #[test]
+    fn the_ends_and_samples_of_colormaps_are_their_tables() {
+        assert_eq!(Colormap::Viridis.color(0.0), [68, 1, 84, 255]);
+        assert_eq!(Colormap::Viridis.color(1.0), [253, 231, 37, 255]);
+        assert_eq!(Colormap::Inferno.color(0.0), [0, 0, 4, 255]);
+        assert_eq!(Colormap::Inferno.color(0.5), [188, 55, 84, 255]);
+        assert_eq!(Colormap::Turbo.color(0.3), [57, 162, 252, 255]);
+    }
+
+    #[test]
+    fn colors_between_samples_are_interpolated() {
+        // halfway between the last two samples of inferno, [246, 215, 70] and [252, 255, 164]
+        assert_eq!(Colormap::Inferno.color(0.95), [249, 235, 117, 255]);
+        // a quarter of the way from [68, 1, 84] to [72, 36, 117]
+        assert_eq!(Colormap::Viridis.color(0.025), [69, 10, 92, 255]);
+    }
+
+    #[test]
+    fn scales_out_of_range_are_clamped() {
+        assert_eq!(Colormap::Viridis.color(-3.0), Colormap::Viridis.color(0.0));
+        assert_eq!(Colormap::Viridis.color(7.0), Colormap::Viridis.color(1.0));
+        assert_eq!(Colormap::Viridis.color(f32::NAN), Colormap::Viridis.color(0.0));
+    }
+
+    #[test]
+    fn colormap_scaling_ignores_the_color_of_the_paint() {
+        for paint in [Rgba([255, 0, 0, 200]), Rgba([0, 0, 0, 200])] {
+            assert_eq!(ColormapScalar::<Inferno>::scale(&paint, 0.5, None), Rgba([188, 55, 84, 200]));
+            assert_eq!(ColormapScalar::<Inferno>::scale(&paint, 2.0, None), Rgba([252, 255, 164, 200]));
+        }
+        assert_eq!(ColormapScalar::<Viridis>::scale(&Rgb([1, 2, 3]), -1.0, None), Rgb([68, 1, 84]));
+        // narrowed further by the clamp of the rasterizer
+        assert_eq!(ColormapScalar::<Viridis>::scale(&Rgb([1, 2, 3]), 0.9, Some(|scale| scale.min(0.5))), Rgb([33, 145, 140]));
+    }
+}
@@ -1,11 +1,17 @@
-use std::io::Write;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::iter::repeat;
 use crate::render::cpu;
+use crate::render::gif_comment::CommentWriter;
+use crate::render::viewport::Viewport;
+use crate::world::{self, MassPoint};
 use std::marker::PhantomData;
-use std::mem;
 use std::ops::{Deref, DerefMut};
-use num_traits::ToPrimitive;
+use std::ptr;
+use num_traits::{NumCast, ToPrimitive};
 
+/// draws frames of mass points as circles with `Rasterizer`, onto canvases of `FrameHandler` which it hands back
 pub struct CPURenderer<
     Canvas,
     Paint,
@@ -30,6 +36,27 @@ impl <
             __phantom: PhantomData
         }
     }
+
+    /// draws a frame of every one of `mass_points` as a circle of its [`radius`](world::radius) in pixels, at
+    /// where `viewport` puts it, in `paint`
+    pub fn render(&mut self, mass_points: &[MassPoint], viewport: &Viewport, paint: Paint) where Paint: Copy {
+        let mut canvas = self.frame_handler.produce();
+        for mass_point in mass_points {
+            let (px, py) = viewport.world_to_canvas(mass_point.position.0, mass_point.position.1);
+            Rasterizer::draw_filled_circle(&mut canvas, px, py, world::radius(mass_point.mass), paint);
+        }
+        self.frame_handler.consume(canvas);
+    }
+
+    /// the handler frames are drawn onto, for drawing frames of more than circles of a single paint with `Rasterizer`
+    /// and handing them to it directly
+    pub fn frame_handler_mut(&mut self) -> &mut FrameHandler {
+        &mut self.frame_handler
+    }
+
+    pub fn into_frame_handler(self) -> FrameHandler {
+        self.frame_handler
+    }
 }
 
 pub trait FrameHandler {
@@ -44,15 +71,36 @@ pub struct GifHandler<W: Write> {
     width: u32,
     height: u32,
     default_color: image::Rgba<u8>,
-    encoder: image::codecs::gif::GifEncoder<W>
+    /// how long every frame is shown for
+    delay: image::Delay,
+    encoder: gif::Encoder<CommentWriter<W>>
 }
 
 impl <W: Write> GifHandler<W> {
-    pub fn new(width: u32, height: u32, default_color: image::Rgba<u8>, writer: W) -> Self {
-        use image::codecs::gif::*;
-        let mut encoder = GifEncoder::new(writer);
-        encoder.set_repeat(Repeat::Infinite).expect("unable to make gif infinitely repeatable");
-        Self { width, height, default_color, encoder }
+    /// every frame is shown for `delay`, which gifs round to hundredths of a second
+    pub fn new(width: u32, height: u32, default_color: image::Rgba<u8>, delay: image::Delay, writer: W) -> Self {
+        Self::with_comment(width, height, default_color, delay, writer, "")
+    }
+
+    /// [`new`](Self::new) with `comment` in the gif, see [`CommentWriter`]
+    pub fn with_comment(width: u32, height: u32, default_color: image::Rgba<u8>, delay: image::Delay, writer: W, comment: &str) -> Self {
+        let dimension = |pixels: u32| u16::try_from(pixels).expect("gifs are at most 65535 pixels wide and high");
+        let mut encoder = gif::Encoder::new(CommentWriter::new(writer, comment), dimension(width), dimension(height), &[])
+            .expect("unable to write gif header");
+        encoder.set_repeat(gif::Repeat::Infinite).expect("unable to make gif infinitely repeatable");
+        Self { width, height, default_color, delay, encoder }
+    }
+
+    /// encodes `canvas` as the next frame without taking it, so that it can be drawn over again, which is what
+    /// [`consume`](FrameHandler::consume) does, the quantization to the palette of the frame changes its pixels
+    ///
+    /// writes the same bytes as [`GifEncoder`](image::codecs::gif::GifEncoder) would
+    pub fn encode(&mut self, canvas: &mut HorizontalLineImage<image::Rgba<u8>, Vec<u8>>) {
+        let (numerator, denominator) = self.delay.numer_denom_ms();
+        let mut frame = gif::Frame::from_rgba_speed(self.width as u16, self.height as u16, canvas.as_raw_mut(), 1);
+        frame.delay = u16::try_from(numerator / denominator / 10).unwrap_or(u16::MAX);
+        frame.dispose = gif::DisposalMethod::Background;
+        self.encoder.write_frame(&frame).expect("unable to encode frame");
     }
 }
 
@@ -65,8 +113,191 @@ impl <W: Write> FrameHandler for GifHandler<W> {
         })
     }
 
+    fn consume(&mut self, mut canvas: Self::Canvas) {
+        self.encode(&mut canvas);
+    }
+}
+
+/// keeps every frame in memory, for looking at the pixels drawn without encoding and decoding them
+pub struct CollectingHandler {
+    width: u32,
+    height: u32,
+    default_color: image::Rgba<u8>,
+    frames: Vec<image::RgbaImage>
+}
+
+impl CollectingHandler {
+    pub fn new(width: u32, height: u32, default_color: image::Rgba<u8>) -> Self {
+        Self { width, height, default_color, frames: Vec::new() }
+    }
+
+    /// the frames consumed so far, in order
+    pub fn frames(&self) -> &[image::RgbaImage] {
+        &self.frames
+    }
+
+    pub fn into_frames(self) -> Vec<image::RgbaImage> {
+        self.frames
+    }
+}
+
+impl FrameHandler for CollectingHandler {
+    type Canvas = HorizontalLineImage<image::Rgba<u8>, Vec<u8>>;
+
+    fn produce(&mut self) -> Self::Canvas {
+        HorizontalLineImage::new(self.width, self.height, |size| {
+            repeat(self.default_color.0).flatten().take(size).collect()
+        })
+    }
+
+    fn consume(&mut self, canvas: Self::Canvas) {
+        self.frames.push(canvas.into());
+    }
+}
+
+/// encodes frames into an animated png, which keeps every color of them unlike a gif
+///
+/// the number of frames is written before the first of them, so the file is only complete once that many are consumed
+pub struct ApngHandler<W: Write> {
+    width: u32,
+    height: u32,
+    default_color: image::Rgba<u8>,
+    writer: png::Writer<W>
+}
+
+impl <W: Write> ApngHandler<W> {
+    /// `frame_count` frames shown for `delay` seconds each, as a numerator and denominator, played `plays` times or
+    /// forever when 0
+    pub fn new(width: u32, height: u32, default_color: image::Rgba<u8>, writer: W, frame_count: u32, delay: (u16, u16), plays: u32) -> Result<Self, png::EncodingError> {
+        let mut encoder = png::Encoder::new(writer, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(frame_count, plays)?;
+        encoder.set_frame_delay(delay.0, delay.1)?;
+        // so that finish notices missing frames
+        encoder.validate_sequence(true);
+        Ok(Self { width, height, default_color, writer: encoder.write_header()? })
+    }
+
+    /// ends the file, failing if fewer frames were consumed than it was made for
+    pub fn finish(self) -> Result<(), png::EncodingError> {
+        self.writer.finish()
+    }
+}
+
+impl <W: Write> FrameHandler for ApngHandler<W> {
+    type Canvas = HorizontalLineImage<image::Rgba<u8>, Vec<u8>>;
+
+    fn produce(&mut self) -> Self::Canvas {
+        HorizontalLineImage::new(self.width, self.height, |size| {
+            repeat(self.default_color.0).flatten().take(size).collect()
+        })
+    }
+
+    fn consume(&mut self, canvas: Self::Canvas) {
+        self.writer.write_image_data(&canvas.data).expect("unable to encode frame");
+    }
+}
+
+/// writes every frame to a png of its own, `frame_00000.png` onwards, in a directory
+///
+/// [`consume`](FrameHandler::consume) can't return errors, so the first one is kept and the frames after it are
+/// dropped, [`finish`](Self::finish) returns it
+pub struct PngSequenceHandler {
+    width: u32,
+    height: u32,
+    default_color: image::Rgba<u8>,
+    directory: PathBuf,
+    frame: usize,
+    error: Option<image::ImageError>
+}
+
+impl PngSequenceHandler {
+    /// creates `directory` and the directories above it if they don't exist yet
+    pub fn new<P: AsRef<Path>>(width: u32, height: u32, default_color: image::Rgba<u8>, directory: P) -> io::Result<Self> {
+        fs::create_dir_all(&directory)?;
+        Ok(Self { width, height, default_color, directory: directory.as_ref().to_path_buf(), frame: 0, error: None })
+    }
+
+    /// the number of frames written, or the first error writing them
+    pub fn finish(self) -> image::ImageResult<usize> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.frame)
+        }
+    }
+}
+
+impl FrameHandler for PngSequenceHandler {
+    type Canvas = HorizontalLineImage<image::Rgba<u8>, Vec<u8>>;
+
+    fn produce(&mut self) -> Self::Canvas {
+        HorizontalLineImage::new(self.width, self.height, |size| {
+            repeat(self.default_color.0).flatten().take(size).collect()
+        })
+    }
+
     fn consume(&mut self, canvas: Self::Canvas) {
-        self.encoder.encode_frame(image::Frame::new(canvas.into())).expect("unable to encode frame");
+        if self.error.is_some() {
+            return
+        }
+        let path = self.directory.join(format!("frame_{:05}.png", self.frame));
+        let image: image::RgbaImage = canvas.into();
+        match image.save_with_format(path, image::ImageFormat::Png) {
+            Ok(()) => self.frame += 1,
+            Err(error) => self.error = Some(error)
+        }
+    }
+}
+
+/// long exposure of frames, the sum of every frame drawn into it, so that the paths of particles show up as lines
+pub struct ExposureHandler {
+    width: u32,
+    height: u32,
+    /// red, green and blue of every pixel, summed over every frame
+    sums: Vec<f32>
+}
+
+/// how [`ExposureHandler::to_image`] maps the sums to 8 bits, relative to the largest sum, so that only the
+/// brightest pixel is white
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ToneMap {
+    /// `ln(1 + sum) / ln(1 + max)`, for paths only a few frames pass through
+    Log,
+    /// `(sum / max)^(1 / gamma)`
+    Gamma(f32)
+}
+
+impl ExposureHandler {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height, sums: vec![0.0; width as usize * height as usize * 3] }
+    }
+
+    /// adds every pixel of `canvas`, which has to be as large as this, to the sums
+    pub fn accumulate(&mut self, canvas: &HorizontalLineImage<image::Rgba<u8>, Vec<u8>>) {
+        assert_eq!((canvas.width, canvas.height), (self.width, self.height), "canvas must be as large as the exposure");
+        for (sum, pixel) in self.sums.chunks_exact_mut(3).zip(canvas.data.chunks_exact(4)) {
+            for channel in 0..3 {
+                sum[channel] += pixel[channel] as f32;
+            }
+        }
+    }
+
+    /// the sums so far, black if nothing was drawn
+    pub fn to_image(&self, tone_map: ToneMap) -> image::RgbImage {
+        let max = self.sums.iter().copied().fold(0.0, f32::max);
+        let tone = |sum: f32| -> u8 {
+            if max == 0.0 {
+                return 0
+            }
+            let brightness = match tone_map {
+                ToneMap::Log => sum.ln_1p() / max.ln_1p(),
+                ToneMap::Gamma(gamma) => (sum / max).powf(1.0 / gamma)
+            };
+            (brightness * 255.0).round() as u8
+        };
+        let data = self.sums.iter().map(|&sum| tone(sum)).collect();
+        image::RgbImage::from_raw(self.width, self.height, data).expect("sums must cover every pixel")
     }
 }
 
@@ -79,6 +310,10 @@ pub trait PaintScalar<Paint> {
 /// Paint: [RGB](image::Rgb) -> takes the `R` component and multiplies it by `scale`, then expands it to fill the `G` and `B` components
 ///
 /// Paint: [RGBA](image::Rgba) -> same procedure as RGB, `A` is simply copied from the input (not scaled)
+///
+/// Paint: [Luma](image::Luma), [LumaA](image::LumaA) -> `L` is multiplied by `scale`, `A` is copied
+///
+/// colored paints turn gray, [`RgbScalar`] keeps their color
 pub struct GrayscaleRgbScalar;
 
 impl PaintScalar<image::Rgb<u8>> for GrayscaleRgbScalar {
@@ -97,9 +332,157 @@ impl PaintScalar<image::Rgba<u8>> for GrayscaleRgbScalar {
     }
 }
 
+impl PaintScalar<image::Luma<u8>> for GrayscaleRgbScalar {
+    fn scale(paint: &image::Luma<u8>, scale: f32, _: Option<fn(f32) -> f32>) -> image::Luma<u8> {
+        // converting f32 to u8 through `as` is a clamping operation, so `clamp` can be ignored
+        [(paint.0[0] as f32 * scale) as u8].into()
+    }
+}
+
+impl PaintScalar<image::LumaA<u8>> for GrayscaleRgbScalar {
+    fn scale(paint: &image::LumaA<u8>, scale: f32, _: Option<fn(f32) -> f32>) -> image::LumaA<u8> {
+        // converting f32 to u8 through `as` is a clamping operation, so `clamp` can be ignored
+        let [l, a] = paint.0;
+        [(l as f32 * scale) as u8, a].into()
+    }
+}
+
+impl PaintScalar<image::Rgb<u16>> for GrayscaleRgbScalar {
+    fn scale(paint: &image::Rgb<u16>, scale: f32, _: Option<fn(f32) -> f32>) -> image::Rgb<u16> {
+        // converting f32 to u16 through `as` is a clamping operation, so `clamp` can be ignored
+        let c = (paint.0[0] as f32 * scale) as u16;
+        [c; 3].into()
+    }
+}
+
+impl PaintScalar<image::Rgba<f32>> for GrayscaleRgbScalar {
+    fn scale(paint: &image::Rgba<f32>, scale: f32, clamp: Option<fn(f32) -> f32>) -> image::Rgba<f32> {
+        // nothing clamps floats, so a scale just over 1 would make the paint brighter than it is
+        let scale = clamp.map_or(scale, |clamp| clamp(scale));
+        let c = paint.0[0] * scale;
+        [c, c, c, paint.0[3]].into()
+    }
+}
+
+/// RGB scaling
+///
+/// Paint: [RGB](image::Rgb) -> multiplies every component by `scale`, keeping the color of the paint
+///
+/// Paint: [RGBA](image::Rgba) -> same procedure as RGB, `A` is simply copied from the input (not scaled),
+/// [`LinearScalar`] scales it as well
+pub struct RgbScalar;
+
+impl PaintScalar<image::Rgb<u8>> for RgbScalar {
+    fn scale(paint: &image::Rgb<u8>, scale: f32, clamp: Option<fn(f32) -> f32>) -> image::Rgb<u8> {
+        PerChannelScalar::<PreserveAlpha>::scale(paint, scale, clamp)
+    }
+}
+
+impl PaintScalar<image::Rgba<u8>> for RgbScalar {
+    fn scale(paint: &image::Rgba<u8>, scale: f32, clamp: Option<fn(f32) -> f32>) -> image::Rgba<u8> {
+        PerChannelScalar::<PreserveAlpha>::scale(paint, scale, clamp)
+    }
+}
+
+impl PaintScalar<image::Rgb<u16>> for RgbScalar {
+    fn scale(paint: &image::Rgb<u16>, scale: f32, _: Option<fn(f32) -> f32>) -> image::Rgb<u16> {
+        // converting f32 to u16 through `as` is a clamping operation, so `clamp` can be ignored
+        paint.0.map(|c| (c as f32 * scale) as u16).into()
+    }
+}
+
+impl PaintScalar<image::Rgba<f32>> for RgbScalar {
+    fn scale(paint: &image::Rgba<f32>, scale: f32, clamp: Option<fn(f32) -> f32>) -> image::Rgba<f32> {
+        // nothing clamps floats, so a scale just over 1 would make the paint brighter than it is
+        let scale = clamp.map_or(scale, |clamp| clamp(scale));
+        let [r, g, b, a] = paint.0;
+        [r * scale, g * scale, b * scale, a].into()
+    }
+}
+
+/// per channel scaling, which keeps the hue of colored paints at the antialiased edges of what is drawn with them
+///
+/// Paint: [RGB](image::Rgb) -> multiplies every component by `scale`
+///
+/// Paint: [RGBA](image::Rgba) -> same procedure as RGB, `A` is scaled as well with [`ScaleAlpha`], or copied from
+/// the input with [`PreserveAlpha`], which is what [`RgbScalar`] does
+pub struct PerChannelScalar<Alpha: AlphaScaling> {
+    __phantom: PhantomData<Alpha>
+}
+
+/// whether [`PerChannelScalar`] scales the alpha of a paint along with its color
+pub trait AlphaScaling {
+    const SCALED: bool;
+}
+
+/// the alpha is scaled along with the color, for drawing onto transparent canvases
+pub struct ScaleAlpha;
+
+impl AlphaScaling for ScaleAlpha {
+    const SCALED: bool = true;
+}
+
+/// the alpha is copied from the paint
+pub struct PreserveAlpha;
+
+impl AlphaScaling for PreserveAlpha {
+    const SCALED: bool = false;
+}
+
+impl<Alpha: AlphaScaling> PaintScalar<image::Rgb<u8>> for PerChannelScalar<Alpha> {
+    fn scale(paint: &image::Rgb<u8>, scale: f32, _: Option<fn(f32) -> f32>) -> image::Rgb<u8> {
+        // converting f32 to u8 through `as` is a clamping operation, so `clamp` can be ignored
+        paint.0.map(|c| (c as f32 * scale) as u8).into()
+    }
+}
+
+impl<Alpha: AlphaScaling> PaintScalar<image::Rgba<u8>> for PerChannelScalar<Alpha> {
+    fn scale(paint: &image::Rgba<u8>, scale: f32, _: Option<fn(f32) -> f32>) -> image::Rgba<u8> {
+        // converting f32 to u8 through `as` is a clamping operation, so `clamp` can be ignored
+        let [r, g, b, a] = paint.0;
+        let a = if Alpha::SCALED { (a as f32 * scale) as u8 } else { a };
+        [(r as f32 * scale) as u8, (g as f32 * scale) as u8, (b as f32 * scale) as u8, a].into()
+    }
+}
+
+/// linear scaling
+///
+/// Paint: any [pixel](image::Pixel) -> multiplies every channel by `scale`, alpha included, integer channels
+/// saturate like they do through `as`
+pub struct LinearScalar;
+
+impl <Pixel: image::Pixel> PaintScalar<Pixel> for LinearScalar {
+    fn scale(paint: &Pixel, scale: f32, clamp: Option<fn(f32) -> f32>) -> Pixel {
+        let scale = clamp.map_or(scale, |clamp| clamp(scale));
+        paint.map(|c| {
+            let c = c.to_f32().unwrap_or(0.0) * scale;
+            // NumCast fails out of the range of integers, and on NaN, where `as` gives 0
+            NumCast::from(c).unwrap_or(if c > 0.0 {
+                <Pixel::Subpixel as image::Primitive>::DEFAULT_MAX_VALUE
+            } else {
+                <Pixel::Subpixel as image::Primitive>::DEFAULT_MIN_VALUE
+            })
+        })
+    }
+}
+
 pub trait Rasterizer<Canvas, Paint, Scalar: PaintScalar<Paint>> {
     // r should not be negative
     fn draw_filled_circle(canvas: &mut Canvas, cx: f32, cy: f32, r: f32, paint: Paint);
+
+    /// an ellipse with radii `rx` along the x axis and `ry` along the y axis, rotated by `angle` radians from the x
+    /// axis towards the y axis, nothing is drawn if a radius isn't positive
+    ///
+    /// rasterizers that only draw circles don't need to implement this, it panics for them
+    fn draw_filled_ellipse(_canvas: &mut Canvas, _cx: f32, _cy: f32, _rx: f32, _ry: f32, _angle: f32, _paint: Paint) {
+        unimplemented!("this rasterizer only draws circles")
+    }
+}
+
+/// draws lines `width` pixels wide with round ends, the parts of them outside of the canvas are clipped off,
+/// however far outside of it the ends are
+pub trait LineRasterizer<Canvas, Paint, Scalar: PaintScalar<Paint>> {
+    fn draw_line(canvas: &mut Canvas, x0: f32, y0: f32, x1: f32, y1: f32, width: f32, paint: Paint);
 }
 
 pub trait FixedSizeCanvas {
@@ -108,14 +491,88 @@ pub trait FixedSizeCanvas {
     fn height(&self) -> u32;
 }
 
+/// the unchecked methods are what canvases implement and rasterizers draw with, the bounds of which rasterizers
+/// check themselves, the others check the bounds first and are safe to call with any coordinates
 pub trait HorizontalLineCanvas<Paint>: FixedSizeCanvas {
     unsafe fn draw_pixel_unchecked(&mut self, x: u32, y: u32, paint: Paint);
 
     unsafe fn draw_horizontal_line_unchecked(&mut self, x0: u32, x1: u32, y: u32, paint: Paint);
+
+    /// the paint of a pixel
+    ///
+    /// # Safety
+    /// the pixel has to be within the canvas
+    unsafe fn get_pixel_unchecked(&self, x: u32, y: u32) -> Paint;
+
+    /// draws the pixel if it is within the canvas, returning whether it is
+    fn draw_pixel(&mut self, x: u32, y: u32, paint: Paint) -> bool {
+        let within = x < self.width() && y < self.height();
+        if within {
+            // SAFETY: just checked
+            unsafe { self.draw_pixel_unchecked(x, y, paint); }
+        }
+        within
+    }
+
+    /// draws the pixels from `x0` up to `x1` in row `y`, the part of them outside of the canvas is left out
+    fn draw_horizontal_line(&mut self, x0: u32, x1: u32, y: u32, paint: Paint) {
+        let x1 = x1.min(self.width());
+        if x0 < x1 && y < self.height() {
+            // SAFETY: x0..x1 was just cut off at the width, and y checked
+            unsafe { self.draw_horizontal_line_unchecked(x0, x1, y, paint); }
+        }
+    }
+
+    /// the paint of a pixel, `None` outside of the canvas
+    fn get_pixel(&self, x: u32, y: u32) -> Option<Paint> {
+        // SAFETY: the closure only runs when the pixel is within the canvas
+        (x < self.width() && y < self.height()).then(|| unsafe { self.get_pixel_unchecked(x, y) })
+    }
+
+    /// [`draw_pixel_unchecked`](Self::draw_pixel_unchecked), combining `paint` with the pixel under it through `B`
+    ///
+    /// # Safety
+    /// the pixel has to be within the canvas
+    unsafe fn draw_pixel_blend_unchecked<B: Blend<Paint>>(&mut self, x: u32, y: u32, paint: Paint);
+
+    /// [`draw_horizontal_line_unchecked`](Self::draw_horizontal_line_unchecked), combining `paint` with every pixel
+    /// under it through `B`
+    ///
+    /// # Safety
+    /// `x0..x1` and `y` have to be within the canvas
+    unsafe fn draw_horizontal_line_blend_unchecked<B: Blend<Paint>>(&mut self, x0: u32, x1: u32, y: u32, paint: Paint);
+}
+
+/// how a paint is combined with the pixel it is drawn over, see [`BlendingRasterizer`]
+pub trait Blend<Paint> {
+    fn blend(under: &mut Paint, paint: Paint);
+}
+
+/// source-over compositing by the alpha of the paint, see [`image::Pixel::blend`]
+pub struct SourceOver;
+
+impl<Pixel: image::Pixel> Blend<Pixel> for SourceOver {
+    #[inline(always)]
+    fn blend(under: &mut Pixel, paint: Pixel) {
+        under.blend(&paint);
+    }
+}
+
+/// adds every channel of the paint to that of the pixel, clamping at the brightest value, so that overlapping
+/// circles get brighter
+pub struct Additive;
+
+impl<Pixel: image::Pixel> Blend<Pixel> for Additive {
+    #[inline(always)]
+    fn blend(under: &mut Pixel, paint: Pixel) {
+        let max = <Pixel::Subpixel as image::Primitive>::DEFAULT_MAX_VALUE;
+        under.apply2(&paint, |a, b| if a > max - b { max } else { a + b });
+    }
 }
 
 /// `HorizontalLineImage` represents an image, supports fast horizontal line drawing, and is
 /// convertible from and to image::ImageBuffer
+#[derive(Clone)]
 pub struct HorizontalLineImage<Pixel: image::Pixel, Container: Deref<Target = [Pixel::Subpixel]> + DerefMut> {
     width: u32,
     height: u32,
@@ -139,9 +596,20 @@ impl <Pixel: image::Pixel, Container: Deref<Target = [Pixel::Subpixel]> + DerefM
         }
     }
 
+    /// the subpixels of every pixel, row by row
+    pub fn as_raw(&self) -> &[Pixel::Subpixel] {
+        &self.data
+    }
+
+    /// [`as_raw`](Self::as_raw), mutably
+    pub fn as_raw_mut(&mut self) -> &mut [Pixel::Subpixel] {
+        &mut self.data
+    }
+
+    /// index of the first subpixel of a pixel in `data`, which is a slice of subpixels rather than pixels or bytes
     #[inline(always)]
     fn to_data_index(&self, x: u32, y: u32) -> usize {
-        (y as usize * self.width as usize + x as usize) * mem::size_of::<Pixel>()
+        (y as usize * self.width as usize + x as usize) * Pixel::CHANNEL_COUNT as usize
     }
 }
 
@@ -159,11 +627,7 @@ impl<Pixel: image::Pixel, Container: Deref<Target=[Pixel::Subpixel]> + DerefMut>
 
 impl <Pixel: image::Pixel, Container: Deref<Target = [Pixel::Subpixel]> + DerefMut> HorizontalLineCanvas<Pixel> for HorizontalLineImage<Pixel, Container> {
     unsafe fn draw_pixel_unchecked(&mut self, x: u32, y: u32, color: Pixel) {
-        debug_assert!(x < self.width);
-        debug_assert!(y < self.height);
-        let index = self.to_data_index(x, y);
-        let ptr = self.data.get_unchecked_mut(index) as *mut _ as *mut Pixel;
-        *ptr = color;
+        *self.pixel_unchecked_mut(x, y) = color;
     }
 
     unsafe fn draw_horizontal_line_unchecked(&mut self, x0: u32, x1: u32, y: u32, color: Pixel) {
@@ -171,15 +635,104 @@ impl <Pixel: image::Pixel, Container: Deref<Target = [Pixel::Subpixel]> + DerefM
         debug_assert!(x0 < self.width, "x0({x0}) must be less than self.width({})", self.width);
         debug_assert!(x1 <= self.width, "x1({x1}) must be less than or equal to self.width({})", self.width);
         debug_assert!(y < self.height, "y({y}) must be less than self.height({})", self.height);
-        let start = self.to_data_index(x0, y);
-        let mut addr = self.data.get_unchecked_mut(start) as *mut _ as usize;
-        let end = self.to_data_index(x1, y);
-        let end_addr = self.data.get_unchecked(end) as *const _ as usize;
-        while addr < end_addr {
-            // SAFETY: image::Pixel is Copy
-            *(addr as *mut Pixel) = color;
-            addr += mem::size_of::<Pixel>();
+        // the pointer is taken from the whole of the data, so that it may be used past the first pixel
+        let stride = Pixel::CHANNEL_COUNT as usize;
+        let mut subpixel = self.data.as_mut_ptr().add(self.to_data_index(x0, y));
+        for _ in x0..x1 {
+            ptr::copy_nonoverlapping(color.channels().as_ptr(), subpixel, stride);
+            subpixel = subpixel.add(stride);
+        }
+    }
+
+    unsafe fn get_pixel_unchecked(&self, x: u32, y: u32) -> Pixel {
+        debug_assert!(x < self.width, "x({x}) must be less than self.width({})", self.width);
+        debug_assert!(y < self.height, "y({y}) must be less than self.height({})", self.height);
+        let index = self.to_data_index(x, y);
+        *Pixel::from_slice(self.data.get_unchecked(index..index + Pixel::CHANNEL_COUNT as usize))
+    }
+
+    unsafe fn draw_pixel_blend_unchecked<B: Blend<Pixel>>(&mut self, x: u32, y: u32, color: Pixel) {
+        B::blend(self.pixel_unchecked_mut(x, y), color);
+    }
+
+    unsafe fn draw_horizontal_line_blend_unchecked<B: Blend<Pixel>>(&mut self, x0: u32, x1: u32, y: u32, color: Pixel) {
+        debug_assert!(x0 <= x1, "x0({x0}) must be less than or equal to x1({x1})");
+        debug_assert!(x1 <= self.width, "x1({x1}) must be less than or equal to self.width({})", self.width);
+        debug_assert!(y < self.height, "y({y}) must be less than self.height({})", self.height);
+        for x in x0..x1 {
+            B::blend(self.pixel_unchecked_mut(x, y), color);
+        }
+    }
+}
+
+impl <Pixel: image::Pixel, Container: Deref<Target = [Pixel::Subpixel]> + DerefMut> HorizontalLineImage<Pixel, Container> {
+    #[inline(always)]
+    unsafe fn pixel_unchecked_mut(&mut self, x: u32, y: u32) -> &mut Pixel {
+        debug_assert!(x < self.width, "x({x}) must be less than self.width({})", self.width);
+        debug_assert!(y < self.height, "y({y}) must be less than self.height({})", self.height);
+        let index = self.to_data_index(x, y);
+        Pixel::from_slice_mut(self.data.get_unchecked_mut(index..index + Pixel::CHANNEL_COUNT as usize))
+    }
+}
+
+impl <Pixel: image::Pixel<Subpixel = u8>, Container: Deref<Target = [u8]> + DerefMut> HorizontalLineImage<Pixel, Container> {
+    /// every pixel repeated into a `factor` by `factor` block, for drawing at a higher resolution than the image and
+    /// [`downscale`](Self::downscale)ing back to it
+    pub fn upscale(&self, factor: u32) -> HorizontalLineImage<Pixel, Vec<u8>> {
+        assert!(factor > 0, "the factor must be at least 1");
+        let channels = Pixel::CHANNEL_COUNT as usize;
+        let (width, height) = (self.width.checked_mul(factor), self.height.checked_mul(factor));
+        let (width, height) = width.zip(height).expect("upscaled image must be at most u32::MAX pixels wide and high");
+        let mut data = Vec::with_capacity(width as usize * height as usize * channels);
+        // there are no rows to split an image without pixels into
+        if self.data.is_empty() {
+            return HorizontalLineImage::new(width, height, |_| data)
+        }
+        for row in self.data.chunks_exact(self.width as usize * channels) {
+            let start = data.len();
+            for pixel in row.chunks_exact(channels) {
+                for _ in 0..factor {
+                    data.extend_from_slice(pixel);
+                }
+            }
+            let end = data.len();
+            for _ in 1..factor {
+                data.extend_from_within(start..end);
+            }
+        }
+        HorizontalLineImage::new(width, height, |_| data)
+    }
+
+    /// every `factor` by `factor` block of pixels averaged into one, a box filter, the width and height have to be
+    /// multiples of `factor`
+    pub fn downscale(&self, factor: u32) -> HorizontalLineImage<Pixel, Vec<u8>> {
+        assert!(factor > 0, "the factor must be at least 1");
+        let (width, height) = (self.width / factor, self.height / factor);
+        assert!(width * factor == self.width && height * factor == self.height, "{}x{} isn't a multiple of {}", self.width, self.height, factor);
+        let channels = Pixel::CHANNEL_COUNT as usize;
+        let (factor, block) = (factor as usize, factor * factor);
+        // the sums of the blocks of a row of the downscaled image, which fit as long as a block is under 2^32 / 255
+        // pixels
+        let mut sums = vec![0u32; width as usize * channels];
+        let mut data = Vec::with_capacity(sums.len() * height as usize);
+        if self.data.is_empty() {
+            return HorizontalLineImage::new(width, height, |_| data)
         }
+        for rows in self.data.chunks_exact(self.width as usize * channels * factor) {
+            sums.fill(0);
+            for row in rows.chunks_exact(self.width as usize * channels) {
+                for (sums, pixels) in sums.chunks_exact_mut(channels).zip(row.chunks_exact(channels * factor)) {
+                    for pixel in pixels.chunks_exact(channels) {
+                        for (sum, &subpixel) in sums.iter_mut().zip(pixel) {
+                            *sum += subpixel as u32;
+                        }
+                    }
+                }
+            }
+            // rounded to the nearest value rather than down
+            data.extend(sums.iter().map(|&sum| ((sum + block / 2) / block) as u8));
+        }
+        HorizontalLineImage::new(width, height, |_| data)
     }
 }
 
@@ -203,47 +756,156 @@ impl <Pixel: image::Pixel, Container: Deref<Target = [Pixel::Subpixel]> + DerefM
     }
 }
 
-pub struct IntegerRasterizer;
+/// draws with `Rasterizer`, combining every pixel it draws with the one under it through `B`, rather than
+/// overwriting it, such as `BlendingRasterizer<IntegerRasterizer, Additive>`
+pub struct BlendingRasterizer<Rasterizer, B> {
+    __phantom: PhantomData<(Rasterizer, B)>
+}
 
-impl <Paint: Copy, Canvas: HorizontalLineCanvas<Paint>, Scalar: PaintScalar<Paint>> Rasterizer<Canvas, Paint, Scalar> for IntegerRasterizer {
+impl <Paint: Copy, Canvas: HorizontalLineCanvas<Paint>, Scalar: PaintScalar<Paint>, R, B: Blend<Paint>> Rasterizer<Canvas, Paint, Scalar> for BlendingRasterizer<R, B>
+where R: for<'a> Rasterizer<BlendingCanvas<'a, Canvas, B>, Paint, Scalar> {
     fn draw_filled_circle(canvas: &mut Canvas, cx: f32, cy: f32, r: f32, paint: Paint) {
-        Self::draw_filled_circle_internal(canvas, cx, cy, r, paint);
+        R::draw_filled_circle(&mut BlendingCanvas { canvas, __phantom: PhantomData }, cx, cy, r, paint);
+    }
+
+    fn draw_filled_ellipse(canvas: &mut Canvas, cx: f32, cy: f32, rx: f32, ry: f32, angle: f32, paint: Paint) {
+        R::draw_filled_ellipse(&mut BlendingCanvas { canvas, __phantom: PhantomData }, cx, cy, rx, ry, angle, paint);
     }
 }
 
-impl IntegerRasterizer {
-    // this implementation doesn't draw circles beyond i32::MAX in order to save on a bit of speed
-    // (and my sanity), and because rendering an image that large seems a bit extreme
-    #[inline(always)]
-    fn draw_filled_circle_internal<Paint: Copy, Canvas: HorizontalLineCanvas<Paint>>(canvas: &mut Canvas, cx: f32, cy: f32, r: f32, paint: Paint) -> Option<()> {
-        let x0 = cx.to_i32()?;
-        let y0 = cy.to_i32()?;
-        let r = r.to_i32()?;
-        let mut x = 0;
-        let mut y = r;
-        let mut p = 1 - r;
-        while x <= y {
-            let sub_x0_x = x0.saturating_sub(x).try_into().unwrap_or(0);
-            if sub_x0_x < canvas.width() {
-                let add_x0_x = u32::min(x0.saturating_add(x).try_into().unwrap_or(0) + 1, canvas.width());
-                unsafe {
-                    Self::draw_horizontal_line(canvas, sub_x0_x, add_x0_x, y0.checked_sub(y), paint);
-                    Self::draw_horizontal_line(canvas, sub_x0_x, add_x0_x, y0.checked_add(y), paint);
-                }
-            }
-            let sub_x0_y = x0.saturating_sub(y).try_into().unwrap_or(0);
-            if sub_x0_y < canvas.width() {
-                let add_x0_y = u32::min(x0.saturating_add(y).try_into().unwrap_or(0) + 1, canvas.width());
-                unsafe {
-                    Self::draw_horizontal_line(canvas, sub_x0_y, add_x0_y, y0.checked_sub(x), paint);
-                    Self::draw_horizontal_line(canvas, sub_x0_y, add_x0_y, y0.checked_add(x), paint);
-                }
-            }
+impl <Paint: Copy, Canvas: HorizontalLineCanvas<Paint>, Scalar: PaintScalar<Paint>, R, B: Blend<Paint>> LineRasterizer<Canvas, Paint, Scalar> for BlendingRasterizer<R, B>
+where R: for<'a> LineRasterizer<BlendingCanvas<'a, Canvas, B>, Paint, Scalar> {
+    fn draw_line(canvas: &mut Canvas, x0: f32, y0: f32, x1: f32, y1: f32, width: f32, paint: Paint) {
+        R::draw_line(&mut BlendingCanvas { canvas, __phantom: PhantomData }, x0, y0, x1, y1, width, paint);
+    }
+}
 
-            x += 1;
-            if p < 0 {
+/// canvas that blends everything drawn on it onto `canvas` through `B`, see [`BlendingRasterizer`]
+pub struct BlendingCanvas<'a, Canvas, B> {
+    canvas: &'a mut Canvas,
+    __phantom: PhantomData<B>
+}
+
+impl<Canvas: FixedSizeCanvas, B> FixedSizeCanvas for BlendingCanvas<'_, Canvas, B> {
+    #[inline(always)]
+    fn width(&self) -> u32 {
+        self.canvas.width()
+    }
+
+    #[inline(always)]
+    fn height(&self) -> u32 {
+        self.canvas.height()
+    }
+}
+
+impl<Paint, Canvas: HorizontalLineCanvas<Paint>, B: Blend<Paint>> HorizontalLineCanvas<Paint> for BlendingCanvas<'_, Canvas, B> {
+    unsafe fn draw_pixel_unchecked(&mut self, x: u32, y: u32, paint: Paint) {
+        self.canvas.draw_pixel_blend_unchecked::<B>(x, y, paint);
+    }
+
+    unsafe fn draw_horizontal_line_unchecked(&mut self, x0: u32, x1: u32, y: u32, paint: Paint) {
+        self.canvas.draw_horizontal_line_blend_unchecked::<B>(x0, x1, y, paint);
+    }
+
+    unsafe fn get_pixel_unchecked(&self, x: u32, y: u32) -> Paint {
+        self.canvas.get_pixel_unchecked(x, y)
+    }
+
+    unsafe fn draw_pixel_blend_unchecked<B2: Blend<Paint>>(&mut self, x: u32, y: u32, paint: Paint) {
+        self.canvas.draw_pixel_blend_unchecked::<B2>(x, y, paint);
+    }
+
+    unsafe fn draw_horizontal_line_blend_unchecked<B2: Blend<Paint>>(&mut self, x0: u32, x1: u32, y: u32, paint: Paint) {
+        self.canvas.draw_horizontal_line_blend_unchecked::<B2>(x0, x1, y, paint);
+    }
+}
+
+pub struct IntegerRasterizer;
+
+impl <Paint: Copy, Canvas: HorizontalLineCanvas<Paint>, Scalar: PaintScalar<Paint>> Rasterizer<Canvas, Paint, Scalar> for IntegerRasterizer {
+    fn draw_filled_circle(canvas: &mut Canvas, cx: f32, cy: f32, r: f32, paint: Paint) {
+        Self::draw_filled_circle_internal(canvas, cx, cy, r, paint);
+    }
+
+    /// fills the pixels whose centers are within the ellipse, a row at a time
+    fn draw_filled_ellipse(canvas: &mut Canvas, cx: f32, cy: f32, rx: f32, ry: f32, angle: f32, paint: Paint) {
+        if rx.is_nan() || ry.is_nan() || rx <= 0.0 || ry <= 0.0 {
+            return
+        }
+        let (sin, cos) = angle.sin_cos();
+        let ellipse = Ellipse::new(cx, cy, rx, ry, sin, cos);
+        let (_, half_height) = ellipse.extents();
+        let (min_y, max_y) = match pixel_span(cy, half_height, canvas.height()) {
+            Some(span) => span,
+            None => return
+        };
+        for y in min_y..max_y {
+            let (left, right) = match ellipse.row(y as f32 + 0.5) {
+                Some(row) => row,
+                None => continue
+            };
+            // the pixels from x0 up to x1 have their centers from left to right, casting saturates at 0
+            let x0 = (left - 0.5).ceil() as u32;
+            let x1 = u32::min(((right - 0.5).floor() + 1.0) as u32, canvas.width());
+            if x0 < x1 {
+                unsafe {
+                    canvas.draw_horizontal_line_unchecked(x0, x1, y, paint);
+                }
+            }
+        }
+    }
+}
+
+/// lines up to a pixel wide are drawn with [`draw_line`], wider ones fill the pixels whose centers are within half
+/// of `width` of the line
+impl <Paint: Copy, Canvas: HorizontalLineCanvas<Paint>, Scalar: PaintScalar<Paint>> LineRasterizer<Canvas, Paint, Scalar> for IntegerRasterizer {
+    fn draw_line(canvas: &mut Canvas, x0: f32, y0: f32, x1: f32, y1: f32, width: f32, paint: Paint) {
+        if width <= 1.0 {
+            if width > 0.0 {
+                draw_line(canvas, x0, y0, x1, y1, paint);
+            }
+            return
+        }
+        let half_width = width / 2.0;
+        for_each_line_pixel(canvas.width(), canvas.height(), x0, y0, x1, y1, half_width, |segment, x, y| {
+            if segment.distance(x as f32 + 0.5, y as f32 + 0.5) <= half_width {
+                unsafe {
+                    canvas.draw_pixel_unchecked(x, y, paint);
+                }
+            }
+        });
+    }
+}
+
+impl IntegerRasterizer {
+    // this implementation doesn't draw circles beyond i32::MAX in order to save on a bit of speed
+    // (and my sanity), and because rendering an image that large seems a bit extreme
+    #[inline(always)]
+    fn draw_filled_circle_internal<Paint: Copy, Canvas: HorizontalLineCanvas<Paint>>(canvas: &mut Canvas, cx: f32, cy: f32, r: f32, paint: Paint) -> Option<()> {
+        let x0 = cx.to_i32()?;
+        let y0 = cy.to_i32()?;
+        let r = r.to_i32()?;
+        if r < 0 {
+            return Some(())
+        }
+        // every row is drawn once, which blending relies on, as soon as its half width is known, the rows x from
+        // y0 are the widest at y, and the rows y from y0 at the last x before y moves on
+        let mut x = 0;
+        let mut y = r;
+        let mut p = 1 - r;
+        // rows further than this from y0 are above or below the canvas, and once x is past it so is y
+        let reach = i64::max(y0 as i64, canvas.height() as i64 - 1 - y0 as i64);
+        while x <= y && x as i64 <= reach {
+            Self::draw_rows(canvas, x0, y0, x, y, paint);
+
+            x += 1;
+            if p < 0 {
                 p += 2 * x + 1;
             } else {
+                // unless the row is also one x from y0
+                if y >= x {
+                    Self::draw_rows(canvas, x0, y0, y, x - 1, paint);
+                }
                 y -= 1;
                 p += 2 * (x - y) + 1;
             }
@@ -251,6 +913,24 @@ impl IntegerRasterizer {
         Some(())
     }
 
+    /// draws the rows `offset` above and below `y0`, from `half_width` left of `x0` to as far right of it
+    #[inline(always)]
+    fn draw_rows<Paint: Copy, Canvas: HorizontalLineCanvas<Paint>>(canvas: &mut Canvas, x0: i32, y0: i32, offset: i32, half_width: i32, paint: Paint) {
+        let (left, right) = (x0.saturating_sub(half_width), x0.saturating_add(half_width));
+        // rows entirely left or right of the canvas
+        if right < 0 || left as i64 >= canvas.width() as i64 {
+            return
+        }
+        let sub_x0 = left.max(0) as u32;
+        let add_x0 = u32::min(right as u32 + 1, canvas.width());
+        unsafe {
+            Self::draw_horizontal_line(canvas, sub_x0, add_x0, y0.checked_sub(offset), paint);
+            if offset != 0 {
+                Self::draw_horizontal_line(canvas, sub_x0, add_x0, y0.checked_add(offset), paint);
+            }
+        }
+    }
+
     unsafe fn draw_horizontal_line<Paint: Copy, Canvas: HorizontalLineCanvas<Paint>>(canvas: &mut Canvas, x0: u32, x1: u32, opt_signed_y: Option<i32>, paint: Paint) {
         if let Some(signed_y) = opt_signed_y {
             if signed_y >= 0 {
@@ -263,96 +943,432 @@ impl IntegerRasterizer {
     }
 }
 
+/// draws a line a pixel wide from `(x0, y0)` to `(x1, y1)` with Bresenham's algorithm, the ends are truncated
+/// to whole pixels like the centers of [`IntegerRasterizer`]'s circles
+///
+/// the line is clipped to just outside of the canvas first, so ends far outside of it don't take long to draw
+pub fn draw_line<Paint: Copy, Canvas: HorizontalLineCanvas<Paint>>(canvas: &mut Canvas, x0: f32, y0: f32, x1: f32, y1: f32, paint: Paint) {
+    let (width, height) = (canvas.width() as f32, canvas.height() as f32);
+    let (x0, y0, x1, y1) = match clip_line(x0, y0, x1, y1, -1.0, -1.0, width + 1.0, height + 1.0) {
+        Some(line) => line,
+        None => return
+    };
+    let (mut x, mut y, x1, y1) = match (x0.to_i64(), y0.to_i64(), x1.to_i64(), y1.to_i64()) {
+        (Some(x0), Some(y0), Some(x1), Some(y1)) => (x0, y0, x1, y1),
+        _ => return
+    };
+    let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+    let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+    let mut error = dx + dy;
+    loop {
+        if x >= 0 && y >= 0 && x < canvas.width() as i64 && y < canvas.height() as i64 {
+            unsafe {
+                canvas.draw_pixel_unchecked(x as u32, y as u32, paint);
+            }
+        }
+        if x == x1 && y == y1 {
+            break
+        }
+        let e2 = 2 * error;
+        if e2 >= dy {
+            error += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            error += dx;
+            y += sy;
+        }
+    }
+}
+
+/// fills the pixels from `x0` up to `x1` and from `y0` up to `y1`, the part of the rectangle outside of the canvas is
+/// left out
+pub fn fill_rectangle<Paint: Copy, Canvas: HorizontalLineCanvas<Paint>>(canvas: &mut Canvas, x0: u32, y0: u32, x1: u32, y1: u32, paint: Paint) {
+    let (x1, y1) = (x1.min(canvas.width()), y1.min(canvas.height()));
+    if x0 >= x1 {
+        return
+    }
+    for y in y0..y1 {
+        unsafe {
+            canvas.draw_horizontal_line_unchecked(x0, x1, y, paint);
+        }
+    }
+}
+
 pub struct AreaIntersectionRasterizer;
 
 impl <Paint: Copy, Canvas: HorizontalLineCanvas<Paint>, Scalar: PaintScalar<Paint>> Rasterizer<Canvas, Paint, Scalar> for AreaIntersectionRasterizer {
     fn draw_filled_circle(canvas: &mut Canvas, cx: f32, cy: f32, r: f32, paint: Paint) {
-        let min_y = (cy - r) as u32;
-        if min_y >= canvas.height() {
-            return;
+        if r.is_nan() || r <= 0.0 {
+            return
+        }
+        let ((min_x, max_x), (min_y, max_y)) = match (pixel_span(cx, r, canvas.width()), pixel_span(cy, r, canvas.height())) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return
+        };
+        let area = |x0, y0, x1, y1| area_intersection_circle_rectangle(x0, y0, x1, y1, cx, cy, r);
+
+        if r <= 2.0 {
+            Self::draw_every_pixel::<_, _, Scalar>(canvas, min_x, max_x, min_y, max_y, area, paint);
+        } else {
+            let r_sq = r * r;
+            Self::draw_rows::<_, _, Scalar>(canvas, cx, cy, min_x, max_x, min_y, max_y, |dy| f32::sqrt(f32::max(r_sq - dy * dy, 0.0)), area, paint);
         }
+    }
 
-        let max_y = u32::min((cy + r + 1.0) as u32, canvas.height());
+    fn draw_filled_ellipse(canvas: &mut Canvas, cx: f32, cy: f32, rx: f32, ry: f32, angle: f32, paint: Paint) {
+        // sin and cos this far from 0 are taken as exactly 0, a pixel wide ellipse would need to be hundreds of
+        // thousands of pixels long for it to make a difference
+        const AXIS_ALIGNED: f32 = 1e-6;
 
-        let min_x = (cx - r) as u32;
-        if min_x >= canvas.width() {
-            return;
+        if rx.is_nan() || ry.is_nan() || rx <= 0.0 || ry <= 0.0 {
+            return
         }
+        let (sin, cos) = angle.sin_cos();
+        let (rx, ry) = if sin.abs() < AXIS_ALIGNED {
+            (rx, ry)
+        } else if cos.abs() < AXIS_ALIGNED {
+            (ry, rx)
+        } else {
+            Self::draw_rotated_ellipse::<_, _, Scalar>(canvas, &Ellipse::new(cx, cy, rx, ry, sin, cos), paint);
+            return
+        };
 
-        if r <= 2.0 {
-            let max_x = u32::min((cx + r + 1.0) as u32, canvas.width());
-
-            for y in min_y..max_y {
-                let y0 = y as f32;
-                let y1 = y0 + 1.0;
-                for x in min_x..max_x {
-                    let x0 = x as f32;
-                    let x1 = x0 + 1.0;
-                    let a = area_intersection_circle_rectangle(x0, y0, x1, y1, cx, cy, r);
-                    let scaled_paint = Scalar::scale(&paint, a, Some(|f| clamp(f, 0.0, 1.0)));
-                    unsafe {
-                        canvas.draw_pixel_unchecked(x, y, scaled_paint);
-                    }
+        let ((min_x, max_x), (min_y, max_y)) = match (pixel_span(cx, rx, canvas.width()), pixel_span(cy, ry, canvas.height())) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return
+        };
+        // an axis aligned ellipse is a circle of radius ry stretched along x by rx / ry, so the area it shares with
+        // a pixel is that of the circle and the pixel squeezed back along x, stretched
+        let (squeeze, stretch) = (ry / rx, rx / ry);
+        let area = |x0, y0, x1, y1| {
+            stretch * area_intersection_circle_rectangle(cx + (x0 - cx) * squeeze, y0, cx + (x1 - cx) * squeeze, y1, cx, cy, ry)
+        };
+
+        if f32::max(rx, ry) <= 2.0 {
+            Self::draw_every_pixel::<_, _, Scalar>(canvas, min_x, max_x, min_y, max_y, area, paint);
+        } else {
+            let ry_sq = ry * ry;
+            Self::draw_rows::<_, _, Scalar>(canvas, cx, cy, min_x, max_x, min_y, max_y, |dy| f32::sqrt(f32::max(ry_sq - dy * dy, 0.0)) * stretch, area, paint);
+        }
+    }
+}
+
+impl AreaIntersectionRasterizer {
+    /// draws every pixel from `min_x` up to `max_x` and from `min_y` up to `max_y`, which have to be within the
+    /// canvas, with `paint` scaled by its `area(x0, y0, x1, y1)`, for shapes small enough that most of their pixels
+    /// are only partially covered
+    #[allow(clippy::too_many_arguments)]
+    #[inline(always)]
+    fn draw_every_pixel<Paint: Copy, Canvas: HorizontalLineCanvas<Paint>, Scalar: PaintScalar<Paint>>(canvas: &mut Canvas, min_x: u32, max_x: u32, min_y: u32, max_y: u32, area: impl Fn(f32, f32, f32, f32) -> f32, paint: Paint) {
+        for y in min_y..max_y {
+            let y0 = y as f32;
+            let y1 = y0 + 1.0;
+            for x in min_x..max_x {
+                let x0 = x as f32;
+                let x1 = x0 + 1.0;
+                let a = area(x0, y0, x1, y1);
+                let scaled_paint = Scalar::scale(&paint, a, Some(|f| clamp(f, 0.0, 1.0)));
+                unsafe {
+                    canvas.draw_pixel_unchecked(x, y, scaled_paint);
                 }
             }
-        } else {
-            let r_sq = r * r;
+        }
+    }
 
-            // Rust seems to inline this
-            // the caller must ensure y is within the image, this would be marked as unsafe if that were possible
-            // (worth noting all possible callers are in this function, so this isn't really an issue)
-            let mut draw_horizontal_line_unchecked_y = |r_x: f32, y, y0, y1| {
-                let mut min_x = (cx - r_x) as u32;
-                let mut max_x = u32::min((cx + r_x) as u32 + 1, canvas.width() - 1);
-
-                loop {
-                    let x0 = min_x as f32;
-                    let x1 = x0 + 1.0;
-                    let a = area_intersection_circle_rectangle(x0, y0, x1, y1, cx, cy, r);
-                    if a >= 1.0 {
-                        break
-                    }
+    /// draws a shape that is convex and symmetric across the vertical line through `(cx, cy)` a row at a time, the
+    /// pixels at the ends of a row are scaled by their `area(x0, y0, x1, y1)`, up to the first ones it covers
+    /// entirely, and the ones between are filled
+    ///
+    /// `half_width(dy)` is half the width of the shape `dy` above or below its center, the pixel ranges have to be
+    /// within the canvas
+    #[allow(clippy::too_many_arguments)]
+    #[inline(always)]
+    fn draw_rows<Paint: Copy, Canvas: HorizontalLineCanvas<Paint>, Scalar: PaintScalar<Paint>>(canvas: &mut Canvas, cx: f32, cy: f32, min_x: u32, max_x: u32, min_y: u32, max_y: u32, half_width: impl Fn(f32) -> f32, area: impl Fn(f32, f32, f32, f32) -> f32, paint: Paint) {
+        for y in min_y..max_y {
+            let y0 = y as f32;
+            let y1 = y0 + 1.0;
+            // the shape is widest within the row at the edge of it closest to the center, or at the center itself
+            // when the row holds it
+            let dy = if y1 <= cy {
+                cy - y1
+            } else if y0 >= cy {
+                y0 - cy
+            } else {
+                0.0
+            };
+            let r_x = half_width(dy);
+
+            // the pixels of the row the shape could touch, the inclusive ends are walked inwards over the pixels
+            // it only partially covers, and the ones between them are filled
+            let mut left = i64::max((cx - r_x).floor() as i64, min_x as i64);
+            let mut right = i64::min((cx + r_x).floor() as i64, max_x as i64 - 1);
+            let mut partial = |x: i64| {
+                let x0 = x as f32;
+                let a = area(x0, y0, x0 + 1.0, y1);
+                if a < 1.0 {
                     let scaled_paint = Scalar::scale(&paint, a, Some(|f| f32::max(f, 0.0)));
+                    // SAFETY: left and right stay within min_x..max_x, which is within the canvas, as does y
                     unsafe {
-                        canvas.draw_pixel_unchecked(min_x, y, scaled_paint);
-                    }
-                    min_x += 1;
-                    if min_x >= max_x {
-                        return;
+                        canvas.draw_pixel_unchecked(x as u32, y, scaled_paint);
                     }
                 }
-                loop {
-                    let x0 = max_x as f32;
-                    let x1 = x0 + 1.0;
-                    let a = area_intersection_circle_rectangle(x0, y0, x1, y1, cx, cy, r);
-                    if a >= 1.0 {
-                        break
+                a < 1.0
+            };
+            while left <= right && partial(left) {
+                left += 1;
+            }
+            while left < right && partial(right) {
+                right -= 1;
+            }
+            if left <= right {
+                unsafe {
+                    canvas.draw_horizontal_line_unchecked(left as u32, right as u32 + 1, y, paint);
+                }
+            }
+        }
+    }
+
+    /// there is no closed form for the area a rotated ellipse shares with a pixel that is cheap enough, so it is
+    /// estimated from a grid of points within the pixels the ellipse only partially covers
+    fn draw_rotated_ellipse<Paint: Copy, Canvas: HorizontalLineCanvas<Paint>, Scalar: PaintScalar<Paint>>(canvas: &mut Canvas, ellipse: &Ellipse, paint: Paint) {
+        const SAMPLES: u32 = 4;
+        // the farthest a point of a pixel is from its center
+        const HALF_DIAGONAL: f32 = std::f32::consts::SQRT_2 / 2.0;
+
+        let (half_width, half_height) = ellipse.extents();
+        let ((min_x, max_x), (min_y, max_y)) = match (pixel_span(ellipse.cx, half_width, canvas.width()), pixel_span(ellipse.cy, half_height, canvas.height())) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return
+        };
+        // no point within the ellipse grown by this factor is within half a diagonal of it
+        let reach = 1.0 + HALF_DIAGONAL / f32::min(ellipse.rx, ellipse.ry);
+        let reach_sq = reach * reach;
+        for y in min_y..max_y {
+            let y0 = y as f32;
+            let y1 = y0 + 1.0;
+            for x in min_x..max_x {
+                let x0 = x as f32;
+                let x1 = x0 + 1.0;
+                // the ellipse is convex, so it covers every point of a pixel if it covers its corners
+                let a = if [(x0, y0), (x1, y0), (x0, y1), (x1, y1)].iter().all(|&(x, y)| ellipse.normalized(x, y) <= 1.0) {
+                    1.0
+                } else if ellipse.normalized(x0 + 0.5, y0 + 0.5) > reach_sq {
+                    continue
+                } else {
+                    let mut covered = 0;
+                    for i in 0..SAMPLES {
+                        for j in 0..SAMPLES {
+                            let sample_x = x0 + (i as f32 + 0.5) / SAMPLES as f32;
+                            let sample_y = y0 + (j as f32 + 0.5) / SAMPLES as f32;
+                            if ellipse.normalized(sample_x, sample_y) <= 1.0 {
+                                covered += 1;
+                            }
+                        }
                     }
-                    let scaled_paint = Scalar::scale(&paint, a, Some(|f| f32::max(f, 0.0)));
+                    covered as f32 / (SAMPLES * SAMPLES) as f32
+                };
+                if a > 0.0 {
+                    let scaled_paint = Scalar::scale(&paint, a, Some(|f| clamp(f, 0.0, 1.0)));
                     unsafe {
-                        canvas.draw_pixel_unchecked(max_x, y, scaled_paint);
+                        canvas.draw_pixel_unchecked(x, y, scaled_paint);
                     }
-                    max_x -= 1;
                 }
-                unsafe {
-                    canvas.draw_horizontal_line_unchecked(min_x, max_x + 1, y, paint);
+            }
+        }
+    }
+}
+
+/// lines of every width are antialiased, the paint of a pixel is scaled by how much of it the line covers, which is
+/// estimated from a grid of points within it
+impl <Paint: Copy, Canvas: HorizontalLineCanvas<Paint>, Scalar: PaintScalar<Paint>> LineRasterizer<Canvas, Paint, Scalar> for AreaIntersectionRasterizer {
+    fn draw_line(canvas: &mut Canvas, x0: f32, y0: f32, x1: f32, y1: f32, width: f32, paint: Paint) {
+        const SAMPLES: u32 = 4;
+        // the farthest a point of a pixel is from its center
+        const HALF_DIAGONAL: f32 = std::f32::consts::SQRT_2 / 2.0;
+
+        if width.is_nan() || width <= 0.0 {
+            return
+        }
+        let half_width = width / 2.0;
+        for_each_line_pixel(canvas.width(), canvas.height(), x0, y0, x1, y1, half_width, |segment, x, y| {
+            let center = segment.distance(x as f32 + 0.5, y as f32 + 0.5);
+            let a = if center <= half_width - HALF_DIAGONAL {
+                1.0
+            } else if center >= half_width + HALF_DIAGONAL {
+                0.0
+            } else {
+                let mut covered = 0;
+                for i in 0..SAMPLES {
+                    for j in 0..SAMPLES {
+                        let sample_x = x as f32 + (i as f32 + 0.5) / SAMPLES as f32;
+                        let sample_y = y as f32 + (j as f32 + 0.5) / SAMPLES as f32;
+                        if segment.distance(sample_x, sample_y) <= half_width {
+                            covered += 1;
+                        }
+                    }
                 }
+                covered as f32 / (SAMPLES * SAMPLES) as f32
             };
+            if a > 0.0 {
+                let scaled_paint = Scalar::scale(&paint, a, Some(|f| clamp(f, 0.0, 1.0)));
+                unsafe {
+                    canvas.draw_pixel_unchecked(x, y, scaled_paint);
+                }
+            }
+        });
+    }
+}
+
+/// an ellipse with radii `rx` and `ry`, rotated by an angle of which `sin` and `cos` are the sine and cosine
+struct Ellipse {
+    cx: f32,
+    cy: f32,
+    rx: f32,
+    ry: f32,
+    sin: f32,
+    cos: f32
+}
+
+impl Ellipse {
+    fn new(cx: f32, cy: f32, rx: f32, ry: f32, sin: f32, cos: f32) -> Self {
+        Self { cx, cy, rx, ry, sin, cos }
+    }
+
+    /// half the width and half the height of the smallest axis aligned box around the ellipse
+    fn extents(&self) -> (f32, f32) {
+        (f32::hypot(self.rx * self.cos, self.ry * self.sin), f32::hypot(self.rx * self.sin, self.ry * self.cos))
+    }
+
+    /// 1 on the edge of the ellipse, less within it, more outside of it, and the square of how much the ellipse
+    /// would have to be grown by to reach `(x, y)`
+    #[inline(always)]
+    fn normalized(&self, x: f32, y: f32) -> f32 {
+        let (dx, dy) = (x - self.cx, y - self.cy);
+        let u = (dx * self.cos + dy * self.sin) / self.rx;
+        let v = (dy * self.cos - dx * self.sin) / self.ry;
+        u * u + v * v
+    }
+
+    /// the leftmost and rightmost x of the ellipse along the horizontal line at `y`, `None` if it doesn't reach it
+    fn row(&self, y: f32) -> Option<(f32, f32)> {
+        // normalized(x, y) = 1 is a quadratic a * dx^2 + b * dx + c = 0 in dx = x - cx
+        let dy = y - self.cy;
+        let (rx_sq, ry_sq) = (self.rx * self.rx, self.ry * self.ry);
+        let (sin_sq, cos_sq) = (self.sin * self.sin, self.cos * self.cos);
+        let a = cos_sq / rx_sq + sin_sq / ry_sq;
+        let b = 2.0 * dy * self.sin * self.cos * (1.0 / rx_sq - 1.0 / ry_sq);
+        let c = dy * dy * (sin_sq / rx_sq + cos_sq / ry_sq) - 1.0;
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant.is_nan() || discriminant < 0.0 {
+            return None
+        }
+        let root = discriminant.sqrt();
+        Some((self.cx + (-b - root) / (2.0 * a), self.cx + (-b + root) / (2.0 * a)))
+    }
+}
+
+/// the pixels from `c - r` to `c + r` along an axis of `size` pixels, cut off at the canvas, as an exclusive range,
+/// `None` if none of them are on it
+fn pixel_span(c: f32, r: f32, size: u32) -> Option<(u32, u32)> {
+    // worked out signed, since the shape can be partially or entirely off either side of the canvas
+    match ((c - r).floor().to_i64(), (c + r).floor().to_i64()) {
+        (Some(min), Some(max)) => {
+            let (min, max) = (min.max(0), (max + 1).min(size as i64));
+            (min < max).then_some((min as u32, max as u32))
+        },
+        _ => None
+    }
+}
+
+/// a line segment, for measuring how far pixels are from it
+struct Segment {
+    x0: f32,
+    y0: f32,
+    dx: f32,
+    dy: f32,
+    length_sq: f32
+}
+
+impl Segment {
+    /// distance of `(x, y)` from the closest point of the segment
+    #[inline(always)]
+    fn distance(&self, x: f32, y: f32) -> f32 {
+        let (px, py) = (x - self.x0, y - self.y0);
+        let t = if self.length_sq > 0.0 {
+            clamp((px * self.dx + py * self.dy) / self.length_sq, 0.0, 1.0)
+        } else {
+            0.0
+        };
+        f32::hypot(px - t * self.dx, py - t * self.dy)
+    }
+}
+
+/// calls `f` with every pixel of a `width` by `height` canvas that could be within `reach` of the segment from
+/// `(x0, y0)` to `(x1, y1)`, going a row at a time
+///
+/// the segment is clipped to just beyond `reach` outside of the canvas first, so the ends it is given can be
+/// arbitrarily far away, the clipped ends being outside of the canvas keeps their round caps out of it
+#[allow(clippy::too_many_arguments)]
+fn for_each_line_pixel<F: FnMut(&Segment, u32, u32)>(width: u32, height: u32, x0: f32, y0: f32, x1: f32, y1: f32, reach: f32, mut f: F) {
+    let margin = reach + 1.0;
+    let (x0, y0, x1, y1) = match clip_line(x0, y0, x1, y1, -margin, -margin, width as f32 + margin, height as f32 + margin) {
+        Some(line) => line,
+        None => return
+    };
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    let segment = Segment { x0, y0, dx, dy, length_sq: dx * dx + dy * dy };
+
+    // casting a negative f32 to u32 saturates at 0
+    let min_y = (f32::min(y0, y1) - reach).floor() as u32;
+    let max_y = u32::min((f32::max(y0, y1) + reach).ceil() as u32, height);
+    for y in min_y..max_y {
+        // every point within `reach` of the row is within `reach` of a part of the segment within `reach` of it
+        let band = clip_line(x0, y0, x1, y1, f32::NEG_INFINITY, y as f32 - reach, f32::INFINITY, y as f32 + 1.0 + reach);
+        let (band_x0, _, band_x1, _) = match band {
+            Some(band) => band,
+            None => continue
+        };
+        let min_x = (f32::min(band_x0, band_x1) - reach).floor() as u32;
+        let max_x = u32::min((f32::max(band_x0, band_x1) + reach).ceil() as u32, width);
+        for x in min_x..max_x {
+            f(&segment, x, y);
+        }
+    }
+}
 
-            for y in min_y..cy as u32 {
-                let y0 = y as f32;
-                let y1 = y0 + 1.0;
-                let r_x = f32::sqrt(r_sq - ((y1 - cy) * (y1 - cy)));
-                draw_horizontal_line_unchecked_y(r_x, y, y0, y1);
+/// the part of the segment from `(x0, y0)` to `(x1, y1)` within the rectangle from `(min_x, min_y)` to
+/// `(max_x, max_y)` with the Liang-Barsky algorithm, `None` if no part of it is or an end isn't finite
+#[allow(clippy::too_many_arguments)]
+fn clip_line(x0: f32, y0: f32, x1: f32, y1: f32, min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Option<(f32, f32, f32, f32)> {
+    if !(x0.is_finite() && y0.is_finite() && x1.is_finite() && y1.is_finite()) {
+        return None
+    }
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    // the segment is `(x0, y0) + t * (dx, dy)` for t from 0 to 1, every edge narrows that range
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for (p, q) in [(-dx, x0 - min_x), (dx, max_x - x0), (-dy, y0 - min_y), (dy, max_y - y0)] {
+        if p == 0.0 {
+            // parallel to the edge, and either entirely inside or entirely outside of it
+            if q < 0.0 {
+                return None
             }
-            for y in cy as u32..max_y {
-                let y0 = y as f32;
-                let y1 = y0 + 1.0;
-                let r_x = f32::sqrt(r_sq - ((y0 - cy) * (y0 - cy)));
-                draw_horizontal_line_unchecked_y(r_x, y, y0, y1);
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
             }
         }
     }
+    if t0 > t1 {
+        return None
+    }
+    // ends that weren't clipped are kept exactly
+    let start = if t0 > 0.0 { (x0 + t0 * dx, y0 + t0 * dy) } else { (x0, y0) };
+    let end = if t1 < 1.0 { (x0 + t1 * dx, y0 + t1 * dy) } else { (x1, y1) };
+    Some((start.0, start.1, end.0, end.1))
 }
 
 /// Intersectional area of a rectangle and a circle
@@ -420,3 +1436,601 @@ fn clamp(v: f32, min: f32, max: f32) -> f32 {
 fn g(x: f32, h: f32, r: f32) -> f32 {
     (f32::sqrt(1.0 - x * x / (r * r)) * x * r + r * r * f32::asin(x / r) - 2.0 * h * x) / 2.0
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use image::{AnimationDecoder, Delay, Luma, LumaA, Rgb, Rgba};
+    use rand::{Rng, SeedableRng};
+    use rand::seq::SliceRandom;
+    use rand_pcg::Pcg64Mcg;
+    use crate::render::gif_comment::CommentWriter;
+    use super::*;
+
+    fn frames() -> Vec<HorizontalLineImage<Rgba<u8>, Vec<u8>>> {
+        (0..3u8).map(|i| {
+            HorizontalLineImage::new(7, 5, |size| (0..size).map(|j| (j as u8).wrapping_mul(37).wrapping_add(i * 11) | 3).collect())
+        }).collect()
+    }
+
+    #[test]
+    fn rgba_u8_pixels_and_lines_land_where_drawn() {
+        let mut canvas = HorizontalLineImage::<Rgba<u8>, Vec<u8>>::new(4, 2, |size| vec![0; size]);
+        canvas.draw_pixel(1, 0, Rgba([1, 2, 3, 4]));
+        canvas.draw_horizontal_line(2, 4, 1, Rgba([5, 6, 7, 8]));
+        assert_eq!(canvas.as_raw(), &[
+            0, 0, 0, 0, 1, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 5, 6, 7, 8, 5, 6, 7, 8
+        ]);
+    }
+
+    #[test]
+    fn rgb_u16_pixels_and_lines_land_where_drawn() {
+        let mut canvas = HorizontalLineImage::<Rgb<u16>, Vec<u16>>::new(3, 3, |size| vec![0; size]);
+        canvas.draw_pixel(2, 0, Rgb([1000, 2000, 3000]));
+        canvas.draw_horizontal_line(0, 2, 2, Rgb([40000, 50000, 60000]));
+        assert_eq!(canvas.as_raw(), &[
+            0, 0, 0, 0, 0, 0, 1000, 2000, 3000,
+            0, 0, 0, 0, 0, 0, 0, 0, 0,
+            40000, 50000, 60000, 40000, 50000, 60000, 0, 0, 0
+        ]);
+        assert_eq!(canvas.get_pixel(2, 0), Some(Rgb([1000, 2000, 3000])));
+    }
+
+    #[test]
+    fn luma_f32_pixels_and_lines_land_where_drawn() {
+        let mut canvas = HorizontalLineImage::<Luma<f32>, Vec<f32>>::new(5, 2, |size| vec![0.0; size]);
+        canvas.draw_pixel(4, 1, Luma([0.25]));
+        // cut off at the right edge
+        canvas.draw_horizontal_line(1, 9, 0, Luma([0.5]));
+        assert_eq!(canvas.as_raw(), &[
+            0.0, 0.5, 0.5, 0.5, 0.5,
+            0.0, 0.0, 0.0, 0.0, 0.25
+        ]);
+        assert!(!canvas.draw_pixel(5, 0, Luma([1.0])));
+    }
+
+    /// coordinates of a canvas `size` wide or high, mostly around and past its edges
+    fn coordinate(rng: &mut Pcg64Mcg, size: u32) -> u32 {
+        match rng.gen_range(0..4) {
+            0 => rng.gen(),
+            1 => *[0, size - 1, size, size + 1, u32::MAX - 1, u32::MAX].choose(rng).unwrap(),
+            _ => rng.gen_range(0..size * 2)
+        }
+    }
+
+    #[test]
+    fn safe_drawing_takes_any_coordinates() {
+        let (width, height) = (7, 5);
+        let mut rng = Pcg64Mcg::seed_from_u64(81);
+        let mut canvas = HorizontalLineImage::<Luma<u8>, Vec<u8>>::new(width, height, |size| vec![0; size]);
+        // what the canvas should be, drawn a pixel at a time
+        let mut expected = vec![0u8; (width * height) as usize];
+        // few enough for miri to get through
+        for round in 0..if cfg!(miri) { 200 } else { 20_000 } {
+            let paint = (round % 255) as u8 + 1;
+            let (x, y) = (coordinate(&mut rng, width), coordinate(&mut rng, height));
+            match rng.gen_range(0..3) {
+                0 => {
+                    let within = x < width && y < height;
+                    assert_eq!(canvas.draw_pixel(x, y, Luma([paint])), within, "{}, {}", x, y);
+                    if within {
+                        expected[(y * width + x) as usize] = paint;
+                    }
+                }
+                1 => {
+                    let x1 = coordinate(&mut rng, width);
+                    canvas.draw_horizontal_line(x, x1, y, Luma([paint]));
+                    if y < height {
+                        for x in x..x1.min(width) {
+                            expected[(y * width + x) as usize] = paint;
+                        }
+                    }
+                }
+                _ => {
+                    let pixel = (x < width && y < height).then(|| Luma([expected[(y * width + x) as usize]]));
+                    assert_eq!(canvas.get_pixel(x, y), pixel, "{}, {}", x, y);
+                }
+            }
+        }
+        assert_eq!(canvas.as_raw(), &expected);
+    }
+
+    #[test]
+    fn rasterizers_take_any_shapes() {
+        // the unchecked methods assert their bounds in debug builds, which tests are, IntegerRasterizer takes a step
+        // for every pixel of the radius of circles within i32, so those are kept to millions of pixels
+        let mut rng = Pcg64Mcg::seed_from_u64(81);
+        let mut value = || match rng.gen_range(0..4) {
+            0 => *[f32::NAN, f32::INFINITY, f32::NEG_INFINITY, f32::MAX, f32::MIN, -0.0, 1e6, -1e6].choose(&mut rng).unwrap(),
+            1 => rng.gen_range(-1e4..1e4),
+            _ => rng.gen_range(-3.0..12.0)
+        };
+        let mut canvas = HorizontalLineImage::<Luma<u8>, Vec<u8>>::new(7, 5, |size| vec![0; size]);
+        for _ in 0..if cfg!(miri) { 20 } else { 2_000 } {
+            let [cx, cy, r, rx, ry, angle, x1, y1] = [(); 8].map(|_| value());
+            <IntegerRasterizer as Rasterizer<_, _, GrayscaleRgbScalar>>::draw_filled_circle(&mut canvas, cx, cy, r, Luma([255]));
+            <AreaIntersectionRasterizer as Rasterizer<_, _, GrayscaleRgbScalar>>::draw_filled_circle(&mut canvas, cx, cy, r, Luma([255]));
+            <IntegerRasterizer as Rasterizer<_, _, GrayscaleRgbScalar>>::draw_filled_ellipse(&mut canvas, cx, cy, rx, ry, angle, Luma([255]));
+            <AreaIntersectionRasterizer as Rasterizer<_, _, GrayscaleRgbScalar>>::draw_filled_ellipse(&mut canvas, cx, cy, rx, ry, angle, Luma([255]));
+            <IntegerRasterizer as LineRasterizer<_, _, GrayscaleRgbScalar>>::draw_line(&mut canvas, cx, cy, x1, y1, r, Luma([255]));
+            <AreaIntersectionRasterizer as LineRasterizer<_, _, GrayscaleRgbScalar>>::draw_line(&mut canvas, cx, cy, x1, y1, r, Luma([255]));
+        }
+    }
+
+    const SCALES: [f32; 3] = [-0.5, 0.5, 2.0];
+
+    fn unit_clamp(scale: f32) -> f32 {
+        scale.clamp(0.0, 1.0)
+    }
+
+    /// `paint` scaled by each of [`SCALES`] through `S`
+    fn scaled<Paint, S: PaintScalar<Paint>>(paint: Paint, clamp: Option<fn(f32) -> f32>) -> [Paint; 3] {
+        SCALES.map(|scale| S::scale(&paint, scale, clamp))
+    }
+
+    #[test]
+    fn grayscale_scaling_saturates_integers() {
+        assert_eq!(scaled::<_, GrayscaleRgbScalar>(Luma([200u8]), None), [Luma([0]), Luma([100]), Luma([255])]);
+        assert_eq!(scaled::<_, GrayscaleRgbScalar>(LumaA([200u8, 50]), None), [LumaA([0, 50]), LumaA([100, 50]), LumaA([255, 50])]);
+        assert_eq!(scaled::<_, GrayscaleRgbScalar>(Rgb([200u8, 10, 20]), None), [Rgb([0; 3]), Rgb([100; 3]), Rgb([255; 3])]);
+        assert_eq!(scaled::<_, GrayscaleRgbScalar>(Rgba([200u8, 10, 20, 50]), None), [Rgba([0, 0, 0, 50]), Rgba([100, 100, 100, 50]), Rgba([255, 255, 255, 50])]);
+        assert_eq!(scaled::<_, GrayscaleRgbScalar>(Rgb([40000u16, 1, 2]), None), [Rgb([0; 3]), Rgb([20000; 3]), Rgb([65535; 3])]);
+    }
+
+    #[test]
+    fn grayscale_scaling_clamps_floats() {
+        let paint = Rgba([0.8f32, 0.1, 0.2, 0.6]);
+        assert_eq!(
+            scaled::<_, GrayscaleRgbScalar>(paint, Some(unit_clamp)),
+            [Rgba([0.0, 0.0, 0.0, 0.6]), Rgba([0.4, 0.4, 0.4, 0.6]), Rgba([0.8, 0.8, 0.8, 0.6])]
+        );
+        // nothing clamps them otherwise
+        assert_eq!(scaled::<_, GrayscaleRgbScalar>(paint, None)[2], Rgba([1.6, 1.6, 1.6, 0.6]));
+    }
+
+    #[test]
+    fn rgb_scaling_keeps_the_color() {
+        assert_eq!(scaled::<_, RgbScalar>(Rgb([200u8, 100, 20]), None), [Rgb([0, 0, 0]), Rgb([100, 50, 10]), Rgb([255, 200, 40])]);
+        assert_eq!(scaled::<_, RgbScalar>(Rgba([200u8, 100, 20, 50]), None), [Rgba([0, 0, 0, 50]), Rgba([100, 50, 10, 50]), Rgba([255, 200, 40, 50])]);
+        assert_eq!(scaled::<_, RgbScalar>(Rgb([40000u16, 2000, 2]), None), [Rgb([0, 0, 0]), Rgb([20000, 1000, 1]), Rgb([65535, 4000, 4])]);
+        assert_eq!(
+            scaled::<_, RgbScalar>(Rgba([0.8f32, 0.2, 0.0, 0.6]), Some(unit_clamp)),
+            [Rgba([0.0, 0.0, 0.0, 0.6]), Rgba([0.4, 0.1, 0.0, 0.6]), Rgba([0.8, 0.2, 0.0, 0.6])]
+        );
+    }
+
+    #[test]
+    fn linear_scaling_scales_every_channel() {
+        assert_eq!(scaled::<_, LinearScalar>(Luma([200u8]), None), [Luma([0]), Luma([100]), Luma([255])]);
+        assert_eq!(scaled::<_, LinearScalar>(LumaA([200u8, 50]), None), [LumaA([0, 0]), LumaA([100, 25]), LumaA([255, 100])]);
+        assert_eq!(scaled::<_, LinearScalar>(Rgb([40000u16, 2000, 2]), None), [Rgb([0, 0, 0]), Rgb([20000, 1000, 1]), Rgb([65535, 4000, 4])]);
+        assert_eq!(
+            scaled::<_, LinearScalar>(Rgba([0.8f32, 0.2, 0.0, 0.6]), None),
+            [Rgba([-0.4, -0.1, -0.0, -0.3]), Rgba([0.4, 0.1, 0.0, 0.3]), Rgba([1.6, 0.4, 0.0, 1.2])]
+        );
+        assert_eq!(scaled::<_, LinearScalar>(Rgba([0.8f32, 0.2, 0.0, 0.6]), Some(unit_clamp))[2], Rgba([0.8, 0.2, 0.0, 0.6]));
+    }
+
+    #[test]
+    fn per_channel_scaling_keeps_red_red() {
+        let red = Rgba([255, 0, 0, 255]);
+        assert_eq!(PerChannelScalar::<PreserveAlpha>::scale(&Rgb([255u8, 0, 0]), 0.5, None), Rgb([127, 0, 0]));
+        assert_eq!(PerChannelScalar::<PreserveAlpha>::scale(&red, 0.5, None), Rgba([127, 0, 0, 255]));
+        assert_eq!(PerChannelScalar::<ScaleAlpha>::scale(&red, 0.5, None), Rgba([127, 0, 0, 127]));
+        // which grayscale scaling doesn't
+        assert_eq!(GrayscaleRgbScalar::scale(&red, 0.5, None), Rgba([127, 127, 127, 255]));
+        assert_eq!(GrayscaleRgbScalar::scale(&Rgb([255u8, 0, 0]), 0.5, None), Rgb([127, 127, 127]));
+    }
+
+    #[test]
+    fn antialiased_edges_keep_the_hue() {
+        let mut canvas = HorizontalLineImage::<Rgba<u8>, Vec<u8>>::new(20, 20, |size| vec![0; size]);
+        <AreaIntersectionRasterizer as Rasterizer<_, _, PerChannelScalar<PreserveAlpha>>>::draw_filled_circle(&mut canvas, 10.3, 9.6, 6.2, Rgba([255, 0, 0, 255]));
+        let pixels: Vec<_> = canvas.as_raw().chunks_exact(4).collect();
+        assert!(pixels.iter().any(|pixel| pixel[0] > 0 && pixel[0] < 255), "no edge was antialiased");
+        assert!(pixels.iter().all(|pixel| pixel[1] == 0 && pixel[2] == 0));
+    }
+
+    /// the red channel of a 100x100 canvas with a white circle of radius `r` at `(cx, cy)` drawn by `R`
+    fn circle<R: Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(cx: f32, cy: f32, r: f32) -> Vec<u8> {
+        let mut canvas = HorizontalLineImage::<Rgba<u8>, Vec<u8>>::new(100, 100, |size| vec![0; size]);
+        R::draw_filled_circle(&mut canvas, cx, cy, r, Rgba([255, 255, 255, 255]));
+        canvas.as_raw().iter().step_by(4).copied().collect()
+    }
+
+    #[test]
+    fn antialiasing_only_changes_the_edges_of_circles() {
+        for (cx, cy, r) in [(50.0, 50.0, 20.0), (40.3, 61.7, 12.4), (10.5, 90.5, 3.2)] {
+            let (hard, antialiased) = (circle::<IntegerRasterizer>(cx, cy, r), circle::<AreaIntersectionRasterizer>(cx, cy, r));
+            assert_ne!(hard, antialiased);
+            for (i, (hard, antialiased)) in hard.into_iter().zip(antialiased).enumerate() {
+                let (x, y) = ((i % 100) as f32 + 0.5, (i / 100) as f32 + 0.5);
+                let distance = f32::sqrt((x - cx).powi(2) + (y - cy).powi(2));
+                // a pixel reaches up to half a diagonal from its center, and the hard edges are rounded to whole pixels
+                if distance < r - 1.5 {
+                    assert_eq!((hard, antialiased), (255, 255), "inside at {}, {}", x, y);
+                } else if distance > r + 1.5 {
+                    assert_eq!((hard, antialiased), (0, 0), "outside at {}, {}", x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn supersampled_pixels_average_their_coverage() {
+        let mut image = HorizontalLineImage::<Rgba<u8>, Vec<u8>>::new(4, 3, |size| vec![0; size]).upscale(2);
+        // the left half of the pixel at (1, 1), and a quarter of the one at (2, 2)
+        image.draw_horizontal_line(2, 3, 2, Rgba([255, 255, 255, 255]));
+        image.draw_horizontal_line(2, 3, 3, Rgba([255, 255, 255, 255]));
+        image.draw_pixel(5, 4, Rgba([255, 255, 255, 255]));
+        let image = image.downscale(2);
+        assert_eq!((image.width(), image.height()), (4, 3));
+        for ((x, y), expected) in [((1, 1), 127.5), ((2, 2), 63.75), ((0, 0), 0.0), ((3, 2), 0.0)] {
+            let value = image.as_raw()[(y * 4 + x) * 4];
+            assert!((value as f32 - expected).abs() <= 1.0, "{} rather than {} at {}, {}", value, expected, x, y);
+        }
+    }
+
+    #[test]
+    fn upscaling_and_downscaling_gives_the_image_back() {
+        let image = &frames()[1];
+        for factor in [1, 2, 4] {
+            assert_eq!(image.upscale(factor).downscale(factor).as_raw(), image.as_raw());
+        }
+    }
+
+    /// the pixels of [`IntegerRasterizer`]'s circle, as it found the half width of every row before drawing any
+    fn integer_circle_reference(cx: f32, cy: f32, r: f32, width: i32, height: i32) -> Vec<u8> {
+        let (x0, y0, r) = (cx as i32, cy as i32, r as i32);
+        let mut half_widths = vec![-1; r as usize + 1];
+        let (mut x, mut y, mut p) = (0, r, 1 - r);
+        while x <= y {
+            half_widths[y as usize] = half_widths[y as usize].max(x);
+            half_widths[x as usize] = half_widths[x as usize].max(y);
+            x += 1;
+            if p < 0 {
+                p += 2 * x + 1;
+            } else {
+                y -= 1;
+                p += 2 * (x - y) + 1;
+            }
+        }
+        (0..height).flat_map(|py| (0..width).map(move |px| (px, py))).map(|(px, py)| {
+            match half_widths.get((py - y0).unsigned_abs() as usize) {
+                Some(&half_width) if (px - x0).abs() <= half_width => 1,
+                _ => 0
+            }
+        }).collect()
+    }
+
+    #[test]
+    fn integer_circles_draw_every_row_once() {
+        let centers = [(50.0, 60.0), (0.0, 0.0), (119.5, 60.2), (60.7, -12.0), (-30.0, 60.0), (60.0, 130.0)];
+        for r in 0..=50 {
+            for (cx, cy) in centers {
+                let mut canvas = HorizontalLineImage::<Luma<u8>, Vec<u8>>::new(120, 120, |size| vec![0; size]);
+                <BlendingRasterizer<IntegerRasterizer, Additive> as Rasterizer<_, _, GrayscaleRgbScalar>>::draw_filled_circle(&mut canvas, cx, cy, r as f32, Luma([1]));
+                // a pixel drawn twice would be 2
+                assert_eq!(canvas.as_raw(), &integer_circle_reference(cx, cy, r as f32, 120, 120), "radius {} at {}, {}", r, cx, cy);
+            }
+        }
+    }
+
+    #[test]
+    fn integer_circles_larger_than_the_canvas_fill_it() {
+        let mut canvas = HorizontalLineImage::<Luma<u8>, Vec<u8>>::new(30, 20, |size| vec![0; size]);
+        <BlendingRasterizer<IntegerRasterizer, Additive> as Rasterizer<_, _, GrayscaleRgbScalar>>::draw_filled_circle(&mut canvas, 15.0, 10.0, 1e6, Luma([1]));
+        assert!(canvas.as_raw().iter().all(|&value| value == 1));
+    }
+
+    #[test]
+    fn circles_off_the_top_left_cover_the_sliver_on_the_canvas() {
+        for (cx, cy) in [(-5.0, 50.0), (50.0, -5.0), (-20.0, -20.0)] {
+            let antialiased = circle::<AreaIntersectionRasterizer>(cx, cy, 10.0);
+            for (i, &value) in antialiased.iter().enumerate() {
+                let (x, y) = ((i % 100) as f32, (i / 100) as f32);
+                let expected = 255.0 * area_intersection_circle_rectangle(x, y, x + 1.0, y + 1.0, cx, cy, 10.0).clamp(0.0, 1.0);
+                assert!((value as f32 - expected).abs() <= 1.0, "{} rather than {} at {}, {} for {}, {}", value, expected, x, y, cx, cy);
+            }
+            // the segment of the circle 5 past the edge, or none of it
+            let covered = antialiased.iter().map(|&value| value as f32 / 255.0).sum::<f32>();
+            let visible = if cx < -10.0 { 0.0 } else { 100.0 * f32::acos(0.5) - 5.0 * f32::sqrt(75.0) };
+            assert!((covered - visible).abs() <= 1.0, "{} covered rather than {} for {}, {}", covered, visible, cx, cy);
+        }
+    }
+
+    /// the pixels at `xs` of row 10, after drawing circles of radius 5 at (8, 10) and (14, 10) in `paint` by `R`
+    fn overlapping_circles<R: Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(paint: Rgba<u8>, xs: [u32; 4]) -> [Rgba<u8>; 4] {
+        let mut canvas = HorizontalLineImage::<Rgba<u8>, Vec<u8>>::new(24, 20, |size| vec![0; size]);
+        R::draw_filled_circle(&mut canvas, 8.0, 10.0, 5.0, paint);
+        R::draw_filled_circle(&mut canvas, 14.0, 10.0, 5.0, paint);
+        xs.map(|x| canvas.get_pixel(x, 10).unwrap())
+    }
+
+    /// the red channel of a 100x100 canvas with a white ellipse of radii `rx` and `ry` turned by `angle` at
+    /// `(cx, cy)` drawn by `R`
+    fn ellipse<R: Rasterizer<HorizontalLineImage<Rgba<u8>, Vec<u8>>, Rgba<u8>, RgbScalar>>(cx: f32, cy: f32, rx: f32, ry: f32, angle: f32) -> Vec<u8> {
+        let mut canvas = HorizontalLineImage::<Rgba<u8>, Vec<u8>>::new(100, 100, |size| vec![0; size]);
+        R::draw_filled_ellipse(&mut canvas, cx, cy, rx, ry, angle, Rgba([255, 255, 255, 255]));
+        canvas.as_raw().iter().step_by(4).copied().collect()
+    }
+
+    /// the golden mask of an ellipse, how much of each pixel of a 100x100 canvas it covers out of 255, counted on a
+    /// grid of 64x64 points within the pixel
+    fn ellipse_reference(cx: f32, cy: f32, rx: f32, ry: f32, angle: f32) -> Vec<f32> {
+        const POINTS: usize = 64;
+        let (sin, cos) = angle.sin_cos();
+        let reach = f32::max(rx, ry) + 1.0;
+        (0..100 * 100).map(|i| {
+            let (x, y) = ((i % 100) as f32, (i / 100) as f32);
+            if (x + 0.5 - cx).abs() > reach || (y + 0.5 - cy).abs() > reach {
+                return 0.0
+            }
+            let covered = (0..POINTS * POINTS).filter(|j| {
+                let (dx, dy) = (x + ((j % POINTS) as f32 + 0.5) / POINTS as f32 - cx, y + ((j / POINTS) as f32 + 0.5) / POINTS as f32 - cy);
+                // turned back by the angle onto the axes of the ellipse
+                let (u, v) = (dx * cos + dy * sin, -dx * sin + dy * cos);
+                (u / rx).powi(2) + (v / ry).powi(2) <= 1.0
+            }).count();
+            255.0 * covered as f32 / (POINTS * POINTS) as f32
+        }).collect()
+    }
+
+    /// asserts that every pixel of `drawn` is within `tolerance` of the golden mask, and that they cover the area of
+    /// the ellipse within a pixel or half a percent of it, whichever is more
+    fn assert_ellipse(drawn: &[u8], cx: f32, cy: f32, rx: f32, ry: f32, angle: f32, tolerance: f32) {
+        for (i, (&value, expected)) in drawn.iter().zip(ellipse_reference(cx, cy, rx, ry, angle)).enumerate() {
+            assert!((value as f32 - expected).abs() <= tolerance, "{} rather than {} at {}, {}", value, expected, i % 100, i / 100);
+        }
+        let covered = drawn.iter().map(|&value| value as f32 / 255.0).sum::<f32>();
+        let area = std::f32::consts::PI * rx * ry;
+        assert!((covered - area).abs() <= f32::max(1.0, area / 200.0), "{} covered rather than {}", covered, area);
+    }
+
+    #[test]
+    fn ellipses_of_equal_radii_are_circles() {
+        for (cx, cy, r) in [(50.0, 50.0, 20.0), (40.3, 61.7, 12.4), (10.5, 90.5, 3.2), (70.2, 20.9, 1.6)] {
+            let circle = circle::<AreaIntersectionRasterizer>(cx, cy, r);
+            // the same area math along the axes
+            assert_eq!(ellipse::<AreaIntersectionRasterizer>(cx, cy, r, r, 0.0), circle);
+            assert_eq!(ellipse::<AreaIntersectionRasterizer>(cx, cy, r, r, std::f32::consts::FRAC_PI_2), circle);
+            // and sampled on a 4x4 grid otherwise
+            let turned = ellipse::<AreaIntersectionRasterizer>(cx, cy, r, r, 0.7);
+            for (i, (a, b)) in turned.into_iter().zip(circle).enumerate() {
+                assert!((a as f32 - b as f32).abs() <= 32.0, "{} rather than {} at {}, {}", a, b, i % 100, i / 100);
+            }
+        }
+    }
+
+    #[test]
+    fn axis_aligned_ellipses_match_their_golden_masks() {
+        for (cx, cy, rx, ry) in [(50.0, 50.0, 30.0, 8.0), (40.3, 61.7, 5.5, 17.25), (20.5, 30.5, 1.5, 0.75)] {
+            let drawn = ellipse::<AreaIntersectionRasterizer>(cx, cy, rx, ry, 0.0);
+            assert_ellipse(&drawn, cx, cy, rx, ry, 0.0, 3.0);
+            // turned a quarter, the radii trade places
+            assert_eq!(ellipse::<AreaIntersectionRasterizer>(cx, cy, ry, rx, std::f32::consts::FRAC_PI_2), drawn);
+        }
+    }
+
+    #[test]
+    fn rotated_ellipses_match_their_golden_masks() {
+        let angle = std::f32::consts::FRAC_PI_4;
+        let drawn = ellipse::<AreaIntersectionRasterizer>(50.0, 50.0, 30.0, 8.0, angle);
+        // the edges are sampled on a 4x4 grid, which is up to two of its points off
+        assert_ellipse(&drawn, 50.0, 50.0, 30.0, 8.0, angle, 32.0);
+        // along the major axis, but not the minor one
+        assert_eq!((drawn[30 * 100 + 30], drawn[70 * 100 + 70]), (255, 255));
+        assert_eq!((drawn[40 * 100 + 60], drawn[60 * 100 + 40]), (0, 0));
+        // the same ellipse mirrored along the major axis, and half a turn on
+        for y in 0..100 {
+            for x in 0..100 {
+                assert_eq!(drawn[y * 100 + x], drawn[x * 100 + y], "at {}, {}", x, y);
+            }
+        }
+        let half_turn = ellipse::<AreaIntersectionRasterizer>(50.0, 50.0, 30.0, 8.0, angle + std::f32::consts::PI);
+        assert!(half_turn.iter().zip(&drawn).all(|(&a, &b)| (a as f32 - b as f32).abs() <= 16.0));
+        assert_ellipse(&ellipse::<AreaIntersectionRasterizer>(33.3, 58.1, 12.5, 4.2, 2.0), 33.3, 58.1, 12.5, 4.2, 2.0, 32.0);
+    }
+
+    #[test]
+    fn degenerate_ellipses_draw_nothing() {
+        for (rx, ry) in [(0.0, 5.0), (5.0, 0.0), (0.0, 0.0), (-3.0, 5.0), (f32::NAN, 5.0), (5.0, f32::NAN)] {
+            for angle in [0.0, 0.5, std::f32::consts::FRAC_PI_2] {
+                assert!(ellipse::<AreaIntersectionRasterizer>(50.0, 50.0, rx, ry, angle).iter().all(|&value| value == 0));
+                assert!(ellipse::<IntegerRasterizer>(50.0, 50.0, rx, ry, angle).iter().all(|&value| value == 0));
+            }
+        }
+        // nor do they panic when they are too large to draw
+        ellipse::<AreaIntersectionRasterizer>(50.0, 50.0, f32::INFINITY, 5.0, 0.5);
+        ellipse::<AreaIntersectionRasterizer>(50.0, 50.0, f32::MAX, f32::MAX, 0.0);
+    }
+
+    #[test]
+    fn additive_circles_sum_where_they_overlap() {
+        // outside of both, inside the first, inside both, inside the second
+        let xs = [1, 5, 11, 17];
+        let half = overlapping_circles::<BlendingRasterizer<IntegerRasterizer, Additive>>(Rgba([100, 100, 100, 100]), xs);
+        assert_eq!(half, [Rgba([0; 4]), Rgba([100; 4]), Rgba([200; 4]), Rgba([100; 4])]);
+        let half = overlapping_circles::<BlendingRasterizer<IntegerRasterizer, Additive>>(Rgba([128, 128, 128, 255]), xs);
+        assert_eq!(half, [Rgba([0; 4]), Rgba([128, 128, 128, 255]), Rgba([255; 4]), Rgba([128, 128, 128, 255])]);
+        // overwriting stomps on the first circle instead
+        let overwritten = overlapping_circles::<IntegerRasterizer>(Rgba([128, 128, 128, 255]), xs);
+        assert_eq!(overwritten[2], Rgba([128, 128, 128, 255]));
+    }
+
+    #[test]
+    fn source_over_circles_composite_by_alpha() {
+        let xs = [1, 5, 11, 17];
+        let pixels = overlapping_circles::<BlendingRasterizer<IntegerRasterizer, SourceOver>>(Rgba([255, 255, 255, 128]), xs);
+        // half covered over the transparent canvas, and half of the rest once more where they overlap
+        assert_eq!(pixels, [Rgba([0; 4]), Rgba([255, 255, 255, 128]), Rgba([255, 255, 255, 191]), Rgba([255, 255, 255, 128])]);
+    }
+
+    #[test]
+    fn apngs_keep_every_frame_and_the_gray_levels_of_antialiased_edges() {
+        let mut apng = Vec::new();
+        let mut handler = ApngHandler::new(40, 40, [0, 0, 0, 255].into(), &mut apng, 3, (1, 25), 0).unwrap();
+        for i in 0..3 {
+            let mut canvas = handler.produce();
+            <AreaIntersectionRasterizer as Rasterizer<_, _, RgbScalar>>::draw_filled_circle(&mut canvas, 20.0 + i as f32 * 0.3, 20.0, 12.7, Rgba([255, 255, 255, 255]));
+            handler.consume(canvas);
+        }
+        handler.finish().unwrap();
+        let frames = image::codecs::png::PngDecoder::new(apng.as_slice()).unwrap().apng().into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 3);
+        let grays: HashSet<u8> = frames[0].buffer().pixels().map(|pixel| pixel[0]).collect();
+        // the levels of the edge, which a gif quantizes down to a handful
+        assert!(grays.len() > 8, "{} levels of gray", grays.len());
+    }
+
+    #[test]
+    fn rendered_frames_are_collected_in_order() {
+        let (background, paint) = (Rgba([0, 0, 0, 255]), Rgba([255, 128, 0, 255]));
+        let mut renderer = CPURenderer::<_, _, RgbScalar, _, IntegerRasterizer>::new(CollectingHandler::new(20, 10, background));
+        let viewport = Viewport::new((0.0, 0.0), 1.0, 20, 10);
+        // a circle of radius 2.5, which IntegerRasterizer draws 2 pixels past its center
+        let mass_point = |x: f32| MassPoint { mass: 4.0 / 3.0 * std::f32::consts::PI * 2.5f32.powi(3), position: (x, 5.0), velocity: (0.0, 0.0), id: 0, group: 0 };
+        renderer.render(&[mass_point(5.0)], &viewport, paint);
+        renderer.render(&[mass_point(14.0)], &viewport, paint);
+        let frames = renderer.into_frame_handler().into_frames();
+        assert_eq!(frames.len(), 2);
+        for (frame, cx) in frames.iter().zip([5, 14]) {
+            assert_eq!(frame.dimensions(), (20, 10));
+            // rows 2 pixels wide on either side of the center, and 1 pixel at the top and bottom
+            for (x, y) in [(cx, 5), (cx - 2, 5), (cx + 2, 5), (cx + 2, 4), (cx - 2, 6), (cx - 1, 3), (cx + 1, 7), (cx, 3)] {
+                assert_eq!(frame.get_pixel(x, y), &paint, "{}, {}", x, y);
+            }
+            for (x, y) in [(cx - 3, 5), (cx + 3, 5), (cx + 2, 3), (cx - 2, 7), (cx, 2), (cx, 8)] {
+                assert_eq!(frame.get_pixel(x, y), &background, "{}, {}", x, y);
+            }
+            assert_eq!(frame.pixels().filter(|&pixel| pixel == &paint).count(), 3 + 5 + 5 + 5 + 3);
+        }
+    }
+
+    /// an empty directory in the temporary one for `name` alone
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("newtonian_gravity_{}_{}", name, std::process::id()));
+        // a failed run of a process with the same id leaves it behind, as a file where the directory was removed
+        let _ = fs::remove_dir_all(&dir);
+        let _ = fs::remove_file(&dir);
+        dir
+    }
+
+    #[test]
+    fn png_sequences_write_a_png_per_frame() {
+        let dir = temp_dir("png_sequence");
+        // the handler creates the directories on its way
+        let mut handler = PngSequenceHandler::new(7, 5, [0, 0, 0, 255].into(), dir.join("frames")).unwrap();
+        for i in 0..3 {
+            let mut canvas = handler.produce();
+            canvas.draw_pixel(i, 2, Rgba([255, 255, 255, 255]));
+            handler.consume(canvas);
+        }
+        assert_eq!(handler.finish().unwrap(), 3);
+        for i in 0..3 {
+            let frame = image::open(dir.join(format!("frames/frame_{:05}.png", i))).unwrap().into_rgba8();
+            assert_eq!(frame.dimensions(), (7, 5));
+            assert_eq!(frame.get_pixel(i, 2).0, [255, 255, 255, 255]);
+            assert_eq!(frame.get_pixel(6, 2).0, [0, 0, 0, 255]);
+        }
+        assert!(!dir.join("frames/frame_00003.png").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn png_sequence_errors_are_kept_for_finish() {
+        let dir = temp_dir("png_sequence_error");
+        let mut handler = PngSequenceHandler::new(7, 5, [0, 0, 0, 255].into(), &dir).unwrap();
+        let canvas = handler.produce();
+        handler.consume(canvas);
+        // the directory is gone by the second frame
+        fs::remove_dir_all(&dir).unwrap();
+        fs::write(&dir, "not a directory").unwrap();
+        for _ in 0..2 {
+            let canvas = handler.produce();
+            handler.consume(canvas);
+        }
+        assert!(handler.finish().is_err());
+        fs::remove_file(dir).unwrap();
+    }
+
+    /// the pixels of a `width` by `height` canvas with a white line `line_width` wide from `(x0, y0)` to `(x1, y1)`
+    /// drawn by `R`
+    fn line<R: LineRasterizer<HorizontalLineImage<Luma<u8>, Vec<u8>>, Luma<u8>, GrayscaleRgbScalar>>(width: u32, height: u32, (x0, y0): (f32, f32), (x1, y1): (f32, f32), line_width: f32) -> Vec<u8> {
+        let mut canvas = HorizontalLineImage::<Luma<u8>, Vec<u8>>::new(width, height, |size| vec![0; size]);
+        R::draw_line(&mut canvas, x0, y0, x1, y1, line_width, Luma([255]));
+        canvas.as_raw().to_vec()
+    }
+
+    /// the pixels of 10 by 6 `pixels` that are drawn
+    fn drawn(pixels: &[u8]) -> Vec<(usize, usize)> {
+        pixels.iter().enumerate().filter(|(_, &value)| value > 0).map(|(i, _)| (i % 10, i / 10)).collect()
+    }
+
+    #[test]
+    fn integer_lines_a_pixel_wide_are_bresenham() {
+        let integer = |start, end| drawn(&line::<IntegerRasterizer>(10, 6, start, end, 1.0));
+        assert_eq!(integer((2.0, 3.0), (8.0, 3.0)), (2..=8).map(|x| (x, 3)).collect::<Vec<_>>());
+        assert_eq!(integer((4.0, 1.0), (4.0, 5.0)), (1..=5).map(|y| (4, y)).collect::<Vec<_>>());
+        // a pixel a row, moving right by 2 over the 6 rows
+        let steep = integer((1.0, 0.0), (3.0, 5.0));
+        assert_eq!(steep.iter().map(|&(_, y)| y).collect::<Vec<_>>(), [0, 1, 2, 3, 4, 5]);
+        assert!(steep.windows(2).all(|pair| pair[0].0 <= pair[1].0) && steep[0].0 == 1 && steep[5].0 == 3, "{:?}", steep);
+        // both ends far off the canvas, crossing it
+        assert_eq!(integer((-100.0, 3.0), (100.0, 3.0)), (0..10).map(|x| (x, 3)).collect::<Vec<_>>());
+        assert_eq!(integer((-1e6, -1e6), (1e6, 1e6)), (0..6).map(|i| (i, i)).collect::<Vec<_>>());
+        // and missing it
+        assert_eq!(integer((-50.0, -10.0), (-5.0, 20.0)), []);
+        assert_eq!(integer((-1e9, 7.0), (1e9, 7.0)), []);
+        assert_eq!(drawn(&line::<IntegerRasterizer>(10, 6, (2.0, 3.0), (8.0, 3.0), 0.0)), []);
+    }
+
+    #[test]
+    fn wide_integer_lines_fill_the_pixels_within_half_their_width() {
+        let wide = line::<IntegerRasterizer>(10, 12, (2.0, 5.5), (8.0, 5.5), 3.0);
+        let column: Vec<u8> = (0..12).map(|y| wide[y * 10 + 5]).collect();
+        assert_eq!(column, [0, 0, 0, 0, 255, 255, 255, 0, 0, 0, 0, 0]);
+        // the ends are round, so past them only the middle row is drawn
+        assert_eq!([wide[4 * 10], wide[5 * 10 + 1], wide[6 * 10]], [0, 255, 0]);
+        let steep = line::<IntegerRasterizer>(10, 12, (-20.0, -40.0), (30.0, 60.0), 3.0);
+        assert!((0..12).all(|y| steep[y * 10..(y + 1) * 10].iter().any(|&value| value > 0)), "a row was left out");
+    }
+
+    #[test]
+    fn antialiased_lines_cover_as_much_as_they_are_wide() {
+        for width in [0.5, 1.0, 2.5, 4.0] {
+            let antialiased = line::<AreaIntersectionRasterizer>(10, 12, (-5.0, 6.3), (15.0, 6.3), width);
+            for x in 0..10 {
+                let covered = (0..12).map(|y| antialiased[y * 10 + x] as f32 / 255.0).sum::<f32>();
+                // the grid of points each pixel's coverage is estimated from is a quarter of a pixel apart
+                assert!((covered - width).abs() <= 0.25, "{} covered by a line {} wide at column {}", covered, width, x);
+            }
+        }
+        // a pixel wide along the middle of a row, and straddling two
+        let middle = line::<AreaIntersectionRasterizer>(10, 12, (0.0, 5.5), (10.0, 5.5), 1.0);
+        assert_eq!((0..12).map(|y| middle[y * 10 + 5]).collect::<Vec<_>>(), [0, 0, 0, 0, 0, 255, 0, 0, 0, 0, 0, 0]);
+        let straddling = line::<AreaIntersectionRasterizer>(10, 12, (5.0, 0.0), (5.0, 12.0), 1.0);
+        assert_eq!(&straddling[6 * 10 + 3..6 * 10 + 7], &[0, 127, 127, 0]);
+        assert!(line::<AreaIntersectionRasterizer>(10, 12, (-50.0, -10.0), (-5.0, 20.0), 4.0).iter().all(|&value| value == 0));
+    }
+
+    #[test]
+    fn gif_handler_encodes_like_the_image_crate() {
+        let delay = Delay::from_numer_denom_ms(40, 1);
+        let mut ours = Vec::new();
+        let mut handler = GifHandler::with_comment(7, 5, [0, 0, 0, 255].into(), delay, &mut ours, "frames");
+        for frame in frames() {
+            handler.consume(frame);
+        }
+        drop(handler);
+        let mut theirs = Vec::new();
+        let mut encoder = image::codecs::gif::GifEncoder::new(CommentWriter::new(&mut theirs, "frames"));
+        encoder.set_repeat(image::codecs::gif::Repeat::Infinite).unwrap();
+        for frame in frames() {
+            encoder.encode_frame(image::Frame::from_parts(frame.into(), 0, 0, delay)).unwrap();
+        }
+        drop(encoder);
+        assert_eq!(ours, theirs);
+    }
+}
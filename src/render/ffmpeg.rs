@@ -0,0 +1,171 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Write};
+use std::iter::repeat;
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use crate::render::cpu::{FrameHandler, HorizontalLineImage};
+
+/// pipes frames into an `ffmpeg` child process, which encodes them into a video, h.264 in an mp4 or vp9 in a webm
+///
+/// [`consume`](FrameHandler::consume) can't return errors, so the first one is kept and the frames after it are
+/// dropped, [`finish`](Self::finish) returns it, dropping the handler without finishing still waits for the video
+pub struct FfmpegPipeHandler {
+    width: u32,
+    height: u32,
+    default_color: image::Rgba<u8>,
+    child: Child,
+    /// taken to close it, which ends the video
+    stdin: Option<ChildStdin>,
+    error: Option<FfmpegError>
+}
+
+impl FfmpegPipeHandler {
+    /// starts `ffmpeg` writing `path` at `frame_rate` frames per second, in the format of the extension of `path`,
+    /// `ffmpeg` has to be on the `PATH`
+    pub fn new<P: AsRef<Path>>(width: u32, height: u32, default_color: image::Rgba<u8>, path: P, frame_rate: u32) -> Result<Self, FfmpegError> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("");
+        let codec = match extension {
+            "mp4" => "libx264",
+            "webm" => "libvpx-vp9",
+            _ => return Err(FfmpegError::Extension(extension.to_string()))
+        };
+        let mut child = Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error"])
+            .args(["-f", "rawvideo", "-pixel_format", "rgba"])
+            .args(["-video_size", &format!("{}x{}", width, height), "-framerate", &frame_rate.to_string()])
+            .args(["-i", "-"])
+            // yuv420p, which players expect, halves the resolution of the colors, so the size has to be even
+            .args(["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2", "-pix_fmt", "yuv420p", "-c:v", codec])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|error| match error.kind() {
+                io::ErrorKind::NotFound => FfmpegError::NotFound,
+                _ => FfmpegError::Io(error.to_string())
+            })?;
+        let stdin = child.stdin.take();
+        Ok(Self { width, height, default_color, child, stdin, error: None })
+    }
+
+    /// closes the input of `ffmpeg` and waits for it to finish the video, returns the first error writing to it
+    /// or that of `ffmpeg` itself
+    pub fn finish(mut self) -> Result<(), FfmpegError> {
+        let status = self.wait();
+        match (self.error.take(), status) {
+            (Some(error), _) => Err(error),
+            (None, Err(error)) => Err(FfmpegError::Io(error.to_string())),
+            (None, Ok(status)) if !status.success() => Err(FfmpegError::Failed(status)),
+            (None, Ok(_)) => Ok(())
+        }
+    }
+
+    fn wait(&mut self) -> io::Result<ExitStatus> {
+        drop(self.stdin.take());
+        self.child.wait()
+    }
+}
+
+impl FrameHandler for FfmpegPipeHandler {
+    type Canvas = HorizontalLineImage<image::Rgba<u8>, Vec<u8>>;
+
+    fn produce(&mut self) -> Self::Canvas {
+        HorizontalLineImage::new(self.width, self.height, |size| {
+            repeat(self.default_color.0).flatten().take(size).collect()
+        })
+    }
+
+    fn consume(&mut self, canvas: Self::Canvas) {
+        if self.error.is_some() {
+            return
+        }
+        let image: image::RgbaImage = canvas.into();
+        if let Some(stdin) = &mut self.stdin {
+            if let Err(error) = stdin.write_all(image.as_raw()) {
+                self.error = Some(FfmpegError::Io(error.to_string()));
+            }
+        }
+    }
+}
+
+impl Drop for FfmpegPipeHandler {
+    fn drop(&mut self) {
+        // the video is only complete once ffmpeg has seen the end of its input and exited
+        let _ = self.wait();
+    }
+}
+
+/// why a [`FfmpegPipeHandler`] couldn't write its video
+#[derive(Clone, Debug)]
+pub enum FfmpegError {
+    /// there is no `ffmpeg` on the `PATH`
+    NotFound,
+    /// the path of the video has this extension rather than mp4 or webm
+    Extension(String),
+    /// starting or writing to `ffmpeg` failed
+    Io(String),
+    /// `ffmpeg` exited with this status, after logging why
+    Failed(ExitStatus)
+}
+
+impl Display for FfmpegError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FfmpegError::NotFound => write!(f, "ffmpeg isn't on the PATH"),
+            FfmpegError::Extension(extension) => write!(f, "expected a video ending in .mp4 or .webm, not `.{}`", extension),
+            FfmpegError::Io(error) => write!(f, "unable to write to ffmpeg: {}", error),
+            FfmpegError::Failed(status) => write!(f, "ffmpeg failed with {}", status)
+        }
+    }
+}
+
+impl Error for FfmpegError {}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::render::cpu::HorizontalLineCanvas;
+    use super::*;
+
+    /// whether `program -version` runs, which it doesn't when it isn't on the `PATH`
+    fn on_path(program: &str) -> bool {
+        Command::new(program).arg("-version").stdout(Stdio::null()).stderr(Stdio::null()).status().is_ok()
+    }
+
+    #[test]
+    fn videos_are_mp4_or_webm() {
+        match FfmpegPipeHandler::new(8, 8, [0, 0, 0, 255].into(), "video.gif", 25) {
+            Err(FfmpegError::Extension(extension)) => assert_eq!(extension, "gif"),
+            Err(error) => panic!("{}", error),
+            Ok(_) => panic!("a gif was accepted")
+        }
+    }
+
+    #[test]
+    fn dropped_handlers_finish_the_video() {
+        if !on_path("ffmpeg") || !on_path("ffprobe") {
+            eprintln!("skipping, there is no ffmpeg or ffprobe on the PATH");
+            return
+        }
+        let path = std::env::temp_dir().join(format!("newtonian_gravity_video_{}.mp4", std::process::id()));
+        // an odd size, which ffmpeg pads to an even one
+        let mut handler = FfmpegPipeHandler::new(33, 21, [0, 0, 0, 255].into(), &path, 10).unwrap();
+        for i in 0..5 {
+            let mut canvas = handler.produce();
+            canvas.draw_horizontal_line(0, 4 * i, 10, image::Rgba([255, 255, 255, 255]));
+            handler.consume(canvas);
+        }
+        drop(handler);
+        let output = Command::new("ffprobe")
+            .args(["-v", "error", "-count_frames", "-select_streams", "v:0"])
+            .args(["-show_entries", "stream=nb_read_frames", "-of", "csv=p=0"])
+            .arg(&path)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "5");
+        fs::remove_file(path).unwrap();
+    }
+}
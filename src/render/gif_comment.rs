@@ -0,0 +1,206 @@
+use std::io::{self, ErrorKind, Read, Write};
+
+/// passes a gif through to the writer it wraps, with a comment extension block right after the header, such as
+/// for the settings of the run it is of, which [`read_comments`] reads back
+///
+/// the header is held back until it has been written in full, which encoders do before the first frame
+pub struct CommentWriter<W: Write> {
+    inner: W,
+    /// the comment extension block, `None` once it has been written
+    block: Option<Vec<u8>>,
+    /// what was written of the gif before `block`
+    header: Vec<u8>
+}
+
+impl<W: Write> CommentWriter<W> {
+    /// an empty comment isn't written
+    pub fn new(inner: W, comment: &str) -> Self {
+        let block = (!comment.is_empty()).then(|| {
+            let mut block = vec![EXTENSION, COMMENT_LABEL];
+            for chunk in comment.as_bytes().chunks(255) {
+                block.push(chunk.len() as u8);
+                block.extend_from_slice(chunk);
+            }
+            block.push(0);
+            block
+        });
+        Self { inner, block, header: Vec::new() }
+    }
+}
+
+impl<W: Write> Write for CommentWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let block = match &self.block {
+            Some(block) => block,
+            None => return self.inner.write(buf)
+        };
+        self.header.extend_from_slice(buf);
+        if let Some(length) = header_length(&self.header) {
+            self.inner.write_all(&self.header[..length])?;
+            self.inner.write_all(block)?;
+            self.inner.write_all(&self.header[length..])?;
+            self.block = None;
+            self.header = Vec::new();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+const EXTENSION: u8 = 0x21;
+const COMMENT_LABEL: u8 = 0xFE;
+const IMAGE_DESCRIPTOR: u8 = 0x2C;
+const TRAILER: u8 = 0x3B;
+/// signature, version and logical screen descriptor
+const SCREEN_LENGTH: usize = 13;
+
+/// length of the signature, the logical screen descriptor and the global color table at the start of `gif`,
+/// `None` until there is enough of it to tell
+fn header_length(gif: &[u8]) -> Option<usize> {
+    let length = SCREEN_LENGTH + color_table_length(*gif.get(10)?);
+    (gif.len() >= length).then_some(length)
+}
+
+/// length of the color table the packed fields of a screen or image descriptor say follows it
+fn color_table_length(packed: u8) -> usize {
+    match packed & 0x80 {
+        0 => 0,
+        _ => 3 << ((packed & 0x07) + 1)
+    }
+}
+
+/// every comment of the gif read from `reader`, in order, such as that of a [`CommentWriter`]
+pub fn read_comments<R: Read>(mut reader: R) -> io::Result<Vec<String>> {
+    let mut screen = [0; SCREEN_LENGTH];
+    reader.read_exact(&mut screen)?;
+    if &screen[..3] != b"GIF" {
+        return Err(io::Error::new(ErrorKind::InvalidData, "not a gif"))
+    }
+    skip(&mut reader, color_table_length(screen[10]))?;
+    let mut comments = Vec::new();
+    loop {
+        match read_byte(&mut reader)? {
+            EXTENSION => {
+                let label = read_byte(&mut reader)?;
+                let data = read_sub_blocks(&mut reader)?;
+                if label == COMMENT_LABEL {
+                    comments.push(String::from_utf8_lossy(&data).into_owned());
+                }
+            }
+            IMAGE_DESCRIPTOR => {
+                let mut descriptor = [0; 9];
+                reader.read_exact(&mut descriptor)?;
+                skip(&mut reader, color_table_length(descriptor[8]))?;
+                // the minimum code size of the image data
+                read_byte(&mut reader)?;
+                read_sub_blocks(&mut reader)?;
+            }
+            TRAILER => return Ok(comments),
+            block => return Err(io::Error::new(ErrorKind::InvalidData, format!("unknown block 0x{:02X}", block)))
+        }
+    }
+}
+
+fn read_byte<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn skip<R: Read>(reader: &mut R, length: usize) -> io::Result<()> {
+    let skipped = io::copy(&mut reader.take(length as u64), &mut io::sink())?;
+    match skipped == length as u64 {
+        true => Ok(()),
+        false => Err(ErrorKind::UnexpectedEof.into())
+    }
+}
+
+/// the data of the sub-blocks up to the empty one that ends them
+fn read_sub_blocks<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    loop {
+        let length = read_byte(reader)? as usize;
+        if length == 0 {
+            return Ok(data)
+        }
+        let start = data.len();
+        data.resize(start + length, 0);
+        reader.read_exact(&mut data[start..])?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+    use image::{AnimationDecoder, Delay, Frame, Rgba, RgbaImage};
+    use image::codecs::gif::{GifDecoder, GifEncoder};
+    use super::{read_comments, CommentWriter};
+
+    /// encodes a gif of 3 frames into `writer`
+    fn encode<W: Write>(writer: W) {
+        let mut encoder = GifEncoder::new(writer);
+        for i in 0..3u8 {
+            let image = RgbaImage::from_fn(8, 6, |x, y| Rgba([x as u8 * 30, y as u8 * 40, i * 80, 255]));
+            encoder.encode_frame(Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(40, 1))).unwrap();
+        }
+    }
+
+    /// the gif of [`encode`] with `comment`
+    fn gif(comment: &str) -> Vec<u8> {
+        let mut gif = Vec::new();
+        encode(CommentWriter::new(&mut gif, comment));
+        gif
+    }
+
+    fn gif_without_comment() -> Vec<u8> {
+        let mut gif = Vec::new();
+        encode(&mut gif);
+        gif
+    }
+
+    #[test]
+    fn comments_read_back() {
+        let comment = "seed 23, 100 particles, euler";
+        let gif = gif(comment);
+        assert_eq!(read_comments(gif.as_slice()).unwrap(), [comment]);
+        // and the frames are still there
+        let frames = GifDecoder::new(Cursor::new(gif)).unwrap().into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 3);
+    }
+
+    #[test]
+    fn long_comments_are_split_into_sub_blocks() {
+        let comment: String = (0..600).map(|i| char::from(b'a' + (i % 26) as u8)).collect();
+        assert_eq!(read_comments(gif(&comment).as_slice()).unwrap(), [comment]);
+    }
+
+    #[test]
+    fn empty_comments_are_left_out() {
+        assert_eq!(gif(""), gif_without_comment());
+        assert!(read_comments(gif("").as_slice()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn headers_written_a_byte_at_a_time_get_the_comment() {
+        let plain = gif_without_comment();
+        let mut gif = Vec::new();
+        let mut writer = CommentWriter::new(&mut gif, "bytes");
+        for byte in &plain {
+            writer.write_all(&[*byte]).unwrap();
+        }
+        assert_eq!(read_comments(gif.as_slice()).unwrap(), ["bytes"]);
+        // the introducer, the label, a sub-block and the empty one ending them
+        assert_eq!(gif.len(), plain.len() + 2 + 1 + "bytes".len() + 1);
+    }
+
+    #[test]
+    fn other_files_are_not_gifs() {
+        assert!(read_comments(b"\x89PNG\r\n\x1a\n and the rest".as_slice()).is_err());
+        // a gif cut short
+        let gif = gif("cut");
+        assert!(read_comments(&gif[..gif.len() / 2]).is_err());
+    }
+}
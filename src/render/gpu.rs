@@ -0,0 +1,307 @@
+use std::error::Error;
+use std::sync::Arc;
+use bytemuck::{Pod, Zeroable};
+use log::info;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferToImageInfo, CopyImageToBufferInfo, RenderPassBeginInfo, SubpassContents};
+use vulkano::device::{Device, DeviceCreateInfo, Queue, QueueCreateInfo};
+use vulkano::format::Format;
+use vulkano::image::{AttachmentImage, ImageUsage};
+use vulkano::image::view::ImageView;
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::graphics::color_blend::{AttachmentBlend, BlendFactor, BlendOp, ColorBlendState};
+use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, Subpass};
+use vulkano::shader::ShaderModule;
+use vulkano::sync::{self, GpuFuture, PipelineStage};
+use crate::render::cpu::{FixedSizeCanvas, HorizontalLineImage};
+use crate::world::gpu::{physical_devices, DeviceSelection, GPUInitError};
+
+/// a circle drawn by a [`GPURasterizer`], in pixels of the image it is drawn onto
+#[derive(Copy, Clone, Debug, Default, Zeroable, Pod)]
+#[repr(C)]
+pub struct Circle {
+    pub center: [f32; 2],
+    pub radius: f32,
+    /// red, green, blue and alpha from 0 to 1, the alpha being how much of what is under the circle it covers
+    pub color: [f32; 4]
+}
+
+vulkano::impl_vertex!(Circle, center, radius, color);
+
+impl Circle {
+    pub fn new(cx: f32, cy: f32, r: f32, paint: image::Rgba<u8>) -> Self {
+        Self {
+            center: [cx, cy],
+            radius: r,
+            color: paint.0.map(|c| c as f32 / 255.0)
+        }
+    }
+}
+
+/// draws circles on the GPU onto an image of a fixed size, as a quad each out of which a fragment shader cuts the
+/// circle, the pixels at its edge covered by as much of the circle as is in them, close to what
+/// [`AreaIntersectionRasterizer`](crate::render::cpu::AreaIntersectionRasterizer) draws
+///
+/// faster than drawing on the CPU once there are about 100k circles, the image is copied to the GPU and back for
+/// every [`draw`](Self::draw), which is what it costs with fewer
+pub struct GPURasterizer {
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    width: u32,
+    height: u32,
+    pipeline: Arc<GraphicsPipeline>,
+    image: Arc<AttachmentImage>,
+    framebuffer: Arc<Framebuffer>,
+    /// the pixels of `image`, copied into it before the circles are drawn and out of it after
+    staging: Arc<CpuAccessibleBuffer<[u8]>>
+}
+
+impl GPURasterizer {
+    /// draws onto `width` by `height` pixels, on the device picked by `selection`, logging which one it is,
+    /// see [`GPUWorld::new_on`](crate::world::gpu::GPUWorld::new_on)
+    pub fn new_on(selection: &DeviceSelection, width: u32, height: u32) -> Result<Self, GPUInitError> {
+        let physical = selection.select(physical_devices()?)?;
+        let name = physical.properties().device_name.clone();
+        info!("rasterizing on {} ({:?})", name, physical.properties().device_type);
+        let family_index = physical.queue_family_properties().iter()
+            .position(|q| q.supports_stage(PipelineStage::FragmentShader))
+            .ok_or_else(|| GPUInitError::NoGraphics(name.clone()))? as u32;
+        let (device, mut queues) = Device::new(
+            physical,
+            DeviceCreateInfo {
+                queue_create_infos: vec![QueueCreateInfo {
+                    queue_family_index: family_index,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        ).map_err(|error| GPUInitError::Creation(name.clone(), error.to_string()))?;
+        Self::with_device(device, queues.next().unwrap(), width, height)
+    }
+
+    /// draws onto `width` by `height` pixels with `queue` of `device`, such as those of a
+    /// [`GPUWorld`](crate::world::gpu::GPUWorld::device), rather than setting up the device a second time
+    pub fn with_device(device: Arc<Device>, queue: Arc<Queue>, width: u32, height: u32) -> Result<Self, GPUInitError> {
+        let name = device.physical_device().properties().device_name.clone();
+        // a GPUWorld only picks its queue for compute shaders
+        if !device.physical_device().queue_family_properties()[queue.queue_family_index() as usize].supports_stage(PipelineStage::FragmentShader) {
+            return Err(GPUInitError::NoGraphics(name))
+        }
+        let shader_error = |error: &dyn Error| GPUInitError::Shader(name.clone(), error.to_string());
+        let memory_error = |error: &dyn Error| GPUInitError::Memory(name.clone(), error.to_string());
+        let vertex_shader: Arc<ShaderModule> = circle_vertex_shader::load(device.clone())
+            .map_err(|error| shader_error(&error))?;
+        let fragment_shader: Arc<ShaderModule> = circle_fragment_shader::load(device.clone())
+            .map_err(|error| shader_error(&error))?;
+        let render_pass = vulkano::single_pass_renderpass!(
+            device.clone(),
+            attachments: {
+                // what is under the circles is copied in before they are drawn, so the image isn't cleared
+                color: {
+                    load: Load,
+                    store: Store,
+                    format: Format::R8G8B8A8_UNORM,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        ).map_err(|error| shader_error(&error))?;
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input_state(BuffersDefinition::new().instance::<Circle>())
+            .vertex_shader(
+                vertex_shader.entry_point("main").unwrap(),
+                circle_vertex_shader::SpecializationConstants { width: width as f32, height: height as f32 }
+            )
+            .input_assembly_state(InputAssemblyState::new().topology(PrimitiveTopology::TriangleStrip))
+            .viewport_state(ViewportState::viewport_fixed_scissor_irrelevant([Viewport {
+                origin: [0.0, 0.0],
+                dimensions: [width as f32, height as f32],
+                depth_range: 0.0..1.0
+            }]))
+            .fragment_shader(fragment_shader.entry_point("main").unwrap(), ())
+            // over what is under them, keeping its alpha, as the CPU rasterizers keep that of the paint
+            .color_blend_state(ColorBlendState::new(1).blend(AttachmentBlend {
+                color_op: BlendOp::Add,
+                color_source: BlendFactor::SrcAlpha,
+                color_destination: BlendFactor::OneMinusSrcAlpha,
+                alpha_op: BlendOp::Add,
+                alpha_source: BlendFactor::Zero,
+                alpha_destination: BlendFactor::One
+            }))
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .build(device.clone())
+            .map_err(|error| shader_error(&error))?;
+        let image = AttachmentImage::with_usage(
+            device.clone(),
+            [width, height],
+            Format::R8G8B8A8_UNORM,
+            ImageUsage {
+                color_attachment: true,
+                transfer_src: true,
+                transfer_dst: true,
+                ..ImageUsage::empty()
+            }
+        ).map_err(|error| memory_error(&error))?;
+        let view = ImageView::new_default(image.clone()).map_err(|error| memory_error(&error))?;
+        let framebuffer = Framebuffer::new(render_pass, FramebufferCreateInfo {
+            attachments: vec![view],
+            ..Default::default()
+        }).map_err(|error| GPUInitError::Creation(name.clone(), error.to_string()))?;
+        let usage = BufferUsage {
+            transfer_src: true,
+            transfer_dst: true,
+            ..BufferUsage::empty()
+        };
+        let staging = CpuAccessibleBuffer::from_iter(device.clone(), usage, true, (0..width as usize * height as usize * 4).map(|_| 0u8))
+            .map_err(|error| memory_error(&error))?;
+        Ok(Self { device, queue, width, height, pipeline, image, framebuffer, staging })
+    }
+
+    /// draws `circles` over `canvas`, which has to be the size the rasterizer draws onto, each over those before it
+    pub fn draw(&mut self, canvas: &mut HorizontalLineImage<image::Rgba<u8>, Vec<u8>>, circles: &[Circle]) {
+        assert_eq!((canvas.width(), canvas.height()), (self.width, self.height), "canvas must be the size of the rasterizer");
+        if circles.is_empty() {
+            return
+        }
+        self.staging.write().unwrap().copy_from_slice(canvas.as_raw());
+        let usage = BufferUsage {
+            vertex_buffer: true,
+            ..BufferUsage::empty()
+        };
+        let instances = CpuAccessibleBuffer::from_iter(self.device.clone(), usage, false, circles.iter().copied())
+            .expect("unable to upload the circles");
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.device.clone(),
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit
+        ).unwrap();
+        builder
+            .copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(self.staging.clone(), self.image.clone())).unwrap()
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    // loaded rather than cleared
+                    clear_values: vec![None],
+                    ..RenderPassBeginInfo::framebuffer(self.framebuffer.clone())
+                },
+                SubpassContents::Inline
+            ).unwrap()
+            .bind_pipeline_graphics(self.pipeline.clone())
+            .bind_vertex_buffers(0, instances)
+            // a triangle strip of the 4 corners of every circle's quad
+            .draw(4, circles.len() as u32, 0, 0).unwrap()
+            .end_render_pass().unwrap()
+            .copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(self.image.clone(), self.staging.clone())).unwrap();
+        let command_buffer = Arc::new(builder.build().unwrap());
+        sync::now(self.device.clone())
+            .then_execute(self.queue.clone(), command_buffer).unwrap()
+            .then_signal_fence_and_flush().unwrap()
+            .wait(None).unwrap();
+        canvas.as_raw_mut().copy_from_slice(&self.staging.read().unwrap());
+    }
+}
+
+mod circle_vertex_shader {
+    vulkano_shaders::shader! {
+                ty: "vertex",
+                src: "
+#version 450
+
+// size of the image in pixels
+layout(constant_id = 0) const float width = 1.0;
+layout(constant_id = 1) const float height = 1.0;
+
+// a circle per instance
+layout(location = 0) in vec2 center;
+layout(location = 1) in float radius;
+layout(location = 2) in vec4 color;
+
+// pixels from the center
+layout(location = 0) out vec2 offset;
+layout(location = 1) out float circle_radius;
+layout(location = 2) out vec4 circle_color;
+
+void main() {
+    // a pixel past the edge, so that every pixel the edge passes through is drawn
+    float reach = radius + 1.0;
+    vec2 corner = vec2(gl_VertexIndex & 1, gl_VertexIndex >> 1) * 2.0 - 1.0;
+    offset = corner * reach;
+    gl_Position = vec4((center + offset) / vec2(width, height) * 2.0 - 1.0, 0.0, 1.0);
+    circle_radius = radius;
+    circle_color = color;
+}
+"
+    }
+}
+
+mod circle_fragment_shader {
+    vulkano_shaders::shader! {
+                ty: "fragment",
+                src: "
+#version 450
+
+layout(location = 0) in vec2 offset;
+layout(location = 1) in float radius;
+layout(location = 2) in vec4 color;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    // the distance of the center of the pixel from the edge stands in for how much of the pixel the circle covers,
+    // which can't tell how much of a pixel a circle smaller than a pixel covers, so those are dimmed by their area
+    float coverage = clamp(radius - length(offset) + 0.5, 0.0, 1.0) * min(3.14159265 * radius * radius, 1.0);
+    if (coverage == 0.0)
+        discard;
+    f_color = vec4(color.rgb, color.a * coverage);
+}
+"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+    use crate::render::cpu::{AreaIntersectionRasterizer, HorizontalLineImage, Rasterizer, RgbScalar};
+    use crate::world::gpu::{DeviceSelection, GPUWorld};
+    use super::{Circle, GPURasterizer};
+
+    fn has_device() -> bool {
+        let has_device = !GPUWorld::list_devices().is_empty();
+        if !has_device {
+            eprintln!("skipping the gpu, there is no device vulkan can run on");
+        }
+        has_device
+    }
+
+    #[test]
+    fn circles_cover_the_pixels_area_intersection_does() {
+        if !has_device() {
+            return
+        }
+        // apart from each other, and partly off the canvas
+        let circles = [(20.0, 20.0, 10.0), (60.5, 30.25, 15.3), (30.7, 75.2, 4.6), (85.0, 80.0, 2.5), (98.0, 50.0, 6.0), (55.0, -3.0, 8.0)];
+        let white = Rgba([255, 255, 255, 255]);
+        let mut gpu_canvas = HorizontalLineImage::<Rgba<u8>, Vec<u8>>::new(100, 100, |size| vec![0; size]);
+        let mut cpu_canvas = gpu_canvas.clone();
+        let mut rasterizer = GPURasterizer::new_on(&DeviceSelection::First, 100, 100).unwrap();
+        rasterizer.draw(&mut gpu_canvas, &circles.map(|(cx, cy, r)| Circle::new(cx, cy, r, white)));
+        for (cx, cy, r) in circles {
+            <AreaIntersectionRasterizer as Rasterizer<_, _, RgbScalar>>::draw_filled_circle(&mut cpu_canvas, cx, cy, r, white);
+        }
+        let (gpu, cpu) = (gpu_canvas.as_raw().iter().step_by(4), cpu_canvas.as_raw().iter().step_by(4));
+        let (mut gpu_total, mut cpu_total) = (0.0, 0.0);
+        for (i, (&gpu, &cpu)) in gpu.zip(cpu).enumerate() {
+            // covering the pixels at the edge by their distance from it is off by up to about 12 of 255, twice
+            // that is left for the rounding of the blending
+            assert!(gpu.abs_diff(cpu) <= 24, "{} on the gpu rather than {} at {}, {}", gpu, cpu, i % 100, i / 100);
+            gpu_total += gpu as f32 / 255.0;
+            cpu_total += cpu as f32 / 255.0;
+        }
+        assert!((gpu_total - cpu_total).abs() <= 0.01 * cpu_total, "{} pixels covered on the gpu rather than {}", gpu_total, cpu_total);
+    }
+}
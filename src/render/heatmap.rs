@@ -0,0 +1,102 @@
+use crate::render::colormap::Colormap;
+use crate::render::cpu::HorizontalLineCanvas;
+
+/// mass binned into square bins of pixels, drawn as a heatmap by [`draw`](Self::draw), which takes a pass over
+/// the particles and one over the bins rather than a circle per particle
+#[derive(Clone, Debug)]
+pub struct DensityGrid {
+    /// width and height of a bin in pixels
+    bin_size: u32,
+    /// pixels covered by the grid
+    width: u32,
+    height: u32,
+    columns: u32,
+    rows: u32,
+    mass: Vec<f32>
+}
+
+impl DensityGrid {
+    /// an empty grid covering `width` by `height` pixels, the last bins of a row or column are cut off by the edges
+    pub fn new(width: u32, height: u32, bin_size: u32) -> Self {
+        assert!(bin_size > 0, "bins must be at least a pixel wide");
+        let bins = |pixels: u32| (pixels as f32 / bin_size as f32).ceil() as u32;
+        let (columns, rows) = (bins(width), bins(height));
+        Self { bin_size, width, height, columns, rows, mass: vec![0.0; columns as usize * rows as usize] }
+    }
+
+    /// adds `mass` to the bin containing the pixel `(x, y)`, such as
+    /// [`Viewport::world_to_pixel`](crate::render::viewport::Viewport::world_to_pixel) gives,
+    /// nothing if that is off the grid
+    pub fn add(&mut self, (x, y): (i32, i32), mass: f32) {
+        if x < 0 || y < 0 {
+            return
+        }
+        let (column, row) = (x as u32 / self.bin_size, y as u32 / self.bin_size);
+        if column < self.columns && row < self.rows {
+            self.mass[(row * self.columns + column) as usize] += mass;
+        }
+    }
+
+    /// spreads the mass of every bin over the bins around it with a gaussian of `sigma` bins, cut off at 3 sigma,
+    /// mass spread off the grid is lost
+    pub fn splat(&mut self, sigma: f32) {
+        if sigma.is_nan() || sigma <= 0.0 {
+            return
+        }
+        let reach = (3.0 * sigma).ceil() as i64;
+        let mut kernel: Vec<f32> = (-reach..=reach)
+            .map(|offset| (-(offset * offset) as f32 / (2.0 * sigma * sigma)).exp())
+            .collect();
+        let total: f32 = kernel.iter().sum();
+        kernel.iter_mut().for_each(|weight| *weight /= total);
+        // the gaussian is separable, so it is spread along the rows and then along the columns
+        let (columns, rows) = (self.columns as i64, self.rows as i64);
+        // `lines` lines of `along` bins, the first bins of which are `line_stride` apart and the bins of which are `stride` apart
+        let spread = |mass: &[f32], lines: i64, line_stride: i64, along: i64, stride: i64| {
+            let mut spread = vec![0.0; mass.len()];
+            for line in 0..lines {
+                let bin = |j: i64| (line * line_stride + j * stride) as usize;
+                for j in 0..along {
+                    let source = mass[bin(j)];
+                    if source == 0.0 {
+                        continue
+                    }
+                    for (k, weight) in kernel.iter().enumerate() {
+                        let target = j + k as i64 - reach;
+                        if (0..along).contains(&target) {
+                            spread[bin(target)] += source * weight;
+                        }
+                    }
+                }
+            }
+            spread
+        };
+        let along_rows = spread(&self.mass, rows, columns, columns, 1);
+        self.mass = spread(&along_rows, columns, 1, rows, columns);
+    }
+
+    /// largest mass of a bin, 0 for an empty grid
+    pub fn max(&self) -> f32 {
+        self.mass.iter().copied().fold(0.0, f32::max)
+    }
+
+    /// fills every pixel of `canvas`, which has to be the size of the grid, with `colormap` at the mass of its bin
+    /// relative to `max`
+    pub fn draw<Paint: From<[u8; 4]>, Canvas: HorizontalLineCanvas<Paint>>(&self, canvas: &mut Canvas, colormap: Colormap, max: f32) {
+        assert_eq!((canvas.width(), canvas.height()), (self.width, self.height), "canvas must be the size of the grid");
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let mass = self.mass[(row * self.columns + column) as usize];
+                // an empty grid is drawn as the bottom of the colormap, rather than dividing by 0
+                let t = if max > 0.0 { mass / max } else { 0.0 };
+                let color = colormap.color(t);
+                let x0 = column * self.bin_size;
+                let x1 = (x0 + self.bin_size).min(self.width);
+                for y in row * self.bin_size..((row + 1) * self.bin_size).min(self.height) {
+                    // SAFETY: x0 is left of the last bin's right edge, which is cut off at width, and y is under height
+                    unsafe { canvas.draw_horizontal_line_unchecked(x0, x1, y, color.into()); }
+                }
+            }
+        }
+    }
+}
@@ -1 +1,10 @@
+pub mod colormap;
 pub mod cpu;
+pub mod ffmpeg;
+pub mod gif_comment;
+pub mod gpu;
+pub mod heatmap;
+#[cfg(feature = "preview")]
+pub mod preview;
+pub mod text;
+pub mod viewport;
@@ -0,0 +1,142 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::num::NonZeroU32;
+use std::rc::Rc;
+use std::time::Duration;
+use softbuffer::{Context, Surface};
+use winit::dpi::PhysicalSize;
+use winit::event::{ElementState, Event, KeyEvent, WindowEvent};
+use winit::event_loop::EventLoop;
+use winit::keyboard::{Key, NamedKey};
+use winit::platform::pump_events::{EventLoopExtPumpEvents, PumpStatus};
+use winit::window::{Window, WindowBuilder};
+use crate::render::cpu::{FixedSizeCanvas, HorizontalLineImage};
+
+/// a window showing frames as they are drawn, space pauses and resumes, escape or closing the window stops
+///
+/// the window only handles its events when [`update`](Self::update) is called, which has to be on the thread
+/// that created it, the main thread on some platforms
+pub struct PreviewWindow {
+    event_loop: EventLoop<()>,
+    window: Rc<Window>,
+    surface: Surface<Rc<Window>, Rc<Window>>,
+    /// the last frame shown, as softbuffer wants it, redrawn whenever the window asks for it
+    pixels: Vec<u32>,
+    width: u32,
+    height: u32,
+    paused: bool,
+    closed: bool
+}
+
+impl PreviewWindow {
+    /// opens a window titled `title` fitting frames of `width` by `height` pixels, black until the first is shown
+    pub fn new(title: &str, width: u32, height: u32) -> Result<Self, PreviewError> {
+        let event_loop = EventLoop::new().map_err(|error| PreviewError(error.to_string()))?;
+        let window = WindowBuilder::new()
+            .with_title(title)
+            .with_inner_size(PhysicalSize::new(width, height))
+            .build(&event_loop)
+            .map_err(|error| PreviewError(error.to_string()))?;
+        let window = Rc::new(window);
+        let context = Context::new(window.clone()).map_err(|error| PreviewError(error.to_string()))?;
+        let surface = Surface::new(&context, window.clone()).map_err(|error| PreviewError(error.to_string()))?;
+        Ok(Self {
+            event_loop,
+            window,
+            surface,
+            pixels: vec![0; width as usize * height as usize],
+            width,
+            height,
+            paused: false,
+            closed: false
+        })
+    }
+
+    /// handles the events that came in since the last call, without waiting for more, returns whether the window
+    /// is still open
+    pub fn update(&mut self) -> bool {
+        let (mut paused, mut closed, mut redraw) = (self.paused, self.closed, false);
+        let status = self.event_loop.pump_events(Some(Duration::ZERO), |event, _| {
+            if let Event::WindowEvent { event, .. } = event {
+                match event {
+                    WindowEvent::CloseRequested => closed = true,
+                    WindowEvent::RedrawRequested => redraw = true,
+                    WindowEvent::KeyboardInput { event: KeyEvent { logical_key, state: ElementState::Pressed, repeat: false, .. }, .. } => {
+                        match logical_key {
+                            Key::Named(NamedKey::Space) => paused = !paused,
+                            Key::Named(NamedKey::Escape) => closed = true,
+                            _ => {}
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        });
+        self.paused = paused;
+        self.closed = closed || matches!(status, PumpStatus::Exit(_));
+        if redraw && !self.closed {
+            self.present();
+        }
+        !self.closed
+    }
+
+    /// whether space was pressed to pause, until it is pressed again
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// shows `image`, which has to be the size given to [`new`](Self::new)
+    pub fn show(&mut self, image: &HorizontalLineImage<image::Rgba<u8>, Vec<u8>>) {
+        assert_eq!((image.width(), image.height()), (self.width, self.height), "image must be the size of the window");
+        for (pixel, rgba) in self.pixels.iter_mut().zip(image.as_raw().chunks_exact(4)) {
+            *pixel = zero_rgb(rgba);
+        }
+        self.present();
+    }
+
+    fn present(&mut self) {
+        let (width, height) = match (NonZeroU32::new(self.width), NonZeroU32::new(self.height)) {
+            (Some(width), Some(height)) => (width, height),
+            _ => return
+        };
+        // a preview that can't be drawn isn't worth stopping the run for, the window stays as it was
+        if self.surface.resize(width, height).is_err() {
+            return
+        }
+        if let Ok(mut buffer) = self.surface.buffer_mut() {
+            buffer.copy_from_slice(&self.pixels);
+            let _ = buffer.present();
+        }
+        self.window.request_redraw();
+    }
+}
+
+/// a pixel of a frame as softbuffer takes it, 0RGB, the alpha is left out since the preview is drawn over black as
+/// the frames are
+fn zero_rgb(rgba: &[u8]) -> u32 {
+    (rgba[0] as u32) << 16 | (rgba[1] as u32) << 8 | rgba[2] as u32
+}
+
+/// why a [`PreviewWindow`] couldn't be opened, such as without a display
+#[derive(Clone, Debug)]
+pub struct PreviewError(String);
+
+impl Display for PreviewError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "unable to open a window: {}", self.0)
+    }
+}
+
+impl Error for PreviewError {}
+
+#[cfg(test)]
+mod tests {
+    use super::zero_rgb;
+
+    #[test]
+    fn pixels_are_0rgb() {
+        assert_eq!(zero_rgb(&[0x12, 0x34, 0x56, 0xff]), 0x0012_3456);
+        assert_eq!(zero_rgb(&[0xff, 0, 0, 0]), 0x00ff_0000);
+        assert_eq!(zero_rgb(&[0, 0, 0xff, 0x80]), 0x0000_00ff);
+    }
+}
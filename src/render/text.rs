@@ -0,0 +1,72 @@
+use crate::render::cpu::{fill_rectangle, HorizontalLineCanvas};
+
+/// width of a glyph of [`draw_text`] in pixels of the font, glyphs are a pixel of the font apart
+pub const GLYPH_WIDTH: u32 = 3;
+/// height of a glyph of [`draw_text`] in pixels of the font
+pub const GLYPH_HEIGHT: u32 = 5;
+
+/// width of `text` drawn by [`draw_text`] in pixels
+pub fn text_width(text: &str, scale: u32) -> u32 {
+    match text.chars().count() as u32 {
+        0 => 0,
+        characters => (characters * (GLYPH_WIDTH + 1) - 1) * scale
+    }
+}
+
+/// draws `text` with its top left corner at `(x, y)` in a bitmap font, each pixel of which is `scale` pixels wide
+/// and high, text reaching past the edges of the canvas is cut off
+///
+/// the font only has digits, a few symbols and the letters of "frame", "t", "n", "inf", "cpu", "gpu" and "diff",
+/// other characters are left blank
+pub fn draw_text<Paint: Copy, Canvas: HorizontalLineCanvas<Paint>>(canvas: &mut Canvas, x: u32, y: u32, text: &str, scale: u32, paint: Paint) {
+    for (i, c) in text.chars().enumerate() {
+        let glyph_x = x.saturating_add(i as u32 * (GLYPH_WIDTH + 1) * scale);
+        if glyph_x >= canvas.width() {
+            break
+        }
+        for (row, bits) in glyph(c).iter().enumerate() {
+            let pixel_y = y.saturating_add(row as u32 * scale);
+            for column in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                    let pixel_x = glyph_x.saturating_add(column * scale);
+                    fill_rectangle(canvas, pixel_x, pixel_y, pixel_x.saturating_add(scale), pixel_y.saturating_add(scale), paint);
+                }
+            }
+        }
+    }
+}
+
+/// rows of the glyph of `c` from the top, the highest of the [`GLYPH_WIDTH`] bits of a row being its left pixel
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT as usize] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b011, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        'a' => [0b000, 0b011, 0b101, 0b101, 0b011],
+        'c' => [0b000, 0b011, 0b100, 0b100, 0b011],
+        'd' => [0b001, 0b011, 0b101, 0b101, 0b011],
+        'e' => [0b010, 0b101, 0b111, 0b100, 0b011],
+        'f' => [0b011, 0b100, 0b110, 0b100, 0b100],
+        'g' => [0b011, 0b101, 0b011, 0b001, 0b110],
+        'i' => [0b010, 0b000, 0b010, 0b010, 0b010],
+        'm' => [0b000, 0b110, 0b111, 0b101, 0b101],
+        'n' => [0b000, 0b110, 0b101, 0b101, 0b101],
+        'p' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'r' => [0b000, 0b101, 0b110, 0b100, 0b100],
+        't' => [0b010, 0b111, 0b010, 0b010, 0b011],
+        'u' => [0b000, 0b101, 0b101, 0b101, 0b011],
+        _ => [0; GLYPH_HEIGHT as usize]
+    }
+}
@@ -0,0 +1,95 @@
+use std::ops::Range;
+
+/// the part of the world drawn onto a canvas of `width` by `height` pixels, at the same scale along both axes so that
+/// circles stay circles
+///
+/// when the world and the canvas are of different aspect ratios, [`fit`](Self::fit) centers the world on the canvas,
+/// leaving bars of the background along the sides or along the top and bottom, the letterbox
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Viewport {
+    /// position drawn at the top left corner of the canvas, which is the smallest x and y
+    pub origin: (f32, f32),
+    /// pixels per unit of distance
+    pub scale: f32,
+    pub width: u32,
+    pub height: u32
+}
+
+impl Viewport {
+    pub fn new(origin: (f32, f32), scale: f32, width: u32, height: u32) -> Self {
+        Self { origin, scale, width, height }
+    }
+
+    /// the largest scale that fits all of `x` and `y` of the world onto the canvas, centered on it
+    ///
+    /// bounds of no extent along an axis are fitted along the other one, and bounds of no extent at all, such as
+    /// those of a single particle, are drawn at a scale of 1 in the center of the canvas
+    pub fn fit(x: Range<f32>, y: Range<f32>, width: u32, height: u32) -> Self {
+        let (extent_x, extent_y) = (x.end - x.start, y.end - y.start);
+        let scale = match (extent_x > 0.0, extent_y > 0.0) {
+            (true, true) => f32::min(width as f32 / extent_x, height as f32 / extent_y),
+            (true, false) => width as f32 / extent_x,
+            (false, true) => height as f32 / extent_y,
+            (false, false) => 1.0
+        };
+        let center = ((x.start + x.end) / 2.0, (y.start + y.end) / 2.0);
+        let origin = (center.0 - width as f32 / 2.0 / scale, center.1 - height as f32 / 2.0 / scale);
+        Self { origin, scale, width, height }
+    }
+
+    /// where `(x, y)` of the world is on the canvas, in pixels from its top left corner, with the fraction of a pixel
+    /// that antialiased rasterizers draw circles with
+    pub fn world_to_canvas(&self, x: f32, y: f32) -> (f32, f32) {
+        ((x - self.origin.0) * self.scale, (y - self.origin.1) * self.scale)
+    }
+
+    /// the pixel `(x, y)` of the world is in, which is off the canvas when either is negative, or at least the width
+    /// or height
+    pub fn world_to_pixel(&self, x: f32, y: f32) -> (i32, i32) {
+        let (px, py) = self.world_to_canvas(x, y);
+        (px.floor() as i32, py.floor() as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Viewport;
+
+    #[test]
+    fn wide_bounds_are_letterboxed_above_and_below() {
+        let viewport = Viewport::fit(-2.0..2.0, -0.5..0.5, 100, 100);
+        assert_eq!(viewport.scale, 25.0);
+        assert_eq!(viewport.world_to_pixel(-2.0, -0.5), (0, 37));
+        assert_eq!(viewport.world_to_pixel(0.0, 0.0), (50, 50));
+        assert_eq!(viewport.world_to_pixel(1.99, 0.49), (99, 62));
+    }
+
+    #[test]
+    fn tall_bounds_are_letterboxed_on_the_sides() {
+        let viewport = Viewport::fit(0.0..1.0, 0.0..4.0, 200, 100);
+        assert_eq!(viewport.scale, 25.0);
+        assert_eq!(viewport.world_to_pixel(0.0, 0.0), (87, 0));
+        assert_eq!(viewport.world_to_pixel(0.5, 2.0), (100, 50));
+        assert_eq!(viewport.world_to_pixel(0.99, 3.99), (112, 99));
+    }
+
+    #[test]
+    fn matching_bounds_fill_the_canvas() {
+        let viewport = Viewport::fit(-1.0..1.0, -1.0..1.0, 100, 100);
+        assert_eq!(viewport, Viewport::new((-1.0, -1.0), 50.0, 100, 100));
+        // off the canvas to the top left
+        assert_eq!(viewport.world_to_pixel(-1.01, -1.01), (-1, -1));
+    }
+
+    #[test]
+    fn degenerate_bounds_are_centered() {
+        let point = Viewport::fit(3.0..3.0, -2.0..-2.0, 100, 50);
+        assert_eq!(point.scale, 1.0);
+        assert_eq!(point.world_to_pixel(3.0, -2.0), (50, 25));
+        // a vertical line is fitted along its length
+        let line = Viewport::fit(1.0..1.0, 0.0..10.0, 100, 50);
+        assert_eq!(line.scale, 5.0);
+        assert_eq!(line.world_to_pixel(1.0, 5.0), (50, 25));
+        assert_eq!(line.world_to_pixel(1.0, 0.0), (50, 0));
+    }
+}
@@ -1,8 +1,11 @@
-use std::ops::{Add, AddAssign};
+use std::ops::{Add, AddAssign, Sub};
 use bytemuck::{Pod, Zeroable};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 #[derive(Default, Copy, Clone, Debug, PartialEq, Zeroable, Pod)]
 #[repr(C)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Vector {
     pub direction: f32,
     pub magnitude: f32
@@ -75,3 +78,107 @@ impl AddAssign for Vector {
         *self = *self + rhs;
     }
 }
+
+/// cartesian vector used by the three dimensional worlds
+#[derive(Default, Copy, Clone, Debug, PartialEq, Zeroable, Pod)]
+#[repr(C)]
+pub struct Vector3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32
+}
+
+impl Vector3 {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn scale(&self, scale: f32) -> Self {
+        Self { x: self.x * scale, y: self.y * scale, z: self.z * scale }
+    }
+
+    pub fn step(&mut self, derivative: &Vector3, time: f32) {
+        *self += derivative.scale(time);
+    }
+
+    pub fn length_sq(&self) -> f32 {
+        self.x * self.x + self.y * self.y + self.z * self.z
+    }
+}
+
+impl Add for Vector3 {
+    type Output = Vector3;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self { x: self.x + rhs.x, y: self.y + rhs.y, z: self.z + rhs.z }
+    }
+}
+
+impl AddAssign for Vector3 {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Vector3 {
+    type Output = Vector3;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self { x: self.x - rhs.x, y: self.y - rhs.y, z: self.z - rhs.z }
+    }
+}
+
+/// double precision cartesian counterpart of [`Vector`], used by the `f64` worlds
+#[derive(Default, Copy, Clone, Debug, PartialEq)]
+pub struct VectorF64 {
+    pub x: f64,
+    pub y: f64
+}
+
+impl VectorF64 {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    pub fn scale(&self, scale: f64) -> Self {
+        Self { x: self.x * scale, y: self.y * scale }
+    }
+
+    pub fn step(&mut self, derivative: &VectorF64, time: f64) {
+        *self += derivative.scale(time);
+    }
+
+    pub fn length_sq(&self) -> f64 {
+        self.x * self.x + self.y * self.y
+    }
+}
+
+impl From<Vector> for VectorF64 {
+    fn from(vector: Vector) -> Self {
+        let direction = vector.direction as f64;
+        let magnitude = vector.magnitude as f64;
+        Self::new(magnitude * f64::cos(direction), magnitude * f64::sin(direction))
+    }
+}
+
+impl Add for VectorF64 {
+    type Output = VectorF64;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self { x: self.x + rhs.x, y: self.y + rhs.y }
+    }
+}
+
+impl AddAssign for VectorF64 {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for VectorF64 {
+    type Output = VectorF64;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self { x: self.x - rhs.x, y: self.y - rhs.y }
+    }
+}
@@ -0,0 +1,334 @@
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use rayon::slice::ParallelSliceMut;
+use crate::{Particle, Vector};
+use crate::world::Boundary;
+use crate::world::force::ForceModel;
+
+/// cells are no longer subdivided past this depth, so that particles sharing a position
+/// don't cause endless subdivision
+const MAX_DEPTH: u32 = 32;
+
+/// number of levels a tree built from morton codes can have, each level takes two bits of a u32 code
+const MORTON_LEVELS: u32 = 16;
+
+/// cells with fewer particles than this are built serially by [`QuadTree::new_par`]
+const PARALLEL_BUILD_THRESHOLD: usize = 1024;
+
+/// quadtree over the cartesian positions of a set of particles, used to approximate
+/// the gravitational pull of far away groups of particles by their center of mass
+///
+/// theta is the opening angle, a cell of width `s` at distance `d` is approximated when `s / d < theta`,
+/// a theta of 0.0 computes every pair exactly, values above ~0.7 may let a particle be approximated
+/// together with the cell it is in
+///
+/// with a periodic [`Boundary`] every cell pulls from the closest image of its center of mass,
+/// the tree itself is not wrapped, so cells straddling an edge are only approximately handled
+pub struct QuadTree {
+    root: Option<Node>,
+    positions: Vec<(f32, f32)>,
+    masses: Vec<f32>
+}
+
+struct Node {
+    half_size: f32,
+    mass: f32,
+    center_of_mass: (f32, f32),
+    children: Vec<Node>,
+    // only filled in for leaves
+    particles: Vec<usize>
+}
+
+impl QuadTree {
+    pub fn new(particles: &[Particle]) -> Self {
+        let positions: Vec<(f32, f32)> = particles.iter()
+            .map(|particle| particle.position.to_cartesian())
+            .collect();
+        let masses: Vec<f32> = particles.iter()
+            .map(|particle| particle.mass)
+            .collect();
+        let root = bounding_square(&positions).map(|(center, half_size)| {
+            build(&positions, &masses, center, half_size, (0..particles.len()).collect(), 0)
+        });
+        Self { root, positions, masses }
+    }
+
+    /// builds the same tree as [`QuadTree::new`] (down to a depth of 16) in parallel
+    ///
+    /// the particles are sorted by the morton code of their position, which places every cell
+    /// of the tree in a contiguous range, so independent cells can be built on separate threads
+    /// and each node is summarized from its children once they are done
+    pub fn new_par(particles: &[Particle]) -> Self {
+        let positions: Vec<(f32, f32)> = particles.par_iter()
+            .map(|particle| particle.position.to_cartesian())
+            .collect();
+        let masses: Vec<f32> = particles.par_iter()
+            .map(|particle| particle.mass)
+            .collect();
+        let root = bounding_square(&positions).map(|(center, half_size)| {
+            let (min_x, min_y) = (center.0 - half_size, center.1 - half_size);
+            let cells = (1u32 << MORTON_LEVELS) as f32;
+            let scale = if half_size > 0.0 { cells / (half_size * 2.0) } else { 0.0 };
+            let mut codes: Vec<(u32, usize)> = positions.par_iter()
+                .enumerate()
+                .map(|(i, &(x, y))| {
+                    let cell_x = f32::min((x - min_x) * scale, cells - 1.0) as u32;
+                    let cell_y = f32::min((y - min_y) * scale, cells - 1.0) as u32;
+                    (spread_bits(cell_x) | (spread_bits(cell_y) << 1), i)
+                })
+                .collect();
+            codes.par_sort_unstable();
+            build_sorted(&positions, &masses, center, half_size, &codes, 0)
+        });
+        Self { root, positions, masses }
+    }
+
+    /// approximate gravitational acceleration acting on particle `i`
+    ///
+    /// the pulls are summed as cartesian components, which is much faster than adding [`Vector`]s
+    pub fn acceleration<F: ForceModel>(&self, i: usize, theta: f32, force_model: &F, softening: f32, boundary: Boundary) -> Vector {
+        let position = self.positions[i];
+        let mass = self.masses[i];
+        let (mut x, mut y) = (0.0, 0.0);
+        let mut stack: Vec<&Node> = self.root.iter().collect();
+        while let Some(node) = stack.pop() {
+            if node.mass == 0.0 {
+                continue
+            }
+            if node.children.is_empty() {
+                for &j in &node.particles {
+                    if j != i {
+                        let (ax, ay) = pull(position, mass, self.positions[j], self.masses[j], force_model, softening, boundary);
+                        x += ax;
+                        y += ay;
+                    }
+                }
+            } else {
+                let (dx, dy) = boundary.displacement((node.center_of_mass.0 - position.0, node.center_of_mass.1 - position.1));
+                let d = f32::sqrt(dx * dx + dy * dy);
+                if node.half_size * 2.0 < theta * d {
+                    let (ax, ay) = pull(position, mass, node.center_of_mass, node.mass, force_model, softening, boundary);
+                    x += ax;
+                    y += ay;
+                } else {
+                    stack.extend(&node.children);
+                }
+            }
+        }
+        Vector::from_cartesian(x, y)
+    }
+}
+
+/// gravitational acceleration acting on each particle, approximated with a [`QuadTree`]
+pub fn compute_accelerations<F: ForceModel>(particles: &[Particle], theta: f32, force_model: &F, softening: f32, boundary: Boundary) -> Vec<Vector> {
+    let tree = QuadTree::new(particles);
+    (0..particles.len())
+        .map(|i| tree.acceleration(i, theta, force_model, softening, boundary))
+        .collect()
+}
+
+/// parallel version of [`compute_accelerations`]
+pub fn par_compute_accelerations<F: ForceModel>(particles: &[Particle], theta: f32, force_model: &F, softening: f32, boundary: Boundary) -> Vec<Vector> {
+    let tree = QuadTree::new_par(particles);
+    (0..particles.len())
+        .into_par_iter()
+        .map(|i| tree.acceleration(i, theta, force_model, softening, boundary))
+        .collect()
+}
+
+/// smallest square containing every position, as its center and half of its width
+fn bounding_square(positions: &[(f32, f32)]) -> Option<((f32, f32), f32)> {
+    let (mut min_x, mut min_y) = *positions.first()?;
+    let (mut max_x, mut max_y) = (min_x, min_y);
+    for &(x, y) in positions {
+        min_x = f32::min(min_x, x);
+        min_y = f32::min(min_y, y);
+        max_x = f32::max(max_x, x);
+        max_y = f32::max(max_y, y);
+    }
+    let half_size = f32::max(max_x - min_x, max_y - min_y) / 2.0;
+    Some((((min_x + max_x) / 2.0, (min_y + max_y) / 2.0), half_size))
+}
+
+/// center of the given quadrant of a cell, quadrants are numbered by `x_bit | y_bit << 1`
+fn quadrant_center(center: (f32, f32), half_size: f32, quadrant: usize) -> (f32, f32) {
+    let quarter_size = half_size / 2.0;
+    let x = if quadrant & 1 == 0 { center.0 - quarter_size } else { center.0 + quarter_size };
+    let y = if quadrant & 2 == 0 { center.1 - quarter_size } else { center.1 + quarter_size };
+    (x, y)
+}
+
+/// builds the node for the square cell centered on `center`
+fn build(positions: &[(f32, f32)], masses: &[f32], center: (f32, f32), half_size: f32, particles: Vec<usize>, depth: u32) -> Node {
+    if particles.len() > 1 && depth < MAX_DEPTH {
+        let mut quadrants: [Vec<usize>; 4] = Default::default();
+        for i in particles {
+            let (x, y) = positions[i];
+            let quadrant = (x >= center.0) as usize | (((y >= center.1) as usize) << 1);
+            quadrants[quadrant].push(i);
+        }
+        let children = quadrants.into_iter()
+            .enumerate()
+            .filter(|(_, particles)| !particles.is_empty())
+            .map(|(quadrant, particles)| {
+                let center = quadrant_center(center, half_size, quadrant);
+                build(positions, masses, center, half_size / 2.0, particles, depth + 1)
+            })
+            .collect();
+        Node::branch(center, half_size, children)
+    } else {
+        Node::leaf(positions, masses, center, half_size, particles)
+    }
+}
+
+/// builds the node for the square cell centered on `center`, `codes` must be sorted and
+/// contain exactly the particles inside of the cell
+fn build_sorted(positions: &[(f32, f32)], masses: &[f32], center: (f32, f32), half_size: f32, codes: &[(u32, usize)], level: u32) -> Node {
+    if codes.len() > 1 && level < MORTON_LEVELS {
+        let shift = 2 * (MORTON_LEVELS - 1 - level);
+        let mut ranges = Vec::with_capacity(4);
+        let mut start = 0;
+        for quadrant in 0..4 {
+            let end = start + codes[start..].partition_point(|&(code, _)| (code >> shift) & 3 <= quadrant);
+            if end > start {
+                ranges.push((quadrant as usize, start..end));
+            }
+            start = end;
+        }
+        let build_child = |(quadrant, range): (usize, std::ops::Range<usize>)| {
+            let center = quadrant_center(center, half_size, quadrant);
+            build_sorted(positions, masses, center, half_size / 2.0, &codes[range], level + 1)
+        };
+        let children = if codes.len() >= PARALLEL_BUILD_THRESHOLD {
+            ranges.into_par_iter().map(build_child).collect()
+        } else {
+            ranges.into_iter().map(build_child).collect()
+        };
+        Node::branch(center, half_size, children)
+    } else {
+        let particles = codes.iter().map(|&(_, i)| i).collect();
+        Node::leaf(positions, masses, center, half_size, particles)
+    }
+}
+
+impl Node {
+    fn leaf(positions: &[(f32, f32)], masses: &[f32], center: (f32, f32), half_size: f32, particles: Vec<usize>) -> Self {
+        let mut mass = 0.0;
+        let mut weighted_x = 0.0;
+        let mut weighted_y = 0.0;
+        for &i in &particles {
+            let (x, y) = positions[i];
+            mass += masses[i];
+            weighted_x += masses[i] * x;
+            weighted_y += masses[i] * y;
+        }
+        Self {
+            half_size,
+            mass,
+            center_of_mass: center_of_mass(mass, weighted_x, weighted_y, center),
+            children: Vec::new(),
+            particles
+        }
+    }
+
+    fn branch(center: (f32, f32), half_size: f32, children: Vec<Node>) -> Self {
+        let mut mass = 0.0;
+        let mut weighted_x = 0.0;
+        let mut weighted_y = 0.0;
+        for child in &children {
+            mass += child.mass;
+            weighted_x += child.mass * child.center_of_mass.0;
+            weighted_y += child.mass * child.center_of_mass.1;
+        }
+        Self {
+            half_size,
+            mass,
+            center_of_mass: center_of_mass(mass, weighted_x, weighted_y, center),
+            children,
+            particles: Vec::new()
+        }
+    }
+}
+
+fn center_of_mass(mass: f32, weighted_x: f32, weighted_y: f32, center: (f32, f32)) -> (f32, f32) {
+    if mass > 0.0 {
+        (weighted_x / mass, weighted_y / mass)
+    } else {
+        center
+    }
+}
+
+/// spreads the lower 16 bits of `v` out to the even bits of the result
+fn spread_bits(v: u32) -> u32 {
+    let mut v = v & 0x0000FFFF;
+    v = (v | (v << 8)) & 0x00FF00FF;
+    v = (v | (v << 4)) & 0x0F0F0F0F;
+    v = (v | (v << 2)) & 0x33333333;
+    v = (v | (v << 1)) & 0x55555555;
+    v
+}
+
+/// acceleration of `mass` located at `from` towards `(x, y)` caused by `other_mass` located there, as cartesian components
+#[inline(always)]
+fn pull<F: ForceModel>(from: (f32, f32), mass: f32, (x, y): (f32, f32), other_mass: f32, force_model: &F, softening: f32, boundary: Boundary) -> (f32, f32) {
+    let (dx, dy) = boundary.displacement((x - from.0, y - from.1));
+    let distance_sq = dx * dx + dy * dy;
+    let a = force_model.acceleration(mass, other_mass, distance_sq + softening * softening);
+    if a.is_infinite() || distance_sq == 0.0 {
+        (0.0, 0.0)
+    } else {
+        let a_over_r = a / f32::sqrt(distance_sq);
+        (a_over_r * dx, a_over_r * dy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::G;
+    use crate::world::force::NewtonianGravity;
+    use crate::world::generate::random_particles;
+
+    /// largest distance between two accelerations of the same particle, relative to the size of the first
+    fn largest_relative_difference(a: &[Vector], b: &[Vector]) -> f32 {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| {
+                let (ax, ay) = a.to_cartesian();
+                let (bx, by) = b.to_cartesian();
+                f32::hypot(ax - bx, ay - by) / a.magnitude
+            })
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn theta_of_0_3_approximates_every_pair() {
+        let particles = random_particles(23, 100, 500.0);
+        let force_model = NewtonianGravity { g: G };
+        let exact: Vec<Vector> = particles.iter()
+            .enumerate()
+            .map(|(i, a)| {
+                particles.iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .fold(Vector::new(0.0, 0.0), |acceleration, (_, b)| {
+                        let (x, y) = pull(a.position.to_cartesian(), a.mass, b.position.to_cartesian(), b.mass, &force_model, 0.0, Boundary::Open);
+                        acceleration + Vector::from_cartesian(x, y)
+                    })
+            })
+            .collect();
+        let approximated = compute_accelerations(&particles, 0.3, &force_model, 0.0, Boundary::Open);
+        // the largest is ~2%
+        assert!(largest_relative_difference(&exact, &approximated) < 0.03);
+    }
+
+    #[test]
+    fn parallel_trees_approximate_like_serial_ones() {
+        let force_model = NewtonianGravity { g: G };
+        for count in [1_000, 10_000] {
+            let particles = random_particles(23, count, 500.0);
+            let serial = compute_accelerations(&particles, 0.5, &force_model, 0.01, Boundary::Open);
+            let parallel = par_compute_accelerations(&particles, 0.5, &force_model, 0.01, Boundary::Open);
+            assert!(largest_relative_difference(&serial, &parallel) < 1e-5, "{}", count);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::io::{self, Read, Write};
+use crate::{Particle, Vector};
+
+/// what a [`World`](crate::world::World) needs to continue a run from the end of a frame,
+/// in a compact little-endian binary layout
+///
+/// the settings of the world (integrator, softening and so on) are not part of it, they are the ones
+/// of the world it is resumed into, only the length of a frame is kept to catch resuming with different ones
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    /// frames simulated so far, the next frame to simulate
+    pub frame: usize,
+    /// time simulated per frame
+    pub time: f32,
+    /// substeps per frame
+    pub steps: u16,
+    pub particles: Vec<Particle>,
+    /// see [`Particle::id`]
+    pub next_id: u32,
+    /// particles removed for escaping so far
+    pub escaped: usize
+}
+
+const MAGIC: [u8; 4] = *b"GRAV";
+const VERSION: u32 = 2;
+
+impl Checkpoint {
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(self.frame as u64).to_le_bytes())?;
+        writer.write_all(&self.time.to_le_bytes())?;
+        writer.write_all(&self.steps.to_le_bytes())?;
+        writer.write_all(&self.next_id.to_le_bytes())?;
+        writer.write_all(&(self.escaped as u64).to_le_bytes())?;
+        writer.write_all(&(self.particles.len() as u64).to_le_bytes())?;
+        for particle in &self.particles {
+            for value in [particle.mass, particle.position.direction, particle.position.magnitude, particle.velocity.direction, particle.velocity.magnitude, particle.radius] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            writer.write_all(&particle.fixed.to_le_bytes())?;
+            writer.write_all(&particle.id.to_le_bytes())?;
+            writer.write_all(&particle.group.to_le_bytes())?;
+        }
+        writer.flush()
+    }
+
+    pub fn read(mut reader: impl Read) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a checkpoint"))
+        }
+        let version = read_u32(&mut reader)?;
+        if version != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unsupported checkpoint version {}", version)))
+        }
+        let frame = read_u64(&mut reader)? as usize;
+        let time = read_f32(&mut reader)?;
+        let mut steps = [0; 2];
+        reader.read_exact(&mut steps)?;
+        let steps = u16::from_le_bytes(steps);
+        let next_id = read_u32(&mut reader)?;
+        let escaped = read_u64(&mut reader)? as usize;
+        let length = read_u64(&mut reader)? as usize;
+        // a corrupt length runs out of bytes to read instead of allocating all of it up front
+        let mut particles = Vec::with_capacity(length.min(1 << 16));
+        for _ in 0..length {
+            particles.push(Particle {
+                mass: read_f32(&mut reader)?,
+                position: Vector::new(read_f32(&mut reader)?, read_f32(&mut reader)?),
+                velocity: Vector::new(read_f32(&mut reader)?, read_f32(&mut reader)?),
+                radius: read_f32(&mut reader)?,
+                fixed: read_u32(&mut reader)?,
+                id: read_u32(&mut reader)?,
+                group: read_u32(&mut reader)?
+            });
+        }
+        Ok(Self {
+            frame,
+            time,
+            steps,
+            particles,
+            next_id,
+            escaped
+        })
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f32(reader: &mut impl Read) -> io::Result<f32> {
+    read_u32(reader).map(f32::from_bits)
+}
@@ -0,0 +1,244 @@
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use crate::{Particle, Vector};
+
+/// every pair of touching particles (`i < j`), in ascending order
+pub fn touching_pairs(particles: &[Particle]) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for i in 0..particles.len() {
+        pairs.extend(touching_partners(particles, i));
+    }
+    pairs
+}
+
+/// parallel version of [`touching_pairs`]
+pub fn par_touching_pairs(particles: &[Particle]) -> Vec<(usize, usize)> {
+    (0..particles.len())
+        .into_par_iter()
+        .flat_map_iter(|i| touching_partners(particles, i))
+        .collect()
+}
+
+/// pairs of particle `i` with every touching particle after it
+fn touching_partners(particles: &[Particle], i: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let a = particles[i];
+    (i + 1..particles.len())
+        .filter(move |&j| {
+            let b = particles[j];
+            let touching_distance = a.radius + b.radius;
+            a.position.distance_sq(&b.position) < touching_distance * touching_distance
+        })
+        .map(move |j| (i, j))
+}
+
+/// merges every pair of particles in `pairs` into a single particle, groups of particles connected
+/// through several pairs become a single particle too
+///
+/// the merged particle takes the place of the lowest index of the group, the others are removed
+/// while keeping the order of the remaining particles, returns true if any particles were merged
+pub fn merge(particles: &mut Vec<Particle>, pairs: &[(usize, usize)]) -> bool {
+    if pairs.is_empty() {
+        return false;
+    }
+    // index of the particle each particle has been merged into, a particle that hasn't been merged points to itself
+    let mut merged_into: Vec<usize> = (0..particles.len()).collect();
+    let root = |merged_into: &mut Vec<usize>, mut i: usize| {
+        while merged_into[i] != i {
+            merged_into[i] = merged_into[merged_into[i]];
+            i = merged_into[i];
+        }
+        i
+    };
+    for &(i, j) in pairs {
+        let i = root(&mut merged_into, i);
+        let j = root(&mut merged_into, j);
+        if i == j {
+            continue
+        }
+        let (keep, absorb) = if i < j { (i, j) } else { (j, i) };
+        particles[keep] = merged(&particles[keep], &particles[absorb]);
+        merged_into[absorb] = keep;
+    }
+    let mut i = 0;
+    particles.retain(|_| {
+        let keep = merged_into[i] == i;
+        i += 1;
+        keep
+    });
+    true
+}
+
+/// single particle with the combined mass and momentum of `a` and `b`, placed at their center of mass
+///
+/// if either particle is fixed, the merged particle is fixed in its place instead
+fn merged(a: &Particle, b: &Particle) -> Particle {
+    // volume: r^3 = r1^3 + r2^3
+    let radius = f32::cbrt(a.radius * a.radius * a.radius + b.radius * b.radius * b.radius);
+    let mass = a.mass + b.mass;
+    if a.is_fixed() || b.is_fixed() {
+        let pinned = if a.is_fixed() { a } else { b };
+        return Particle { mass, radius, ..*pinned };
+    }
+    if mass == 0.0 {
+        return *a;
+    }
+    let a_fraction = a.mass / mass;
+    let b_fraction = b.mass / mass;
+    Particle {
+        mass,
+        position: a.position.scale(a_fraction) + b.position.scale(b_fraction),
+        // momentum: m1 * v1 + m2 * v2 = (m1 + m2) * v
+        velocity: a.velocity.scale(a_fraction) + b.velocity.scale(b_fraction),
+        radius,
+        fixed: 0,
+        id: a.id,
+        group: a.group
+    }
+}
+
+/// resolves the collision of every pair of particles in `pairs`, in order
+///
+/// approaching particles receive equal and opposite impulses along the line between their centers,
+/// then overlapping particles are pushed apart along the same line, the lighter one moving further,
+/// fixed particles behave as if their mass was infinite, returns true if any particles were moved
+pub fn bounce(particles: &mut [Particle], pairs: &[(usize, usize)], restitution: f32) -> bool {
+    let inverse_mass = |particle: &Particle| if particle.is_fixed() { 0.0 } else { 1.0 / particle.mass };
+    for &(i, j) in pairs {
+        let (a, b) = (particles[i], particles[j]);
+        if a.mass <= 0.0 || b.mass <= 0.0 {
+            continue
+        }
+        let (a_inverse_mass, b_inverse_mass) = (inverse_mass(&a), inverse_mass(&b));
+        let inverse_mass_sum = a_inverse_mass + b_inverse_mass;
+        if inverse_mass_sum == 0.0 {
+            continue
+        }
+        let (ax, ay) = a.position.to_cartesian();
+        let (bx, by) = b.position.to_cartesian();
+        let distance = f32::hypot(bx - ax, by - ay);
+        // collision normal pointing from a to b, particles sharing a position are separated along x
+        let (nx, ny) = if distance > 0.0 {
+            ((bx - ax) / distance, (by - ay) / distance)
+        } else {
+            (1.0, 0.0)
+        };
+
+        let (mut avx, mut avy) = a.velocity.to_cartesian();
+        let (mut bvx, mut bvy) = b.velocity.to_cartesian();
+        let normal_speed = (bvx - avx) * nx + (bvy - avy) * ny;
+        // only approaching particles bounce, separating ones are already moving apart
+        if normal_speed < 0.0 {
+            let impulse = -(1.0 + restitution) * normal_speed / inverse_mass_sum;
+            avx -= impulse * a_inverse_mass * nx;
+            avy -= impulse * a_inverse_mass * ny;
+            bvx += impulse * b_inverse_mass * nx;
+            bvy += impulse * b_inverse_mass * ny;
+            particles[i].velocity = Vector::from_cartesian(avx, avy);
+            particles[j].velocity = Vector::from_cartesian(bvx, bvy);
+        }
+
+        let overlap = a.radius + b.radius - distance;
+        if overlap > 0.0 {
+            let a_push = overlap * a_inverse_mass / inverse_mass_sum;
+            let b_push = overlap * b_inverse_mass / inverse_mass_sum;
+            particles[i].position = Vector::from_cartesian(ax - a_push * nx, ay - a_push * ny);
+            particles[j].position = Vector::from_cartesian(bx + b_push * nx, by + b_push * ny);
+        }
+    }
+    !pairs.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU16;
+    use crate::{Particle, Vector};
+    use crate::world::{Collisions, World};
+    use crate::world::cpu::CPUWorld;
+    use crate::world::par::ParWorld;
+    use super::{bounce, touching_pairs};
+
+    fn particle(mass: f32, (x, y): (f32, f32), (vx, vy): (f32, f32), radius: f32) -> Particle {
+        Particle {
+            mass,
+            position: Vector::from_cartesian(x, y),
+            velocity: Vector::from_cartesian(vx, vy),
+            radius,
+            fixed: 0,
+            id: 0,
+            group: 0
+        }
+    }
+
+    fn assert_near((x, y): (f32, f32), (expected_x, expected_y): (f32, f32)) {
+        assert!((x - expected_x).abs() < 1e-5 && (y - expected_y).abs() < 1e-5, "({}, {}) isn't ({}, {})", x, y, expected_x, expected_y);
+    }
+
+    #[test]
+    fn head_on_equal_masses_swap_velocities() {
+        let mut particles = [
+            particle(1.0, (-0.45, 0.0), (1.0, 0.0), 0.5),
+            particle(1.0, (0.45, 0.0), (-0.5, 0.0), 0.5)
+        ];
+        let pairs = touching_pairs(&particles);
+        assert_eq!(pairs, [(0, 1)]);
+        assert!(bounce(&mut particles, &pairs, 1.0));
+        assert_near(particles[0].velocity.to_cartesian(), (-0.5, 0.0));
+        assert_near(particles[1].velocity.to_cartesian(), (1.0, 0.0));
+        // the overlap of 0.1 is split evenly
+        assert_near(particles[0].position.to_cartesian(), (-0.5, 0.0));
+        assert_near(particles[1].position.to_cartesian(), (0.5, 0.0));
+    }
+
+    #[test]
+    fn grazing_collision_keeps_tangential_velocity() {
+        let mut particles = [
+            particle(1.0, (0.0, 0.0), (1.0, 0.0), 0.55),
+            particle(1.0, (0.8, 0.6), (0.0, 0.0), 0.55)
+        ];
+        let pairs = touching_pairs(&particles);
+        bounce(&mut particles, &pairs, 1.0);
+        // the normal part of the velocity of the first, 0.8 along (0.8, 0.6), is handed to the second
+        let (avx, avy) = particles[0].velocity.to_cartesian();
+        let (bvx, bvy) = particles[1].velocity.to_cartesian();
+        assert_near((avx, avy), (0.36, -0.48));
+        assert_near((bvx, bvy), (0.64, 0.48));
+        assert_near((avx + bvx, avy + bvy), (1.0, 0.0));
+        assert!((avx * avx + avy * avy + bvx * bvx + bvy * bvy - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn separating_particles_dont_bounce() {
+        let mut particles = [
+            particle(1.0, (-0.5, 0.0), (-1.0, 0.0), 0.5),
+            particle(1.0, (0.5, 0.0), (1.0, 0.0), 0.5)
+        ];
+        bounce(&mut particles, &[(0, 1)], 1.0);
+        assert_near(particles[0].velocity.to_cartesian(), (-1.0, 0.0));
+        assert_near(particles[1].velocity.to_cartesian(), (1.0, 0.0));
+    }
+
+    /// a particle of mass 3 and one of mass 1 heading towards each other at 1, without gravity, that touch after 0.9
+    fn head_on_collision<W: World>(world: impl Fn(Vec<Particle>) -> W) {
+        let mut world = world(vec![
+            particle(3.0, (-1.0, 0.0), (1.0, 0.0), 0.1),
+            particle(1.0, (1.0, 0.0), (-1.0, 0.0), 0.1)
+        ]);
+        world.tick(2.0, NonZeroU16::new(20).unwrap());
+        let mass_points = world.get_mass_points();
+        assert_eq!(mass_points.len(), 1);
+        let merged = mass_points[0];
+        assert_eq!(merged.mass, 4.0);
+        // (3 * 1 + 1 * -1) / 4
+        assert_near(merged.velocity, (0.5, 0.0));
+        // the center of mass starts at -0.5 and keeps moving at 0.5
+        assert_near(merged.position, (0.5, 0.0));
+        assert_eq!(merged.id, 0);
+        let radius = world.get_particles()[0].radius;
+        assert!((radius - f32::cbrt(2.0 * 0.001)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn head_on_collisions_merge() {
+        head_on_collision(|particles| CPUWorld::new(particles).with_g(0.0).with_collisions(Collisions::Merge));
+        head_on_collision(|particles| ParWorld::new(particles).with_g(0.0).with_collisions(Collisions::Merge));
+    }
+}
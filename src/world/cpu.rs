@@ -1,56 +1,848 @@
 use std::num::NonZeroU16;
+#[cfg(feature = "serde")]
+use std::io::{Read, Write};
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
 use std::f32::consts::PI;
 use crate::{MassPoint, Particle, Vector};
+use crate::vector::{Vector3, VectorF64};
+use crate::world::{barnes_hut, collision, damp, Boundary, shortest_timescale, AdaptiveSteps, G, G_F64, Collisions, Escape, Integrator, MassPoint3, Particle3, ParticleF64};
+use crate::world::{assign_ids, diagnostics, escape, simd, World};
+use crate::world::field::{self, Field};
+use crate::world::checkpoint::Checkpoint;
+use crate::world::force::{ForceModel, NewtonianGravity};
+use crate::world::store::ParticleStore;
+use crate::world::summation::{deterministic_acceleration, CompensatedSum, Summation};
+#[cfg(feature = "serde")]
+use crate::world::snapshot::Snapshot;
 
-pub struct CPUWorld {
-    pub particles: Vec<Particle>
+pub struct CPUWorld<F = NewtonianGravity> {
+    pub particles: ParticleStore,
+    integrator: Integrator,
+    force_model: F,
+    softening: f32,
+    drag: f32,
+    boundary: Boundary,
+    barnes_hut_theta: Option<f32>,
+    collisions: Collisions,
+    adaptive_steps: Option<AdaptiveSteps>,
+    fields: Vec<Field>,
+    escape: Option<Escape>,
+    escaped: usize,
+    compensated_summation: bool,
+    deterministic_summation: bool,
+    /// id of the next particle added, see [`Particle::id`]
+    next_id: u32
 }
 
 impl CPUWorld {
-    pub fn new() -> Self {
-        Self { particles: Vec::new() }
+    pub fn new(mut particles: Vec<Particle>) -> Self {
+        assign_ids(&mut particles);
+        let next_id = particles.len() as u32;
+        Self {
+            particles: particles.into(),
+            integrator: Integrator::default(),
+            force_model: NewtonianGravity::default(),
+            softening: 0.0,
+            drag: 0.0,
+            boundary: Boundary::default(),
+            barnes_hut_theta: None,
+            collisions: Collisions::default(),
+            adaptive_steps: None,
+            fields: Vec::new(),
+            escape: None,
+            escaped: 0,
+            compensated_summation: false,
+            deterministic_summation: false,
+            next_id
+        }
+    }
+
+    /// sets the gravitational constant of the default [`NewtonianGravity`] force model, [`G`] by default
+    pub fn with_g(mut self, g: f32) -> Self {
+        self.force_model.g = g;
+        self
+    }
+}
+
+impl<F: ForceModel> CPUWorld<F> {
+    pub fn with_integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    /// replaces the force between particles, which is [`NewtonianGravity`] by default
+    pub fn with_force_model<M: ForceModel>(self, force_model: M) -> CPUWorld<M> {
+        CPUWorld {
+            particles: self.particles,
+            integrator: self.integrator,
+            force_model,
+            softening: self.softening,
+            drag: self.drag,
+            boundary: self.boundary,
+            barnes_hut_theta: self.barnes_hut_theta,
+            collisions: self.collisions,
+            adaptive_steps: self.adaptive_steps,
+            fields: self.fields,
+            escape: self.escape,
+            escaped: self.escaped,
+            compensated_summation: self.compensated_summation,
+            deterministic_summation: self.deterministic_summation,
+            next_id: self.next_id
+        }
+    }
+
+    /// sets the softening length, the force between two particles becomes proportional to
+    /// 1 / (r^2 + softening^2), which keeps close encounters from producing absurd accelerations
+    pub fn with_softening(mut self, softening: f32) -> Self {
+        self.softening = softening;
+        self
+    }
+
+    /// sets the linear drag coefficient `k`, every substep of length `dt` scales the velocities by `1 - k * dt`,
+    /// which makes systems settle instead of scattering forever, 0.0 (the default) disables drag
+    pub fn with_drag(mut self, drag: f32) -> Self {
+        self.drag = drag;
+        self
+    }
+
+    /// sets the shape of the space, see [`Boundary`]
+    pub fn with_boundary(mut self, boundary: Boundary) -> Self {
+        self.boundary = boundary;
+        self
+    }
+
+    /// approximates the forces with a Barnes-Hut [`QuadTree`](barnes_hut::QuadTree) using the opening angle `theta`,
+    /// `None` computes the force between every pair of particles exactly
+    pub fn with_barnes_hut(mut self, theta: Option<f32>) -> Self {
+        self.barnes_hut_theta = theta;
+        self
+    }
+
+    /// sets how touching particles interact, merged particles are removed from the world
+    pub fn with_collisions(mut self, collisions: Collisions) -> Self {
+        self.collisions = collisions;
+        self
+    }
+
+    /// sizes substeps to the shortest free-fall or crossing time between two particles,
+    /// the `steps` passed to [`tick`](Self::tick) are ignored while this is set
+    pub fn with_adaptive_steps(mut self, adaptive_steps: Option<AdaptiveSteps>) -> Self {
+        self.adaptive_steps = adaptive_steps;
+        self
+    }
+
+    /// removes particles that have escaped the system at the end of every tick, see [`Escape`],
+    /// `None` (the default) keeps every particle
+    pub fn with_escape(mut self, escape: Option<Escape>) -> Self {
+        self.escape = escape;
+        self
+    }
+
+    /// sums the accelerations of every pair of particles with a compensated (Neumaier) sum instead of adding them directly,
+    /// has no effect with Barnes-Hut
+    ///
+    /// this skips the vectorized pairwise loop, which is already more precise than
+    /// adding [`Vector`]s as it sums cartesian components, and much faster
+    pub fn with_compensated_summation(mut self, compensated_summation: bool) -> Self {
+        self.compensated_summation = compensated_summation;
+        self
+    }
+
+    /// sums the accelerations acting on each particle in ascending order of index in a fixed binary tree,
+    /// instead of visiting every pair once, takes precedence over compensated summation and has no effect with Barnes-Hut
+    ///
+    /// [`ParWorld`](crate::world::par::ParWorld) sums the same way, so both follow bit for bit the same trajectories,
+    /// which they don't otherwise as the order of floating point additions changes their rounding,
+    /// slower as the force between every pair is computed twice and isn't vectorized
+    pub fn with_deterministic_summation(mut self, deterministic_summation: bool) -> Self {
+        self.deterministic_summation = deterministic_summation;
+        self
+    }
+
+    /// adds an external force field acting on every particle, see [`Field`]
+    pub fn add_field(&mut self, field: Field) {
+        self.fields.push(field);
+    }
+
+    /// adds a particle to the world, it is pulled on and pulls from the next tick on,
+    /// returns the id it was given
+    pub fn add_particle(&mut self, particle: Particle) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.particles.push(Particle { id, ..particle });
+        id
+    }
+
+    /// removes the particle at `index` and returns it, the last particle takes its index
+    pub fn remove_particle(&mut self, index: usize) -> Particle {
+        self.particles.swap_remove(index)
     }
 
-    pub fn tick(&mut self, time: f32, steps: NonZeroU16) {
+    /// advances the world by `time`, returns the number of substeps taken
+    ///
+    /// the velocity and position columns of [`particles`](Self::particles) are stepped in place, unless a setting
+    /// that works on whole particles is set, see [`ticks_columns`](Self::ticks_columns)
+    pub fn tick(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        if !self.ticks_columns() {
+            return self.tick_with_observer(time, steps, |_| {})
+        }
         let stepped_time = time / steps.get() as f32;
-        for _ in 0..steps.get() {
-            let particles_len = self.particles.len();
-            let mut accelerations = vec![Vector::new(0.0, 0.0); particles_len];
-            for i in 0..particles_len {
-                for j in i + 1..particles_len {
-                    let a = self.particles[i];
-                    let b = self.particles[j];
-                    let r_sq = Vector::distance_sq(&a.position, &b.position);
-                    // Newtons law of universal gravitation: (G * m1 * m2) / r^2
-                    let f = (6.67430e-11 * a.mass * b.mass / r_sq) * stepped_time;
-                    if f.is_infinite() {
-                        continue
-                    } else {
-                        let (x1, y1) = a.position.to_cartesian();
-                        let (x2, y2) = b.position.to_cartesian();
-                        let d1 = f32::atan2(y2 - y1, x2 - x1);
-                        let d2 = d1 + PI;
-                        // f = ma
-                        accelerations[i] += Vector::new(d1, f / a.mass);
-                        accelerations[j] += Vector::new(d2, f / b.mass);
-                    }
+        let (force_model, softening, drag, boundary) = (&self.force_model, self.softening, self.drag, self.boundary);
+        self.integrator.integrate(&mut self.particles, stepped_time, steps, |particles| {
+            simd::compute_store_accelerations(particles, force_model, softening, boundary)
+        }, |particles| {
+            particles.damp(drag, stepped_time);
+            particles.apply_boundary(boundary);
+            false
+        });
+        steps.get()
+    }
+
+    /// whether [`tick`](Self::tick) steps the columns of the particles in place, which it does unless Barnes-Hut,
+    /// compensated or deterministic summation, fields, collisions, adaptive steps or escaping are set, which take
+    /// whole particles out of the store for the tick and put them back afterwards
+    pub fn ticks_columns(&self) -> bool {
+        self.barnes_hut_theta.is_none()
+            && !self.compensated_summation
+            && !self.deterministic_summation
+            && self.fields.is_empty()
+            && self.collisions == Collisions::None
+            && self.adaptive_steps.is_none()
+            && self.escape.is_none()
+    }
+
+    /// [`tick`](Self::tick), calling `observer` with every particle after each substep, once drag,
+    /// the boundary and collisions have been applied, particles that escape are only removed after the last substep
+    ///
+    /// the particles are taken out of the store for the tick, as the observer needs them whole
+    pub fn tick_with_observer(&mut self, time: f32, steps: NonZeroU16, mut observer: impl FnMut(&[Particle])) -> u16 {
+        let mut particles = self.particles.to_vec();
+        let taken = match self.adaptive_steps {
+            Some(adaptive_steps) => {
+                let mut remaining = time;
+                let mut taken = 0;
+                while remaining > 0.0 {
+                    let timescale = shortest_timescale(&particles, &self.force_model, self.softening);
+                    let stepped_time = adaptive_steps.substep(timescale, time, remaining, taken);
+                    self.substeps(&mut particles, stepped_time, NonZeroU16::new(1).unwrap(), &mut observer);
+                    remaining -= stepped_time;
+                    taken += 1;
                 }
+                taken
             }
-            for (i, particle) in self.particles.iter_mut().enumerate() {
-                particle.velocity.step(&accelerations[i], stepped_time);
-                particle.position.step(&particle.velocity, stepped_time);
+            None => {
+                self.substeps(&mut particles, time / steps.get() as f32, steps, &mut observer);
+                steps.get()
             }
+        };
+        if let Some(escape) = self.escape {
+            self.escaped += escape::cull(&mut particles, escape, &self.force_model, self.softening, &self.fields);
         }
+        self.particles = particles.into();
+        taken
+    }
+
+    /// runs the world backwards by `time`, returns the number of substeps taken
+    ///
+    /// velocities are reversed, the world is ticked forwards, then velocities are reversed again,
+    /// which exactly undoes a tick with Verlet or Yoshida (up to rounding) as long as there is no drag,
+    /// collision or escape, which aren't reversible
+    ///
+    /// two orbits of a Kepler orbit forwards and back, in 100 frames of 20 substeps, return within
+    /// ~2e-4 of the semi-major axis with Verlet and ~7e-4 with Yoshida, chaotic systems with close
+    /// encounters amplify rounding and may not return at all
+    pub fn tick_reverse(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        self.particles.reverse_velocities();
+        let taken = self.tick(time, steps);
+        self.particles.reverse_velocities();
+        taken
+    }
+
+    /// number of particles removed for escaping so far
+    pub fn escaped(&self) -> usize {
+        self.escaped
+    }
+
+    fn substeps(&self, particles: &mut Vec<Particle>, stepped_time: f32, steps: NonZeroU16, observer: &mut impl FnMut(&[Particle])) {
+        let force_model = &self.force_model;
+        let fields = &self.fields;
+        let softening = self.softening;
+        let drag = self.drag;
+        let boundary = self.boundary;
+        let barnes_hut_theta = self.barnes_hut_theta;
+        let collisions = self.collisions;
+        let compensated_summation = self.compensated_summation;
+        let deterministic_summation = self.deterministic_summation;
+        self.integrator.integrate(particles, stepped_time, steps, |particles| {
+            let mut accelerations = match barnes_hut_theta {
+                Some(theta) => barnes_hut::compute_accelerations(particles, theta, force_model, softening, boundary),
+                None if deterministic_summation => (0..particles.len())
+                    .map(|i| deterministic_acceleration(particles, i, force_model, softening, boundary))
+                    .collect(),
+                None if compensated_summation => compute_accelerations::<F, CompensatedSum>(particles, force_model, softening, boundary),
+                None => simd::compute_accelerations(particles, force_model, softening, boundary)
+            };
+            field::apply(fields, particles, &mut accelerations, force_model, softening);
+            accelerations
+        }, |particles| {
+            damp(particles, drag, stepped_time);
+            boundary.apply(particles);
+            let changed = match collisions {
+                Collisions::None => false,
+                Collisions::Merge => {
+                    let pairs = collision::touching_pairs(particles);
+                    collision::merge(particles, &pairs)
+                }
+                Collisions::Elastic { restitution } => {
+                    let pairs = collision::touching_pairs(particles);
+                    collision::bounce(particles, &pairs, restitution)
+                }
+            };
+            observer(particles);
+            changed
+        });
     }
 
     pub fn get_mass_points(&self) -> Vec<MassPoint> {
-        let mut mass_points = Vec::with_capacity(self.particles.len());
-        for particle in &self.particles {
-            mass_points.push(MassPoint {
+        self.particles.mass_points()
+    }
+
+    /// every particle with its velocity, in the same order as [`get_mass_points`](Self::get_mass_points),
+    /// velocities are taken at the same instant as positions, as every integrator ends a tick with both in step
+    ///
+    /// a world created from these particles continues exactly like this one, except that it numbers the ids anew
+    pub fn get_particles(&self) -> Vec<Particle> {
+        self.particles.to_vec()
+    }
+
+    /// kinetic energy of every moving particle
+    pub fn kinetic_energy(&self) -> f32 {
+        diagnostics::kinetic_energy(&self.particles.to_vec())
+    }
+
+    /// potential energy of every pair of particles and of every particle in the fields of this world
+    pub fn potential_energy(&self) -> f32 {
+        diagnostics::potential_energy(&self.particles.to_vec(), &self.force_model, self.softening, self.boundary, &self.fields)
+    }
+
+    pub fn total_energy(&self) -> f32 {
+        self.kinetic_energy() + self.potential_energy()
+    }
+
+    /// momentum of every moving particle
+    pub fn total_momentum(&self) -> Vector {
+        diagnostics::total_momentum(&self.particles.to_vec())
+    }
+
+    /// mass weighted average position of every particle
+    pub fn center_of_mass(&self) -> (f32, f32) {
+        diagnostics::center_of_mass(self.get_mass_points())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<F: ForceModel + Clone + Serialize + DeserializeOwned> CPUWorld<F> {
+    /// writes the particles and settings of this world as JSON, see [`Snapshot`]
+    pub fn save_snapshot(&self, writer: impl Write) -> serde_json::Result<()> {
+        Snapshot {
+            particles: self.particles.to_vec(),
+            next_id: self.next_id,
+            integrator: self.integrator,
+            force_model: self.force_model.clone(),
+            softening: self.softening,
+            drag: self.drag,
+            boundary: self.boundary,
+            barnes_hut_theta: self.barnes_hut_theta,
+            collisions: self.collisions,
+            adaptive_steps: self.adaptive_steps,
+            fields: self.fields.clone(),
+            escape: self.escape,
+            escaped: self.escaped,
+            compensated_summation: self.compensated_summation,
+            deterministic_summation: self.deterministic_summation
+        }.write(writer)
+    }
+
+    /// reads a world written by [`save_snapshot`](Self::save_snapshot)
+    pub fn from_snapshot(reader: impl Read) -> serde_json::Result<Self> {
+        let snapshot = Snapshot::read(reader)?;
+        Ok(Self {
+            particles: snapshot.particles.into(),
+            integrator: snapshot.integrator,
+            force_model: snapshot.force_model,
+            softening: snapshot.softening,
+            drag: snapshot.drag,
+            boundary: snapshot.boundary,
+            barnes_hut_theta: snapshot.barnes_hut_theta,
+            collisions: snapshot.collisions,
+            adaptive_steps: snapshot.adaptive_steps,
+            fields: snapshot.fields,
+            escape: snapshot.escape,
+            escaped: snapshot.escaped,
+            compensated_summation: snapshot.compensated_summation,
+            deterministic_summation: snapshot.deterministic_summation,
+            next_id: snapshot.next_id
+        })
+    }
+}
+
+impl<F: ForceModel> World for CPUWorld<F> {
+    fn tick(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        CPUWorld::tick(self, time, steps)
+    }
+
+    fn tick_with_observer<O: FnMut(&[Particle])>(&mut self, time: f32, steps: NonZeroU16, observer: O) -> u16 {
+        CPUWorld::tick_with_observer(self, time, steps, observer)
+    }
+
+    fn tick_reverse(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        CPUWorld::tick_reverse(self, time, steps)
+    }
+
+    fn get_mass_points(&self) -> Vec<MassPoint> {
+        CPUWorld::get_mass_points(self)
+    }
+
+    fn get_particles(&self) -> Vec<Particle> {
+        CPUWorld::get_particles(self)
+    }
+
+    fn total_energy(&self) -> f32 {
+        CPUWorld::total_energy(self)
+    }
+
+    fn add_particle(&mut self, particle: Particle) -> u32 {
+        CPUWorld::add_particle(self, particle)
+    }
+
+    fn remove_particle(&mut self, index: usize) -> Particle {
+        CPUWorld::remove_particle(self, index)
+    }
+
+    fn checkpoint(&self, frame: usize, time: f32, steps: NonZeroU16) -> Checkpoint {
+        Checkpoint {
+            frame,
+            time,
+            steps: steps.get(),
+            particles: self.get_particles(),
+            next_id: self.next_id,
+            escaped: self.escaped
+        }
+    }
+
+    fn resume(&mut self, checkpoint: &Checkpoint) {
+        self.particles = checkpoint.particles.clone().into();
+        self.next_id = checkpoint.next_id;
+        self.escaped = checkpoint.escaped;
+    }
+}
+
+/// gravitational acceleration acting on each particle, indexed the same as `particles`
+fn compute_accelerations<F: ForceModel, S: Summation>(particles: &[Particle], force_model: &F, softening: f32, boundary: Boundary) -> Vec<Vector> {
+    let particles_len = particles.len();
+    let mut accelerations = vec![S::default(); particles_len];
+    // every unordered pair is visited once, its force acts on both particles in opposite directions
+    for i in 0..particles_len {
+        for j in i + 1..particles_len {
+            let a = particles[i];
+            let b = particles[j];
+            let (distance_sq, d1) = boundary.separation(&a.position, &b.position);
+            let r_sq = distance_sq + softening * softening;
+            let f = force_model.force(a.mass, b.mass, r_sq);
+            if f.is_infinite() {
+                continue
+            } else {
+                let d2 = d1 + PI;
+                // f = ma
+                accelerations[i].add(Vector::new(d1, f / a.mass));
+                accelerations[j].add(Vector::new(d2, f / b.mass));
+            }
+        }
+    }
+    accelerations.into_iter().map(S::to_vector).collect()
+}
+
+/// three dimensional counterpart of [`CPUWorld`], without Barnes-Hut or collisions
+pub struct CPUWorld3 {
+    pub particles: Vec<Particle3>,
+    integrator: Integrator,
+    g: f32,
+    softening: f32
+}
+
+impl CPUWorld3 {
+    pub fn new(particles: Vec<Particle3>) -> Self {
+        Self {
+            particles,
+            integrator: Integrator::default(),
+            g: G,
+            softening: 0.0
+        }
+    }
+
+    pub fn with_integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    /// sets the gravitational constant, [`G`] by default
+    pub fn with_g(mut self, g: f32) -> Self {
+        self.g = g;
+        self
+    }
+
+    /// sets the softening length, see [`CPUWorld::with_softening`]
+    pub fn with_softening(mut self, softening: f32) -> Self {
+        self.softening = softening;
+        self
+    }
+
+    /// advances the world by `time` in `steps` substeps, returns the number of substeps taken
+    pub fn tick(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        let stepped_time = time / steps.get() as f32;
+        let g = self.g;
+        let softening = self.softening;
+        self.integrator.integrate(&mut self.particles, stepped_time, steps, |particles| {
+            compute_accelerations_3(particles, g, softening)
+        }, |_| false);
+        steps.get()
+    }
+
+    pub fn get_mass_points(&self) -> Vec<MassPoint3> {
+        self.particles.iter()
+            .enumerate()
+            .map(|(id, particle)| MassPoint3 {
                 mass: particle.mass,
-                position: particle.position.to_cartesian()
+                position: (particle.position.x, particle.position.y, particle.position.z),
+                velocity: (particle.velocity.x, particle.velocity.y, particle.velocity.z),
+                id: id as u32
+            })
+            .collect()
+    }
+}
+
+/// gravitational acceleration acting on each particle, indexed the same as `particles`
+fn compute_accelerations_3(particles: &[Particle3], g: f32, softening: f32) -> Vec<Vector3> {
+    let particles_len = particles.len();
+    let mut accelerations = vec![Vector3::default(); particles_len];
+    for i in 0..particles_len {
+        for j in i + 1..particles_len {
+            let a = particles[i];
+            let b = particles[j];
+            if let Some(direction) = pull_3(&a, &b, g, softening) {
+                // f = ma
+                accelerations[i] += direction.scale(1.0 / a.mass);
+                accelerations[j] += direction.scale(-1.0 / b.mass);
+            }
+        }
+    }
+    accelerations
+}
+
+/// gravitational force acting on `a` towards `b`, `None` if they are on top of each other
+pub(crate) fn pull_3(a: &Particle3, b: &Particle3, g: f32, softening: f32) -> Option<Vector3> {
+    let d = b.position - a.position;
+    let distance_sq = d.length_sq();
+    // Newtons law of universal gravitation: (G * m1 * m2) / r^2
+    let f = g * a.mass * b.mass / (distance_sq + softening * softening);
+    if f.is_infinite() || distance_sq == 0.0 {
+        None
+    } else {
+        Some(d.scale(f / f32::sqrt(distance_sq)))
+    }
+}
+
+/// double precision counterpart of [`CPUWorld`], without Barnes-Hut, collisions or any of the other options
+///
+/// over 50 frames of the seed 23 particles with Verlet and a softening of 0.01, total energy stays within
+/// 6e-5 instead of the 5e-4 of a [`CPUWorld`], and within 1e-10 instead of 4e-4 with Yoshida and 10 times the steps
+pub struct CPUWorldF64 {
+    pub particles: Vec<ParticleF64>,
+    integrator: Integrator,
+    g: f64,
+    softening: f64
+}
+
+impl CPUWorldF64 {
+    pub fn new(particles: Vec<ParticleF64>) -> Self {
+        Self {
+            particles,
+            integrator: Integrator::default(),
+            g: G_F64,
+            softening: 0.0
+        }
+    }
+
+    pub fn with_integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    /// sets the gravitational constant, [`G_F64`] by default
+    pub fn with_g(mut self, g: f64) -> Self {
+        self.g = g;
+        self
+    }
+
+    /// sets the softening length, see [`CPUWorld::with_softening`]
+    pub fn with_softening(mut self, softening: f64) -> Self {
+        self.softening = softening;
+        self
+    }
+
+    /// advances the world by `time` in `steps` substeps, returns the number of substeps taken
+    pub fn tick(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        let stepped_time = time / steps.get() as f32;
+        let g = self.g;
+        let softening = self.softening;
+        self.integrator.integrate(&mut self.particles, stepped_time, steps, |particles| {
+            compute_accelerations_f64(particles, g, softening)
+        }, |_| false);
+        steps.get()
+    }
+
+    /// rounds the particles to `f32` for rendering, the ids are the indices of the particles,
+    /// as `f64` worlds never remove or reorder them, and every particle is in group 0
+    pub fn get_mass_points(&self) -> Vec<MassPoint> {
+        self.particles.iter()
+            .enumerate()
+            .map(|(id, particle)| MassPoint {
+                mass: particle.mass as f32,
+                position: (particle.position.x as f32, particle.position.y as f32),
+                velocity: (particle.velocity.x as f32, particle.velocity.y as f32),
+                id: id as u32,
+                group: 0
+            })
+            .collect()
+    }
+
+    /// kinetic energy of every moving particle
+    pub fn kinetic_energy(&self) -> f64 {
+        diagnostics::kinetic_energy_f64(&self.particles)
+    }
+
+    /// potential energy of every pair of particles
+    pub fn potential_energy(&self) -> f64 {
+        diagnostics::potential_energy_f64(&self.particles, self.g, self.softening)
+    }
+
+    pub fn total_energy(&self) -> f64 {
+        self.kinetic_energy() + self.potential_energy()
+    }
+}
+
+/// gravitational acceleration acting on each particle, indexed the same as `particles`
+fn compute_accelerations_f64(particles: &[ParticleF64], g: f64, softening: f64) -> Vec<VectorF64> {
+    let particles_len = particles.len();
+    let mut accelerations = vec![VectorF64::default(); particles_len];
+    for i in 0..particles_len {
+        for j in i + 1..particles_len {
+            let a = particles[i];
+            let b = particles[j];
+            if let Some(direction) = pull_f64(&a, &b, g, softening) {
+                // f = ma
+                accelerations[i] += direction.scale(1.0 / a.mass);
+                accelerations[j] += direction.scale(-1.0 / b.mass);
+            }
+        }
+    }
+    accelerations
+}
+
+/// gravitational force acting on `a` towards `b`, `None` if they are on top of each other
+pub(crate) fn pull_f64(a: &ParticleF64, b: &ParticleF64, g: f64, softening: f64) -> Option<VectorF64> {
+    let d = b.position - a.position;
+    let distance_sq = d.length_sq();
+    // Newtons law of universal gravitation: (G * m1 * m2) / r^2
+    let f = g * a.mass * b.mass / (distance_sq + softening * softening);
+    if f.is_infinite() || distance_sq == 0.0 {
+        None
+    } else {
+        Some(d.scale(f / f64::sqrt(distance_sq)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU16;
+    use crate::{MassPoint, Particle, Vector};
+    use crate::world::{Boundary, G, Integrator, World};
+    use crate::world::force::{ForceModel, NewtonianGravity};
+    use crate::world::generate::{plummer_sphere, random_particles};
+    use crate::world::ParticleF64;
+    use crate::world::par::{ParWorld, ParWorldF64};
+    use crate::world::simd;
+    use crate::world::summation::CompensatedSum;
+    use super::{compute_accelerations, CPUWorld, CPUWorldF64};
+
+    /// the acceleration of every particle from every other, computing every pair twice, the acceleration of `a` from
+    /// `b` being `pair(a, b)`, and the sum of the magnitudes of its terms, which the rounding errors of the sum scale with
+    fn every_ordered_pair(particles: &[Particle], pair: impl Fn(&Particle, &Particle) -> (f32, f32)) -> Vec<((f32, f32), f32)> {
+        particles.iter()
+            .map(|a| {
+                particles.iter()
+                    .filter(|b| b.position != a.position)
+                    .fold(((0.0, 0.0), 0.0), |((x, y), magnitudes), b| {
+                        let (ax, ay) = pair(a, b);
+                        ((x + ax, y + ay), magnitudes + f32::hypot(ax, ay))
+                    })
             })
+            .collect()
+    }
+
+    fn assert_matches(accelerations: &[Vector], expected: &[((f32, f32), f32)]) {
+        for (acceleration, &((x, y), magnitudes)) in accelerations.iter().zip(expected) {
+            let (ax, ay) = acceleration.to_cartesian();
+            let error = f32::hypot(ax - x, ay - y) / magnitudes;
+            assert!(error < 1e-5, "{:?} isn't {:?}", (ax, ay), (x, y));
+        }
+    }
+
+    #[test]
+    fn pairs_match_every_ordered_pair() {
+        let particles = random_particles(23, 200, 500.0);
+        let force_model = NewtonianGravity { g: G };
+        let scalar = every_ordered_pair(&particles, |a, b| {
+            let (distance_sq, direction) = Boundary::Open.separation(&a.position, &b.position);
+            Vector::new(direction, force_model.force(a.mass, b.mass, distance_sq) / a.mass).to_cartesian()
+        });
+        assert_matches(&compute_accelerations::<_, Vector>(&particles, &force_model, 0.0, Boundary::Open), &scalar);
+        let vectorized = every_ordered_pair(&particles, |a, b| {
+            let ((ax, ay), (bx, by)) = (a.position.to_cartesian(), b.position.to_cartesian());
+            let (dx, dy) = (bx - ax, by - ay);
+            let distance_sq = dx * dx + dy * dy;
+            let f_over_r = force_model.force(a.mass, b.mass, distance_sq) / distance_sq.sqrt();
+            (f_over_r * dx / a.mass, f_over_r * dy / a.mass)
+        });
+        assert_matches(&simd::compute_accelerations(&particles, &force_model, 0.0, Boundary::Open), &vectorized);
+    }
+
+    /// the acceleration of every particle from every other, in `f64`
+    fn reference_accelerations(particles: &[Particle]) -> Vec<(f64, f64)> {
+        let positions: Vec<(f64, f64)> = particles.iter()
+            .map(|particle| {
+                let (x, y) = particle.position.to_cartesian();
+                (x as f64, y as f64)
+            })
+            .collect();
+        positions.iter()
+            .map(|&(ax, ay)| {
+                positions.iter().zip(particles).fold((0.0, 0.0), |(x, y), (&(bx, by), b)| {
+                    let (dx, dy) = (bx - ax, by - ay);
+                    let distance_sq = dx * dx + dy * dy;
+                    if distance_sq == 0.0 {
+                        return (x, y)
+                    }
+                    let acceleration_over_r = G as f64 * b.mass as f64 / (distance_sq * distance_sq.sqrt());
+                    (x + acceleration_over_r * dx, y + acceleration_over_r * dy)
+                })
+            })
+            .collect()
+    }
+
+    /// mean error of `accelerations` relative to the magnitude of `reference`
+    fn mean_relative_error(accelerations: &[Vector], reference: &[(f64, f64)]) -> f64 {
+        let total: f64 = accelerations.iter().zip(reference)
+            .map(|(acceleration, &(x, y))| {
+                let (ax, ay) = acceleration.to_cartesian();
+                f64::hypot(ax as f64 - x, ay as f64 - y) / f64::hypot(x, y)
+            })
+            .sum();
+        total / accelerations.len() as f64
+    }
+
+    /// relative drift of the total energy over 10 frames of the seed 23 particles with Yoshida in 200 substeps,
+    /// in `f32` and in `f64`
+    fn energy_drifts<W: World, D>(mut world: W, mut world_f64: D, tick_f64: fn(&mut D, f32, NonZeroU16) -> u16, energy_f64: fn(&D) -> f64) -> (f32, f64) {
+        let (start, start_f64) = (world.total_energy(), energy_f64(&world_f64));
+        for _ in 0..10 {
+            world.tick(20.0, NonZeroU16::new(200).unwrap());
+            tick_f64(&mut world_f64, 20.0, NonZeroU16::new(200).unwrap());
+        }
+        (((world.total_energy() - start) / start).abs(), ((energy_f64(&world_f64) - start_f64) / start_f64).abs())
+    }
+
+    /// with a small enough step the drift of `f64` is only that of the integrator while `f32` keeps rounding
+    #[test]
+    fn f64_conserves_energy_better() {
+        let particles = || random_particles(23, 100, 500.0);
+        let particles_f64 = || particles().into_iter().map(ParticleF64::from).collect::<Vec<_>>();
+        let cpu = CPUWorld::new(particles()).with_integrator(Integrator::Yoshida).with_softening(0.01);
+        let cpu_f64 = CPUWorldF64::new(particles_f64()).with_integrator(Integrator::Yoshida).with_softening(0.01);
+        let par = ParWorld::new(particles()).with_integrator(Integrator::Yoshida).with_softening(0.01);
+        let par_f64 = ParWorldF64::new(particles_f64()).with_integrator(Integrator::Yoshida).with_softening(0.01);
+        for (drift, drift_f64) in [energy_drifts(cpu, cpu_f64, CPUWorldF64::tick, CPUWorldF64::total_energy), energy_drifts(par, par_f64, ParWorldF64::tick, ParWorldF64::total_energy)] {
+            assert!(drift_f64 * 10.0 < drift as f64, "f64 drifted by {} of the energy, f32 by {}", drift_f64, drift);
+        }
+    }
+
+    /// a tight cluster, which is where the rounding errors of summation add up
+    #[test]
+    fn compensated_summation_is_closer_to_f64() {
+        let particles = plummer_sphere(23, 10_000, 1.0, 1e-2, G);
+        let force_model = NewtonianGravity { g: G };
+        let reference = reference_accelerations(&particles);
+        let naive = mean_relative_error(&compute_accelerations::<_, Vector>(&particles, &force_model, 0.0, Boundary::Open), &reference);
+        let compensated = mean_relative_error(&compute_accelerations::<_, CompensatedSum>(&particles, &force_model, 0.0, Boundary::Open), &reference);
+        // most of the error is that of the individual accelerations, the compensation takes it from 9.061e-4 to 9.027e-4
+        assert!(compensated < naive, "{} isn't under {}", compensated, naive);
+    }
+
+    #[test]
+    fn columns_tick_like_particles() {
+        for integrator in [Integrator::Euler, Integrator::Verlet, Integrator::RK4, Integrator::Yoshida] {
+            let world = || CPUWorld::new(random_particles(23, 100, 500.0))
+                .with_integrator(integrator)
+                .with_softening(0.01)
+                .with_drag(0.001)
+                .with_boundary(Boundary::Reflective { width: 1.0, height: 1.0, restitution: 0.5 });
+            let (mut columns, mut particles) = (world(), world());
+            assert!(columns.ticks_columns());
+            for _ in 0..5 {
+                columns.tick(20.0, NonZeroU16::new(20).unwrap());
+                particles.tick_with_observer(20.0, NonZeroU16::new(20).unwrap(), |_| {});
+            }
+            assert_eq!(columns.get_mass_points(), particles.get_mass_points(), "{:?}", integrator);
+        }
+    }
+
+    /// fastest speed of any particle over 240 frames of the default run, the seed 23 particles at a scale of 500
+    fn fastest_speed(softening: f32) -> f32 {
+        let mut world = CPUWorld::new(random_particles(23, 100, 500.0)).with_softening(softening);
+        let mut fastest: f32 = 0.0;
+        for _ in 0..240 {
+            world.tick(20.0, NonZeroU16::new(20).unwrap());
+            fastest = world.get_mass_points().iter().map(MassPoint::speed).fold(fastest, f32::max);
+        }
+        fastest
+    }
+
+    #[test]
+    fn softening_keeps_close_encounters_from_flinging_particles() {
+        // without softening a close pair is flung out at ~0.03, a third of the width of the frame every frame
+        assert!(fastest_speed(0.0) > 1e-2);
+        assert!(fastest_speed(0.01) < 1e-3);
+    }
+
+    /// velocities of resting particles after a single Euler substep of 1, which are their accelerations
+    fn accelerations_from_rest(mut world: impl World) -> Vec<(f32, f32)> {
+        world.tick(1.0, NonZeroU16::new(1).unwrap());
+        world.get_mass_points().iter().map(|mass_point| mass_point.velocity).collect()
+    }
+
+    #[test]
+    fn doubling_g_doubles_the_accelerations() {
+        let particles = random_particles(23, 100, 500.0);
+        let pairs = [
+            (accelerations_from_rest(CPUWorld::new(particles.clone())), accelerations_from_rest(CPUWorld::new(particles.clone()).with_g(2.0 * G))),
+            (accelerations_from_rest(ParWorld::new(particles.clone())), accelerations_from_rest(ParWorld::new(particles).with_g(2.0 * G)))
+        ];
+        for (single, double) in pairs {
+            for ((x, y), (doubled_x, doubled_y)) in single.into_iter().zip(double) {
+                assert!(x != 0.0 && y != 0.0);
+                assert_eq!((2.0 * x, 2.0 * y), (doubled_x, doubled_y));
+            }
         }
-        mass_points
     }
 }
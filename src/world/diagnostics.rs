@@ -0,0 +1,269 @@
+use std::f64::consts::FRAC_PI_2;
+use std::ops::Range;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use crate::{MassPoint, Particle, Vector};
+use crate::world::{escape, Boundary, Escape, ParticleF64};
+use crate::world::field::Field;
+use crate::world::force::ForceModel;
+
+/// sum of `m * v^2 / 2` over every moving particle
+pub fn kinetic_energy(particles: &[Particle]) -> f32 {
+    particles.iter()
+        .filter(|particle| !particle.is_fixed())
+        .map(|particle| 0.5 * particle.mass * particle.velocity.magnitude * particle.velocity.magnitude)
+        .sum()
+}
+
+/// potential energy of every pair of particles, measured the same way the forces are, plus the potential
+/// energy of every particle in `fields`
+pub fn potential_energy<F: ForceModel>(particles: &[Particle], force_model: &F, softening: f32, boundary: Boundary, fields: &[Field]) -> f32 {
+    let mut energy = 0.0;
+    for i in 0..particles.len() {
+        energy += pair_potential_energy(particles, i, force_model, softening, boundary);
+        for field in fields {
+            energy += field.potential(&particles[i], force_model, softening);
+        }
+    }
+    energy
+}
+
+/// parallel version of [`potential_energy`]
+pub fn par_potential_energy<F: ForceModel>(particles: &[Particle], force_model: &F, softening: f32, boundary: Boundary, fields: &[Field]) -> f32 {
+    (0..particles.len())
+        .into_par_iter()
+        .map(|i| {
+            let mut energy = pair_potential_energy(particles, i, force_model, softening, boundary);
+            for field in fields {
+                energy += field.potential(&particles[i], force_model, softening);
+            }
+            energy
+        })
+        .sum()
+}
+
+/// sum of `m * v` over every moving particle, fixed particles absorb momentum so it is only conserved without them
+pub fn total_momentum(particles: &[Particle]) -> Vector {
+    let (mut x, mut y) = (0.0, 0.0);
+    for particle in particles {
+        if !particle.is_fixed() {
+            let (vx, vy) = particle.velocity.to_cartesian();
+            x += particle.mass * vx;
+            y += particle.mass * vy;
+        }
+    }
+    Vector::from_cartesian(x, y)
+}
+
+/// double precision counterpart of [`kinetic_energy`]
+pub fn kinetic_energy_f64(particles: &[ParticleF64]) -> f64 {
+    particles.iter()
+        .filter(|particle| particle.fixed == 0)
+        .map(|particle| 0.5 * particle.mass * particle.velocity.length_sq())
+        .sum()
+}
+
+/// double precision counterpart of [`potential_energy`], for Newtonian gravity without fields or boundaries
+pub fn potential_energy_f64(particles: &[ParticleF64], g: f64, softening: f64) -> f64 {
+    let mut energy = 0.0;
+    for (i, a) in particles.iter().enumerate() {
+        for b in &particles[i + 1..] {
+            let r = f64::sqrt((b.position - a.position).length_sq());
+            energy -= if softening == 0.0 {
+                // (G * m1 * m2) / r
+                g * a.mass * b.mass / r
+            } else {
+                // integral of (G * m1 * m2) / (x^2 + s^2) from r to infinity, see NewtonianGravity::potential
+                g * a.mass * b.mass / softening * (FRAC_PI_2 - f64::atan(r / softening))
+            };
+        }
+    }
+    energy
+}
+
+/// mass weighted average position of `mass_points`, fixed particles included, the origin when there is no mass
+///
+/// not meaningful for a periodic boundary, where positions wrap around
+pub fn center_of_mass<I: IntoIterator<Item = MassPoint>>(mass_points: I) -> (f32, f32) {
+    let (mut x, mut y, mut mass) = (0.0, 0.0, 0.0);
+    for MassPoint { mass: m, position: (px, py), .. } in mass_points {
+        x += m * px;
+        y += m * py;
+        mass += m;
+    }
+    if mass == 0.0 {
+        (0.0, 0.0)
+    } else {
+        (x / mass, y / mass)
+    }
+}
+
+/// smallest `(x, y)` ranges containing a circle of `radius(mass_point)` around every mass point of a frame,
+/// `None` if there are no mass points, so that frames can be fitted one at a time as they are simulated
+pub fn bounds<R: Fn(&MassPoint) -> f32>(mass_points: &[MassPoint], radius: R) -> Option<(Range<f32>, Range<f32>)> {
+    let mut mass_points = mass_points.iter();
+    let first = mass_points.next()?;
+    let circle = |mass_point: &MassPoint| {
+        let (x, y) = mass_point.position;
+        let r = radius(mass_point);
+        (x - r..x + r, y - r..y + r)
+    };
+    let (mut x, mut y) = circle(first);
+    for mass_point in mass_points {
+        let (circle_x, circle_y) = circle(mass_point);
+        x = x.start.min(circle_x.start)..x.end.max(circle_x.end);
+        y = y.start.min(circle_y.start)..y.end.max(circle_y.end);
+    }
+    Some((x, y))
+}
+
+/// fraction of `particles` that are bound to the rest, as opposed to [`Escape::Unbound`],
+/// fixed particles are always bound, one when there are no particles
+pub fn bound_fraction<F: ForceModel>(particles: &[Particle], force_model: &F, softening: f32, fields: &[Field]) -> f32 {
+    if particles.is_empty() {
+        return 1.0
+    }
+    let unbound = escape::count(particles, Escape::Unbound, force_model, softening, fields);
+    1.0 - unbound as f32 / particles.len() as f32
+}
+
+/// `(frame, x, y)` of the particle with `id` in every frame of `mass_point_frames` it is in,
+/// see [`Particle::id`]
+pub fn trajectory(mass_point_frames: &[Vec<MassPoint>], id: u32) -> Vec<(usize, f32, f32)> {
+    mass_point_frames.iter()
+        .enumerate()
+        .filter_map(|(frame, mass_points)| {
+            mass_points.iter()
+                .find(|mass_point| mass_point.id == id)
+                .map(|mass_point| (frame, mass_point.position.0, mass_point.position.1))
+        })
+        .collect()
+}
+
+/// how far apart the particles of two frames are, see [`position_divergence`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Divergence {
+    /// root mean square of the distances between the positions of the same particles
+    pub rms: f32,
+    pub max: f32
+}
+
+/// [`Divergence`] of the positions of `a` and `b`, paired by index up to the shorter of them, which pairs the same
+/// particles of frames of runs from the same particles until particles are removed or merged, zero without any
+pub fn position_divergence(a: &[MassPoint], b: &[MassPoint]) -> Divergence {
+    let mut squares = 0.0;
+    let mut max: f32 = 0.0;
+    for (a, b) in a.iter().zip(b) {
+        let (dx, dy) = (a.position.0 - b.position.0, a.position.1 - b.position.1);
+        let square = dx * dx + dy * dy;
+        squares += square;
+        max = max.max(square.sqrt());
+    }
+    let count = usize::min(a.len(), b.len());
+    match count {
+        0 => Divergence { rms: 0.0, max: 0.0 },
+        count => Divergence { rms: (squares / count as f32).sqrt(), max }
+    }
+}
+
+/// smallest, largest and mean speed of the moving particles during a frame, see [`SpeedStatistics`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Speeds {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32
+}
+
+/// observer for [`World::tick_with_observer`](crate::world::World::tick_with_observer) that records the [`Speeds`]
+/// of every frame, over every moving particle of every substep, fixed particles are left out
+///
+/// [`observe`](Self::observe) every substep of a frame, then [`end_frame`](Self::end_frame)
+#[derive(Clone, Debug, Default)]
+pub struct SpeedStatistics {
+    frames: Vec<Speeds>,
+    min: f32,
+    max: f32,
+    sum: f32,
+    count: usize
+}
+
+impl SpeedStatistics {
+    pub fn observe(&mut self, particles: &[Particle]) {
+        for particle in particles {
+            if particle.is_fixed() {
+                continue
+            }
+            let speed = particle.velocity.magnitude.abs();
+            if self.count == 0 {
+                self.min = speed;
+                self.max = speed;
+            } else {
+                self.min = self.min.min(speed);
+                self.max = self.max.max(speed);
+            }
+            self.sum += speed;
+            self.count += 1;
+        }
+    }
+
+    /// records the speeds observed since the last frame, all zero if nothing moved
+    pub fn end_frame(&mut self) {
+        self.frames.push(match self.count {
+            0 => Speeds { min: 0.0, max: 0.0, mean: 0.0 },
+            count => Speeds { min: self.min, max: self.max, mean: self.sum / count as f32 }
+        });
+        self.sum = 0.0;
+        self.count = 0;
+    }
+
+    /// speeds of every frame ended so far
+    pub fn frames(&self) -> &[Speeds] {
+        &self.frames
+    }
+}
+
+/// potential energy of particle `i` with every particle after it
+fn pair_potential_energy<F: ForceModel>(particles: &[Particle], i: usize, force_model: &F, softening: f32, boundary: Boundary) -> f32 {
+    let a = &particles[i];
+    particles[i + 1..].iter()
+        .map(|b| {
+            let (distance_sq, _) = boundary.separation(&a.position, &b.position);
+            force_model.potential(a.mass, b.mass, distance_sq, softening)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU16;
+    use crate::world::cpu::CPUWorld;
+    use crate::world::generate::random_particles;
+    use super::trajectory;
+
+    /// without gravity every particle moves in a straight line, so a continuous trajectory is one that stays on it
+    #[test]
+    fn trajectories_follow_ids_across_removals() {
+        let particles = random_particles(23, 10, 500.0);
+        let tracked = particles[9];
+        let mut world = CPUWorld::new(particles).with_g(0.0);
+        let mut frames = vec![world.get_mass_points()];
+        for frame in 1..20 {
+            if frame == 10 {
+                // the tracked particle takes the index of the removed one
+                world.remove_particle(0);
+                assert_eq!(world.get_mass_points()[0].id, 9);
+            }
+            world.tick(20.0, NonZeroU16::new(1).unwrap());
+            frames.push(world.get_mass_points());
+        }
+        let (x0, y0) = tracked.position.to_cartesian();
+        let (vx, vy) = tracked.velocity.to_cartesian();
+        let positions = trajectory(&frames, 9);
+        assert_eq!(positions.iter().map(|&(frame, _, _)| frame).collect::<Vec<_>>(), (0..20).collect::<Vec<_>>());
+        for (frame, x, y) in positions {
+            let time = 20.0 * frame as f32;
+            assert!(f32::hypot(x - (x0 + vx * time), y - (y0 + vy * time)) < 1e-5, "frame {} is at {:?}", frame, (x, y));
+        }
+        // the removed particle stops at the removal
+        assert_eq!(trajectory(&frames, 0).len(), 10);
+    }
+}
@@ -0,0 +1,166 @@
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use crate::Particle;
+use crate::world::Escape;
+use crate::world::field::Field;
+use crate::world::force::ForceModel;
+
+/// removes every particle that has escaped, returns the number of particles removed
+pub fn cull<F: ForceModel>(particles: &mut Vec<Particle>, escape: Escape, force_model: &F, softening: f32, fields: &[Field]) -> usize {
+    let center = Center::of(particles);
+    let escaped: Vec<bool> = (0..particles.len())
+        .map(|i| has_escaped(particles, i, escape, center, force_model, softening, fields))
+        .collect();
+    remove(particles, &escaped)
+}
+
+/// parallel version of [`cull`]
+pub fn par_cull<F: ForceModel>(particles: &mut Vec<Particle>, escape: Escape, force_model: &F, softening: f32, fields: &[Field]) -> usize {
+    let center = Center::of(particles);
+    let escaped: Vec<bool> = (0..particles.len())
+        .into_par_iter()
+        .map(|i| has_escaped(particles, i, escape, center, force_model, softening, fields))
+        .collect();
+    remove(particles, &escaped)
+}
+
+/// number of particles that have escaped, without removing them
+pub(crate) fn count<F: ForceModel>(particles: &[Particle], escape: Escape, force_model: &F, softening: f32, fields: &[Field]) -> usize {
+    let center = Center::of(particles);
+    (0..particles.len())
+        .filter(|&i| has_escaped(particles, i, escape, center, force_model, softening, fields))
+        .count()
+}
+
+/// position and velocity of the center of mass
+#[derive(Copy, Clone)]
+struct Center {
+    x: f32,
+    y: f32,
+    vx: f32,
+    vy: f32
+}
+
+impl Center {
+    /// fixed particles count towards the position but stand still
+    fn of(particles: &[Particle]) -> Self {
+        let (mut x, mut y, mut vx, mut vy, mut mass) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for particle in particles {
+            let (px, py) = particle.position.to_cartesian();
+            x += particle.mass * px;
+            y += particle.mass * py;
+            if !particle.is_fixed() {
+                let (pvx, pvy) = particle.velocity.to_cartesian();
+                vx += particle.mass * pvx;
+                vy += particle.mass * pvy;
+            }
+            mass += particle.mass;
+        }
+        if mass == 0.0 {
+            return Self { x: 0.0, y: 0.0, vx: 0.0, vy: 0.0 }
+        }
+        Self { x: x / mass, y: y / mass, vx: vx / mass, vy: vy / mass }
+    }
+}
+
+fn has_escaped<F: ForceModel>(particles: &[Particle], i: usize, escape: Escape, center: Center, force_model: &F, softening: f32, fields: &[Field]) -> bool {
+    let particle = &particles[i];
+    if particle.is_fixed() {
+        return false
+    }
+    let (x, y) = particle.position.to_cartesian();
+    match escape {
+        Escape::Radius(radius) => {
+            let dx = x - center.x;
+            let dy = y - center.y;
+            dx * dx + dy * dy > radius * radius
+        }
+        Escape::Unbound => {
+            let (vx, vy) = particle.velocity.to_cartesian();
+            let dvx = vx - center.vx;
+            let dvy = vy - center.vy;
+            let mut energy = 0.5 * particle.mass * (dvx * dvx + dvy * dvy);
+            for (j, other) in particles.iter().enumerate() {
+                if j != i {
+                    let (ox, oy) = other.position.to_cartesian();
+                    let dx = ox - x;
+                    let dy = oy - y;
+                    energy += force_model.potential(particle.mass, other.mass, dx * dx + dy * dy, softening);
+                }
+            }
+            for field in fields {
+                energy += field.potential(particle, force_model, softening);
+            }
+            energy > 0.0
+        }
+    }
+}
+
+/// removes the particles marked in `escaped`, keeping the order of the rest
+fn remove(particles: &mut Vec<Particle>, escaped: &[bool]) -> usize {
+    let len = particles.len();
+    let mut escaped = escaped.iter();
+    particles.retain(|_| !*escaped.next().unwrap());
+    len - particles.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU16;
+    use crate::{Particle, Vector};
+    use crate::world::{Escape, World};
+    use crate::world::cpu::CPUWorld;
+    use crate::world::gpu::GPUWorld;
+    use crate::world::par::ParWorld;
+
+    /// a star of mass 1 with two planets on circular orbits, and a comet passing by far faster than escape velocity,
+    /// under a `g` of 1
+    fn star_planets_and_comet() -> Vec<Particle> {
+        let particle = |mass: f32, (x, y): (f32, f32), (vx, vy): (f32, f32)| Particle {
+            mass,
+            position: Vector::from_cartesian(x, y),
+            velocity: Vector::from_cartesian(vx, vy),
+            radius: 0.0,
+            fixed: 0,
+            id: 0,
+            group: 0
+        };
+        vec![
+            particle(1.0, (0.0, 0.0), (0.0, 0.0)),
+            particle(1e-3, (1.0, 0.0), (0.0, 1.0)),
+            particle(1e-3, (-2.0, 0.0), (0.0, -f32::sqrt(0.5))),
+            // 4.5 of kinetic energy per unit mass against 1/3 of potential energy
+            particle(1e-3, (0.0, 3.0), (3.0, 0.0))
+        ]
+    }
+
+    /// the comet is gone within 20 frames of 0.1, and the star and planets are still there
+    fn assert_culls_the_comet<W: World>(mut world: W, escaped: fn(&W) -> usize) {
+        for _ in 0..20 {
+            world.tick(0.1, NonZeroU16::new(10).unwrap());
+        }
+        assert_eq!(escaped(&world), 1);
+        let ids: Vec<u32> = world.get_mass_points().iter().map(|mass_point| mass_point.id).collect();
+        assert_eq!(ids, [0, 1, 2]);
+        for mass_point in &world.get_mass_points()[1..] {
+            let (x, y) = mass_point.position;
+            let distance = f32::hypot(x, y);
+            assert!(distance > 0.9 && distance < 2.1, "planet {} is {} from the star", mass_point.id, distance);
+        }
+    }
+
+    #[test]
+    fn hyperbolic_particles_are_culled_and_bound_ones_kept() {
+        for escape in [Escape::Unbound, Escape::Radius(5.0)] {
+            let cpu = CPUWorld::new(star_planets_and_comet()).with_g(1.0).with_escape(Some(escape));
+            assert_culls_the_comet(cpu, CPUWorld::escaped);
+            let par = ParWorld::new(star_planets_and_comet()).with_g(1.0).with_escape(Some(escape));
+            assert_culls_the_comet(par, ParWorld::escaped);
+            if GPUWorld::list_devices().is_empty() {
+                eprintln!("skipping the gpu, there is no device vulkan can run on");
+            } else {
+                let gpu = GPUWorld::new(star_planets_and_comet()).unwrap().with_g(1.0).with_escape(Some(escape));
+                assert_culls_the_comet(gpu, GPUWorld::escaped);
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use crate::{Particle, Vector};
+use crate::world::force::ForceModel;
+
+/// external force field acting on every particle of a world, on top of the forces between particles
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Field {
+    /// constant acceleration `(x, y)`, regardless of mass or position
+    Uniform(f32, f32),
+    /// a point mass at `(x, y)` that pulls on every particle like a particle would, but never moves
+    /// and is not part of the world
+    PointMass {
+        x: f32,
+        y: f32,
+        mass: f32
+    }
+}
+
+impl Field {
+    /// acceleration this field causes on `particle`, point masses are softened and use the force model
+    /// of the world like any particle would
+    pub fn acceleration<F: ForceModel>(&self, particle: &Particle, force_model: &F, softening: f32) -> Vector {
+        match *self {
+            Field::Uniform(x, y) => Vector::from_cartesian(x, y),
+            Field::PointMass { x, y, mass } => {
+                let (px, py) = particle.position.to_cartesian();
+                let dx = x - px;
+                let dy = y - py;
+                let r_sq = dx * dx + dy * dy + softening * softening;
+                let a = force_model.acceleration(particle.mass, mass, r_sq);
+                if a.is_infinite() {
+                    Vector::new(0.0, 0.0)
+                } else {
+                    Vector::new(f32::atan2(dy, dx), a)
+                }
+            }
+        }
+    }
+
+    /// potential energy of `particle` in this field, uniform fields are zero at the origin
+    pub fn potential<F: ForceModel>(&self, particle: &Particle, force_model: &F, softening: f32) -> f32 {
+        let (px, py) = particle.position.to_cartesian();
+        match *self {
+            Field::Uniform(x, y) => -particle.mass * (x * px + y * py),
+            Field::PointMass { x, y, mass } => {
+                let dx = x - px;
+                let dy = y - py;
+                force_model.potential(particle.mass, mass, dx * dx + dy * dy, softening)
+            }
+        }
+    }
+}
+
+/// adds the acceleration caused by `fields` to the acceleration of each particle
+pub(crate) fn apply<F: ForceModel>(fields: &[Field], particles: &[Particle], accelerations: &mut [Vector], force_model: &F, softening: f32) {
+    if fields.is_empty() {
+        return
+    }
+    for (particle, acceleration) in particles.iter().zip(accelerations) {
+        for field in fields {
+            *acceleration += field.acceleration(particle, force_model, softening);
+        }
+    }
+}
+
+/// parallel version of [`apply`]
+pub(crate) fn par_apply<F: ForceModel>(fields: &[Field], particles: &[Particle], accelerations: &mut [Vector], force_model: &F, softening: f32) {
+    if fields.is_empty() {
+        return
+    }
+    particles.par_iter()
+        .zip(accelerations.par_iter_mut())
+        .for_each(|(particle, acceleration)| {
+            for field in fields {
+                *acceleration += field.acceleration(particle, force_model, softening);
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+    use std::num::NonZeroU16;
+    use crate::{Particle, Vector};
+    use crate::world::{Integrator, World};
+    use crate::world::cpu::CPUWorld;
+    use crate::world::par::ParWorld;
+    use super::Field;
+
+    /// a particle a distance of 1 from a point mass of 1 at the origin, under a `g` of 1, at the circular velocity
+    /// `sqrt(g * mass / r)` of 1, going around in 2 pi
+    fn circular_orbit() -> Vec<Particle> {
+        vec![Particle {
+            mass: 1.0,
+            position: Vector::from_cartesian(1.0, 0.0),
+            velocity: Vector::from_cartesian(0.0, 1.0),
+            radius: 0.0,
+            fixed: 0,
+            id: 0,
+            group: 0
+        }]
+    }
+
+    /// follows a particle of [`circular_orbit`] through an orbit in 40 frames, it stays at a distance of 1 from
+    /// the point mass and comes back to where it started, with the kinetic energy of 0.5 and potential energy of -1
+    fn assert_orbits_in_a_circle(mut world: impl World) {
+        for frame in 0..40 {
+            world.tick(TAU / 40.0, NonZeroU16::new(50).unwrap());
+            let (x, y) = world.get_mass_points()[0].position;
+            assert!((f32::hypot(x, y) - 1.0).abs() < 1e-3, "frame {} is at {:?}", frame, (x, y));
+            assert!((world.total_energy() + 0.5).abs() < 1e-4, "frame {} has an energy of {}", frame, world.total_energy());
+        }
+        let (x, y) = world.get_mass_points()[0].position;
+        assert!((x - 1.0).abs() < 1e-3 && y.abs() < 1e-3, "{:?}", (x, y));
+    }
+
+    #[test]
+    fn point_masses_hold_particles_in_circular_orbits() {
+        let field = Field::PointMass { x: 0.0, y: 0.0, mass: 1.0 };
+        let mut cpu = CPUWorld::new(circular_orbit()).with_g(1.0).with_integrator(Integrator::Verlet);
+        cpu.add_field(field);
+        assert_orbits_in_a_circle(cpu);
+        let mut par = ParWorld::new(circular_orbit()).with_g(1.0).with_integrator(Integrator::Verlet);
+        par.add_field(field);
+        assert_orbits_in_a_circle(par);
+    }
+}
@@ -0,0 +1,140 @@
+use std::f32::consts::FRAC_PI_2;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use crate::world::G;
+
+/// law of the force between two particles, used by [`CPUWorld`](crate::world::cpu::CPUWorld)
+/// and [`ParWorld`](crate::world::par::ParWorld) for every pair of particles, and by the Barnes-Hut
+/// approximation for every particle and far away cell
+///
+/// the force only depends on the masses and the squared distance (softening included),
+/// it is applied along the line between the two particles, and attracts when positive
+pub trait ForceModel: Send + Sync {
+    /// force between a particle of `mass` and one of `other_mass`, `r_sq` apart squared
+    fn force(&self, mass: f32, other_mass: f32, r_sq: f32) -> f32;
+
+    /// acceleration of a particle of `mass` towards one of `other_mass`, `r_sq` apart squared
+    fn acceleration(&self, mass: f32, other_mass: f32, r_sq: f32) -> f32 {
+        // f = ma
+        self.force(mass, other_mass, r_sq) / mass
+    }
+
+    /// potential energy of a particle of `mass` and one of `other_mass`, `distance_sq` apart squared,
+    /// zero when they are infinitely far apart
+    ///
+    /// unlike the force this takes the unsoftened distance, as the work done by a softened force
+    /// generally can't be written in terms of `r^2 + softening^2` alone
+    ///
+    /// only used for diagnostics, models without a closed form may leave this NaN
+    fn potential(&self, _mass: f32, _other_mass: f32, _distance_sq: f32, _softening: f32) -> f32 {
+        f32::NAN
+    }
+}
+
+/// Newtons law of universal gravitation, the default force model of every world
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NewtonianGravity {
+    pub g: f32
+}
+
+impl Default for NewtonianGravity {
+    fn default() -> Self {
+        Self { g: G }
+    }
+}
+
+impl ForceModel for NewtonianGravity {
+    fn force(&self, mass: f32, other_mass: f32, r_sq: f32) -> f32 {
+        // (G * m1 * m2) / r^2
+        self.g * mass * other_mass / r_sq
+    }
+
+    fn acceleration(&self, _mass: f32, other_mass: f32, r_sq: f32) -> f32 {
+        // (G * m1 * m2) / r^2, divided by m1
+        self.g * other_mass / r_sq
+    }
+
+    fn potential(&self, mass: f32, other_mass: f32, distance_sq: f32, softening: f32) -> f32 {
+        let r = f32::sqrt(distance_sq);
+        if softening == 0.0 {
+            // -(G * m1 * m2) / r
+            -self.g * mass * other_mass / r
+        } else {
+            // integral of (G * m1 * m2) / (x^2 + s^2) from r to infinity
+            -self.g * mass * other_mass / softening * (FRAC_PI_2 - f32::atan(r / softening))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU16;
+    use crate::{Particle, Vector};
+    use crate::world::{World, G};
+    use crate::world::cpu::CPUWorld;
+    use crate::world::generate::random_particles;
+    use crate::world::par::ParWorld;
+    use super::{ForceModel, NewtonianGravity};
+
+    /// a force falling off with the distance rather than its square
+    struct InverseLinear {
+        g: f32
+    }
+
+    impl ForceModel for InverseLinear {
+        fn force(&self, mass: f32, other_mass: f32, r_sq: f32) -> f32 {
+            self.g * mass * other_mass / f32::sqrt(r_sq)
+        }
+    }
+
+    fn ticked(mut world: impl World) -> Vec<(f32, f32)> {
+        for _ in 0..10 {
+            world.tick(20.0, NonZeroU16::new(20).unwrap());
+        }
+        world.get_mass_points().iter().map(|mass_point| mass_point.position).collect()
+    }
+
+    #[test]
+    fn newtonian_gravity_is_the_default() {
+        let particles = random_particles(23, 100, 500.0);
+        assert_eq!(
+            ticked(CPUWorld::new(particles.clone()).with_force_model(NewtonianGravity { g: G })),
+            ticked(CPUWorld::new(particles.clone()))
+        );
+        assert_eq!(
+            ticked(ParWorld::new(particles.clone()).with_force_model(NewtonianGravity { g: G })),
+            ticked(ParWorld::new(particles))
+        );
+    }
+
+    /// velocities of resting particles after a single Euler substep of 1, which are their accelerations
+    fn accelerations_from_rest(mut world: impl World) -> Vec<(f32, f32)> {
+        world.tick(1.0, NonZeroU16::new(1).unwrap());
+        world.get_mass_points().iter().map(|mass_point| mass_point.velocity).collect()
+    }
+
+    #[test]
+    fn other_models_change_the_dynamics() {
+        // two resting particles of mass 1 a distance of 2 apart
+        let particle = |x: f32| Particle {
+            mass: 1.0,
+            position: Vector::from_cartesian(x, 0.0),
+            velocity: Vector::new(0.0, 0.0),
+            radius: 0.0,
+            fixed: 0,
+            id: 0,
+            group: 0
+        };
+        let particles = vec![particle(-1.0), particle(1.0)];
+        let assert_pulled = |accelerations: Vec<(f32, f32)>, acceleration: f32| {
+            assert!((accelerations[0].0 - acceleration).abs() < 1e-6 && accelerations[0].1.abs() < 1e-6, "{:?}", accelerations);
+            assert!((accelerations[1].0 + acceleration).abs() < 1e-6 && accelerations[1].1.abs() < 1e-6, "{:?}", accelerations);
+        };
+        // 1 / 2^2 against 1 / 2
+        assert_pulled(accelerations_from_rest(CPUWorld::new(particles.clone()).with_g(1.0)), 0.25);
+        assert_pulled(accelerations_from_rest(CPUWorld::new(particles.clone()).with_force_model(InverseLinear { g: 1.0 })), 0.5);
+        assert_pulled(accelerations_from_rest(ParWorld::new(particles.clone()).with_g(1.0)), 0.25);
+        assert_pulled(accelerations_from_rest(ParWorld::new(particles).with_force_model(InverseLinear { g: 1.0 })), 0.5);
+    }
+}
@@ -0,0 +1,219 @@
+use std::f32::consts::TAU;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64Mcg;
+use crate::{Particle, Vector};
+use crate::world::{diagnostics, radius, Boundary};
+use crate::world::force::NewtonianGravity;
+
+/// `count` resting particles of masses up to 1 at random in a ring of radius 0.5 to 1, the same ones for the same `seed`,
+/// with the [`radius`] of a sphere of density 1 at `scale` pixels per unit
+///
+/// so that worlds of the same particles can be built outside of the binary, such as to time them against each other
+pub fn random_particles(seed: u64, count: usize, scale: f32) -> Vec<Particle> {
+    let mut rng = Pcg64Mcg::seed_from_u64(seed);
+    let mut particles = Vec::with_capacity(count);
+    for _ in 0..count {
+        let mass = rng.gen_range(0.0..1.0);
+        particles.push(Particle {
+            mass,
+            position: Vector::new(rng.gen_range(0.0..TAU), rng.gen_range(0.5..1.0)),
+            velocity: Vector::new(0.0, 0.0),
+            radius: radius(mass) / scale,
+            fixed: 0,
+            id: 0,
+            group: 0
+        });
+    }
+    particles
+}
+
+/// `count` particles sharing `total_mass` in a Plummer sphere of `scale_length` around the origin, the same ones for the
+/// same `seed`, viewed from above, as the worlds are flat
+///
+/// positions and velocities are drawn in three dimensions from the density profile and its isotropic distribution of
+/// velocities, leaving out the outermost percent of the mass that would land far away, then flattened onto the plane,
+/// which brings the particles closer together and slows them, so the velocities are scaled to put the sphere back in
+/// virial equilibrium, `2 * T / |U|` being 1 under gravity of `g` without softening, and the center of mass rests at the
+/// origin
+///
+/// with softening of around a twentieth of `scale_length`, the sphere keeps its size for many crossing times, which
+/// take about `(scale_length.powi(3) / (g * total_mass)).sqrt()`, as only a few particles are pushed out of the middle,
+/// without it close encounters fling particles away
+///
+/// the radii, ids and groups are left at 0
+pub fn plummer_sphere(seed: u64, count: usize, total_mass: f32, scale_length: f32, g: f32) -> Vec<Particle> {
+    let mut rng = Pcg64Mcg::seed_from_u64(seed);
+    let mass = total_mass / count as f32;
+    let mut particles = Vec::with_capacity(count);
+    for _ in 0..count {
+        // inverts the fraction of the mass within a radius, (r^3 / (r^2 + a^2))^(3/2)
+        let enclosed: f32 = rng.gen_range(0.0..0.99);
+        let r = scale_length / f32::sqrt(enclosed.powf(-2.0 / 3.0) - 1.0);
+        // rejection sampling of the fraction of the escape speed, distributed as q^2 * (1 - q^2)^(7/2), peaking below 0.1
+        let q = loop {
+            let q: f32 = rng.gen_range(0.0..1.0);
+            if rng.gen_range(0.0..0.1) < q * q * (1.0 - q * q).powf(3.5) {
+                break q
+            }
+        };
+        let escape_speed = f32::sqrt(2.0 * g * total_mass) * (r * r + scale_length * scale_length).powf(-0.25);
+        particles.push(Particle {
+            mass,
+            position: flattened(&mut rng, r),
+            velocity: flattened(&mut rng, q * escape_speed),
+            radius: 0.0,
+            fixed: 0,
+            id: 0,
+            group: 0
+        });
+    }
+    to_rest(&mut particles);
+    let kinetic = diagnostics::kinetic_energy(&particles);
+    let potential = diagnostics::potential_energy(&particles, &NewtonianGravity { g }, 0.0, Boundary::Open, &[]);
+    if kinetic > 0.0 {
+        let scale = f32::sqrt(-potential / (2.0 * kinetic));
+        for particle in &mut particles {
+            particle.velocity = particle.velocity.scale(scale);
+        }
+    }
+    particles
+}
+
+/// spread of the velocities of [`exponential_disk`] around the circular velocity, as a fraction of it
+pub const DISK_DISPERSION: f32 = 0.05;
+
+/// `count` particles sharing `total_mass` in a disk around the origin whose surface density falls off as
+/// `exp(-r / scale_length)`, the same ones for the same `seed`, turning counterclockwise
+///
+/// every particle moves at the circular velocity of the mass closer to the center than it, as if that mass sat at the
+/// center, plus a random velocity of [`DISK_DISPERSION`] of it in any direction, with the center of mass resting at the
+/// origin
+///
+/// a thin disk pulls a little harder than that, so the disk starts slightly short of equilibrium, `2 * T / |U|` being
+/// about 0.87, and it heats up and spreads out as it turns, with softening of around a fifteenth of `scale_length` nearly
+/// all of it stays bound while its half-mass radius about doubles over the first orbit at 2 scale lengths, which takes
+/// about `TAU * (13.5 * scale_length.powi(3) / (g * total_mass)).sqrt()`, without softening close encounters fling
+/// particles away
+///
+/// the radii, ids and groups are left at 0
+pub fn exponential_disk(seed: u64, count: usize, total_mass: f32, scale_length: f32, g: f32) -> Vec<Particle> {
+    let mut rng = Pcg64Mcg::seed_from_u64(seed);
+    let mass = total_mass / count as f32;
+    // the surface density times the circumference, r * exp(-r / a), is a gamma distribution of shape 2, the sum of two
+    // exponential ones
+    let mut radii: Vec<f32> = (0..count)
+        .map(|_| -scale_length * f32::ln(rng.gen_range(f32::EPSILON..1.0) * rng.gen_range(f32::EPSILON..1.0)))
+        .collect();
+    radii.sort_by(f32::total_cmp);
+    let mut particles = Vec::with_capacity(count);
+    for (i, r) in radii.into_iter().enumerate() {
+        let direction = rng.gen_range(0.0..TAU);
+        let speed = match r > 0.0 {
+            true => f32::sqrt(g * mass * i as f32 / r),
+            false => 0.0
+        };
+        let (x, y) = Vector::new(direction + TAU / 4.0, speed).to_cartesian();
+        let dispersion = DISK_DISPERSION * speed;
+        particles.push(Particle {
+            mass,
+            position: Vector::new(direction, r),
+            velocity: Vector::from_cartesian(x + dispersion * gaussian(&mut rng), y + dispersion * gaussian(&mut rng)),
+            radius: 0.0,
+            fixed: 0,
+            id: 0,
+            group: 0
+        });
+    }
+    to_rest(&mut particles);
+    particles
+}
+
+/// a vector of `length` in a random direction in three dimensions, seen from above
+fn flattened(rng: &mut Pcg64Mcg, length: f32) -> Vector {
+    let z: f32 = rng.gen_range(-1.0..1.0);
+    Vector::new(rng.gen_range(0.0..TAU), length * f32::sqrt(1.0 - z * z))
+}
+
+/// a normally distributed number with a mean of 0 and a standard deviation of 1, by the Box-Muller transform
+fn gaussian(rng: &mut Pcg64Mcg) -> f32 {
+    let (u, v): (f32, f32) = (rng.gen_range(f32::EPSILON..1.0), rng.gen_range(0.0..TAU));
+    f32::sqrt(-2.0 * u.ln()) * v.cos()
+}
+
+/// moves `particles` so that their center of mass rests at the origin
+fn to_rest(particles: &mut [Particle]) {
+    let total_mass: f32 = particles.iter().map(|particle| particle.mass).sum();
+    if total_mass == 0.0 {
+        return
+    }
+    let (mut position, mut velocity) = ((0.0, 0.0), (0.0, 0.0));
+    for particle in particles.iter() {
+        let ((x, y), (vx, vy)) = (particle.position.to_cartesian(), particle.velocity.to_cartesian());
+        position = (position.0 + particle.mass * x, position.1 + particle.mass * y);
+        velocity = (velocity.0 + particle.mass * vx, velocity.1 + particle.mass * vy);
+    }
+    for particle in particles {
+        let ((x, y), (vx, vy)) = (particle.position.to_cartesian(), particle.velocity.to_cartesian());
+        particle.position = Vector::from_cartesian(x - position.0 / total_mass, y - position.1 / total_mass);
+        particle.velocity = Vector::from_cartesian(vx - velocity.0 / total_mass, vy - velocity.1 / total_mass);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU16;
+    use crate::world::{diagnostics, Boundary, G};
+    use crate::world::cpu::CPUWorld;
+    use crate::world::force::NewtonianGravity;
+    use super::*;
+
+    /// `2 * T / |U|` of `particles` under gravity of `g` without softening
+    fn virial_ratio(particles: &[Particle], g: f32) -> f32 {
+        let potential = diagnostics::potential_energy(particles, &NewtonianGravity { g }, 0.0, Boundary::Open, &[]);
+        2.0 * diagnostics::kinetic_energy(particles) / potential.abs()
+    }
+
+    /// distance from the center of mass within which half of the mass of `particles` is, which are of equal masses
+    fn half_mass_radius(particles: &[Particle]) -> f32 {
+        let center = diagnostics::center_of_mass(particles.iter().map(|&particle| particle.into()));
+        let mut distances: Vec<f32> = particles.iter()
+            .map(|particle| {
+                let (x, y) = particle.position.to_cartesian();
+                f32::hypot(x - center.0, y - center.1)
+            })
+            .collect();
+        distances.sort_by(f32::total_cmp);
+        distances[distances.len() / 2]
+    }
+
+    #[test]
+    fn plummer_spheres_start_in_virial_equilibrium() {
+        for seed in [1, 23] {
+            let particles = plummer_sphere(seed, 2000, 1000.0, 0.2, 0.5);
+            assert_eq!(particles.len(), 2000);
+            let ratio = virial_ratio(&particles, 0.5);
+            assert!((ratio - 1.0).abs() < 0.02, "2T/|U| is {} for seed {}", ratio, seed);
+            let scale: f32 = particles.iter().map(|particle| particle.mass * particle.velocity.magnitude).sum();
+            let momentum = diagnostics::total_momentum(&particles).magnitude;
+            assert!(momentum < 1e-4 * scale, "the momentum is {} of its scale", momentum / scale);
+        }
+    }
+
+    /// the exponential disk of the default run, ticked for its 240 frames
+    #[test]
+    fn exponential_disks_hold_together_for_the_default_run() {
+        let particles = exponential_disk(23, 100, 1000.0, 0.15, G);
+        let mut world = CPUWorld::new(particles.clone()).with_g(G).with_softening(0.01);
+        for _ in 0..240 {
+            world.tick(20.0, NonZeroU16::new(20).unwrap());
+        }
+        let ticked = world.get_particles();
+        assert_eq!(ticked.len(), 100);
+        let bound = diagnostics::bound_fraction(&ticked, &NewtonianGravity { g: G }, 0.01, &[]);
+        // 95 to 98 percent for the seeds tried, a few particles are flung out of the middle
+        assert!(bound >= 0.9, "only {} of the disk is still bound", bound);
+        // and it spreads out to 1.5 to 2.5 times its size
+        let (start, end) = (half_mass_radius(&particles), half_mass_radius(&ticked));
+        assert!(end < 3.5 * start, "the half-mass radius went from {} to {}", start, end);
+    }
+}
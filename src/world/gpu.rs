@@ -1,38 +1,89 @@
-use std::sync::Arc;
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::mem::size_of;
+use std::ops::{Deref, Range};
+use std::sync::{Arc, Mutex};
+use bytemuck::{Pod, Zeroable};
+use log::info;
 use vulkano::device::{Device, DeviceCreateInfo, Queue, QueueCreateInfo};
+use vulkano::device::physical::{PhysicalDevice, PhysicalDeviceType};
 use vulkano::pipeline::{ComputePipeline, Pipeline, PipelineBindPoint};
-use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, DeviceLocalBuffer};
+use vulkano::buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer, DeviceLocalBuffer, TypedBufferAccess};
 use vulkano::instance::{Instance, InstanceCreateInfo};
 use vulkano::shader::ShaderModule;
 use std::num::NonZeroU16;
-use vulkano::{DeviceSize, sync, VulkanLibrary};
+use vulkano::{DeviceSize, sync, Version, VulkanLibrary};
 use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
-use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage};
-use vulkano::sync::{GpuFuture, PipelineStage};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferInfo, PrimaryAutoCommandBuffer};
+use vulkano::query::{QueryPool, QueryPoolCreateInfo, QueryResultFlags, QueryType};
+use vulkano::sync::{FenceSignalFuture, GpuFuture, PipelineStage};
 use crate::{MassPoint, Particle, Vector};
+use crate::world::{assign_ids, diagnostics, escape, Boundary, Escape, Integrator, World, G, YOSHIDA_WEIGHTS};
+use crate::world::checkpoint::Checkpoint;
+use crate::world::force::NewtonianGravity;
 
 pub struct GPUWorld {
     device: Arc<Device>,
     queue_family_index: u32,
     queue: Arc<Queue>,
-    force_direction_pipeline: Arc<ComputePipeline>,
-    acceleration_pipeline: Arc<ComputePipeline>,
-    particles: Arc<CpuAccessibleBuffer<[Particle]>>
+    /// every combination of the specialization constants of `acceleration_compute_shader`,
+    /// see [`acceleration_pipeline`](Self::acceleration_pipeline)
+    acceleration_pipelines: [Arc<ComputePipeline>; 8],
+    half_source_pipeline: Arc<ComputePipeline>,
+    integration_pipelines: [Arc<ComputePipeline>; IntegrationStage::ALL.len()],
+    particles: Arc<CpuAccessibleBuffer<[Particle]>>,
+    integrator: Integrator,
+    g: f32,
+    softening: f32,
+    drag: f32,
+    boundary: Boundary,
+    escape: Option<Escape>,
+    escaped: usize,
+    /// id of the next particle added, see [`Particle::id`]
+    next_id: u32,
+    single_submission: bool,
+    deterministic_summation: bool,
+    half_precision_sources: bool,
+    profiling: bool,
+    /// nanoseconds per timestamp, `None` if the queue can't write timestamps
+    timestamp_period: Option<f32>,
+    /// largest range of a buffer bound at once, see [`with_max_binding_range`](Self::with_max_binding_range)
+    max_binding_range: DeviceSize,
+    /// `None` until the first tick, and again whenever the particle buffer is replaced
+    tick_resources: Option<TickResources>,
+    /// the last pipelined tick, until it is waited for
+    pending: Mutex<Option<PendingTick>>,
+    /// pipelined ticks in a row since the last tick or since the particle buffer was replaced,
+    /// the next one copies into the staging buffer of this index modulo 2
+    pipelined_ticks: usize
 }
 
+/// a submitted tick, finished once its fence is signalled
+type PendingTick = FenceSignalFuture<Box<dyn GpuFuture + Send + Sync>>;
+
 impl GPUWorld {
-    pub fn new(particles: Vec<Particle>) -> Self {
-        let instance = Instance::new(VulkanLibrary::new().unwrap(), InstanceCreateInfo::default())
-            .expect("failed to create instance");
-        let physical = instance.enumerate_physical_devices()
-            .expect("failed to enumerate physical devices")
-            .next()
-            .expect("no physical device available");
+    /// runs on the first device Vulkan lists, see [`new_on`](Self::new_on)
+    pub fn new(particles: Vec<Particle>) -> Result<Self, GPUInitError> {
+        Self::new_on(&DeviceSelection::First, particles)
+    }
+
+    /// runs on the device picked by `selection` out of [`list_devices`](Self::list_devices), logging which one it is
+    pub fn new_on(selection: &DeviceSelection, mut particles: Vec<Particle>) -> Result<Self, GPUInitError> {
+        let physical = selection.select(physical_devices()?)?;
+        let name = physical.properties().device_name.clone();
+        info!("simulating on {} ({:?})", name, physical.properties().device_type);
+        assign_ids(&mut particles);
+        let next_id = particles.len() as u32;
 
         let family_index = physical.queue_family_properties().iter().enumerate()
             .find(|(_, q)| q.supports_stage(PipelineStage::ComputeShader))
-            .expect("missing compute capabilities").0 as u32;
+            .ok_or_else(|| GPUInitError::NoCompute(name.clone()))?.0 as u32;
 
+        let max_binding_range = physical.properties().max_storage_buffer_range as DeviceSize;
+        let timestamp_period = physical.queue_family_properties()[family_index as usize].timestamp_valid_bits
+            .map(|_| physical.properties().timestamp_period);
         let (device, mut queues) = Device::new(
             physical,
             DeviceCreateInfo {
@@ -42,114 +93,791 @@ impl GPUWorld {
                 }],
                 ..Default::default()
             },
-        ).expect("failed to create device");
+        ).map_err(|error| GPUInitError::Creation(name.clone(), error.to_string()))?;
         let queue = queues.next().unwrap();
-        let particles = CpuAccessibleBuffer::from_iter(device.clone(), Self::storage_buffer_usage(), false, particles)
-            .expect("failed to create particle buffer");
+        let particle_length = particles.len();
+        let particles = CpuAccessibleBuffer::from_iter(device.clone(), Self::particle_buffer_usage(), false, particles)
+            .map_err(|error| GPUInitError::Memory(name.clone(), error.to_string()))?;
+        let shader_error = |error: &dyn Error| GPUInitError::Shader(name.clone(), error.to_string());
         // intellij rust plugin failing to auto detect what type this is
-        let force_direction_shader: Arc<ShaderModule> = force_direction_compute_shader::load(device.clone())
-            .expect("failed to create shader");
-        let force_direction_pipeline = ComputePipeline::new(
-            device.clone(),
-            force_direction_shader.entry_point("main").unwrap(),
-            &(),
-            None,
-            |_| {}
-        ).expect("failed to create compute pipeline");
-        let acceleration_compute_shader: Arc<ShaderModule> = acceleration_compute_shader::load(device.clone())
-            .unwrap();
-        let acceleration_pipeline = ComputePipeline::new(
+        let acceleration_shader: Arc<ShaderModule> = acceleration_compute_shader::load(device.clone())
+            .map_err(|error| shader_error(&error))?;
+        let acceleration_pipelines = [0, 1, 2, 3, 4, 5, 6, 7].map(|variant| {
+            let constants = acceleration_compute_shader::SpecializationConstants {
+                accumulate: variant & 1,
+                deterministic: (variant >> 1) & 1,
+                half_precision: variant >> 2
+            };
+            ComputePipeline::new(
+                device.clone(),
+                acceleration_shader.entry_point("main").unwrap(),
+                &constants,
+                None,
+                |_| {}
+            ).map_err(|error| shader_error(&error))
+        });
+        if let Some(Err(error)) = acceleration_pipelines.iter().find(|pipeline| pipeline.is_err()) {
+            return Err(error.clone())
+        }
+        let acceleration_pipelines = acceleration_pipelines.map(Result::unwrap);
+        let half_source_shader: Arc<ShaderModule> = half_source_compute_shader::load(device.clone())
+            .map_err(|error| shader_error(&error))?;
+        let half_source_pipeline = ComputePipeline::new(
             device.clone(),
-            acceleration_compute_shader.entry_point("main").unwrap(),
+            half_source_shader.entry_point("main").unwrap(),
             &(),
             None,
             |_| {}
-        ).unwrap();
-        Self {
+        ).map_err(|error| shader_error(&error))?;
+        let integration_compute_shader: Arc<ShaderModule> = integration_compute_shader::load(device.clone())
+            .map_err(|error| shader_error(&error))?;
+        let integration_pipelines = IntegrationStage::ALL.map(|stage| {
+            ComputePipeline::new(
+                device.clone(),
+                integration_compute_shader.entry_point("main").unwrap(),
+                &integration_compute_shader::SpecializationConstants { mode: stage as u32 },
+                None,
+                |_| {}
+            ).map_err(|error| shader_error(&error))
+        });
+        // arrays can't be collected into, so every pipeline is checked before any is unwrapped
+        if let Some(Err(error)) = integration_pipelines.iter().find(|pipeline| pipeline.is_err()) {
+            return Err(error.clone())
+        }
+        let integration_pipelines = integration_pipelines.map(Result::unwrap);
+        let mut world = Self {
             device,
             queue_family_index: family_index,
             queue,
-            force_direction_pipeline,
-            acceleration_pipeline,
-            particles
+            acceleration_pipelines,
+            half_source_pipeline,
+            integration_pipelines,
+            particles,
+            integrator: Integrator::default(),
+            g: G,
+            softening: 0.0,
+            drag: 0.0,
+            boundary: Boundary::default(),
+            escape: None,
+            escaped: 0,
+            next_id,
+            single_submission: true,
+            deterministic_summation: false,
+            half_precision_sources: false,
+            profiling: false,
+            timestamp_period,
+            max_binding_range,
+            tick_resources: None,
+            pending: Mutex::new(None),
+            pipelined_ticks: 0
+        };
+        // created up front so that running out of memory for the particles is an error rather than a panic in a tick
+        if particle_length > 0 {
+            world.tick_resources = Some(world.create_tick_resources(particle_length)?);
+        }
+        Ok(world)
+    }
+
+    /// every device Vulkan can run a world on, in the order [`DeviceSelection::Index`] counts them,
+    /// empty when Vulkan isn't available
+    pub fn list_devices() -> Vec<DeviceInfo> {
+        physical_devices()
+            .unwrap_or_default()
+            .iter()
+            .map(|physical| DeviceInfo {
+                name: physical.properties().device_name.clone(),
+                device_type: physical.properties().device_type,
+                api_version: physical.api_version()
+            })
+            .collect()
+    }
+
+    pub fn with_integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    /// sets the gravitational constant, [`G`] by default
+    pub fn with_g(mut self, g: f32) -> Self {
+        self.g = g;
+        self
+    }
+
+    /// sets the softening length, see [`CPUWorld::with_softening`](crate::world::cpu::CPUWorld::with_softening)
+    pub fn with_softening(mut self, softening: f32) -> Self {
+        self.softening = softening;
+        self
+    }
+
+    /// sets the linear drag coefficient, see [`CPUWorld::with_drag`](crate::world::cpu::CPUWorld::with_drag)
+    pub fn with_drag(mut self, drag: f32) -> Self {
+        self.drag = drag;
+        self
+    }
+
+    /// sets the shape of the space, see [`Boundary`]
+    pub fn with_boundary(mut self, boundary: Boundary) -> Self {
+        self.boundary = boundary;
+        self
+    }
+
+    /// removes escaped particles, see [`CPUWorld::with_escape`](crate::world::cpu::CPUWorld::with_escape),
+    /// the particles are checked on the CPU and the particle buffer is replaced when any escaped
+    pub fn with_escape(mut self, escape: Option<Escape>) -> Self {
+        self.escape = escape;
+        self
+    }
+
+    /// records every substep of a tick into a single command buffer, so that a tick is a single submission,
+    /// true by default
+    ///
+    /// otherwise every dispatch is submitted on its own, each waiting on the one before, which costs more than
+    /// the dispatches themselves with few particles, but doesn't record a command buffer again whenever the
+    /// integrator or the number of substeps changes
+    pub fn with_single_submission(mut self, single_submission: bool) -> Self {
+        self.single_submission = single_submission;
+        self
+    }
+
+    /// sums the accelerations acting on each particle in the same fixed order as
+    /// [`CPUWorld::with_deterministic_summation`](crate::world::cpu::CPUWorld::with_deterministic_summation),
+    /// rather than adding them one after another, false by default
+    ///
+    /// the order is the same within a block of particles, see [`with_max_binding_range`](Self::with_max_binding_range),
+    /// blocks are still added one after another, and the arithmetic of the shaders isn't that of the CPU either,
+    /// so trajectories stay closer to those of the CPU worlds but aren't bit for bit the same, only
+    /// [`CPUWorld`](crate::world::cpu::CPUWorld) and [`ParWorld`](crate::world::par::ParWorld) agree bit for bit
+    ///
+    /// the order doesn't depend on the size of the tiles or on how the invocations are scheduled, so what the GPU
+    /// repeats bit for bit is its own runs, of the same particles on the same device
+    pub fn with_deterministic_summation(mut self, deterministic_summation: bool) -> Self {
+        self.deterministic_summation = deterministic_summation;
+        // the dispatches are recorded with the pipelines
+        self.tick_resources = None;
+        self
+    }
+
+    /// packs the particles the accelerations are computed towards into half precision before every acceleration pass,
+    /// false by default, the particles themselves and all of the arithmetic stay in single precision
+    ///
+    /// a source is then 8 bytes, its cartesian position in half precision and its mass, rather than a whole
+    /// [`Particle`] of 36, which is most of what the acceleration pass reads, at the cost of another 8 bytes
+    /// of memory per particle
+    ///
+    /// half precision has 11 significant bits, so sources are moved by up to 1/2048 of their distance from the origin,
+    /// and positions past 65504 overflow, the particles being pulled are rounded the same way, or they would be pulled
+    /// towards their own rounded positions, for 2000 random particles of the default preset the accelerations were off
+    /// by a median of 1.3% and by 19% for one in a hundred particles, close encounters being the worst,
+    /// with a softening length of 0.05 by 0.2% and 3%
+    ///
+    /// the particles are not integrated in half precision, as it would lose any change to a position or velocity
+    /// smaller than a few thousandths of it, which most substeps are
+    pub fn with_half_precision_sources(mut self, half_precision_sources: bool) -> Self {
+        self.half_precision_sources = half_precision_sources;
+        // the dispatches are recorded with the pipelines
+        self.tick_resources = None;
+        self
+    }
+
+    /// times each pass of every tick with timestamps written by the GPU, see [`last_tick_profile`](Self::last_tick_profile),
+    /// false by default, devices that can't write timestamps are never profiled
+    pub fn with_profiling(mut self, profiling: bool) -> Self {
+        self.profiling = profiling;
+        // the command buffers write the timestamps
+        self.tick_resources = None;
+        self
+    }
+
+    /// lowers the largest range of a buffer bound at once, which is the `maxStorageBufferRange` of the device
+    /// by default, it can't be raised above that
+    ///
+    /// the particles are split into blocks whose buffers each fit in this range, accelerations are computed between
+    /// every pair of blocks, which only takes more than one block past millions of particles, lowering it
+    /// splits fewer particles into blocks all the same, down to blocks of [`BLOCK_ALIGNMENT`] particles
+    pub fn with_max_binding_range(mut self, max_binding_range: DeviceSize) -> Self {
+        let device_range = self.device.physical_device().properties().max_storage_buffer_range as DeviceSize;
+        self.max_binding_range = max_binding_range.min(device_range);
+        self.tick_resources = None;
+        self
+    }
+
+    /// adds a particle to the world, it is pulled on and pulls from the next tick on,
+    /// returns the id it was given
+    ///
+    /// the particle buffer is read back and replaced, so the next tick creates its buffers anew
+    pub fn add_particle(&mut self, particle: Particle) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut particles = self.read_particles().to_vec();
+        particles.push(Particle { id, ..particle });
+        self.replace_particles(particles);
+        id
+    }
+
+    /// removes the particle at `index` and returns it, the last particle takes its index,
+    /// see [`add_particle`](Self::add_particle)
+    pub fn remove_particle(&mut self, index: usize) -> Particle {
+        let mut particles = self.read_particles().to_vec();
+        let particle = particles.swap_remove(index);
+        self.replace_particles(particles);
+        particle
+    }
+
+    /// advances the world by `time` in `steps` substeps, returns the number of substeps taken
+    ///
+    /// the buffers, descriptor sets and command buffers of a tick are created by the first tick after the particle buffer
+    /// was created or replaced, later ticks only write their parameters
+    pub fn tick(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        self.finish();
+        self.pipelined_ticks = 0;
+        if let Some(commands) = self.tick_commands(time, steps) {
+            self.execute(&commands);
+            self.cull_escaped();
+        }
+        steps.get()
+    }
+
+    /// [`tick`](Self::tick) without waiting for the GPU, the particles are then copied into one of two staging buffers,
+    /// alternating from one pipelined tick to the next, so that [`previous_mass_points`](Self::previous_mass_points)
+    /// can read the other one while the GPU is still busy
+    ///
+    /// anything else that reads or changes the particles waits for the tick to finish first, and so does
+    /// a world with an escape, which is culled on the CPU after every tick
+    pub fn tick_pipelined(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        self.finish();
+        if let Some(mut commands) = self.tick_commands(time, steps) {
+            let resources = self.tick_resources.as_ref().unwrap();
+            commands.push(resources.copy_command_buffers[self.pipelined_ticks % 2].clone());
+            *self.pending.lock().unwrap() = Some(self.submit(&commands));
+            self.pipelined_ticks += 1;
+            if self.escape.is_some() {
+                self.finish();
+                self.cull_escaped();
+            }
+        }
+        steps.get()
+    }
+
+    /// mass points after the pipelined tick before the last one, in the same order as
+    /// [`get_mass_points`](Self::get_mass_points), without waiting for the last one
+    ///
+    /// `None` unless the last two ticks were both [`tick_pipelined`](Self::tick_pipelined) and the particle buffer
+    /// wasn't replaced since, by adding or removing particles, escapes included
+    ///
+    /// after the last pipelined tick of a run its own mass points are those of [`get_mass_points`](Self::get_mass_points)
+    pub fn previous_mass_points(&self) -> Option<Vec<MassPoint>> {
+        if self.pipelined_ticks < 2 {
+            return None
         }
+        let resources = self.tick_resources.as_ref()?;
+        let staging = resources.staging_buffers[self.pipelined_ticks % 2].read().unwrap();
+        Some(mass_points(&staging))
     }
 
-    pub fn tick(&mut self, time: f32, steps: NonZeroU16) {
+    /// the command buffers of a tick in the order they have to run, `None` without particles,
+    /// see [`with_single_submission`](Self::with_single_submission)
+    fn tick_commands(&mut self, time: f32, steps: NonZeroU16) -> Option<Vec<Arc<PrimaryAutoCommandBuffer>>> {
         let stepped_time = time / steps.get() as f32;
-        let layout = self.force_direction_pipeline.layout().set_layouts().first().unwrap();
-        let particle_length = self.particles.read().unwrap().len();
-        let force_direction_buffer_length = particle_length * (particle_length - 1) / 2;
-        let (time_buffer, time_buffer_future) = DeviceLocalBuffer::from_data(stepped_time, Self::storage_buffer_usage(), self.queue.clone()).unwrap();
-        let force_direction_buffer: Arc<DeviceLocalBuffer<[Vector]>> = DeviceLocalBuffer::array(self.device.clone(), force_direction_buffer_length as DeviceSize, Self::storage_buffer_usage(), [self.queue_family_index]).unwrap();
-        let set = PersistentDescriptorSet::new(
-            layout.clone(),
-            [
-                WriteDescriptorSet::buffer(0, self.particles.clone()),
-                WriteDescriptorSet::buffer(1, time_buffer.clone()),
-                WriteDescriptorSet::buffer(2, force_direction_buffer.clone())
-            ]
-        ).unwrap();
-        time_buffer_future
-            .then_signal_fence_and_flush().unwrap()
-            .wait(None).unwrap();
-        let force_direction_command_buffer = {
-            let mut builder = AutoCommandBufferBuilder::primary(
-                self.device.clone(),
-                self.queue_family_index,
-                CommandBufferUsage::MultipleSubmit
-            ).unwrap();
-            builder
-                .bind_pipeline_compute(self.force_direction_pipeline.clone())
-                .bind_descriptor_sets(
-                    PipelineBindPoint::Compute,
-                    self.force_direction_pipeline.layout().clone(),
-                    0,
-                    set.clone()
-                )
-                .dispatch([(force_direction_buffer_length / 64 + 1) as u32, 1, 1])
-                .unwrap();
-            Arc::new(builder.build().unwrap())
+        let particle_length = self.read_particles().len();
+        if particle_length == 0 {
+            return None
+        }
+        if self.tick_resources.is_none() {
+            let resources = self.create_tick_resources(particle_length).unwrap_or_else(|error| panic!("{}", error));
+            self.tick_resources = Some(resources);
+        }
+        let resources = self.tick_resources.as_ref().unwrap();
+        self.write_parameters(resources, stepped_time);
+        if !self.single_submission {
+            let dispatches = self.tick_dispatches(resources, steps);
+            return Some(dispatches.into_iter().map(|dispatch| dispatch.command_buffer).collect())
+        }
+        let recorded_for = (self.integrator, steps);
+        if resources.tick_command_buffer.as_ref().map(|recorded| recorded.recorded_for) != Some(recorded_for) {
+            let dispatches = self.tick_dispatches(resources, steps);
+            let timestamp_pool = self.timestamp_pool(dispatches.len() as u32 + 1);
+            let mut builder = self.command_buffer_builder();
+            if let Some(pool) = &timestamp_pool {
+                unsafe { builder.reset_query_pool(pool.clone(), 0..dispatches.len() as u32 + 1).unwrap(); }
+                write_timestamp(&mut builder, pool, 0);
+            }
+            // the builder inserts the barriers between dispatches that the separate submissions had in between
+            for (i, dispatch) in dispatches.iter().enumerate() {
+                record_dispatch(&mut builder, &dispatch.pipeline, dispatch.set.clone(), dispatch.groups);
+                if let Some(pool) = &timestamp_pool {
+                    write_timestamp(&mut builder, pool, i as u32 + 1);
+                }
+            }
+            let accelerations = dispatches.iter()
+                .map(|dispatch| !self.integration_pipelines.iter().any(|pipeline| Arc::ptr_eq(pipeline, &dispatch.pipeline)))
+                .collect();
+            self.tick_resources.as_mut().unwrap().tick_command_buffer = Some(RecordedTick {
+                recorded_for,
+                command_buffer: Arc::new(builder.build().unwrap()),
+                timestamps: timestamp_pool.map(|pool| (pool, accelerations))
+            });
+        }
+        let recorded = self.tick_resources.as_ref().unwrap().tick_command_buffer.as_ref().unwrap();
+        Some(vec![recorded.command_buffer.clone()])
+    }
+
+    /// a pool of `queries` timestamps if profiling is enabled and the device can write timestamps
+    fn timestamp_pool(&self, queries: u32) -> Option<Arc<QueryPool>> {
+        if !self.profiling || self.timestamp_period.is_none() {
+            return None
+        }
+        let create_info = QueryPoolCreateInfo {
+            query_count: queries,
+            ..QueryPoolCreateInfo::query_type(QueryType::Timestamp)
         };
+        Some(QueryPool::new(self.device.clone(), create_info).expect("failed to create timestamp query pool"))
+    }
 
-        let acceleration_command_buffer = {
-            let mut builder = AutoCommandBufferBuilder::primary(
-                self.device.clone(),
-                self.queue_family_index,
-                CommandBufferUsage::MultipleSubmit
-            ).unwrap();
-            builder
-                .bind_pipeline_compute(self.acceleration_pipeline.clone())
-                .bind_descriptor_sets(
-                    PipelineBindPoint::Compute,
-                    self.acceleration_pipeline.layout().clone(),
-                    0,
-                    set.clone()
-                )
-                .dispatch([(particle_length / 64 + 1) as u32, 1, 1])
+    /// the device the world runs on and its queue, for running something else on the same device, such as a
+    /// [`GPURasterizer`](crate::render::gpu::GPURasterizer)
+    pub fn device(&self) -> (Arc<Device>, Arc<Queue>) {
+        (self.device.clone(), self.queue.clone())
+    }
+
+    /// how long each pass of the last tick took on the GPU, see [`GpuTickProfile`], waits for a pipelined tick
+    ///
+    /// `None` unless profiling was enabled with [`with_profiling`](Self::with_profiling), the device can write
+    /// timestamps and the last tick was a [single submission](Self::with_single_submission)
+    pub fn last_tick_profile(&self) -> Option<GpuTickProfile> {
+        let period = self.timestamp_period?;
+        let resources = self.tick_resources.as_ref()?;
+        let (pool, accelerations) = resources.tick_command_buffer.as_ref()?.timestamps.as_ref()?;
+        self.finish();
+        let microseconds = |pool: &QueryPool, queries: Range<u32>| {
+            let mut timestamps = vec![0u64; queries.len()];
+            pool.queries_range(queries).unwrap()
+                .get_results(&mut timestamps, QueryResultFlags { wait: true, ..Default::default() })
                 .unwrap();
-            Arc::new(builder.build().unwrap())
+            timestamps.windows(2)
+                .map(|pair| pair[1].wrapping_sub(pair[0]) as f32 * period / 1000.0)
+                .collect::<Vec<_>>()
         };
+        let mut profile = GpuTickProfile { acceleration: 0.0, integration: 0.0, copy: None };
+        let durations = microseconds(pool, 0..accelerations.len() as u32 + 1);
+        for (duration, &acceleration) in durations.iter().zip(accelerations) {
+            match acceleration {
+                true => profile.acceleration += duration,
+                false => profile.integration += duration
+            }
+        }
+        if let (Some(pool), true) = (&resources.copy_timestamps, self.pipelined_ticks > 0) {
+            // the last pipelined tick copied into the staging buffer before the one the next one copies into
+            let staging = (self.pipelined_ticks - 1) as u32 % 2;
+            profile.copy = Some(microseconds(pool, 2 * staging..2 * staging + 2)[0]);
+        }
+        Some(profile)
+    }
+
+    /// the dispatches of a tick in the order they have to run, each pass is a dispatch per block of particles,
+    /// see [`with_max_binding_range`](Self::with_max_binding_range)
+    fn tick_dispatches(&self, resources: &TickResources, steps: NonZeroU16) -> Vec<Dispatch> {
+        let mut passes: Vec<&[Dispatch]> = Vec::new();
+        let particles_acceleration = &resources.particles_acceleration[..];
+        let stage_acceleration = &resources.stage_acceleration[..];
+        let integration = |stage: IntegrationStage| &resources.integrations[stage as usize][..];
+
+        match self.integrator {
+            Integrator::Euler => {
+                let euler = integration(IntegrationStage::Euler);
+                for _ in 0..steps.get() {
+                    passes.extend([particles_acceleration, euler]);
+                }
+            }
+            Integrator::Verlet => {
+                let kick_drift = integration(IntegrationStage::VerletKickDrift);
+                let kick_kick_drift = integration(IntegrationStage::VerletKickKickDrift);
+                let kick = integration(IntegrationStage::VerletKick);
+                // the closing kick of a substep and the opening kick of the next one share the same forces
+                passes.extend([particles_acceleration, kick_drift]);
+                for _ in 1..steps.get() {
+                    passes.extend([particles_acceleration, kick_kick_drift]);
+                }
+                passes.extend([particles_acceleration, kick]);
+            }
+            Integrator::RK4 => {
+                let stage_1 = integration(IntegrationStage::RK4Stage1);
+                let stage_2 = integration(IntegrationStage::RK4Stage2);
+                let stage_3 = integration(IntegrationStage::RK4Stage3);
+                let stage_final = integration(IntegrationStage::RK4Final);
+                for _ in 0..steps.get() {
+                    passes.extend([
+                        particles_acceleration, stage_1,
+                        stage_acceleration, stage_2,
+                        stage_acceleration, stage_3,
+                        stage_acceleration, stage_final
+                    ]);
+                }
+            }
+            Integrator::Yoshida => {
+                let weighted_steps: Vec<_> = resources.yoshida_integrations.iter()
+                    .map(|[kick_drift, kick]| [particles_acceleration, &kick_drift[..], particles_acceleration, &kick[..]])
+                    .collect();
+                for _ in 0..steps.get() {
+                    for weighted_step in &weighted_steps {
+                        passes.extend(weighted_step);
+                    }
+                }
+            }
+        }
+        passes.into_iter().flatten().cloned().collect()
+    }
+
+    /// removes the particles that escaped, on the CPU
+    fn cull_escaped(&mut self) {
+        if let Some(escape) = self.escape {
+            let mut particles = self.read_particles().to_vec();
+            let escaped = escape::cull(&mut particles, escape, &NewtonianGravity { g: self.g }, self.softening, &[]);
+            if escaped > 0 {
+                self.replace_particles(particles);
+                self.escaped += escaped;
+            }
+        }
+    }
+
+    /// [`tick`](Self::tick), calling `observer` with every particle after each substep, see
+    /// [`CPUWorld::tick_with_observer`](crate::world::cpu::CPUWorld::tick_with_observer)
+    ///
+    /// each substep is a tick of its own followed by a read back, so this is much slower than a single tick,
+    /// and Verlet and Yoshida compute the forces at the start of every substep again
+    pub fn tick_with_observer(&mut self, time: f32, steps: NonZeroU16, mut observer: impl FnMut(&[Particle])) -> u16 {
+        let stepped_time = time / steps.get() as f32;
         for _ in 0..steps.get() {
-            sync::now(self.device.clone())
-                .then_execute(self.queue.clone(), force_direction_command_buffer.clone()).unwrap()
-                .then_signal_semaphore_and_flush().unwrap()
-                .then_execute_same_queue(acceleration_command_buffer.clone()).unwrap()
-                .then_signal_fence_and_flush().unwrap()
-                .wait(None).unwrap();
+            self.tick(stepped_time, NonZeroU16::new(1).unwrap());
+            observer(&self.read_particles());
         }
+        steps.get()
+    }
+
+    /// runs the world backwards by `time`, returns the number of substeps taken
+    ///
+    /// velocities are reversed, the world is ticked forwards on the GPU, then velocities are reversed again,
+    /// which exactly undoes a tick with Verlet or Yoshida (up to rounding) as long as there is no drag,
+    /// collision or escape, which aren't reversible
+    ///
+    /// velocities are reversed on the CPU, in place in the particle buffer
+    pub fn tick_reverse(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        self.reverse_velocities();
+        let taken = self.tick(time, steps);
+        self.reverse_velocities();
+        taken
+    }
+
+    /// number of particles removed for escaping so far
+    pub fn escaped(&self) -> usize {
+        self.escaped
     }
 
     pub fn get_mass_points(&self) -> Vec<MassPoint> {
-        let particles = self.particles.read().unwrap();
-        let mut mass_points = Vec::with_capacity(particles.len());
-        for particle in &*particles {
-            mass_points.push(MassPoint {
-                mass: particle.mass,
-                position: particle.position.to_cartesian()
-            })
+        mass_points(&self.read_particles())
+    }
+
+    /// every particle with its velocity, in the same order as [`get_mass_points`](Self::get_mass_points),
+    /// velocities are taken at the same instant as positions, as every integrator ends a tick with both in step
+    ///
+    /// a world created from these particles continues exactly like this one, except that it numbers the ids anew
+    ///
+    /// positions and velocities are read back from the same buffer
+    pub fn get_particles(&self) -> Vec<Particle> {
+        self.read_particles().to_vec()
+    }
+
+    /// kinetic energy of every moving particle, computed on the CPU
+    pub fn kinetic_energy(&self) -> f32 {
+        diagnostics::kinetic_energy(&self.read_particles())
+    }
+
+    /// potential energy of every pair of particles, computed on the CPU
+    pub fn potential_energy(&self) -> f32 {
+        let force_model = NewtonianGravity { g: self.g };
+        diagnostics::potential_energy(&self.read_particles(), &force_model, self.softening, self.boundary, &[])
+    }
+
+    pub fn total_energy(&self) -> f32 {
+        self.kinetic_energy() + self.potential_energy()
+    }
+
+    /// momentum of every moving particle, computed on the CPU
+    pub fn total_momentum(&self) -> Vector {
+        diagnostics::total_momentum(&self.read_particles())
+    }
+
+    /// mass weighted average position of every particle, computed on the CPU
+    pub fn center_of_mass(&self) -> (f32, f32) {
+        diagnostics::center_of_mass(self.get_mass_points())
+    }
+
+    fn reverse_velocities(&mut self) {
+        self.finish();
+        for particle in self.particles.write().unwrap().iter_mut() {
+            particle.velocity = particle.velocity.scale(-1.0);
         }
-        mass_points
+    }
+
+    /// buffers sized for `particle_length` particles, and the descriptor sets and command buffers of every stage
+    /// bound to them and to the current particle buffer
+    fn create_tick_resources(&self, particle_length: usize) -> Result<TickResources, GPUInitError> {
+        let parameter_buffer = || {
+            CpuAccessibleBuffer::from_data(self.device.clone(), Self::storage_buffer_usage(), false, IntegrationParameters::zeroed())
+                .expect("failed to create parameter buffer")
+        };
+        let force_parameter_buffer = CpuAccessibleBuffer::from_data(self.device.clone(), Self::storage_buffer_usage(), false, ForceParameters::zeroed())
+            .expect("failed to create parameter buffer");
+        let integration_parameter_buffer = parameter_buffer();
+        let yoshida_parameter_buffers = YOSHIDA_WEIGHTS.map(|_| parameter_buffer());
+        // the acceleration of each particle towards all of the others, in cartesian coordinates so that blocks add up exactly
+        let acceleration_buffer: Arc<DeviceLocalBuffer<[[f32; 2]]>> = DeviceLocalBuffer::array(self.device.clone(), particle_length as DeviceSize, Self::storage_buffer_usage(), [self.queue_family_index])
+            .map_err(|error| self.memory_error(error))?;
+        // the sources of the acceleration pass in half precision, see `with_half_precision_sources`,
+        // a single one when unused as every binding has to be bound to a buffer
+        let half_source_length = if self.half_precision_sources { particle_length } else { 1 };
+        let half_source_buffer: Arc<DeviceLocalBuffer<[[u32; 2]]>> = DeviceLocalBuffer::array(self.device.clone(), half_source_length as DeviceSize, Self::storage_buffer_usage(), [self.queue_family_index])
+            .map_err(|error| self.memory_error(error))?;
+        // intermediate state the forces are computed from during the later Runge-Kutta stages
+        let stage_buffer: Arc<DeviceLocalBuffer<[Particle]>> = DeviceLocalBuffer::array(self.device.clone(), particle_length as DeviceSize, Self::storage_buffer_usage(), [self.queue_family_index])
+            .map_err(|error| self.memory_error(error))?;
+        // a velocity and acceleration for each of the four Runge-Kutta stages of each particle
+        let derivative_buffer: Arc<DeviceLocalBuffer<[Vector]>> = DeviceLocalBuffer::array(self.device.clone(), (particle_length * 8) as DeviceSize, Self::storage_buffer_usage(), [self.queue_family_index])
+            .map_err(|error| self.memory_error(error))?;
+
+        let block_length = self.block_length();
+        let blocks: Vec<Range<DeviceSize>> = (0..particle_length).step_by(block_length)
+            .map(|start| start as DeviceSize..(start + block_length).min(particle_length) as DeviceSize)
+            .collect();
+        let particles = |block: Range<DeviceSize>| -> Arc<dyn BufferAccess> { self.particles.slice(block).unwrap() };
+        let stage = |block: Range<DeviceSize>| -> Arc<dyn BufferAccess> { stage_buffer.slice(block).unwrap() };
+
+        let half_sources = |block: Range<DeviceSize>| -> Arc<dyn BufferAccess> {
+            match self.half_precision_sources {
+                true => half_source_buffer.slice(block).unwrap(),
+                false => half_source_buffer.clone()
+            }
+        };
+
+        // the accelerations of every block of `input` towards every block of `input`,
+        // after packing every block into half precision first with `with_half_precision_sources`
+        let acceleration = |input: &dyn Fn(Range<DeviceSize>) -> Arc<dyn BufferAccess>| {
+            let mut dispatches = Vec::with_capacity(blocks.len() * (blocks.len() + 1));
+            if self.half_precision_sources {
+                let layout = self.half_source_pipeline.layout().set_layouts().first().unwrap();
+                for block in &blocks {
+                    let set = PersistentDescriptorSet::new(
+                        layout.clone(),
+                        [
+                            WriteDescriptorSet::buffer(0, input(block.clone())),
+                            WriteDescriptorSet::buffer(1, half_sources(block.clone()))
+                        ]
+                    ).unwrap();
+                    dispatches.push(self.dispatch(&self.half_source_pipeline, set, ((block.end - block.start) / 64 + 1) as u32));
+                }
+            }
+            for targets in &blocks {
+                for (i, sources) in blocks.iter().enumerate() {
+                    // the first block of sources sets the accelerations of the targets, the others add to them
+                    let pipeline = self.acceleration_pipeline(i > 0);
+                    let layout = pipeline.layout().set_layouts().first().unwrap();
+                    let set = PersistentDescriptorSet::new(
+                        layout.clone(),
+                        [
+                            WriteDescriptorSet::buffer(0, input(targets.clone())),
+                            WriteDescriptorSet::buffer(1, acceleration_buffer.slice(targets.clone()).unwrap()),
+                            WriteDescriptorSet::buffer(2, force_parameter_buffer.clone()),
+                            WriteDescriptorSet::buffer(3, input(sources.clone())),
+                            WriteDescriptorSet::buffer(4, half_sources(sources.clone()))
+                        ]
+                    ).unwrap();
+                    let groups = ((targets.end - targets.start) / TILE_SIZE as DeviceSize + 1) as u32;
+                    dispatches.push(self.dispatch(pipeline, set, groups));
+                }
+            }
+            dispatches
+        };
+
+        let integration = |stage: IntegrationStage, integration_parameter_buffer: Arc<CpuAccessibleBuffer<IntegrationParameters>>| {
+            let pipeline = &self.integration_pipelines[stage as usize];
+            let layout = pipeline.layout().set_layouts().first().unwrap();
+            blocks.iter()
+                .map(|block| {
+                    let set = PersistentDescriptorSet::new(
+                        layout.clone(),
+                        [
+                            WriteDescriptorSet::buffer(0, self.particles.slice(block.clone()).unwrap()),
+                            WriteDescriptorSet::buffer(1, integration_parameter_buffer.clone()),
+                            WriteDescriptorSet::buffer(2, acceleration_buffer.slice(block.clone()).unwrap()),
+                            WriteDescriptorSet::buffer(3, stage_buffer.slice(block.clone()).unwrap()),
+                            WriteDescriptorSet::buffer(4, derivative_buffer.slice(block.start * 8..block.end * 8).unwrap())
+                        ]
+                    ).unwrap();
+                    self.dispatch(pipeline, set, ((block.end - block.start) / 64 + 1) as u32)
+                })
+                .collect()
+        };
+
+        let staging_buffers = [(); 2].map(|_| {
+            let usage = BufferUsage {
+                transfer_dst: true,
+                ..BufferUsage::empty()
+            };
+            CpuAccessibleBuffer::from_iter(self.device.clone(), usage, true, (0..particle_length).map(|_| Particle::zeroed()))
+                .map_err(|error| self.memory_error(error))
+        });
+        // arrays can't be collected into, so both are checked before either is unwrapped
+        if let Some(Err(error)) = staging_buffers.iter().find(|buffer| buffer.is_err()) {
+            return Err(error.clone())
+        }
+        let staging_buffers = staging_buffers.map(Result::unwrap);
+        // a timestamp before and after each copy
+        let copy_timestamps = self.timestamp_pool(4);
+        let mut staging = 0;
+        let copy_command_buffers = staging_buffers.clone().map(|staging_buffer| {
+            let mut builder = self.command_buffer_builder();
+            if let Some(pool) = &copy_timestamps {
+                unsafe { builder.reset_query_pool(pool.clone(), 2 * staging..2 * staging + 2).unwrap(); }
+                write_timestamp(&mut builder, pool, 2 * staging);
+            }
+            builder.copy_buffer(CopyBufferInfo::buffers(self.particles.clone(), staging_buffer)).unwrap();
+            if let Some(pool) = &copy_timestamps {
+                write_timestamp(&mut builder, pool, 2 * staging + 1);
+            }
+            staging += 1;
+            Arc::new(builder.build().unwrap())
+        });
+
+        Ok(TickResources {
+            particles_acceleration: acceleration(&particles),
+            stage_acceleration: acceleration(&stage),
+            integrations: IntegrationStage::ALL.map(|stage| integration(stage, integration_parameter_buffer.clone())),
+            yoshida_integrations: yoshida_parameter_buffers.clone().map(|parameter_buffer| [
+                integration(IntegrationStage::VerletKickDrift, parameter_buffer.clone()),
+                integration(IntegrationStage::VerletKick, parameter_buffer)
+            ]),
+            tick_command_buffer: None,
+            copy_command_buffers,
+            copy_timestamps,
+            force_parameter_buffer,
+            integration_parameter_buffer,
+            yoshida_parameter_buffers,
+            staging_buffers
+        })
+    }
+
+    /// the pipeline of `acceleration_compute_shader` for the summation and sources of this world,
+    /// `accumulate` to add to the accelerations of the blocks of sources before
+    fn acceleration_pipeline(&self, accumulate: bool) -> &Arc<ComputePipeline> {
+        let variant = accumulate as usize | (self.deterministic_summation as usize) << 1 | (self.half_precision_sources as usize) << 2;
+        &self.acceleration_pipelines[variant]
+    }
+
+    /// particles per block, as many as every buffer bound per block has room for within
+    /// [`max_binding_range`](Self::with_max_binding_range), but at least [`BLOCK_ALIGNMENT`]
+    fn block_length(&self) -> usize {
+        // the derivatives take the most room per particle, a velocity and an acceleration for each of four stages
+        let bytes_per_particle = (8 * size_of::<Vector>()) as DeviceSize;
+        let fitting = (self.max_binding_range / bytes_per_particle) as usize;
+        (fitting / BLOCK_ALIGNMENT * BLOCK_ALIGNMENT).max(BLOCK_ALIGNMENT)
+    }
+
+    fn memory_error(&self, error: impl Display) -> GPUInitError {
+        GPUInitError::Memory(self.device.physical_device().properties().device_name.clone(), error.to_string())
+    }
+
+    /// writes the parameters of a tick of substeps of `stepped_time` into the parameter buffers of `resources`,
+    /// no commands are running between ticks so they can be written in place
+    fn write_parameters(&self, resources: &TickResources, stepped_time: f32) {
+        let (period_width, period_height) = match self.boundary {
+            Boundary::Periodic { width, height } => (width, height),
+            _ => (0.0, 0.0)
+        };
+        *resources.force_parameter_buffer.write().unwrap() = ForceParameters {
+            g: self.g,
+            softening: self.softening,
+            period_width,
+            period_height
+        };
+        let (boundary, width, height, restitution) = match self.boundary {
+            Boundary::Open => (BOUNDARY_OPEN, 0.0, 0.0, 0.0),
+            Boundary::Periodic { width, height } => (BOUNDARY_PERIODIC, width, height, 0.0),
+            Boundary::Reflective { width, height, restitution } => (BOUNDARY_REFLECTIVE, width, height, restitution)
+        };
+        let integration_parameters = IntegrationParameters {
+            time: stepped_time,
+            drag: self.drag,
+            boundary,
+            width,
+            height,
+            restitution
+        };
+        *resources.integration_parameter_buffer.write().unwrap() = integration_parameters;
+        // a Verlet step for each weight, which only differ in the time of their parameters,
+        // unlike on the CPU drag and the boundary are applied after each of them
+        for (parameter_buffer, weight) in resources.yoshida_parameter_buffers.iter().zip(YOSHIDA_WEIGHTS) {
+            *parameter_buffer.write().unwrap() = IntegrationParameters {
+                time: stepped_time * weight,
+                ..integration_parameters
+            };
+        }
+    }
+
+    fn replace_particles(&mut self, particles: Vec<Particle>) {
+        self.finish();
+        self.particles = CpuAccessibleBuffer::from_iter(self.device.clone(), Self::particle_buffer_usage(), false, particles)
+            .expect("failed to create particle buffer");
+        // bound to the old particle buffer and sized for its particles
+        self.tick_resources = None;
+        self.pipelined_ticks = 0;
+    }
+
+    /// the particles, once the last pipelined tick has finished
+    fn read_particles(&self) -> impl Deref<Target = [Particle]> + '_ {
+        self.finish();
+        self.particles.read().unwrap()
+    }
+
+    /// waits for the last pipelined tick to finish, if it hasn't yet
+    fn finish(&self) {
+        if let Some(pending) = self.pending.lock().unwrap().take() {
+            pending.wait(None).unwrap();
+        }
+    }
+
+    fn command_buffer_builder(&self) -> AutoCommandBufferBuilder<PrimaryAutoCommandBuffer> {
+        AutoCommandBufferBuilder::primary(
+            self.device.clone(),
+            self.queue_family_index,
+            CommandBufferUsage::MultipleSubmit
+        ).unwrap()
+    }
+
+    fn dispatch(&self, pipeline: &Arc<ComputePipeline>, set: Arc<PersistentDescriptorSet>, groups: u32) -> Dispatch {
+        let mut builder = self.command_buffer_builder();
+        record_dispatch(&mut builder, pipeline, set.clone(), groups);
+        Dispatch {
+            pipeline: pipeline.clone(),
+            set,
+            groups,
+            command_buffer: Arc::new(builder.build().unwrap())
+        }
+    }
+
+    /// submits the command buffers in order, each one waiting on the previous one, without waiting for them
+    fn submit(&self, command_buffers: &[Arc<PrimaryAutoCommandBuffer>]) -> PendingTick {
+        let mut future = sync::now(self.device.clone()).boxed_send_sync();
+        for command_buffer in command_buffers {
+            future = future
+                .then_execute(self.queue.clone(), command_buffer.clone()).unwrap()
+                .then_signal_semaphore_and_flush().unwrap()
+                .boxed_send_sync();
+        }
+        future.then_signal_fence_and_flush().unwrap()
+    }
+
+    /// [`submit`](Self::submit)s the command buffers and blocks until all have finished
+    fn execute(&self, command_buffers: &[Arc<PrimaryAutoCommandBuffer>]) {
+        self.submit(command_buffers).wait(None).unwrap();
     }
 
     fn storage_buffer_usage() -> BufferUsage {
@@ -158,9 +886,359 @@ impl GPUWorld {
             ..BufferUsage::empty()
         }
     }
+
+    /// also copied from into the staging buffers of pipelined ticks
+    fn particle_buffer_usage() -> BufferUsage {
+        BufferUsage {
+            storage_buffer: true,
+            transfer_src: true,
+            ..BufferUsage::empty()
+        }
+    }
+}
+
+pub(crate) fn physical_devices() -> Result<Vec<Arc<PhysicalDevice>>, GPUInitError> {
+    let library = VulkanLibrary::new().map_err(|error| GPUInitError::Unavailable(error.to_string()))?;
+    let instance = Instance::new(library, InstanceCreateInfo::default())
+        .map_err(|error| GPUInitError::Unavailable(error.to_string()))?;
+    let physical_devices = instance.enumerate_physical_devices()
+        .map_err(|error| GPUInitError::Unavailable(error.to_string()))?;
+    Ok(physical_devices.collect())
+}
+
+/// a device [`GPUWorld::list_devices`] found
+#[derive(Clone, Debug)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub device_type: PhysicalDeviceType,
+    /// highest Vulkan version the device supports
+    pub api_version: Version
+}
+
+/// which device a [`GPUWorld`] runs on, see [`GPUWorld::new_on`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum DeviceSelection {
+    /// the first device Vulkan lists, whichever that is
+    #[default]
+    First,
+    /// a discrete gpu, then an integrated one, then anything else
+    HighPerformance,
+    /// an integrated gpu, then a discrete one, then anything else
+    LowPower,
+    /// the device at this index of [`GPUWorld::list_devices`]
+    Index(usize),
+    /// the first device whose name contains this, ignoring case
+    Name(String)
+}
+
+impl DeviceSelection {
+    pub(crate) fn select(&self, physical_devices: Vec<Arc<PhysicalDevice>>) -> Result<Arc<PhysicalDevice>, GPUInitError> {
+        // devices of the lowest rank are preferred, the first one listed of those
+        let by_rank = |rank: fn(PhysicalDeviceType) -> u8| {
+            physical_devices.iter().min_by_key(|physical| rank(physical.properties().device_type)).cloned()
+        };
+        let selected = match self {
+            DeviceSelection::First => physical_devices.first().cloned(),
+            DeviceSelection::HighPerformance => by_rank(|device_type| match device_type {
+                PhysicalDeviceType::DiscreteGpu => 0,
+                PhysicalDeviceType::IntegratedGpu => 1,
+                PhysicalDeviceType::VirtualGpu => 2,
+                _ => 3
+            }),
+            DeviceSelection::LowPower => by_rank(|device_type| match device_type {
+                PhysicalDeviceType::IntegratedGpu => 0,
+                PhysicalDeviceType::DiscreteGpu => 1,
+                PhysicalDeviceType::VirtualGpu => 2,
+                _ => 3
+            }),
+            DeviceSelection::Index(index) => physical_devices.get(*index).cloned(),
+            DeviceSelection::Name(name) => {
+                let name = name.to_lowercase();
+                physical_devices.iter()
+                    .find(|physical| physical.properties().device_name.to_lowercase().contains(&name))
+                    .cloned()
+            }
+        };
+        selected.ok_or_else(|| GPUInitError::NotFound(self.clone(), physical_devices.len()))
+    }
+}
+
+/// `first`, `high-performance`, `low-power`, an index, or otherwise part of a name
+impl FromStr for DeviceSelection {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "first" => DeviceSelection::First,
+            "high-performance" => DeviceSelection::HighPerformance,
+            "low-power" => DeviceSelection::LowPower,
+            _ => match s.parse() {
+                Ok(index) => DeviceSelection::Index(index),
+                Err(_) => DeviceSelection::Name(s.to_string())
+            }
+        })
+    }
+}
+
+impl Display for DeviceSelection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceSelection::First => write!(f, "first"),
+            DeviceSelection::HighPerformance => write!(f, "high-performance"),
+            DeviceSelection::LowPower => write!(f, "low-power"),
+            DeviceSelection::Index(index) => write!(f, "{}", index),
+            DeviceSelection::Name(name) => write!(f, "{}", name)
+        }
+    }
+}
+
+/// why a [`GPUWorld`] or a [`GPURasterizer`](crate::render::gpu::GPURasterizer) couldn't be created, such as on a
+/// machine without Vulkan
+#[derive(Clone, Debug)]
+pub enum GPUInitError {
+    /// the Vulkan library couldn't be loaded or set up, so there are no devices at all
+    Unavailable(String),
+    /// no device matches the selection, out of this many devices
+    NotFound(DeviceSelection, usize),
+    /// the named device can't run compute shaders
+    NoCompute(String),
+    /// the named device, or the queue given for it, can't draw
+    NoGraphics(String),
+    /// the named device was found but couldn't be set up
+    Creation(String, String),
+    /// the shaders or their pipelines couldn't be created on the named device
+    Shader(String, String),
+    /// the named device doesn't have the memory for the buffers of the particles, or for the image drawn onto
+    Memory(String, String)
+}
+
+impl Display for GPUInitError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GPUInitError::Unavailable(error) => write!(f, "vulkan is unavailable: {}", error),
+            GPUInitError::NotFound(selection, count) => write!(f, "no device matches `{}` out of {} devices", selection, count),
+            GPUInitError::NoCompute(name) => write!(f, "{} can't run compute shaders", name),
+            GPUInitError::NoGraphics(name) => write!(f, "{} can't draw", name),
+            GPUInitError::Creation(name, error) => write!(f, "failed to set up {}: {}", name, error),
+            GPUInitError::Shader(name, error) => write!(f, "failed to create the shaders on {}: {}", name, error),
+            GPUInitError::Memory(name, error) => write!(f, "{} doesn't have the memory for the buffers: {}", name, error)
+        }
+    }
+}
+
+impl Error for GPUInitError {}
+
+impl World for GPUWorld {
+    fn tick(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        GPUWorld::tick(self, time, steps)
+    }
+
+    fn tick_with_observer<O: FnMut(&[Particle])>(&mut self, time: f32, steps: NonZeroU16, observer: O) -> u16 {
+        GPUWorld::tick_with_observer(self, time, steps, observer)
+    }
+
+    fn tick_reverse(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        GPUWorld::tick_reverse(self, time, steps)
+    }
+
+    fn get_mass_points(&self) -> Vec<MassPoint> {
+        GPUWorld::get_mass_points(self)
+    }
+
+    fn get_particles(&self) -> Vec<Particle> {
+        GPUWorld::get_particles(self)
+    }
+
+    fn total_energy(&self) -> f32 {
+        GPUWorld::total_energy(self)
+    }
+
+    fn add_particle(&mut self, particle: Particle) -> u32 {
+        GPUWorld::add_particle(self, particle)
+    }
+
+    fn remove_particle(&mut self, index: usize) -> Particle {
+        GPUWorld::remove_particle(self, index)
+    }
+
+    fn checkpoint(&self, frame: usize, time: f32, steps: NonZeroU16) -> Checkpoint {
+        Checkpoint {
+            frame,
+            time,
+            steps: steps.get(),
+            particles: self.get_particles(),
+            next_id: self.next_id,
+            escaped: self.escaped
+        }
+    }
+
+    fn resume(&mut self, checkpoint: &Checkpoint) {
+        self.replace_particles(checkpoint.particles.clone());
+        self.next_id = checkpoint.next_id;
+        self.escaped = checkpoint.escaped;
+    }
+    fn last_tick_profile(&self) -> Option<GpuTickProfile> {
+        GPUWorld::last_tick_profile(self)
+    }
 }
 
-mod force_direction_compute_shader {
+fn mass_points(particles: &[Particle]) -> Vec<MassPoint> {
+    particles.iter().copied().map(MassPoint::from).collect()
+}
+
+fn write_timestamp(builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, pool: &Arc<QueryPool>, query: u32) {
+    // once every command before it has finished
+    unsafe { builder.write_timestamp(pool.clone(), query, PipelineStage::BottomOfPipe).unwrap(); }
+}
+
+fn record_dispatch(builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, pipeline: &Arc<ComputePipeline>, set: Arc<PersistentDescriptorSet>, groups: u32) {
+    builder
+        .bind_pipeline_compute(pipeline.clone())
+        .bind_descriptor_sets(
+            PipelineBindPoint::Compute,
+            pipeline.layout().clone(),
+            0,
+            set
+        )
+        .dispatch([groups, 1, 1])
+        .unwrap();
+}
+
+/// a pipeline dispatched over a descriptor set, recorded into a command buffer of its own, and into that of
+/// a whole tick with [`GPUWorld::with_single_submission`]
+#[derive(Clone)]
+struct Dispatch {
+    pipeline: Arc<ComputePipeline>,
+    set: Arc<PersistentDescriptorSet>,
+    groups: u32,
+    command_buffer: Arc<PrimaryAutoCommandBuffer>
+}
+
+/// what a tick binds and runs, kept from tick to tick as long as the particle buffer is the same one,
+/// the other buffers are kept alive by the descriptor sets of the command buffers
+///
+/// the particles are split into blocks that fit in [`GPUWorld::with_max_binding_range`], every stage is
+/// a dispatch per block, and the accelerations a dispatch per pair of blocks
+struct TickResources {
+    particles_acceleration: Vec<Dispatch>,
+    /// accelerations of the particles of the intermediate state of the Runge-Kutta stages
+    stage_acceleration: Vec<Dispatch>,
+    /// every [`IntegrationStage`] with the parameters of a substep, by index
+    integrations: [Vec<Dispatch>; IntegrationStage::ALL.len()],
+    /// the Verlet kick-drift and kick stages with the parameters of each of the [`YOSHIDA_WEIGHTS`]
+    yoshida_integrations: [[Vec<Dispatch>; 2]; YOSHIDA_WEIGHTS.len()],
+    /// see [`GPUWorld::with_single_submission`]
+    tick_command_buffer: Option<RecordedTick>,
+    force_parameter_buffer: Arc<CpuAccessibleBuffer<ForceParameters>>,
+    integration_parameter_buffer: Arc<CpuAccessibleBuffer<IntegrationParameters>>,
+    yoshida_parameter_buffers: [Arc<CpuAccessibleBuffer<IntegrationParameters>>; YOSHIDA_WEIGHTS.len()],
+    /// copies of the particles made by pipelined ticks, see [`GPUWorld::tick_pipelined`]
+    staging_buffers: [Arc<CpuAccessibleBuffer<[Particle]>>; 2],
+    /// copies the particles into the staging buffer of the same index
+    copy_command_buffers: [Arc<PrimaryAutoCommandBuffer>; 2],
+    /// written before and after each copy, see [`GPUWorld::with_profiling`]
+    copy_timestamps: Option<Arc<QueryPool>>
+}
+
+/// every dispatch of a tick in a single command buffer
+struct RecordedTick {
+    /// the integrator and substeps it was recorded for
+    recorded_for: (Integrator, NonZeroU16),
+    command_buffer: Arc<PrimaryAutoCommandBuffer>,
+    /// written before the first dispatch and after every dispatch, with whether each dispatch computed accelerations
+    /// rather than integrating, see [`GPUWorld::with_profiling`]
+    timestamps: Option<(Arc<QueryPool>, Vec<bool>)>
+}
+
+/// how long each pass of a tick took on the GPU in microseconds, see [`GPUWorld::last_tick_profile`]
+///
+/// each pass is timed from the end of the one before, so the barriers in between count towards the later pass
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GpuTickProfile {
+    /// every dispatch of `acceleration_compute_shader`, and of `half_source_compute_shader` before it
+    pub acceleration: f32,
+    /// every dispatch of `integration_compute_shader`
+    pub integration: f32,
+    /// copying the particles into a staging buffer, `None` unless the tick was [pipelined](GPUWorld::tick_pipelined)
+    pub copy: Option<f32>
+}
+
+impl Display for GpuTickProfile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "acceleration {:.0}µs, integration {:.0}µs", self.acceleration, self.integration)?;
+        if let Some(copy) = self.copy {
+            write!(f, ", copy {:.0}µs", copy)?;
+        }
+        Ok(())
+    }
+}
+
+/// parameters of the force law, must match `ForceParameters` in `acceleration_compute_shader`
+#[derive(Copy, Clone, Debug, Zeroable, Pod)]
+#[repr(C)]
+struct ForceParameters {
+    g: f32,
+    softening: f32,
+    /// size of a periodic domain, 0.0 when the boundary is not periodic
+    period_width: f32,
+    period_height: f32
+}
+
+/// parameters of a substep, must match `IntegrationParameters` in `integration_compute_shader`
+#[derive(Copy, Clone, Debug, Zeroable, Pod)]
+#[repr(C)]
+struct IntegrationParameters {
+    time: f32,
+    drag: f32,
+    /// one of the `BOUNDARY_` constants
+    boundary: u32,
+    width: f32,
+    height: f32,
+    restitution: f32
+}
+
+/// particles each workgroup of `acceleration_compute_shader` loads into shared memory at a time, which is also
+/// its workgroup size, must match the constant of the same name in the shader
+///
+/// larger tiles read the particle buffer less often, but fewer workgroups fit on the GPU at once
+const TILE_SIZE: u32 = 256;
+
+/// particles per block are a multiple of this, so that every block starts at a multiple of 256 bytes into every buffer,
+/// the largest `minStorageBufferOffsetAlignment` a device may have, see [`GPUWorld::with_max_binding_range`]
+pub const BLOCK_ALIGNMENT: usize = 256;
+
+// must match the constants of the same name in `integration_compute_shader`
+const BOUNDARY_OPEN: u32 = 0;
+const BOUNDARY_PERIODIC: u32 = 1;
+const BOUNDARY_REFLECTIVE: u32 = 2;
+
+/// variants of `integration_compute_shader`, the discriminants must match the `mode` constants in the shader
+#[derive(Copy, Clone, Debug)]
+enum IntegrationStage {
+    Euler,
+    VerletKickDrift,
+    VerletKickKickDrift,
+    VerletKick,
+    RK4Stage1,
+    RK4Stage2,
+    RK4Stage3,
+    RK4Final
+}
+
+impl IntegrationStage {
+    const ALL: [Self; 8] = [
+        Self::Euler,
+        Self::VerletKickDrift,
+        Self::VerletKickKickDrift,
+        Self::VerletKick,
+        Self::RK4Stage1,
+        Self::RK4Stage2,
+        Self::RK4Stage3,
+        Self::RK4Final
+    ];
+}
+
+mod acceleration_compute_shader {
     vulkano_shaders::shader! {
                 ty: "compute",
                 src: "
@@ -203,16 +1281,6 @@ vec2 vector_to_cartesian(Vector self) {
     return vec2(x, y);
 }
 
-float vector_distance_sq(Vector self, Vector other) {
-    float r1 = self.magnitude;
-    float r2 = other.magnitude;
-    float t1 = self.direction;
-    float t2 = other.direction;
-    return r1 * r1 +
-        r2 * r2 -
-        2.0 * r1 * r2 * cos(t1 - t2);
-}
-
 struct MassPoint {
     float mass;
     vec2 position;
@@ -222,79 +1290,167 @@ struct Particle {
     float mass;
     Vector position;
     Vector velocity;
+    float radius;
+    uint pinned; // `fixed` in Rust, which is reserved in GLSL
+    uint id;
+    uint group;
 };
 
-struct ForceDirection {
-    float force;
-    float direction;
+// must match `TILE_SIZE` in Rust
+const uint TILE_SIZE = 256;
+
+layout(local_size_x = TILE_SIZE, local_size_y = 1, local_size_z = 1) in;
+
+// whether the accelerations are added to those of the blocks of sources before, rather than replacing them
+layout(constant_id = 0) const uint accumulate = 0;
+
+// whether the accelerations towards the sources are summed in a fixed binary tree, like `PairwiseSum` in Rust,
+// rather than one after another, see `GPUWorld::with_deterministic_summation`
+layout(constant_id = 1) const uint deterministic = 0;
+
+// whether the sources are read from `half_sources` rather than `sources`, see `GPUWorld::with_half_precision_sources`
+layout(constant_id = 2) const uint half_precision = 0;
+
+// a block of either the particles themselves or an intermediate integration stage, see `GPUWorld::with_max_binding_range`
+layout(set = 0, binding = 0) readonly buffer Particles {
+    Particle particles[];
 };
 
-uint leading_zeros(uint n) {
-    uint M = 1 << 31;
-    for (uint c = 0; c < 32; c++) {
-        if ((n & M) == M)
-            return c;
-        n = n << 1;
-    }
-    return 32;
-}
-
-uint sqrt(uint n) {
-    uint MAX_SHIFT = 31;
-    uint shift = (MAX_SHIFT - leading_zeros(n)) & ~1u;
-    uint bit = 1 << shift;
-    uint result = 0;
-    while (bit != 0) {
-        if (n >= (result + bit)) {
-            n = n - (result + bit);
-            result = (result >> 1) + bit;
-        } else {
-            result = result >> 1;
+// of the particles of the block, in cartesian coordinates
+layout(set = 0, binding = 1) buffer Accelerations {
+    vec2 accelerations[];
+};
+
+layout(set = 0, binding = 2) readonly buffer ForceParameters {
+    float g;
+    float softening;
+    // size of a periodic domain, 0.0 when the boundary is not periodic
+    float period_width;
+    float period_height;
+};
+
+// the block the particles are pulled towards, the same block as the particles or another one
+layout(set = 0, binding = 3) readonly buffer Sources {
+    Particle sources[];
+};
+
+// `sources` packed by `half_source_compute_shader`, the cartesian position in half precision then the mass
+layout(set = 0, binding = 4) readonly buffer HalfSources {
+    uvec2 half_sources[];
+};
+
+// cartesian position and mass of the sources of the current tile
+shared vec3 tile[TILE_SIZE];
+
+// every workgroup goes through the sources a tile at a time, each invocation loads one source of the tile
+// into shared memory, then every invocation accumulates the acceleration of its own particle towards each of them,
+// so the sources are read once per workgroup rather than once per invocation
+void main() {
+    uint p = gl_GlobalInvocationID.x;
+    uint n = half_precision != 0 ? half_sources.length() : sources.length();
+    // invocations past the last particle still load tiles, as every invocation has to reach every barrier
+    vec2 position = p < particles.length() ? vector_to_cartesian(particles[p].position) : vec2(0.0);
+    // rounded like the sources, so that a particle isn't pulled towards where its own rounded position moved it
+    if (half_precision != 0)
+        position = unpackHalf2x16(packHalf2x16(position));
+    vec2 acceleration = vec2(0.0);
+    // sums of complete subtrees of the accelerations summed so far, from the largest to the smallest
+    vec2 partial[32];
+    uint depth = 0;
+    uint count = 0;
+    for (uint tile_start = 0; tile_start < n; tile_start += TILE_SIZE) {
+        uint loaded = tile_start + gl_LocalInvocationID.x;
+        if (loaded < n && half_precision != 0)
+            tile[gl_LocalInvocationID.x] = vec3(unpackHalf2x16(half_sources[loaded].x), uintBitsToFloat(half_sources[loaded].y));
+        else if (loaded < n)
+            tile[gl_LocalInvocationID.x] = vec3(vector_to_cartesian(sources[loaded].position), sources[loaded].mass);
+        barrier();
+        uint tile_length = min(TILE_SIZE, n - tile_start);
+        for (uint k = 0; k < tile_length; k++) {
+            vec2 d = tile[k].xy - position;
+            if (period_width != 0.0) {
+                // minimum image, see `Boundary::Periodic`
+                vec2 period = vec2(period_width, period_height);
+                d -= period * round(d / period);
+            }
+            float distance_sq = d.x * d.x + d.y * d.y;
+            // (G * m1 * m2) / r^2, divided by m1
+            float a = g * tile[k].z / (distance_sq + softening * softening);
+            // zero for the particle itself, particles on top of it or infinitely strong forces, like `CPUWorld`
+            if (distance_sq != 0.0 && !isinf(a)) {
+                vec2 pull = d * (a / sqrt(distance_sq));
+                if (deterministic != 0) {
+                    // every trailing one of the count is a subtree of the same size as the one being added
+                    for (uint merged = count; (merged & 1) != 0; merged >>= 1)
+                        pull += partial[--depth];
+                    partial[depth++] = pull;
+                    count++;
+                } else {
+                    acceleration += pull;
+                }
+            }
         }
-        bit = bit >> 2;
+        // the next tile can't be loaded until every invocation is done with this one
+        barrier();
+    }
+    for (uint k = depth; k > 0; k--)
+        acceleration += partial[k - 1];
+    if (p < particles.length())
+        accelerations[p] = accumulate != 0 ? accelerations[p] + acceleration : acceleration;
+}
+"
     }
-    return result;
 }
 
+mod half_source_compute_shader {
+    vulkano_shaders::shader! {
+                ty: "compute",
+                src: "
+#version 450
+
+struct Vector {
+    float direction;
+    float magnitude;
+};
+
+vec2 vector_to_cartesian(Vector self) {
+    float x = self.magnitude * cos(self.direction);
+    float y = self.magnitude * sin(self.direction);
+    return vec2(x, y);
+}
+
+struct Particle {
+    float mass;
+    Vector position;
+    Vector velocity;
+    float radius;
+    uint pinned; // `fixed` in Rust, which is reserved in GLSL
+    uint id;
+    uint group;
+};
+
 layout(local_size_x = 64, local_size_y = 1, local_size_z = 1) in;
 
+// a block of either the particles themselves or an intermediate integration stage
 layout(set = 0, binding = 0) readonly buffer Particles {
     Particle particles[];
 };
 
-layout(set = 0, binding = 1) readonly buffer Time {
-    float time;
-};
-
-layout(set = 0, binding = 2) writeonly buffer ForceDirections {
-    ForceDirection force_directions[];
+// the cartesian position in half precision then the mass, see `GPUWorld::with_half_precision_sources`
+layout(set = 0, binding = 1) writeonly buffer HalfSources {
+    uvec2 half_sources[];
 };
 
 void main() {
-    uint x = gl_GlobalInvocationID.x;
-    if (x < force_directions.length()) {
-        uint i = particles.length() - 1 - (1 + sqrt(1 + 8 * x)) / 2;
-        uint j = (particles.length() - i) * (particles.length() - i - 1) / 2 + i - x;
-        Particle a = particles[i];
-        Particle b = particles[j];
-        float r_sq = vector_distance_sq(a.position, b.position);
-        float f = (6.67430e-11 * a.mass * b.mass / r_sq) * time;
-        if (isinf(f)) {
-            force_directions[x].force = 0.0;
-        } else {
-            force_directions[x].force = f;
-            vec2 p1 = vector_to_cartesian(a.position);
-            vec2 p2 = vector_to_cartesian(b.position);
-            float d1 = atan(p2.y - p1.y, p2.x - p1.x);
-            force_directions[x].direction = d1;
-        }
-    }
+    uint p = gl_GlobalInvocationID.x;
+    if (p < particles.length())
+        half_sources[p] = uvec2(packHalf2x16(vector_to_cartesian(particles[p].position)), floatBitsToUint(particles[p].mass));
 }
 "
     }
 }
 
-mod acceleration_compute_shader {
+mod integration_compute_shader {
     vulkano_shaders::shader! {
                 ty: "compute",
                 src: "
@@ -356,56 +1512,476 @@ struct Particle {
     float mass;
     Vector position;
     Vector velocity;
-};
-
-struct ForceDirection {
-    float force;
-    float direction;
+    float radius;
+    uint pinned; // `fixed` in Rust, which is reserved in GLSL
+    uint id;
+    uint group;
 };
 
 layout(local_size_x = 64, local_size_y = 1, local_size_z = 1) in;
 
+// which part of an integration scheme this pipeline performs, see `IntegrationStage`
+layout(constant_id = 0) const uint mode = 0;
+
+const uint EULER = 0;
+const uint VERLET_KICK_DRIFT = 1;
+const uint VERLET_KICK_KICK_DRIFT = 2;
+const uint VERLET_KICK = 3;
+const uint RK4_STAGE_1 = 4;
+const uint RK4_STAGE_2 = 5;
+const uint RK4_STAGE_3 = 6;
+const uint RK4_FINAL = 7;
+
+struct Derivative {
+    Vector velocity;
+    Vector acceleration;
+};
+
 layout(set = 0, binding = 0) buffer Particles {
     Particle particles[];
 };
 
-layout(set = 0, binding = 1) readonly buffer Time {
+const uint BOUNDARY_OPEN = 0;
+const uint BOUNDARY_PERIODIC = 1;
+const uint BOUNDARY_REFLECTIVE = 2;
+
+layout(set = 0, binding = 1) readonly buffer IntegrationParameters {
     float time;
+    float drag;
+    uint boundary;
+    float width;
+    float height;
+    float restitution;
+};
+
+// written by `acceleration_compute_shader` from either the particles or the stage, in cartesian coordinates
+layout(set = 0, binding = 2) readonly buffer Accelerations {
+    vec2 accelerations[];
 };
 
-layout(set = 0, binding = 2) readonly buffer ForceDirections {
-    ForceDirection force_directions[];
+layout(set = 0, binding = 3) buffer Stage {
+    Particle stage[];
 };
 
-// if GPU groups are executed in SIMD as I've been reading, then this function
-// could have very poor performance due to many group cycles being completely NOP
-// because of other members of the group still working on the first for loop
-//
-// I will look into if this is actually an issue, and fixing it if, so in the
-// future, but this will be good enough for now
+layout(set = 0, binding = 4) buffer Derivatives {
+    Derivative derivatives[];
+};
+
+Vector vector_from_cartesian(vec2 v) {
+    return Vector(atan(v.y, v.x), sqrt(v.x * v.x + v.y * v.y));
+}
+
+float wrap(float v, float size) {
+    float half_size = size / 2.0;
+    if (v < -half_size || v >= half_size) {
+        float wrapped = v - size * floor(v / size + 0.5);
+        return wrapped >= half_size ? wrapped - size : wrapped;
+    }
+    return v;
+}
+
+bool bounce(inout float x, inout float v, float half_size) {
+    if (x < -half_size) {
+        x = -half_size;
+        v = abs(v) * restitution;
+        return true;
+    } else if (x > half_size) {
+        x = half_size;
+        v = -abs(v) * restitution;
+        return true;
+    }
+    return false;
+}
+
+// linear drag and the boundary at the end of a substep, see `CPUWorld::with_drag` and `Boundary::apply`
+void end_substep(inout Particle particle) {
+    particle.velocity = vector_scale(particle.velocity, max(0.0, 1.0 - drag * time));
+    vec2 position = vector_to_cartesian(particle.position);
+    if (boundary == BOUNDARY_PERIODIC) {
+        vec2 wrapped = vec2(wrap(position.x, width), wrap(position.y, height));
+        if (wrapped != position)
+            particle.position = vector_from_cartesian(wrapped);
+    } else if (boundary == BOUNDARY_REFLECTIVE) {
+        vec2 velocity = vector_to_cartesian(particle.velocity);
+        bool bounced_x = bounce(position.x, velocity.x, width / 2.0);
+        bool bounced_y = bounce(position.y, velocity.y, height / 2.0);
+        if (bounced_x || bounced_y) {
+            particle.position = vector_from_cartesian(position);
+            particle.velocity = vector_from_cartesian(velocity);
+        }
+    }
+}
+
 void main() {
     uint p = gl_GlobalInvocationID.x;
     if (p < particles.length()) {
-        float m = particles[p].mass;
-        Vector acceleration = Vector(0.0, 0.0);
-        for (uint i = 0; i < p; i++) {
-            uint x = (particles.length() - i) * (particles.length() - i - 1) / 2 - (p - i);
-            float d = force_directions[x].direction + 3.14159265358979323846264338327950288;
-            float f = force_directions[x].force;
-            if (!isinf(f))
-                acceleration = vector_add(acceleration, Vector(d, f / m));
-        }
-        for (uint j = p+1; j < particles.length(); j++) {
-            uint x = (particles.length() - p) * (particles.length() - p - 1) / 2 - (j - p);
-            float d = force_directions[x].direction;
-            float f = force_directions[x].force;
-            if (!isinf(f))
-                acceleration = vector_add(acceleration, Vector(d, f / m));
-        }
-        vector_step(particles[p].velocity, acceleration, time);
-        vector_step(particles[p].position, particles[p].velocity, time);
+        if (particles[p].pinned != 0) {
+            // fixed particles still pull on the others, so the intermediate stages need their unchanging state
+            if (mode == RK4_STAGE_1 || mode == RK4_STAGE_2 || mode == RK4_STAGE_3)
+                stage[p] = particles[p];
+            return;
+        }
+        Vector acceleration = vector_from_cartesian(accelerations[p]);
+        if (mode == EULER) {
+            vector_step(particles[p].velocity, acceleration, time);
+            vector_step(particles[p].position, particles[p].velocity, time);
+            end_substep(particles[p]);
+        } else if (mode == VERLET_KICK_DRIFT) {
+            vector_step(particles[p].velocity, acceleration, time / 2.0);
+            vector_step(particles[p].position, particles[p].velocity, time);
+        } else if (mode == VERLET_KICK_KICK_DRIFT) {
+            // the previous substep ends after the first kick
+            vector_step(particles[p].velocity, acceleration, time / 2.0);
+            end_substep(particles[p]);
+            vector_step(particles[p].velocity, acceleration, time / 2.0);
+            vector_step(particles[p].position, particles[p].velocity, time);
+        } else if (mode == VERLET_KICK) {
+            vector_step(particles[p].velocity, acceleration, time / 2.0);
+            end_substep(particles[p]);
+        } else if (mode == RK4_FINAL) {
+            Derivative k1 = derivatives[4 * p];
+            Derivative k2 = derivatives[4 * p + 1];
+            Derivative k3 = derivatives[4 * p + 2];
+            Vector k4_velocity = stage[p].velocity;
+            vector_step(particles[p].position, k1.velocity, time / 6.0);
+            vector_step(particles[p].position, k2.velocity, time / 3.0);
+            vector_step(particles[p].position, k3.velocity, time / 3.0);
+            vector_step(particles[p].position, k4_velocity, time / 6.0);
+            vector_step(particles[p].velocity, k1.acceleration, time / 6.0);
+            vector_step(particles[p].velocity, k2.acceleration, time / 3.0);
+            vector_step(particles[p].velocity, k3.acceleration, time / 3.0);
+            vector_step(particles[p].velocity, acceleration, time / 6.0);
+            end_substep(particles[p]);
+        } else {
+            // RK4_STAGE_1, RK4_STAGE_2 or RK4_STAGE_3
+            uint k = mode - RK4_STAGE_1;
+            // the position derivative of each stage is the velocity of that stage
+            Vector velocity = k == 0 ? particles[p].velocity : stage[p].velocity;
+            derivatives[4 * p + k] = Derivative(velocity, acceleration);
+            float stage_time = k == 2 ? time : time / 2.0;
+            Particle next = particles[p];
+            vector_step(next.position, velocity, stage_time);
+            vector_step(next.velocity, acceleration, stage_time);
+            stage[p] = next;
+        }
     }
 }
 "
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::mem::size_of;
+    use std::num::NonZeroU16;
+    use std::sync::Arc;
+    use half::f16;
+    use vulkano::DeviceSize;
+    use crate::{MassPoint, Particle, Vector};
+    use crate::world::{Integrator, World, G};
+    use crate::world::cpu::CPUWorld;
+    use crate::world::generate::random_particles;
+    use super::{DeviceSelection, GPUInitError, GPUWorld, BLOCK_ALIGNMENT};
+
+    /// the tests need a device vulkan can run on, and pass without checking anything when there is none
+    fn has_device() -> bool {
+        let has_device = !GPUWorld::list_devices().is_empty();
+        if !has_device {
+            eprintln!("skipping the gpu, there is no device vulkan can run on");
+        }
+        has_device
+    }
+
+    fn ticked<W: World>(mut world: W, frames: usize) -> Vec<MassPoint> {
+        for _ in 0..frames {
+            world.tick(20.0, NonZeroU16::new(20).unwrap());
+        }
+        world.get_mass_points()
+    }
+
+    fn distance((ax, ay): (f32, f32), (bx, by): (f32, f32)) -> f32 {
+        f32::sqrt((ax - bx).powi(2) + (ay - by).powi(2))
+    }
+
+    #[test]
+    fn device_selections_parse_back_from_what_they_print() {
+        let selections = [
+            DeviceSelection::First,
+            DeviceSelection::HighPerformance,
+            DeviceSelection::LowPower,
+            DeviceSelection::Index(2),
+            DeviceSelection::Name("GeForce".to_string())
+        ];
+        for selection in selections {
+            assert_eq!(selection.to_string().parse::<DeviceSelection>().unwrap(), selection);
+        }
+    }
+
+    #[test]
+    fn missing_devices_are_errors_rather_than_panics() {
+        let devices = GPUWorld::list_devices();
+        for selection in [DeviceSelection::Index(devices.len()), DeviceSelection::Name("no such device".to_string())] {
+            match GPUWorld::new_on(&selection, random_particles(23, 10, 500.0)) {
+                Err(GPUInitError::NotFound(missing, count)) => {
+                    assert_eq!(missing, selection);
+                    assert_eq!(count, devices.len());
+                },
+                // without vulkan there are no devices to select from at all
+                Err(GPUInitError::Unavailable(_)) => assert!(devices.is_empty()),
+                Err(error) => panic!("{} failed with {}", selection, error),
+                Ok(_) => panic!("{} selected a device", selection)
+            }
+        }
+    }
+
+    #[test]
+    fn every_listed_device_can_be_selected() {
+        if !has_device() {
+            return
+        }
+        for (index, device) in GPUWorld::list_devices().iter().enumerate() {
+            let world = GPUWorld::new_on(&DeviceSelection::Index(index), random_particles(23, 10, 500.0)).unwrap();
+            assert_eq!(world.device.physical_device().properties().device_name, device.name);
+        }
+    }
+
+    #[test]
+    fn tiled_accelerations_agree_with_the_cpu() {
+        if !has_device() {
+            return
+        }
+        // several tiles, the last of which isn't full
+        let particles = random_particles(23, 1000, 500.0);
+        let cpu = ticked(CPUWorld::new(particles.clone()), 10);
+        let gpu = ticked(GPUWorld::new(particles.clone()).unwrap(), 10);
+        for ((a, b), particle) in cpu.iter().zip(&gpu).zip(&particles) {
+            let moved = distance(particle.position.to_cartesian(), a.position);
+            let apart = distance(a.position, b.position);
+            // the shaders have their own trigonometry, so the trajectories part by more than the rounding of the sums
+            assert!(apart <= 0.01 * moved + 1e-5, "particle {} is {} apart after moving {}", a.id, apart, moved);
+        }
+    }
+
+    #[test]
+    fn doubling_g_doubles_the_accelerations() {
+        if !has_device() {
+            return
+        }
+        let particles = random_particles(23, 100, 500.0);
+        let accelerations_from_rest = |g: f32| {
+            let mut world = GPUWorld::new(particles.clone()).unwrap().with_g(g);
+            world.tick(1.0, NonZeroU16::new(1).unwrap());
+            world.get_mass_points()
+        };
+        for (single, double) in accelerations_from_rest(G).iter().zip(accelerations_from_rest(2.0 * G)) {
+            let (x, y) = single.velocity;
+            let (doubled_x, doubled_y) = double.velocity;
+            assert!(distance((2.0 * x, 2.0 * y), (doubled_x, doubled_y)) <= 1e-6 * f32::hypot(doubled_x, doubled_y));
+        }
+    }
+
+    #[test]
+    fn deterministic_summation_repeats_bit_for_bit() {
+        if !has_device() {
+            return
+        }
+        let particles = random_particles(23, 1000, 500.0);
+        let run = |single_submission| ticked(
+            GPUWorld::new(particles.clone()).unwrap()
+                .with_deterministic_summation(true)
+                .with_single_submission(single_submission),
+            50
+        );
+        let first = run(false);
+        assert_eq!(run(false), first);
+        assert_eq!(run(true), first);
+    }
+
+    #[test]
+    fn kept_resources_tick_like_fresh_worlds() {
+        if !has_device() {
+            return
+        }
+        let particles = random_particles(23, 200, 500.0);
+        let mut kept = GPUWorld::new(particles.clone()).unwrap().with_deterministic_summation(true);
+        let recorded = |world: &GPUWorld| world.tick_resources.as_ref().unwrap().tick_command_buffer.as_ref().unwrap().command_buffer.clone();
+        kept.tick(20.0, NonZeroU16::new(20).unwrap());
+        let first_recorded = recorded(&kept);
+        let mut fresh_particles = kept.get_particles();
+        for frame in 1..10 {
+            kept.tick(20.0, NonZeroU16::new(20).unwrap());
+            assert!(Arc::ptr_eq(&recorded(&kept), &first_recorded), "frame {} recorded its commands anew", frame);
+            // every frame on buffers and commands of its own
+            let mut fresh = GPUWorld::new(fresh_particles).unwrap().with_deterministic_summation(true);
+            fresh.tick(20.0, NonZeroU16::new(20).unwrap());
+            assert_eq!(kept.get_mass_points(), fresh.get_mass_points(), "frame {}", frame);
+            fresh_particles = fresh.get_particles();
+        }
+    }
+
+    #[test]
+    fn pipelined_ticks_read_back_every_frame_in_order() {
+        if !has_device() {
+            return
+        }
+        let particles = random_particles(23, 200, 500.0);
+        let world = || GPUWorld::new(particles.clone()).unwrap().with_deterministic_summation(true);
+        let mut serial = world();
+        let expected: Vec<Vec<MassPoint>> = (0..10)
+            .map(|_| {
+                serial.tick(20.0, NonZeroU16::new(20).unwrap());
+                serial.get_mass_points()
+            })
+            .collect();
+        let mut pipelined = world();
+        let mut frames = Vec::new();
+        for tick in 0..10 {
+            pipelined.tick_pipelined(20.0, NonZeroU16::new(20).unwrap());
+            let previous = pipelined.previous_mass_points();
+            // the first tick has no tick before it to read back
+            assert_eq!(previous.is_some(), tick > 0, "tick {}", tick);
+            frames.extend(previous);
+        }
+        frames.push(pipelined.get_mass_points());
+        assert_eq!(frames, expected);
+    }
+
+    #[test]
+    fn single_submissions_tick_like_a_submission_per_dispatch() {
+        if !has_device() {
+            return
+        }
+        // few enough particles that submitting is most of a tick
+        let particles = random_particles(23, 100, 500.0);
+        for integrator in [Integrator::Euler, Integrator::Verlet, Integrator::RK4, Integrator::Yoshida] {
+            let run = |single_submission| ticked(
+                GPUWorld::new(particles.clone()).unwrap()
+                    .with_integrator(integrator)
+                    .with_deterministic_summation(true)
+                    .with_single_submission(single_submission),
+                10
+            );
+            assert_eq!(run(true), run(false), "{:?}", integrator);
+        }
+    }
+
+    #[test]
+    fn small_binding_ranges_tick_like_a_single_block() {
+        if !has_device() {
+            return
+        }
+        let particles = random_particles(23, 1000, 500.0);
+        let single = ticked(GPUWorld::new(particles.clone()).unwrap(), 10);
+        // blocks of BLOCK_ALIGNMENT particles, the last of which isn't full
+        let range = (8 * BLOCK_ALIGNMENT * size_of::<Vector>()) as DeviceSize;
+        let blocked = GPUWorld::new(particles.clone()).unwrap().with_max_binding_range(range);
+        assert_eq!(blocked.block_length(), BLOCK_ALIGNMENT);
+        let blocked = ticked(blocked, 10);
+        for ((a, b), particle) in single.iter().zip(&blocked).zip(&particles) {
+            let moved = distance(particle.position.to_cartesian(), a.position);
+            // the blocks are summed in a different order
+            assert!(distance(a.position, b.position) <= 0.01 * moved + 1e-5, "particle {}", a.id);
+        }
+    }
+
+    #[test]
+    fn ticks_are_only_profiled_when_asked_to() {
+        if !has_device() {
+            return
+        }
+        let particles = random_particles(23, 100, 500.0);
+        let mut unprofiled = GPUWorld::new(particles.clone()).unwrap();
+        unprofiled.tick(20.0, NonZeroU16::new(20).unwrap());
+        assert_eq!(unprofiled.last_tick_profile(), None);
+
+        let mut profiled = GPUWorld::new(particles).unwrap().with_profiling(true);
+        profiled.tick(20.0, NonZeroU16::new(20).unwrap());
+        match (profiled.timestamp_period, profiled.last_tick_profile()) {
+            (Some(_), Some(profile)) => {
+                assert!(profile.acceleration > 0.0 && profile.integration > 0.0, "{:?}", profile);
+                assert_eq!(profile.copy, None);
+            },
+            // devices that can't write timestamps are never profiled
+            (None, None) => return,
+            (period, profile) => panic!("{:?} with a timestamp period of {:?}", profile, period)
+        }
+        // pipelined ticks copy into a staging buffer, which is timed as well
+        profiled.tick_pipelined(20.0, NonZeroU16::new(20).unwrap());
+        assert!(profiled.last_tick_profile().unwrap().copy.is_some());
+    }
+
+    /// what `packHalf2x16` of `half_source_compute_shader` packs, the first component in the low 16 bits
+    fn pack_half_2x16((x, y): (f32, f32)) -> u32 {
+        f16::from_f32(x).to_bits() as u32 | (f16::from_f32(y).to_bits() as u32) << 16
+    }
+
+    /// what `unpackHalf2x16` of `acceleration_compute_shader` unpacks
+    fn unpack_half_2x16(packed: u32) -> (f32, f32) {
+        (f16::from_bits(packed as u16).to_f32(), f16::from_bits((packed >> 16) as u16).to_f32())
+    }
+
+    #[test]
+    fn half_sources_pack_like_glsl() {
+        // 1.0 is 0x3c00 and -2.0 is 0xc000 in half precision
+        assert_eq!(pack_half_2x16((1.0, -2.0)), 0xc000_3c00);
+        assert_eq!(unpack_half_2x16(0xc000_3c00), (1.0, -2.0));
+        for value in [0.0, 1.0, -0.75, 0.1, 2.71, -123.456, 1000.5, 65504.0] {
+            let (x, y) = unpack_half_2x16(pack_half_2x16((value, -value)));
+            // 11 significant bits
+            let tolerance = value.abs() / 2048.0;
+            assert!((x - value).abs() <= tolerance && (y + value).abs() <= tolerance, "{} came back as {}, {}", value, x, y);
+        }
+        assert_eq!(unpack_half_2x16(pack_half_2x16((65520.0, 0.0))).0, f32::INFINITY);
+    }
+
+    /// the accelerations `acceleration_compute_shader` computes, with every position rounded to half precision when
+    /// `half_precision` is set
+    fn accelerations(particles: &[Particle], half_precision: bool) -> Vec<(f32, f32)> {
+        let position = |particle: &Particle| match half_precision {
+            true => unpack_half_2x16(pack_half_2x16(particle.position.to_cartesian())),
+            false => particle.position.to_cartesian()
+        };
+        particles.iter()
+            .map(|particle| {
+                let (x, y) = position(particle);
+                particles.iter().fold((0.0, 0.0), |(ax, ay), source| {
+                    let (sx, sy) = position(source);
+                    let (dx, dy) = (sx - x, sy - y);
+                    let distance_sq = dx * dx + dy * dy;
+                    if distance_sq == 0.0 {
+                        return (ax, ay)
+                    }
+                    let a = G * source.mass / distance_sq / distance_sq.sqrt();
+                    (ax + dx * a, ay + dy * a)
+                })
+            })
+            .collect()
+    }
+
+    /// the errors [`GPUWorld::with_half_precision_sources`] documents
+    #[test]
+    fn half_sources_stay_close_to_single_precision() {
+        let particles = random_particles(23, 2000, 500.0);
+        let mut errors: Vec<f32> = accelerations(&particles, false).into_iter()
+            .zip(accelerations(&particles, true))
+            .map(|((ax, ay), (bx, by))| distance((ax, ay), (bx, by)) / f32::sqrt(ax * ax + ay * ay))
+            .collect();
+        errors.sort_by(f32::total_cmp);
+        let (median, percentile_99) = (errors[errors.len() / 2], errors[errors.len() * 99 / 100]);
+        assert!(median < 0.02 && percentile_99 < 0.3, "median {}, 99th percentile {}", median, percentile_99);
+    }
+
+    #[test]
+    fn half_sources_follow_single_precision_for_20_frames() {
+        if !has_device() {
+            return
+        }
+        let particles = random_particles(23, 1000, 500.0);
+        let single = ticked(GPUWorld::new(particles.clone()).unwrap(), 20);
+        let half = ticked(GPUWorld::new(particles.clone()).unwrap().with_half_precision_sources(true), 20);
+        for ((a, b), particle) in single.iter().zip(&half).zip(&particles) {
+            let moved = distance(particle.position.to_cartesian(), a.position);
+            assert!(distance(a.position, b.position) <= 0.3 * moved + 1e-4, "particle {}", a.id);
+        }
+    }
+}
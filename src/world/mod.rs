@@ -1,23 +1,220 @@
+use std::f32::consts::PI;
+use std::num::NonZeroU16;
 use bytemuck::{Pod, Zeroable};
-use crate::vector::Vector;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use crate::vector::{Vector, Vector3, VectorF64};
+use crate::world::checkpoint::Checkpoint;
+use crate::world::force::ForceModel;
+use crate::world::gpu::GpuTickProfile;
+use crate::world::neighbors::Neighbors;
 
 pub mod cpu;
 pub mod par;
 pub mod gpu;
+pub mod barnes_hut;
+pub mod checkpoint;
+mod collision;
+pub mod diagnostics;
+mod escape;
+pub mod field;
+pub mod force;
+pub mod generate;
+pub mod neighbors;
+pub mod particle_file;
+pub mod presets;
+pub mod recorded;
+mod simd;
+#[cfg(feature = "serde")]
+pub mod snapshot;
+pub mod store;
+mod summation;
+pub mod trajectory;
+pub mod validation;
+
+/// gravitational constant in SI units, the default of every world
+pub const G: f32 = 6.67430e-11;
+
+/// [`G`] in double precision, the default of the `f64` worlds
+pub const G_F64: f64 = 6.67430e-11;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 #[repr(C)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MassPoint {
     pub mass: f32,
-    pub position: (f32, f32)
+    pub position: (f32, f32),
+    /// cartesian, like the position
+    pub velocity: (f32, f32),
+    /// see [`Particle::id`]
+    pub id: u32,
+    /// see [`Particle::group`]
+    pub group: u32
+}
+
+impl MassPoint {
+    /// magnitude of the velocity
+    pub fn speed(&self) -> f32 {
+        let (vx, vy) = self.velocity;
+        f32::sqrt(vx * vx + vy * vy)
+    }
+}
+
+impl From<Particle> for MassPoint {
+    fn from(particle: Particle) -> Self {
+        Self {
+            mass: particle.mass,
+            position: particle.position.to_cartesian(),
+            velocity: particle.velocity.to_cartesian(),
+            id: particle.id,
+            group: particle.group
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, Zeroable, Pod)]
 #[repr(C)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Particle {
     pub mass: f32,
     pub position: Vector,
-    pub velocity: Vector
+    pub velocity: Vector,
+    /// radius in world units, only used for collisions
+    pub radius: f32,
+    /// 1 if the particle is pinned in place, it still pulls on other particles but never moves itself,
+    /// 0 otherwise (not a bool so that particles can be copied to the GPU as they are)
+    pub fixed: u32,
+    /// identifies the particle across ticks, even as others are removed or merged, given by the world
+    /// when the particle is added to it, in the order they are added starting from 0, overwriting whatever it was
+    ///
+    /// two merging particles keep the id of the fixed one if either is fixed, otherwise of the first one
+    pub id: u32,
+    /// group the particle belongs to, such as the cluster it started in, only used to tell particles apart
+    /// when drawing them, merging particles keep the group of the particle whose id they keep
+    pub group: u32
+}
+
+impl Particle {
+    pub fn is_fixed(&self) -> bool {
+        self.fixed != 0
+    }
+}
+
+/// gives `particles` the ids `0..particles.len()`, see [`Particle::id`]
+pub(crate) fn assign_ids(particles: &mut [Particle]) {
+    for (id, particle) in particles.iter_mut().enumerate() {
+        particle.id = id as u32;
+    }
+}
+
+/// a two dimensional world of [`Particle`]s, implemented by [`CPUWorld`](cpu::CPUWorld),
+/// [`ParWorld`](par::ParWorld) and [`GPUWorld`](gpu::GPUWorld)
+pub trait World {
+    /// simulates `time` in `steps` substeps, returns the number of substeps actually taken
+    fn tick(&mut self, time: f32, steps: NonZeroU16) -> u16;
+
+    /// [`tick`](Self::tick), calling `observer` with every particle after each substep
+    fn tick_with_observer<O: FnMut(&[Particle])>(&mut self, time: f32, steps: NonZeroU16, observer: O) -> u16;
+
+    /// runs the world backwards by `time`, returns the number of substeps taken
+    fn tick_reverse(&mut self, time: f32, steps: NonZeroU16) -> u16;
+
+    fn get_mass_points(&self) -> Vec<MassPoint>;
+
+    /// every particle with its velocity, in the same order as [`get_mass_points`](Self::get_mass_points)
+    fn get_particles(&self) -> Vec<Particle>;
+
+    /// kinetic plus potential energy of every particle
+    fn total_energy(&self) -> f32;
+
+    /// adds a particle to the world, it is pulled on and pulls from the next tick on,
+    /// returns the id it was given
+    fn add_particle(&mut self, particle: Particle) -> u32;
+
+    /// removes the particle at `index` and returns it, the last particle takes its index
+    fn remove_particle(&mut self, index: usize) -> Particle;
+
+    /// the state of this world after `frame` frames of `time` in `steps` substeps each
+    fn checkpoint(&self, frame: usize, time: f32, steps: NonZeroU16) -> Checkpoint;
+
+    /// replaces the particles of this world with those of `checkpoint`, the settings of this world are kept
+    fn resume(&mut self, checkpoint: &Checkpoint);
+
+    /// indices of every particle at most `r` from `(x, y)`, in ascending order, see [`Neighbors::within`]
+    fn neighbors_within(&self, x: f32, y: f32, r: f32) -> Vec<usize> {
+        Neighbors::new(&self.get_mass_points()).within(x, y, r)
+    }
+
+    /// index of the particle closest to particle `i`, see [`Neighbors::nearest`]
+    fn nearest(&self, i: usize) -> Option<usize> {
+        Neighbors::new(&self.get_mass_points()).nearest(i)
+    }
+
+    /// how long each pass of the last tick took on the GPU, see [`GPUWorld::last_tick_profile`](gpu::GPUWorld::last_tick_profile),
+    /// always `None` for worlds that don't run on the GPU
+    fn last_tick_profile(&self) -> Option<GpuTickProfile> {
+        None
+    }
+}
+
+/// three dimensional counterpart of [`MassPoint`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(C)]
+pub struct MassPoint3 {
+    pub mass: f32,
+    pub position: (f32, f32, f32),
+    pub velocity: (f32, f32, f32),
+    /// index of the particle, three dimensional worlds never remove or reorder particles
+    pub id: u32
+}
+
+impl MassPoint3 {
+    /// orthographic projection onto the xy plane, looking down the z axis, in group 0
+    pub fn orthographic(&self) -> MassPoint {
+        let (x, y, _) = self.position;
+        let (vx, vy, _) = self.velocity;
+        MassPoint {
+            mass: self.mass,
+            position: (x, y),
+            velocity: (vx, vy),
+            id: self.id,
+            group: 0
+        }
+    }
+}
+
+/// double precision counterpart of [`Particle`], without a radius as the `f64` worlds have no collisions
+#[derive(Copy, Clone, Debug)]
+pub struct ParticleF64 {
+    pub mass: f64,
+    pub position: VectorF64,
+    pub velocity: VectorF64,
+    /// see [`Particle::fixed`]
+    pub fixed: u32
+}
+
+impl From<Particle> for ParticleF64 {
+    fn from(particle: Particle) -> Self {
+        Self {
+            mass: particle.mass as f64,
+            position: particle.position.into(),
+            velocity: particle.velocity.into(),
+            fixed: particle.fixed
+        }
+    }
+}
+
+/// three dimensional counterpart of [`Particle`]
+#[derive(Copy, Clone, Debug, Zeroable, Pod)]
+#[repr(C)]
+pub struct Particle3 {
+    pub mass: f32,
+    pub position: Vector3,
+    pub velocity: Vector3,
+    /// radius in world units, only used for collisions
+    pub radius: f32,
+    /// see [`Particle::fixed`]
+    pub fixed: u32
 }
 
 #[derive(Default, Copy, Clone, Debug, Zeroable, Pod)]
@@ -26,3 +223,783 @@ struct ForceDirection {
     force: f32,
     direction: f32
 }
+
+/// how touching particles interact, two particles touch when their distance is less than the sum of their radii
+#[derive(Default, Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Collisions {
+    /// particles pass through each other
+    #[default]
+    None,
+    /// touching particles merge into a single particle, conserving mass, momentum and volume
+    Merge,
+    /// touching particles bounce off of each other and are pushed apart until they no longer overlap
+    ///
+    /// `restitution` is the ratio of the relative speed along the collision normal after and before
+    /// the collision, 1.0 is perfectly elastic and 0.0 is perfectly inelastic
+    Elastic {
+        restitution: f32
+    }
+}
+
+/// when a particle has left the system for good and is removed from the world, fixed particles never escape
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Escape {
+    /// the particle is farther than this from the center of mass
+    Radius(f32),
+    /// the kinetic energy of the particle, relative to the center of mass, outweighs its potential energy
+    /// with every other particle and the fields of the world
+    Unbound
+}
+
+/// shape of the space particles move in
+#[derive(Default, Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Boundary {
+    /// particles may move anywhere
+    #[default]
+    Open,
+    /// a `width` by `height` rectangle centered on the origin whose opposite edges are joined,
+    /// particles leaving one edge re-enter on the other, and forces act across the edges
+    /// following the minimum image convention (each particle only pulls with its closest image)
+    Periodic {
+        width: f32,
+        height: f32
+    },
+    /// a `width` by `height` rectangle centered on the origin with walls at its edges, particles crossing a wall
+    /// are put back onto it and bounce off, keeping `restitution` times the velocity perpendicular to the wall
+    Reflective {
+        width: f32,
+        height: f32,
+        restitution: f32
+    }
+}
+
+impl Boundary {
+    /// moves particles outside of the domain back inside, particles inside and fixed particles are left untouched
+    pub fn apply(&self, particles: &mut [Particle]) {
+        if *self == Boundary::Open {
+            return
+        }
+        for particle in particles {
+            if !particle.is_fixed() {
+                self.apply_to(&mut particle.position, &mut particle.velocity);
+            }
+        }
+    }
+
+    /// [`apply`](Self::apply) for a single moving particle
+    pub(crate) fn apply_to(&self, position: &mut Vector, velocity: &mut Vector) {
+        match *self {
+            Boundary::Open => {}
+            Boundary::Periodic { width, height } => {
+                let (x, y) = position.to_cartesian();
+                let wrapped_x = wrap(x, width);
+                let wrapped_y = wrap(y, height);
+                if wrapped_x != x || wrapped_y != y {
+                    *position = Vector::from_cartesian(wrapped_x, wrapped_y);
+                }
+            }
+            Boundary::Reflective { width, height, restitution } => {
+                let (mut x, mut y) = position.to_cartesian();
+                let (mut vx, mut vy) = velocity.to_cartesian();
+                // both walls of a corner can be hit in the same substep
+                let bounced_x = bounce(&mut x, &mut vx, width / 2.0, restitution);
+                let bounced_y = bounce(&mut y, &mut vy, height / 2.0, restitution);
+                if bounced_x || bounced_y {
+                    *position = Vector::from_cartesian(x, y);
+                    *velocity = Vector::from_cartesian(vx, vy);
+                }
+            }
+        }
+    }
+
+    /// shortest displacement `(dx, dy)` between two points, given their plain difference
+    #[inline(always)]
+    pub fn displacement(&self, (dx, dy): (f32, f32)) -> (f32, f32) {
+        match *self {
+            Boundary::Open | Boundary::Reflective { .. } => (dx, dy),
+            Boundary::Periodic { width, height } => (dx - width * f32::round(dx / width), dy - height * f32::round(dy / height))
+        }
+    }
+
+    /// squared distance between two positions, and the direction from `a` to `b`
+    #[inline(always)]
+    pub fn separation(&self, a: &Vector, b: &Vector) -> (f32, f32) {
+        let (x1, y1) = a.to_cartesian();
+        let (x2, y2) = b.to_cartesian();
+        match *self {
+            Boundary::Open | Boundary::Reflective { .. } => (Vector::distance_sq(a, b), f32::atan2(y2 - y1, x2 - x1)),
+            Boundary::Periodic { .. } => {
+                let (dx, dy) = self.displacement((x2 - x1, y2 - y1));
+                (dx * dx + dy * dy, f32::atan2(dy, dx))
+            }
+        }
+    }
+}
+
+/// wraps `v` into `-size / 2.0..size / 2.0`, values already inside are returned unchanged
+fn wrap(v: f32, size: f32) -> f32 {
+    let half_size = size / 2.0;
+    if v < -half_size || v >= half_size {
+        let wrapped = v - size * f32::floor(v / size + 0.5);
+        // rounding can land exactly on the upper edge
+        if wrapped >= half_size { wrapped - size } else { wrapped }
+    } else {
+        v
+    }
+}
+
+/// puts `x` back onto the wall it crossed, if any, and points `v` away from it, true if it did
+fn bounce(x: &mut f32, v: &mut f32, half_size: f32, restitution: f32) -> bool {
+    if *x < -half_size {
+        *x = -half_size;
+        *v = f32::abs(*v) * restitution;
+        true
+    } else if *x > half_size {
+        *x = half_size;
+        *v = -f32::abs(*v) * restitution;
+        true
+    } else {
+        false
+    }
+}
+
+/// applies linear drag `-drag * v` to every moving particle at the end of a substep of length `time`,
+/// velocities are scaled by `1 - drag * time`, clamped so that they can stop but never reverse
+pub(crate) fn damp(particles: &mut [Particle], drag: f32, time: f32) {
+    if let Some(factor) = drag_factor(drag, time) {
+        for particle in particles {
+            if !particle.is_fixed() {
+                particle.velocity = particle.velocity.scale(factor);
+            }
+        }
+    }
+}
+
+/// what [`damp`] scales velocities by, `None` without drag
+pub(crate) fn drag_factor(drag: f32, time: f32) -> Option<f32> {
+    (drag != 0.0).then(|| f32::max(0.0, 1.0 - drag * time))
+}
+
+/// radius of a sphere of the given mass with a density of 1
+pub fn radius(mass: f32) -> f32 {
+    f32::cbrt(3.0 * mass / (4.0 * PI))
+}
+
+/// subdivides each tick into substeps sized to the fastest interaction between two particles,
+/// instead of a fixed number of equal substeps
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AdaptiveSteps {
+    /// fraction of the [`shortest_timescale`] used as the length of a substep
+    pub fraction: f32,
+    /// a tick is never split into more substeps than this, which also bounds how short a substep can be
+    pub max_steps: NonZeroU16
+}
+
+impl AdaptiveSteps {
+    /// length of the next substep of a tick of length `time`, of which `remaining` is left after `taken` substeps
+    pub fn substep(&self, timescale: f32, time: f32, remaining: f32, taken: u16) -> f32 {
+        if taken + 1 >= self.max_steps.get() {
+            return remaining
+        }
+        let substep = f32::max(self.fraction * timescale, time / self.max_steps.get() as f32);
+        f32::min(substep, remaining)
+    }
+}
+
+/// shortest free-fall or crossing time between any two particles, infinite if there are less than two
+///
+/// the free-fall time of a pair is `sqrt(r / a)` with `a` the acceleration of the particles towards each other,
+/// which is `sqrt(r^3 / (G * (m1 + m2)))` for gravity, the crossing time is `r / v` with `v` their relative speed,
+/// `r` includes the softening length
+pub fn shortest_timescale<F: ForceModel>(particles: &[Particle], force_model: &F, softening: f32) -> f32 {
+    let mut shortest = f32::INFINITY;
+    for i in 0..particles.len() {
+        for j in i + 1..particles.len() {
+            shortest = f32::min(shortest, timescale(&particles[i], &particles[j], force_model, softening));
+        }
+    }
+    shortest
+}
+
+/// see [`shortest_timescale`]
+pub fn timescale<F: ForceModel>(a: &Particle, b: &Particle, force_model: &F, softening: f32) -> f32 {
+    if a.is_fixed() && b.is_fixed() {
+        return f32::INFINITY
+    }
+    let r_sq = Vector::distance_sq(&a.position, &b.position) + softening * softening;
+    let r = f32::sqrt(r_sq);
+    let acceleration = force_model.acceleration(a.mass, b.mass, r_sq) + force_model.acceleration(b.mass, a.mass, r_sq);
+    let free_fall = f32::sqrt(r / f32::abs(acceleration));
+    let (vx1, vy1) = a.velocity.to_cartesian();
+    let (vx2, vy2) = b.velocity.to_cartesian();
+    let (dvx, dvy) = (vx2 - vx1, vy2 - vy1);
+    let crossing = r / f32::sqrt(dvx * dvx + dvy * dvy);
+    f32::min(free_fall, crossing)
+}
+
+/// numerical integration scheme used to advance the particles of a world by one substep
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Integrator {
+    /// semi-implicit Euler, the velocity is stepped first and the position is then stepped with the new velocity
+    ///
+    /// total energy drifts by under 0.1% over 50 frames of the seed 23 particles with a softening of 0.01,
+    /// without softening close encounters can make it drift by more than its own magnitude
+    #[default]
+    Euler,
+    /// velocity Verlet (kick-drift-kick)
+    ///
+    /// the accelerations at the end of a substep are reused for the start of the next one,
+    /// so forces are only computed once per substep (plus once at the start of each tick)
+    Verlet,
+    /// classic fourth order Runge-Kutta, forces are computed four times per substep
+    RK4,
+    /// fourth order symplectic integrator by Yoshida, three velocity Verlet steps of
+    /// [`YOSHIDA_WEIGHTS`] times the substep, the middle one going backwards in time
+    ///
+    /// forces are computed three times per substep, like Verlet the energy error stays within a band
+    /// instead of drifting, while positions are fourth order accurate
+    Yoshida
+}
+
+/// fractions of a substep taken by each of the Verlet steps of [`Integrator::Yoshida`], they sum to 1
+pub const YOSHIDA_WEIGHTS: [f32; 3] = [
+    // 1 / (2 - 2^(1/3))
+    1.3512072,
+    // -2^(1/3) / (2 - 2^(1/3))
+    -1.7024144,
+    1.3512072
+];
+
+impl Integrator {
+    /// advances `particles` by `steps` substeps of `stepped_time` each
+    ///
+    /// `accelerations` must return the acceleration acting on each particle of the given state,
+    /// indexed the same as the state
+    ///
+    /// `after_substep` is called after every substep, and may modify, add or remove particles,
+    /// it must return true if it did so, which discards any accelerations cached between substeps
+    pub fn integrate<B, A, S>(self, particles: &mut B, stepped_time: f32, steps: NonZeroU16, mut accelerations: A, mut after_substep: S)
+        where B: Bodies, A: FnMut(&B) -> Vec<B::Vector>, S: FnMut(&mut B) -> bool {
+        match self {
+            Integrator::Euler => {
+                for _ in 0..steps.get() {
+                    let accelerations = accelerations(particles);
+                    kick_drift(particles, &accelerations, stepped_time, stepped_time);
+                    after_substep(particles);
+                }
+            }
+            Integrator::Verlet => {
+                let half_time = stepped_time / 2.0;
+                let mut current_accelerations = accelerations(particles);
+                for _ in 0..steps.get() {
+                    kick_drift(particles, &current_accelerations, half_time, stepped_time);
+                    current_accelerations = accelerations(particles);
+                    kick(particles, &current_accelerations, half_time);
+                    if after_substep(particles) {
+                        current_accelerations = accelerations(particles);
+                    }
+                }
+            }
+            Integrator::RK4 => {
+                let half_time = stepped_time / 2.0;
+                let third_time = stepped_time / 3.0;
+                let sixth_time = stepped_time / 6.0;
+                let mut stage = particles.clone();
+                for _ in 0..steps.get() {
+                    // the position derivative of each stage is the velocity of that stage
+                    let k1_velocities = velocities(particles);
+                    let k1_accelerations = accelerations(particles);
+                    rk4_stage(particles, &mut stage, &k1_velocities, &k1_accelerations, half_time);
+                    let k2_velocities = velocities(&stage);
+                    let k2_accelerations = accelerations(&stage);
+                    rk4_stage(particles, &mut stage, &k2_velocities, &k2_accelerations, half_time);
+                    let k3_velocities = velocities(&stage);
+                    let k3_accelerations = accelerations(&stage);
+                    rk4_stage(particles, &mut stage, &k3_velocities, &k3_accelerations, stepped_time);
+                    let k4_accelerations = accelerations(&stage);
+                    for i in 0..particles.len() {
+                        if particles.is_fixed(i) {
+                            continue
+                        }
+                        particles.drift(i, &k1_velocities[i], sixth_time);
+                        particles.drift(i, &k2_velocities[i], third_time);
+                        particles.drift(i, &k3_velocities[i], third_time);
+                        particles.drift(i, &stage.velocity(i), sixth_time);
+                        particles.kick(i, &k1_accelerations[i], sixth_time);
+                        particles.kick(i, &k2_accelerations[i], third_time);
+                        particles.kick(i, &k3_accelerations[i], third_time);
+                        particles.kick(i, &k4_accelerations[i], sixth_time);
+                    }
+                    // every stage starts over from a copy of the particles, so it doesn't matter if they changed
+                    after_substep(particles);
+                }
+            }
+            Integrator::Yoshida => {
+                let mut current_accelerations = accelerations(particles);
+                for _ in 0..steps.get() {
+                    for weight in YOSHIDA_WEIGHTS {
+                        let weighted_time = stepped_time * weight;
+                        kick_drift(particles, &current_accelerations, weighted_time / 2.0, weighted_time);
+                        current_accelerations = accelerations(particles);
+                        kick(particles, &current_accelerations, weighted_time / 2.0);
+                    }
+                    if after_substep(particles) {
+                        current_accelerations = accelerations(particles);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// steps the velocity of each moving particle by its acceleration over `kick_time`,
+/// then its position by the new velocity over `drift_time`
+fn kick_drift<B: Bodies>(particles: &mut B, accelerations: &[B::Vector], kick_time: f32, drift_time: f32) {
+    for (i, acceleration) in accelerations.iter().enumerate() {
+        if particles.is_fixed(i) {
+            continue
+        }
+        particles.kick(i, acceleration, kick_time);
+        particles.drift(i, &particles.velocity(i), drift_time);
+    }
+}
+
+/// steps the velocity of each moving particle by its acceleration over `time`
+fn kick<B: Bodies>(particles: &mut B, accelerations: &[B::Vector], time: f32) {
+    for (i, acceleration) in accelerations.iter().enumerate() {
+        if particles.is_fixed(i) {
+            continue
+        }
+        particles.kick(i, acceleration, time);
+    }
+}
+
+fn velocities<B: Bodies>(particles: &B) -> Vec<B::Vector> {
+    (0..particles.len()).map(|i| particles.velocity(i)).collect()
+}
+
+/// every particle of a world, in a form an [`Integrator`] can advance, a `Vec` of [`Body`]s or the columns of a
+/// [`ParticleStore`](store::ParticleStore)
+pub trait Bodies: Clone {
+    type Vector: Copy;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn velocity(&self, i: usize) -> Self::Vector;
+
+    fn is_fixed(&self, i: usize) -> bool;
+
+    /// steps the velocity of particle `i` by `acceleration` over `time`
+    fn kick(&mut self, i: usize, acceleration: &Self::Vector, time: f32);
+
+    /// steps the position of particle `i` by `velocity` over `time`
+    fn drift(&mut self, i: usize, velocity: &Self::Vector, time: f32);
+}
+
+impl<P: Body> Bodies for Vec<P> {
+    type Vector = P::Vector;
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn velocity(&self, i: usize) -> P::Vector {
+        self[i].velocity()
+    }
+
+    fn is_fixed(&self, i: usize) -> bool {
+        self[i].is_fixed()
+    }
+
+    fn kick(&mut self, i: usize, acceleration: &P::Vector, time: f32) {
+        self[i].kick(acceleration, time);
+    }
+
+    fn drift(&mut self, i: usize, velocity: &P::Vector, time: f32) {
+        self[i].drift(velocity, time);
+    }
+}
+
+/// state of a single particle that an [`Integrator`] can advance
+pub trait Body: Copy {
+    type Vector: Copy;
+
+    fn velocity(&self) -> Self::Vector;
+
+    fn is_fixed(&self) -> bool;
+
+    /// steps the velocity by `acceleration` over `time`
+    fn kick(&mut self, acceleration: &Self::Vector, time: f32);
+
+    /// steps the position by `velocity` over `time`
+    fn drift(&mut self, velocity: &Self::Vector, time: f32);
+}
+
+impl Body for Particle {
+    type Vector = Vector;
+
+    fn velocity(&self) -> Vector {
+        self.velocity
+    }
+
+    fn is_fixed(&self) -> bool {
+        self.fixed != 0
+    }
+
+    fn kick(&mut self, acceleration: &Vector, time: f32) {
+        self.velocity.step(acceleration, time);
+    }
+
+    fn drift(&mut self, velocity: &Vector, time: f32) {
+        self.position.step(velocity, time);
+    }
+}
+
+impl Body for Particle3 {
+    type Vector = Vector3;
+
+    fn velocity(&self) -> Vector3 {
+        self.velocity
+    }
+
+    fn is_fixed(&self) -> bool {
+        self.fixed != 0
+    }
+
+    fn kick(&mut self, acceleration: &Vector3, time: f32) {
+        self.velocity.step(acceleration, time);
+    }
+
+    fn drift(&mut self, velocity: &Vector3, time: f32) {
+        self.position.step(velocity, time);
+    }
+}
+
+impl Body for ParticleF64 {
+    type Vector = VectorF64;
+
+    fn velocity(&self) -> VectorF64 {
+        self.velocity
+    }
+
+    fn is_fixed(&self) -> bool {
+        self.fixed != 0
+    }
+
+    fn kick(&mut self, acceleration: &VectorF64, time: f32) {
+        self.velocity.step(acceleration, time as f64);
+    }
+
+    fn drift(&mut self, velocity: &VectorF64, time: f32) {
+        self.position.step(velocity, time as f64);
+    }
+}
+
+/// sets `stage` to `particles` stepped by the given derivatives over `time`
+fn rk4_stage<B: Bodies>(particles: &B, stage: &mut B, velocities: &[B::Vector], accelerations: &[B::Vector], time: f32) {
+    stage.clone_from(particles);
+    for i in 0..stage.len() {
+        if stage.is_fixed(i) {
+            continue
+        }
+        stage.drift(i, &velocities[i], time);
+        stage.kick(i, &accelerations[i], time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_1_SQRT_2, PI};
+    use std::num::NonZeroU16;
+    use crate::{Particle, Vector};
+    use crate::vector::Vector3;
+    use super::{radius, Boundary, Integrator, MassPoint3, Particle3, World};
+    use super::cpu::{CPUWorld, CPUWorld3};
+    use super::generate::random_particles;
+    use super::gpu::GPUWorld;
+    use super::par::{ParWorld, ParWorld3};
+
+    #[test]
+    fn radius_of_unit_density_sphere() {
+        // a sphere of radius 1 has a volume of 4/3 pi
+        assert!((radius(4.0 / 3.0 * PI) - 1.0).abs() < 1e-6);
+        assert!((radius(4.0 / 3.0 * PI * 8.0) - 2.0).abs() < 1e-6);
+        assert_eq!(radius(0.0), 0.0);
+    }
+
+    /// two particles of mass 1 a distance of 1 apart on a circular orbit in the xz plane, under a `g` of 1
+    fn xz_orbit() -> Vec<Particle3> {
+        // each goes around the center at 0.5, which takes a speed of sqrt(g * m / (2 * d))
+        let speed = FRAC_1_SQRT_2;
+        let particle = |x: f32, vz: f32| Particle3 {
+            mass: 1.0,
+            position: Vector3::new(x, 0.0, 0.0),
+            velocity: Vector3::new(0.0, 0.0, vz),
+            radius: 0.0,
+            fixed: 0
+        };
+        vec![particle(0.5, speed), particle(-0.5, -speed)]
+    }
+
+    /// follows the first particle of an [`xz_orbit`] through a period in 40 frames, looked at down the z axis it
+    /// goes back and forth along the x axis between -0.5 and 0.5
+    fn assert_orbits_in_xz(mut tick: impl FnMut(f32, NonZeroU16) -> Vec<MassPoint3>) {
+        let period = PI / FRAC_1_SQRT_2;
+        let mut xs = Vec::new();
+        for _ in 0..40 {
+            let mass_points = tick(period / 40.0, NonZeroU16::new(50).unwrap());
+            let (x, _, z) = mass_points[0].position;
+            assert!((f32::hypot(x, z) - 0.5).abs() < 1e-3, "{:?}", mass_points[0]);
+            assert_eq!(mass_points[0].orthographic().position, (x, 0.0));
+            xs.push(x);
+        }
+        // a quarter, half, three quarters and all of the way around
+        for (frame, x) in [(9, 0.0), (19, -0.5), (29, 0.0), (39, 0.5)] {
+            assert!((xs[frame] - x).abs() < 1e-3, "frame {} is at {}", frame, xs[frame]);
+        }
+    }
+
+    #[test]
+    fn xz_orbits_project_onto_the_x_axis() {
+        let mut cpu = CPUWorld3::new(xz_orbit()).with_integrator(Integrator::Verlet).with_g(1.0);
+        assert_orbits_in_xz(|time, steps| {
+            cpu.tick(time, steps);
+            cpu.get_mass_points()
+        });
+        let mut par = ParWorld3::new(xz_orbit()).with_integrator(Integrator::Verlet).with_g(1.0);
+        assert_orbits_in_xz(|time, steps| {
+            par.tick(time, steps);
+            par.get_mass_points()
+        });
+    }
+
+    /// a particle of mass 1 at `(x, y)` moving at `(vx, vy)`
+    fn particle((x, y): (f32, f32), (vx, vy): (f32, f32)) -> Particle {
+        Particle {
+            mass: 1.0,
+            position: Vector::from_cartesian(x, y),
+            velocity: Vector::from_cartesian(vx, vy),
+            radius: 0.0,
+            fixed: 0,
+            id: 0,
+            group: 0
+        }
+    }
+
+    /// a particle alone in the world, moving along x at 1
+    fn lone_particle() -> Vec<Particle> {
+        vec![particle((0.0, 0.0), (1.0, 0.0))]
+    }
+
+    /// velocity of a [`lone_particle`] after a tick of `time` in 1000 substeps
+    fn dragged(mut world: impl World, time: f32) -> (f32, f32) {
+        world.tick(time, NonZeroU16::new(1000).unwrap());
+        world.get_mass_points()[0].velocity
+    }
+
+    /// a drag of 0.5 slows the particle down to `exp(-0.5 * t)` of its speed, a drag of 1000 stops it within the
+    /// first substep without turning it around, and no drag leaves it alone, `dragged` ticks a world of the particle
+    /// with a drag for a time
+    fn assert_drags(dragged: impl Fn(f32, f32) -> (f32, f32)) {
+        for time in [1.0, 2.0, 4.0] {
+            let (vx, vy) = dragged(0.5, time);
+            assert!((vx - f32::exp(-0.5 * time)).abs() < 1e-3 && vy.abs() < 1e-6, "{:?} after {}", (vx, vy), time);
+        }
+        assert_eq!(dragged(1000.0, 2.0), (0.0, 0.0));
+        assert_eq!(dragged(0.0, 2.0), (1.0, 0.0));
+    }
+
+    #[test]
+    fn drag_slows_particles_exponentially() {
+        assert_drags(|drag, time| dragged(CPUWorld::new(lone_particle()).with_drag(drag), time));
+        assert_drags(|drag, time| dragged(ParWorld::new(lone_particle()).with_drag(drag), time));
+        if GPUWorld::list_devices().is_empty() {
+            eprintln!("skipping the gpu, there is no device vulkan can run on");
+        } else {
+            assert_drags(|drag, time| dragged(GPUWorld::new(lone_particle()).unwrap().with_drag(drag), time));
+        }
+    }
+
+    /// positions of the particles of `world` after a tick of `time` in a single substep
+    fn ticked(mut world: impl World, time: f32) -> Vec<(f32, f32)> {
+        world.tick(time, NonZeroU16::new(1).unwrap());
+        world.get_mass_points().iter().map(|mass_point| mass_point.position).collect()
+    }
+
+    /// in a periodic 2 by 2 domain, a particle at 0.9 moving right at 1 comes back in on the left at -0.9 after 0.2,
+    /// and two resting particles at -0.95 and 0.95 are pulled towards each other across the seam rather than through
+    /// the middle, `ticked` ticks a world of particles for a time
+    fn assert_wraps(ticked: impl Fn(Vec<Particle>, f32) -> Vec<(f32, f32)>) {
+        let (x, y) = ticked(vec![particle((0.9, 0.5), (1.0, 0.0))], 0.2)[0];
+        assert!((x + 0.9).abs() < 1e-5 && (y - 0.5).abs() < 1e-5, "{:?}", (x, y));
+        // 0.1 apart across the seam, each moves by g * m / r^2 * t^2 = 0.01 in a substep of 0.01
+        let positions = ticked(vec![particle((-0.95, 0.0), (0.0, 0.0)), particle((0.95, 0.0), (0.0, 0.0))], 0.01);
+        assert!((positions[0].0 + 0.96).abs() < 1e-4, "{:?}", positions);
+        assert!((positions[1].0 - 0.96).abs() < 1e-4, "{:?}", positions);
+    }
+
+    #[test]
+    fn periodic_boundaries_wrap_positions_and_forces() {
+        let boundary = Boundary::Periodic { width: 2.0, height: 2.0 };
+        assert_wraps(|particles, time| ticked(CPUWorld::new(particles).with_g(1.0).with_boundary(boundary), time));
+        assert_wraps(|particles, time| ticked(ParWorld::new(particles).with_g(1.0).with_boundary(boundary), time));
+        if GPUWorld::list_devices().is_empty() {
+            eprintln!("skipping the gpu, there is no device vulkan can run on");
+        } else {
+            assert_wraps(|particles, time| ticked(GPUWorld::new(particles).unwrap().with_g(1.0).with_boundary(boundary), time));
+        }
+    }
+
+    /// particles fired at the walls of a 2 by 2 box, which they hit in the first of two substeps, `bounced` ticks a
+    /// world of a particle in a box as [`bounced`] does
+    fn assert_bounces(bounced: impl Fn(Vec<Particle>, Boundary) -> ((f32, f32), (f32, f32))) {
+        let assert_near = |(x, y): (f32, f32), (expected_x, expected_y): (f32, f32)| {
+            assert!((x - expected_x).abs() < 1e-5 && (y - expected_y).abs() < 1e-5, "{:?} isn't {:?}", (x, y), (expected_x, expected_y));
+        };
+        // put back onto the wall at 1, then heading back at 1
+        let (position, velocity) = bounced(
+            vec![particle((0.9, 0.5), (1.0, 0.0))],
+            Boundary::Reflective { width: 2.0, height: 2.0, restitution: 1.0 }
+        );
+        assert_near(position, (0.8, 0.5));
+        assert_near(velocity, (-1.0, 0.0));
+        // half as fast
+        let (position, velocity) = bounced(
+            vec![particle((0.5, -0.9), (0.0, -1.0))],
+            Boundary::Reflective { width: 2.0, height: 2.0, restitution: 0.5 }
+        );
+        assert_near(position, (0.5, -0.9));
+        assert_near(velocity, (0.0, 0.5));
+        // both walls of a corner
+        let (position, velocity) = bounced(
+            vec![particle((-0.9, 0.9), (-1.0, 1.0))],
+            Boundary::Reflective { width: 2.0, height: 2.0, restitution: 1.0 }
+        );
+        assert_near(position, (-0.8, 0.8));
+        assert_near(velocity, (1.0, -1.0));
+    }
+
+    /// the position and velocity of the only particle of `world` after a tick of 0.4 in two substeps
+    fn bounced(mut world: impl World) -> ((f32, f32), (f32, f32)) {
+        world.tick(0.4, NonZeroU16::new(2).unwrap());
+        let mass_point = world.get_mass_points()[0];
+        (mass_point.position, mass_point.velocity)
+    }
+
+    #[test]
+    fn reflective_walls_bounce_particles_back() {
+        assert_bounces(|particles, boundary| bounced(CPUWorld::new(particles).with_boundary(boundary)));
+        assert_bounces(|particles, boundary| bounced(ParWorld::new(particles).with_boundary(boundary)));
+        if GPUWorld::list_devices().is_empty() {
+            eprintln!("skipping the gpu, there is no device vulkan can run on");
+        } else {
+            assert_bounces(|particles, boundary| bounced(GPUWorld::new(particles).unwrap().with_boundary(boundary)));
+        }
+    }
+
+    /// two light particles passing at a height of 1 and -1 at 0.1, under a `g` of 1
+    fn passing_pair() -> Vec<Particle> {
+        [1.0, -1.0].map(|y| Particle { mass: 1e-6, ..particle((0.0, y), (0.1, 0.0)) }).to_vec()
+    }
+
+    /// positions of a [`passing_pair`] after 20 frames of 0.1, a star of mass 1 at (1.5, 0) being added after
+    /// the first 10 when `star` is set, `world` makes a world of particles
+    fn passed<W: World>(world: impl Fn(Vec<Particle>) -> W, star: bool) -> Vec<(f32, f32)> {
+        let mut world = world(passing_pair());
+        for _ in 0..10 {
+            world.tick(0.1, NonZeroU16::new(10).unwrap());
+        }
+        if star {
+            let id = world.add_particle(particle((1.5, 0.0), (0.0, 0.0)));
+            let mass_points = world.get_mass_points();
+            assert_eq!(mass_points.len(), 3);
+            assert_eq!((mass_points[2].id, mass_points[2].position), (id, (1.5, 0.0)));
+        }
+        for _ in 0..10 {
+            world.tick(0.1, NonZeroU16::new(10).unwrap());
+        }
+        world.get_mass_points()[..2].iter().map(|mass_point| mass_point.position).collect()
+    }
+
+    /// the star pulls the pair towards the x axis by ~0.1 over the last 10 frames, without it they go on straight
+    fn assert_bends_towards_added_particles<W: World>(world: impl Fn(Vec<Particle>) -> W) {
+        let straight = passed(&world, false);
+        let bent = passed(&world, true);
+        for ((_, y), (_, bent_y)) in straight.into_iter().zip(bent) {
+            assert!((y.abs() - 1.0).abs() < 1e-4, "{}", y);
+            assert!(bent_y.abs() < 0.95, "{}", bent_y);
+        }
+    }
+
+    #[test]
+    fn particles_added_halfway_pull_on_the_others() {
+        assert_bends_towards_added_particles(|particles| CPUWorld::new(particles).with_g(1.0));
+        assert_bends_towards_added_particles(|particles| ParWorld::new(particles).with_g(1.0));
+        if GPUWorld::list_devices().is_empty() {
+            eprintln!("skipping the gpu, there is no device vulkan can run on");
+        } else {
+            assert_bends_towards_added_particles(|particles| GPUWorld::new(particles).unwrap().with_g(1.0));
+        }
+    }
+
+    /// `world` made out of the seed 23 particles with an integrator, ticked 8 frames straight, has to end where one
+    /// ticked 4 frames, remade out of its [`World::get_particles`] and ticked 4 more does
+    fn assert_continues_from_its_particles<W: World>(world: impl Fn(Vec<Particle>, Integrator) -> W) {
+        for integrator in [Integrator::Euler, Integrator::Verlet, Integrator::RK4, Integrator::Yoshida] {
+            let mut straight = world(random_particles(23, 100, 500.0), integrator);
+            let mut first_half = world(random_particles(23, 100, 500.0), integrator);
+            for _ in 0..4 {
+                straight.tick(20.0, NonZeroU16::new(20).unwrap());
+                first_half.tick(20.0, NonZeroU16::new(20).unwrap());
+            }
+            let mut second_half = world(first_half.get_particles(), integrator);
+            for _ in 0..4 {
+                straight.tick(20.0, NonZeroU16::new(20).unwrap());
+                second_half.tick(20.0, NonZeroU16::new(20).unwrap());
+            }
+            assert_eq!(second_half.get_mass_points(), straight.get_mass_points(), "{:?}", integrator);
+        }
+    }
+
+    #[test]
+    fn worlds_continue_from_their_particles() {
+        assert_continues_from_its_particles(|particles, integrator| CPUWorld::new(particles).with_integrator(integrator));
+        assert_continues_from_its_particles(|particles, integrator| ParWorld::new(particles).with_integrator(integrator));
+        if GPUWorld::list_devices().is_empty() {
+            eprintln!("skipping the gpu, there is no device vulkan can run on");
+        } else {
+            assert_continues_from_its_particles(|particles, integrator| GPUWorld::new(particles).unwrap().with_integrator(integrator));
+        }
+    }
+
+    /// the particles of a snapshot have to come back with exactly the bits they were written with, a few frames into
+    /// the run so that they aren't just the round numbers of the generator
+    #[cfg(feature = "serde")]
+    #[test]
+    fn snapshots_round_trip_bit_for_bit() {
+        let mut cpu = CPUWorld::new(random_particles(23, 100, 500.0)).with_softening(0.01);
+        let mut par = ParWorld::new(random_particles(23, 100, 500.0)).with_softening(0.01);
+        for _ in 0..5 {
+            cpu.tick(20.0, NonZeroU16::new(20).unwrap());
+            par.tick(20.0, NonZeroU16::new(20).unwrap());
+        }
+        let mut json = Vec::new();
+        cpu.save_snapshot(&mut json).unwrap();
+        let loaded: CPUWorld = CPUWorld::from_snapshot(json.as_slice()).unwrap();
+        let bytes = |particles: &[Particle]| bytemuck::cast_slice::<Particle, u8>(particles).to_vec();
+        assert_eq!(bytes(&loaded.get_particles()), bytes(&cpu.get_particles()));
+        let mut json = Vec::new();
+        par.save_snapshot(&mut json).unwrap();
+        let loaded: ParWorld = ParWorld::from_snapshot(json.as_slice()).unwrap();
+        assert_eq!(bytes(&loaded.get_particles()), bytes(&par.get_particles()));
+    }
+}
@@ -0,0 +1,255 @@
+use crate::MassPoint;
+
+/// below this many particles every query looks at every particle, a grid isn't worth it
+const BRUTE_FORCE_THRESHOLD: usize = 64;
+/// particles per cell of the grid on average, if they were spread evenly over their bounding square
+const PARTICLES_PER_CELL: f32 = 2.0;
+
+/// positions of particles, indexed for finding the particles near a point or another particle,
+/// the indices are those of the mass points it was built from
+///
+/// worth keeping around for many queries on the same frame, [`World::neighbors_within`](crate::world::World::neighbors_within)
+/// and [`World::nearest`](crate::world::World::nearest) build one for a single query
+///
+/// distances are measured in the plane, periodic boundaries aren't wrapped around
+pub struct Neighbors {
+    positions: Vec<(f32, f32)>,
+    /// `None` below [`BRUTE_FORCE_THRESHOLD`] particles, or when the particles are too far apart to be divided into cells
+    grid: Option<Grid>
+}
+
+impl Neighbors {
+    pub fn new(mass_points: &[MassPoint]) -> Self {
+        let positions: Vec<_> = mass_points.iter().map(|mass_point| mass_point.position).collect();
+        let grid = match positions.len() {
+            len if len < BRUTE_FORCE_THRESHOLD => None,
+            _ => Grid::new(&positions)
+        };
+        Self { positions, grid }
+    }
+
+    /// indices of every particle at most `r` from `(x, y)`, in ascending order
+    ///
+    /// particles at non-finite positions are never within any distance, nothing is within a negative or NaN `r`
+    pub fn within(&self, x: f32, y: f32, r: f32) -> Vec<usize> {
+        if r.is_nan() || r < 0.0 {
+            return Vec::new()
+        }
+        let is_within = |i: usize| {
+            let (px, py) = self.positions[i];
+            px.is_finite() && py.is_finite() && distance_sq((px, py), (x, y)) <= r * r
+        };
+        let grid = match &self.grid {
+            Some(grid) => grid,
+            None => return (0..self.positions.len()).filter(|&i| is_within(i)).collect()
+        };
+        let (min_column, min_row) = grid.cell((x - r, y - r));
+        let (max_column, max_row) = grid.cell((x + r, y + r));
+        let mut found = Vec::new();
+        for row in min_row..=max_row {
+            for column in min_column..=max_column {
+                found.extend(grid.cell_indices(column, row).iter().copied().filter(|&i| is_within(i)));
+            }
+        }
+        found.sort_unstable();
+        found
+    }
+
+    /// index of the particle closest to particle `i`, other than `i` itself, the lowest index of those
+    /// equally close, `None` if there is no particle `i`, it isn't at a finite position or no other particle is
+    pub fn nearest(&self, i: usize) -> Option<usize> {
+        let from = *self.positions.get(i)?;
+        let mut best: Option<(f32, usize)> = None;
+        let consider = |best: &mut Option<(f32, usize)>, j: usize| {
+            if j == i {
+                return
+            }
+            let d_sq = distance_sq(self.positions[j], from);
+            // NaN distances never compare as closer
+            let closer = match *best {
+                None => d_sq.is_finite(),
+                Some((best_d_sq, best_j)) => d_sq < best_d_sq || (d_sq == best_d_sq && j < best_j)
+            };
+            if closer {
+                *best = Some((d_sq, j));
+            }
+        };
+        let grid = match &self.grid {
+            Some(grid) if from.0.is_finite() && from.1.is_finite() => grid,
+            _ => {
+                for j in 0..self.positions.len() {
+                    consider(&mut best, j);
+                }
+                return best.map(|(_, j)| j)
+            }
+        };
+        let (column, row) = grid.cell(from);
+        // rings of cells around the cell of `i`, until the closest particle so far is closer than any cell left,
+        // strictly, so that an equally close particle of a lower index in the next ring is still found
+        for ring in 0..grid.columns.max(grid.rows) {
+            let min_column = column.saturating_sub(ring);
+            let max_column = (column + ring).min(grid.columns - 1);
+            let min_row = row.saturating_sub(ring);
+            let max_row = (row + ring).min(grid.rows - 1);
+            for r in min_row..=max_row {
+                for c in min_column..=max_column {
+                    if c.abs_diff(column) == ring || r.abs_diff(row) == ring {
+                        for &j in grid.cell_indices(c, r) {
+                            consider(&mut best, j);
+                        }
+                    }
+                }
+            }
+            if let Some((best_d_sq, _)) = best {
+                // cells outside of this ring are at least this far from any point in the cell of `i`
+                let unvisited_distance = ring as f32 * grid.cell_size;
+                if best_d_sq < unvisited_distance * unvisited_distance {
+                    break
+                }
+            }
+        }
+        best.map(|(_, j)| j)
+    }
+}
+
+fn distance_sq((ax, ay): (f32, f32), (bx, by): (f32, f32)) -> f32 {
+    let dx = ax - bx;
+    let dy = ay - by;
+    dx * dx + dy * dy
+}
+
+/// uniform grid of square cells covering every finite position, each cell holds the indices of the positions in it
+struct Grid {
+    min: (f32, f32),
+    cell_size: f32,
+    columns: usize,
+    rows: usize,
+    cells: Vec<Vec<usize>>
+}
+
+impl Grid {
+    /// `None` if the positions are spread too far for the size of a cell to be finite
+    fn new(positions: &[(f32, f32)]) -> Option<Self> {
+        let finite = || positions.iter().enumerate().filter(|(_, (x, y))| x.is_finite() && y.is_finite());
+        let (mut min, mut max) = ((f32::INFINITY, f32::INFINITY), (f32::NEG_INFINITY, f32::NEG_INFINITY));
+        for (_, &(x, y)) in finite() {
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x), max.1.max(y));
+        }
+        if min.0 > max.0 {
+            // nothing is finite, a single empty cell
+            min = (0.0, 0.0);
+            max = (0.0, 0.0);
+        }
+        let side = f32::max(max.0 - min.0, max.1 - min.1);
+        if !side.is_finite() {
+            return None
+        }
+        let cells_per_side = f32::sqrt(positions.len() as f32 / PARTICLES_PER_CELL).ceil();
+        let cell_size = match side / cells_per_side {
+            // every position is the same
+            0.0 => 1.0,
+            size => size
+        };
+        let columns = ((max.0 - min.0) / cell_size) as usize + 1;
+        let rows = ((max.1 - min.1) / cell_size) as usize + 1;
+        let mut grid = Self {
+            min,
+            cell_size,
+            columns,
+            rows,
+            cells: vec![Vec::new(); columns * rows]
+        };
+        for (i, &position) in finite() {
+            let (column, row) = grid.cell(position);
+            grid.cells[row * columns + column].push(i);
+        }
+        Some(grid)
+    }
+
+    /// `(column, row)` of the cell containing `(x, y)`, or of the closest cell if it is outside of the grid
+    fn cell(&self, (x, y): (f32, f32)) -> (usize, usize) {
+        // converting f32 to usize through `as` saturates, and NaN becomes 0
+        let column = ((x - self.min.0) / self.cell_size) as usize;
+        let row = ((y - self.min.1) / self.cell_size) as usize;
+        (column.min(self.columns - 1), row.min(self.rows - 1))
+    }
+
+    fn cell_indices(&self, column: usize, row: usize) -> &[usize] {
+        &self.cells[row * self.columns + column]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::MassPoint;
+    use crate::world::World;
+    use crate::world::cpu::CPUWorld;
+    use crate::world::generate::random_particles;
+    use super::Neighbors;
+
+    fn neighbors(positions: &[(f32, f32)]) -> Neighbors {
+        let mass_points: Vec<_> = positions.iter()
+            .map(|&position| MassPoint { mass: 1.0, position, velocity: (0.0, 0.0), id: 0, group: 0 })
+            .collect();
+        Neighbors::new(&mass_points)
+    }
+
+    /// a 10 by 10 lattice of points a distance of 1 apart, point `y * 10 + x` at `(x, y)`, enough to be put in a grid
+    fn lattice() -> Vec<(f32, f32)> {
+        (0..100).map(|i| ((i % 10) as f32, (i / 10) as f32)).collect()
+    }
+
+    #[test]
+    fn empty_worlds_have_no_neighbors() {
+        let empty = neighbors(&[]);
+        assert!(empty.within(0.0, 0.0, 1.0).is_empty());
+        assert_eq!(empty.nearest(0), None);
+        assert_eq!(neighbors(&[(0.0, 0.0)]).nearest(0), None);
+    }
+
+    #[test]
+    fn hand_placed_particles() {
+        let few = neighbors(&[(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (-1.0, 0.0), (3.0, 3.0), (f32::NAN, 0.0)]);
+        // the edge of the circle counts
+        assert_eq!(few.within(0.0, 0.0, 1.0), [0, 1, 2, 3]);
+        assert_eq!(few.within(0.0, 0.0, 0.5), [0]);
+        assert!(few.within(0.0, 0.0, -1.0).is_empty());
+        assert!(few.within(0.0, 0.0, f32::NAN).is_empty());
+        // 1, 2 and 3 are equally close to 0, and 1 and 2 to 4, the lowest index wins
+        assert_eq!(few.nearest(0), Some(1));
+        assert_eq!(few.nearest(4), Some(1));
+        assert_eq!(few.nearest(5), None);
+        assert_eq!(few.nearest(6), None);
+    }
+
+    #[test]
+    fn grids_find_what_looking_at_every_particle_does() {
+        let positions = lattice();
+        let grid = neighbors(&positions);
+        assert!(grid.grid.is_some());
+        assert_eq!(grid.within(4.5, 4.5, 1.0), [44, 45, 54, 55]);
+        assert_eq!(grid.within(0.0, 0.0, 1.0), [0, 1, 10]);
+        assert!(grid.within(-5.0, -5.0, 1.0).is_empty());
+        // 1 and 10 are equally close to 0, and every neighbor of 55 is a distance of 1 away
+        assert_eq!(grid.nearest(0), Some(1));
+        assert_eq!(grid.nearest(55), Some(45));
+        assert_eq!(grid.nearest(100), None);
+    }
+
+    #[test]
+    fn worlds_answer_queries_on_their_particles() {
+        let world = CPUWorld::new(random_particles(23, 200, 500.0));
+        let positions: Vec<_> = world.get_mass_points().iter().map(|mass_point| mass_point.position).collect();
+        let (x, y) = positions[7];
+        let within = world.neighbors_within(x, y, 0.1);
+        let expected: Vec<_> = (0..positions.len())
+            .filter(|&i| f32::hypot(positions[i].0 - x, positions[i].1 - y) <= 0.1)
+            .collect();
+        assert_eq!(within, expected);
+        assert!(within.contains(&7));
+        let nearest = world.nearest(7).unwrap();
+        let distance = |i: usize| f32::hypot(positions[i].0 - x, positions[i].1 - y);
+        assert!((0..positions.len()).filter(|&i| i != 7).all(|i| distance(i) >= distance(nearest)));
+    }
+}
@@ -1,80 +1,826 @@
 use std::f32::consts::PI;
 use std::num::NonZeroU16;
-use std::sync::Arc;
-use rayon::iter::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
+#[cfg(feature = "serde")]
+use std::io::{Read, Write};
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use rayon::{ThreadPool, ThreadPoolBuilder};
 use crate::{MassPoint, Particle, Vector};
+use crate::vector::{Vector3, VectorF64};
+use crate::world::{barnes_hut, collision, damp, Boundary, timescale, AdaptiveSteps, G, G_F64, Collisions, Escape, Integrator, MassPoint3, Particle3, ParticleF64};
+use crate::world::cpu::{pull_3, pull_f64};
+use crate::world::{assign_ids, diagnostics, escape, simd, World};
+use crate::world::field::{self, Field};
+use crate::world::checkpoint::Checkpoint;
+use crate::world::force::{ForceModel, NewtonianGravity};
+use crate::world::store::ParticleStore;
+use crate::world::summation::{deterministic_acceleration, CompensatedSum, Summation};
+#[cfg(feature = "serde")]
+use crate::world::snapshot::Snapshot;
 
-pub struct ParWorld {
-    particles: Arc<Vec<Particle>>
+pub struct ParWorld<F = NewtonianGravity> {
+    particles: ParticleStore,
+    integrator: Integrator,
+    force_model: F,
+    softening: f32,
+    drag: f32,
+    boundary: Boundary,
+    barnes_hut_theta: Option<f32>,
+    collisions: Collisions,
+    adaptive_steps: Option<AdaptiveSteps>,
+    fields: Vec<Field>,
+    escape: Option<Escape>,
+    escaped: usize,
+    compensated_summation: bool,
+    deterministic_summation: bool,
+    /// see [`with_threads`](Self::with_threads), rayon's global pool when `None`
+    pool: Option<ThreadPool>,
+    /// see [`with_chunk_size`](Self::with_chunk_size)
+    chunk_size: Option<usize>,
+    /// id of the next particle added, see [`Particle::id`]
+    next_id: u32
 }
 
 impl ParWorld {
-    pub fn new(particles: Vec<Particle>) -> Self {
+    pub fn new(mut particles: Vec<Particle>) -> Self {
+        assign_ids(&mut particles);
+        let next_id = particles.len() as u32;
         Self {
-            particles: Arc::new(particles)
+            particles: particles.into(),
+            integrator: Integrator::default(),
+            force_model: NewtonianGravity::default(),
+            softening: 0.0,
+            drag: 0.0,
+            boundary: Boundary::default(),
+            barnes_hut_theta: None,
+            collisions: Collisions::default(),
+            adaptive_steps: None,
+            fields: Vec::new(),
+            escape: None,
+            escaped: 0,
+            compensated_summation: false,
+            deterministic_summation: false,
+            pool: None,
+            chunk_size: None,
+            next_id
         }
     }
 
-    pub fn tick(&mut self, time: f32, steps: NonZeroU16) {
+    /// sets the gravitational constant of the default [`NewtonianGravity`] force model, [`G`](crate::world::G) by default
+    pub fn with_g(mut self, g: f32) -> Self {
+        self.force_model.g = g;
+        self
+    }
+}
+
+impl<F: ForceModel> ParWorld<F> {
+    pub fn with_integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    /// replaces the force between particles, see [`CPUWorld::with_force_model`](crate::world::cpu::CPUWorld::with_force_model)
+    pub fn with_force_model<M: ForceModel>(self, force_model: M) -> ParWorld<M> {
+        ParWorld {
+            particles: self.particles,
+            integrator: self.integrator,
+            force_model,
+            softening: self.softening,
+            drag: self.drag,
+            boundary: self.boundary,
+            barnes_hut_theta: self.barnes_hut_theta,
+            collisions: self.collisions,
+            adaptive_steps: self.adaptive_steps,
+            fields: self.fields,
+            escape: self.escape,
+            escaped: self.escaped,
+            compensated_summation: self.compensated_summation,
+            deterministic_summation: self.deterministic_summation,
+            pool: self.pool,
+            chunk_size: self.chunk_size,
+            next_id: self.next_id
+        }
+    }
+
+    /// sets the softening length, see [`CPUWorld::with_softening`](crate::world::cpu::CPUWorld::with_softening)
+    pub fn with_softening(mut self, softening: f32) -> Self {
+        self.softening = softening;
+        self
+    }
+
+    /// sets the linear drag coefficient, see [`CPUWorld::with_drag`](crate::world::cpu::CPUWorld::with_drag)
+    pub fn with_drag(mut self, drag: f32) -> Self {
+        self.drag = drag;
+        self
+    }
+
+    /// sets the shape of the space, see [`Boundary`]
+    pub fn with_boundary(mut self, boundary: Boundary) -> Self {
+        self.boundary = boundary;
+        self
+    }
+
+    /// approximates the forces with a Barnes-Hut [`QuadTree`](barnes_hut::QuadTree) built in parallel,
+    /// see [`CPUWorld::with_barnes_hut`](crate::world::cpu::CPUWorld::with_barnes_hut)
+    pub fn with_barnes_hut(mut self, theta: Option<f32>) -> Self {
+        self.barnes_hut_theta = theta;
+        self
+    }
+
+    /// sets how touching particles interact, merged particles are removed from the world
+    pub fn with_collisions(mut self, collisions: Collisions) -> Self {
+        self.collisions = collisions;
+        self
+    }
+
+    /// sizes substeps dynamically, see [`CPUWorld::with_adaptive_steps`](crate::world::cpu::CPUWorld::with_adaptive_steps)
+    pub fn with_adaptive_steps(mut self, adaptive_steps: Option<AdaptiveSteps>) -> Self {
+        self.adaptive_steps = adaptive_steps;
+        self
+    }
+
+    /// removes escaped particles, see [`CPUWorld::with_escape`](crate::world::cpu::CPUWorld::with_escape)
+    pub fn with_escape(mut self, escape: Option<Escape>) -> Self {
+        self.escape = escape;
+        self
+    }
+
+    /// sums the accelerations of every pair of particles with a compensated (Neumaier) sum instead of adding them directly,
+    /// has no effect with Barnes-Hut, see [`CPUWorld::with_compensated_summation`](crate::world::cpu::CPUWorld::with_compensated_summation)
+    pub fn with_compensated_summation(mut self, compensated_summation: bool) -> Self {
+        self.compensated_summation = compensated_summation;
+        self
+    }
+
+    /// sums the accelerations acting on each particle in a fixed order, the same as
+    /// [`CPUWorld::with_deterministic_summation`](crate::world::cpu::CPUWorld::with_deterministic_summation)
+    /// whatever the number of threads
+    pub fn with_deterministic_summation(mut self, deterministic_summation: bool) -> Self {
+        self.deterministic_summation = deterministic_summation;
+        self
+    }
+
+    /// runs on a thread pool of its own with `threads` threads rather than on rayon's global one, so that worlds
+    /// can share the cores in a set proportion without the application building the global pool,
+    /// 0 picks the number of threads the way rayon does
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.pool = Some(ThreadPoolBuilder::new().num_threads(threads).build().expect("failed to create thread pool"));
+        self
+    }
+
+    /// rows of pairs, or particles with deterministic summation, that each task of the parallel force loop computes
+    /// at least, `None` by default, which splits the rows into [`TASKS_PER_THREAD`] tasks per thread, but into tasks
+    /// of [`MIN_PAIRS_PER_TASK`] pairs on average at the least, so that up to about 128 particles are a single task
+    ///
+    /// a single task computes exactly what [`CPUWorld`](crate::world::cpu::CPUWorld) does,
+    /// otherwise the accelerations differ by the rounding of adding up the tasks
+    pub fn with_chunk_size(mut self, chunk_size: Option<usize>) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// adds an external force field acting on every particle, see [`Field`]
+    pub fn add_field(&mut self, field: Field) {
+        self.fields.push(field);
+    }
+
+    /// adds a particle to the world, it is pulled on and pulls from the next tick on,
+    /// returns the id it was given
+    pub fn add_particle(&mut self, particle: Particle) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.particles.push(Particle { id, ..particle });
+        id
+    }
+
+    /// removes the particle at `index` and returns it, the last particle takes its index
+    pub fn remove_particle(&mut self, index: usize) -> Particle {
+        self.particles.swap_remove(index)
+    }
+
+    /// advances the world by `time`, returns the number of substeps taken
+    ///
+    /// the velocity and position columns of the particles are stepped in place, unless a setting that works on whole
+    /// particles is set, see [`ticks_columns`](Self::ticks_columns)
+    pub fn tick(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        if !self.ticks_columns() {
+            return self.tick_with_observer(time, steps, |_| {})
+        }
         let stepped_time = time / steps.get() as f32;
-        for _ in 0..steps.get() {
-            let accelerations = Self::tick_split(self.particles.clone(), 0, self.particles.len(), stepped_time);
-            Arc::get_mut(&mut self.particles).unwrap().par_iter_mut()
-                .zip(accelerations)
-                .for_each(|(particle, acceleration)| {
-                    particle.velocity.step(&acceleration, stepped_time);
-                    particle.position.step(&particle.velocity, stepped_time);
-                });
+        let (pool, force_model, softening, drag, boundary, chunk_size) = (&self.pool, &self.force_model, self.softening, self.drag, self.boundary, self.chunk_size);
+        self.integrator.integrate(&mut self.particles, stepped_time, steps, |particles| install_in(pool, || {
+            let rows = rows_per_task(chunk_size, particles.len());
+            simd::par_compute_store_accelerations(particles, rows, force_model, softening, boundary)
+        }), |particles| {
+            particles.damp(drag, stepped_time);
+            particles.apply_boundary(boundary);
+            false
+        });
+        steps.get()
+    }
+
+    /// see [`CPUWorld::ticks_columns`](crate::world::cpu::CPUWorld::ticks_columns)
+    pub fn ticks_columns(&self) -> bool {
+        self.barnes_hut_theta.is_none()
+            && !self.compensated_summation
+            && !self.deterministic_summation
+            && self.fields.is_empty()
+            && self.collisions == Collisions::None
+            && self.adaptive_steps.is_none()
+            && self.escape.is_none()
+    }
+
+    /// [`tick`](Self::tick), calling `observer` with every particle after each substep, once drag,
+    /// the boundary and collisions have been applied, particles that escape are only removed after the last substep
+    ///
+    /// the particles are taken out of the store for the tick, as the observer needs them whole
+    pub fn tick_with_observer(&mut self, time: f32, steps: NonZeroU16, mut observer: impl FnMut(&[Particle])) -> u16 {
+        let mut particles = self.particles.to_vec();
+        let taken = match self.adaptive_steps {
+            Some(adaptive_steps) => {
+                let mut remaining = time;
+                let mut taken = 0;
+                while remaining > 0.0 {
+                    let timescale = self.install(|| par_shortest_timescale(&particles, &self.force_model, self.softening));
+                    let stepped_time = adaptive_steps.substep(timescale, time, remaining, taken);
+                    self.substeps(&mut particles, stepped_time, NonZeroU16::new(1).unwrap(), &mut observer);
+                    remaining -= stepped_time;
+                    taken += 1;
+                }
+                taken
+            }
+            None => {
+                self.substeps(&mut particles, time / steps.get() as f32, steps, &mut observer);
+                steps.get()
+            }
+        };
+        if let Some(escape) = self.escape {
+            self.escaped += self.install(|| escape::par_cull(&mut particles, escape, &self.force_model, self.softening, &self.fields));
         }
+        self.particles = particles.into();
+        taken
     }
 
-    fn tick_split(particles: Arc<Vec<Particle>>, lo: usize, hi: usize, stepped_time: f32) -> Vec<Vector> {
-        let mid = (lo + hi) / 2;
-        if mid == lo {
-            let mut accelerations = vec![Vector::new(0.0, 0.0); particles.len()];
-            let i = lo;
-            for j in i + 1..particles.len() {
-                let a = particles[i];
-                let b = particles[j];
-                let r_sq = Vector::distance_sq(&a.position, &b.position);
-                // Newtons law of universal gravitation: (G * m1 * m2) / r^2
-                let f = (6.67430e-11 * a.mass * b.mass / r_sq) * stepped_time;
-                if f.is_infinite() {
-                    continue
-                } else {
-                    let (x1, y1) = a.position.to_cartesian();
-                    let (x2, y2) = b.position.to_cartesian();
-                    let d1 = f32::atan2(y2 - y1, x2 - x1);
-                    let d2 = d1 + PI;
-                    // f = ma
-                    accelerations[i] += Vector::new(d1, f / a.mass);
-                    accelerations[j] += Vector::new(d2, f / b.mass);
+    /// runs the world backwards by `time`, returns the number of substeps taken
+    ///
+    /// velocities are reversed, the world is ticked forwards, then velocities are reversed again,
+    /// which exactly undoes a tick with Verlet or Yoshida (up to rounding) as long as there is no drag,
+    /// collision or escape, which aren't reversible
+    pub fn tick_reverse(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        self.particles.reverse_velocities();
+        let taken = self.tick(time, steps);
+        self.particles.reverse_velocities();
+        taken
+    }
+
+    /// number of particles removed for escaping so far
+    pub fn escaped(&self) -> usize {
+        self.escaped
+    }
+
+    fn substeps(&self, particles: &mut Vec<Particle>, stepped_time: f32, steps: NonZeroU16, observer: &mut impl FnMut(&[Particle])) {
+        let force_model = &self.force_model;
+        let fields = &self.fields;
+        let softening = self.softening;
+        let drag = self.drag;
+        let boundary = self.boundary;
+        let barnes_hut_theta = self.barnes_hut_theta;
+        let collisions = self.collisions;
+        let compensated_summation = self.compensated_summation;
+        let deterministic_summation = self.deterministic_summation;
+        let chunk_size = self.chunk_size;
+        // the observer isn't necessarily `Send`, so only the parallel parts of a substep run in the pool
+        self.integrator.integrate(particles, stepped_time, steps, |particles| self.install(|| {
+            // collisions can merge particles between substeps
+            let rows = rows_per_task(chunk_size, particles.len());
+            let mut accelerations = match barnes_hut_theta {
+                Some(theta) => barnes_hut::par_compute_accelerations(particles, theta, force_model, softening, boundary),
+                None if deterministic_summation => (0..particles.len())
+                    .into_par_iter()
+                    .with_min_len(rows)
+                    .map(|i| deterministic_acceleration(particles, i, force_model, softening, boundary))
+                    .collect(),
+                None if compensated_summation => Self::tick_split::<CompensatedSum>(particles, 0, particles.len(), rows, force_model, softening, boundary)
+                    .into_iter()
+                    .map(CompensatedSum::to_vector)
+                    .collect(),
+                None => simd::par_compute_accelerations(particles, rows, force_model, softening, boundary)
+            };
+            field::par_apply(fields, particles, &mut accelerations, force_model, softening);
+            accelerations
+        }), |particles| {
+            damp(particles, drag, stepped_time);
+            boundary.apply(particles);
+            let changed = match collisions {
+                Collisions::None => false,
+                Collisions::Merge => {
+                    let pairs = self.install(|| collision::par_touching_pairs(particles));
+                    collision::merge(particles, &pairs)
+                }
+                Collisions::Elastic { restitution } => {
+                    let pairs = self.install(|| collision::par_touching_pairs(particles));
+                    collision::bounce(particles, &pairs, restitution)
+                }
+            };
+            observer(particles);
+            changed
+        });
+    }
+
+    /// runs `op` in the pool of this world, its parallel iterators then run in that pool as well
+    fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        install_in(&self.pool, op)
+    }
+
+    /// accelerations caused by every pair of particles whose lower index is in `lo..hi`, each task of up to `rows`
+    /// rows accumulates into its own buffer which are then merged in a fixed order, so the result does not depend
+    /// on scheduling, only on `rows`
+    fn tick_split<S: Summation>(particles: &[Particle], lo: usize, hi: usize, rows: usize, force_model: &F, softening: f32, boundary: Boundary) -> Vec<S> {
+        if hi - lo <= rows {
+            let mut accelerations = vec![S::default(); particles.len()];
+            for i in lo..hi {
+                for j in i + 1..particles.len() {
+                    let a = particles[i];
+                    let b = particles[j];
+                    let (distance_sq, d1) = boundary.separation(&a.position, &b.position);
+                    let r_sq = distance_sq + softening * softening;
+                    let f = force_model.force(a.mass, b.mass, r_sq);
+                    if f.is_infinite() {
+                        continue
+                    } else {
+                        let d2 = d1 + PI;
+                        // f = ma
+                        accelerations[i].add(Vector::new(d1, f / a.mass));
+                        accelerations[j].add(Vector::new(d2, f / b.mass));
+                    }
                 }
             }
             accelerations
         } else {
-            let particles_lo = particles.clone();
-            let (lo, hi) = rayon::join(
-                || Self::tick_split(particles_lo, lo, mid, stepped_time),
-                || Self::tick_split(particles, mid, hi, stepped_time)
+            let mid = (lo + hi) / 2;
+            let (mut lo, hi) = rayon::join(
+                || Self::tick_split::<S>(particles, lo, mid, rows, force_model, softening, boundary),
+                || Self::tick_split::<S>(particles, mid, hi, rows, force_model, softening, boundary)
             );
-            lo.into_iter()
-                .zip(hi)
-                .map(|(a, b)| {
-                    a + b
+            for (a, b) in lo.iter_mut().zip(hi) {
+                *a = a.merge(b);
+            }
+            lo
+        }
+    }
+
+    pub fn get_mass_points(&self) -> Vec<MassPoint> {
+        self.install(|| self.particles.par_mass_points())
+    }
+
+    /// every particle with its velocity, in the same order as [`get_mass_points`](Self::get_mass_points),
+    /// velocities are taken at the same instant as positions, as every integrator ends a tick with both in step
+    ///
+    /// a world created from these particles continues exactly like this one, except that it numbers the ids anew
+    pub fn get_particles(&self) -> Vec<Particle> {
+        self.particles.to_vec()
+    }
+
+    /// kinetic energy of every moving particle
+    pub fn kinetic_energy(&self) -> f32 {
+        diagnostics::kinetic_energy(&self.particles.to_vec())
+    }
+
+    /// potential energy of every pair of particles and of every particle in the fields of this world
+    pub fn potential_energy(&self) -> f32 {
+        self.install(|| diagnostics::par_potential_energy(&self.particles.to_vec(), &self.force_model, self.softening, self.boundary, &self.fields))
+    }
+
+    pub fn total_energy(&self) -> f32 {
+        self.kinetic_energy() + self.potential_energy()
+    }
+
+    /// momentum of every moving particle
+    pub fn total_momentum(&self) -> Vector {
+        diagnostics::total_momentum(&self.particles.to_vec())
+    }
+
+    /// mass weighted average position of every particle
+    pub fn center_of_mass(&self) -> (f32, f32) {
+        diagnostics::center_of_mass(self.get_mass_points())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<F: ForceModel + Clone + Serialize + DeserializeOwned> ParWorld<F> {
+    /// writes the particles and settings of this world as JSON, see [`Snapshot`]
+    pub fn save_snapshot(&self, writer: impl Write) -> serde_json::Result<()> {
+        Snapshot {
+            particles: self.particles.to_vec(),
+            next_id: self.next_id,
+            integrator: self.integrator,
+            force_model: self.force_model.clone(),
+            softening: self.softening,
+            drag: self.drag,
+            boundary: self.boundary,
+            barnes_hut_theta: self.barnes_hut_theta,
+            collisions: self.collisions,
+            adaptive_steps: self.adaptive_steps,
+            fields: self.fields.clone(),
+            escape: self.escape,
+            escaped: self.escaped,
+            compensated_summation: self.compensated_summation,
+            deterministic_summation: self.deterministic_summation
+        }.write(writer)
+    }
+
+    /// reads a world written by [`save_snapshot`](Self::save_snapshot)
+    pub fn from_snapshot(reader: impl Read) -> serde_json::Result<Self> {
+        let snapshot = Snapshot::read(reader)?;
+        Ok(Self {
+            particles: snapshot.particles.into(),
+            integrator: snapshot.integrator,
+            force_model: snapshot.force_model,
+            softening: snapshot.softening,
+            drag: snapshot.drag,
+            boundary: snapshot.boundary,
+            barnes_hut_theta: snapshot.barnes_hut_theta,
+            collisions: snapshot.collisions,
+            adaptive_steps: snapshot.adaptive_steps,
+            fields: snapshot.fields,
+            escape: snapshot.escape,
+            escaped: snapshot.escaped,
+            compensated_summation: snapshot.compensated_summation,
+            deterministic_summation: snapshot.deterministic_summation,
+            pool: None,
+            chunk_size: None,
+            next_id: snapshot.next_id
+        })
+    }
+}
+
+impl<F: ForceModel> World for ParWorld<F> {
+    fn tick(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        ParWorld::tick(self, time, steps)
+    }
+
+    fn tick_with_observer<O: FnMut(&[Particle])>(&mut self, time: f32, steps: NonZeroU16, observer: O) -> u16 {
+        ParWorld::tick_with_observer(self, time, steps, observer)
+    }
+
+    fn tick_reverse(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        ParWorld::tick_reverse(self, time, steps)
+    }
+
+    fn get_mass_points(&self) -> Vec<MassPoint> {
+        ParWorld::get_mass_points(self)
+    }
+
+    fn get_particles(&self) -> Vec<Particle> {
+        ParWorld::get_particles(self)
+    }
+
+    fn total_energy(&self) -> f32 {
+        ParWorld::total_energy(self)
+    }
+
+    fn add_particle(&mut self, particle: Particle) -> u32 {
+        ParWorld::add_particle(self, particle)
+    }
+
+    fn remove_particle(&mut self, index: usize) -> Particle {
+        ParWorld::remove_particle(self, index)
+    }
+
+    fn checkpoint(&self, frame: usize, time: f32, steps: NonZeroU16) -> Checkpoint {
+        Checkpoint {
+            frame,
+            time,
+            steps: steps.get(),
+            particles: self.get_particles(),
+            next_id: self.next_id,
+            escaped: self.escaped
+        }
+    }
+
+    fn resume(&mut self, checkpoint: &Checkpoint) {
+        self.particles = checkpoint.particles.clone().into();
+        self.next_id = checkpoint.next_id;
+        self.escaped = checkpoint.escaped;
+    }
+}
+
+/// parallel version of [`shortest_timescale`](crate::world::shortest_timescale)
+fn par_shortest_timescale<F: ForceModel>(particles: &[Particle], force_model: &F, softening: f32) -> f32 {
+    (0..particles.len())
+        .into_par_iter()
+        .map(|i| {
+            particles[i + 1..].iter()
+                .map(|b| timescale(&particles[i], b, force_model, softening))
+                .fold(f32::INFINITY, f32::min)
+        })
+        .reduce(|| f32::INFINITY, f32::min)
+}
+
+/// tasks per thread the parallel force loop of [`ParWorld`] is split into by default, each task allocates and later
+/// merges a buffer with an acceleration for every particle, so more tasks means better load balancing but more merging,
+/// the rows of the first particles having the most pairs
+pub const TASKS_PER_THREAD: usize = 8;
+
+/// pairs a single task of [`ParWorld`] computes at least by default, splitting fewer pairs off
+/// costs more than computing them, 100 particles are 4950 pairs
+pub const MIN_PAIRS_PER_TASK: usize = 8192;
+
+/// runs `op` in `pool`, or in rayon's global pool when `None`, see [`ParWorld::install`]
+fn install_in<R: Send>(pool: &Option<ThreadPool>, op: impl FnOnce() -> R + Send) -> R {
+    match pool {
+        Some(pool) => pool.install(op),
+        None => op()
+    }
+}
+
+/// see [`ParWorld::with_chunk_size`], in the pool the force loop runs in
+fn rows_per_task(chunk_size: Option<usize>, particles: usize) -> usize {
+    match chunk_size {
+        Some(chunk_size) => chunk_size.max(1),
+        // a row has `particles / 2` pairs on average
+        None => usize::max(
+            2 * MIN_PAIRS_PER_TASK / particles.max(1),
+            particles / (TASKS_PER_THREAD * rayon::current_num_threads())
+        ).max(1)
+    }
+}
+
+/// three dimensional counterpart of [`ParWorld`], without Barnes-Hut or collisions
+///
+/// every particle sums up its own acceleration on a separate task, which computes each pair twice
+/// but needs no reduction
+pub struct ParWorld3 {
+    particles: Vec<Particle3>,
+    integrator: Integrator,
+    g: f32,
+    softening: f32
+}
+
+impl ParWorld3 {
+    pub fn new(particles: Vec<Particle3>) -> Self {
+        Self {
+            particles,
+            integrator: Integrator::default(),
+            g: G,
+            softening: 0.0
+        }
+    }
+
+    pub fn with_integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    /// sets the gravitational constant, [`G`](crate::world::G) by default
+    pub fn with_g(mut self, g: f32) -> Self {
+        self.g = g;
+        self
+    }
+
+    /// sets the softening length, see [`CPUWorld::with_softening`](crate::world::cpu::CPUWorld::with_softening)
+    pub fn with_softening(mut self, softening: f32) -> Self {
+        self.softening = softening;
+        self
+    }
+
+    /// advances the world by `time` in `steps` substeps, returns the number of substeps taken
+    pub fn tick(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        let stepped_time = time / steps.get() as f32;
+        let g = self.g;
+        let softening = self.softening;
+        self.integrator.integrate(&mut self.particles, stepped_time, steps, |particles| {
+            particles.par_iter()
+                .enumerate()
+                .map(|(i, a)| {
+                    let mut acceleration = Vector3::default();
+                    for (j, b) in particles.iter().enumerate() {
+                        if i == j {
+                            continue
+                        }
+                        if let Some(direction) = pull_3(a, b, g, softening) {
+                            // f = ma
+                            acceleration += direction.scale(1.0 / a.mass);
+                        }
+                    }
+                    acceleration
                 })
                 .collect()
+        }, |_| false);
+        steps.get()
+    }
+
+    pub fn get_mass_points(&self) -> Vec<MassPoint3> {
+        self.particles.iter()
+            .enumerate()
+            .map(|(id, particle)| MassPoint3 {
+                mass: particle.mass,
+                position: (particle.position.x, particle.position.y, particle.position.z),
+                velocity: (particle.velocity.x, particle.velocity.y, particle.velocity.z),
+                id: id as u32
+            })
+            .collect()
+    }
+}
+
+/// double precision counterpart of [`ParWorld`], without Barnes-Hut, collisions or any of the other options
+pub struct ParWorldF64 {
+    particles: Vec<ParticleF64>,
+    integrator: Integrator,
+    g: f64,
+    softening: f64
+}
+
+impl ParWorldF64 {
+    pub fn new(particles: Vec<ParticleF64>) -> Self {
+        Self {
+            particles,
+            integrator: Integrator::default(),
+            g: G_F64,
+            softening: 0.0
         }
     }
 
+    pub fn with_integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    /// sets the gravitational constant, [`G_F64`](crate::world::G_F64) by default
+    pub fn with_g(mut self, g: f64) -> Self {
+        self.g = g;
+        self
+    }
+
+    /// sets the softening length, see [`CPUWorld::with_softening`](crate::world::cpu::CPUWorld::with_softening)
+    pub fn with_softening(mut self, softening: f64) -> Self {
+        self.softening = softening;
+        self
+    }
+
+    /// advances the world by `time` in `steps` substeps, returns the number of substeps taken
+    pub fn tick(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        let stepped_time = time / steps.get() as f32;
+        let g = self.g;
+        let softening = self.softening;
+        self.integrator.integrate(&mut self.particles, stepped_time, steps, |particles| {
+            particles.par_iter()
+                .enumerate()
+                .map(|(i, a)| {
+                    let mut acceleration = VectorF64::default();
+                    for (j, b) in particles.iter().enumerate() {
+                        if i == j {
+                            continue
+                        }
+                        if let Some(direction) = pull_f64(a, b, g, softening) {
+                            // f = ma
+                            acceleration += direction.scale(1.0 / a.mass);
+                        }
+                    }
+                    acceleration
+                })
+                .collect()
+        }, |_| false);
+        steps.get()
+    }
+
+    /// rounds the particles to `f32` for rendering, the ids are the indices of the particles,
+    /// as `f64` worlds never remove or reorder them, and every particle is in group 0
     pub fn get_mass_points(&self) -> Vec<MassPoint> {
-        let mut mass_points = Vec::with_capacity(self.particles.len());
-        for particle in &*self.particles {
-            mass_points.push(MassPoint {
-                mass: particle.mass,
-                position: particle.position.to_cartesian()
+        self.particles.iter()
+            .enumerate()
+            .map(|(id, particle)| MassPoint {
+                mass: particle.mass as f32,
+                position: (particle.position.x as f32, particle.position.y as f32),
+                velocity: (particle.velocity.x as f32, particle.velocity.y as f32),
+                id: id as u32,
+                group: 0
             })
+            .collect()
+    }
+
+    /// kinetic energy of every moving particle
+    pub fn kinetic_energy(&self) -> f64 {
+        diagnostics::kinetic_energy_f64(&self.particles)
+    }
+
+    /// potential energy of every pair of particles
+    pub fn potential_energy(&self) -> f64 {
+        diagnostics::potential_energy_f64(&self.particles, self.g, self.softening)
+    }
+
+    pub fn total_energy(&self) -> f64 {
+        self.kinetic_energy() + self.potential_energy()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU16;
+    use std::thread;
+    use rayon::ThreadPoolBuilder;
+    use crate::world::{Boundary, Integrator};
+    use crate::world::cpu::CPUWorld;
+    use crate::world::generate::random_particles;
+    use super::{rows_per_task, ParWorld, TASKS_PER_THREAD};
+
+    #[test]
+    fn columns_tick_like_particles() {
+        for integrator in [Integrator::Euler, Integrator::RK4] {
+            let world = || ParWorld::new(random_particles(23, 100, 500.0))
+                .with_integrator(integrator)
+                .with_softening(0.01)
+                .with_boundary(Boundary::Periodic { width: 1.0, height: 1.0 })
+                .with_chunk_size(Some(8));
+            let (mut columns, mut particles) = (world(), world());
+            assert!(columns.ticks_columns());
+            for _ in 0..5 {
+                columns.tick(20.0, NonZeroU16::new(20).unwrap());
+                particles.tick_with_observer(20.0, NonZeroU16::new(20).unwrap(), |_| {});
+            }
+            assert_eq!(columns.get_mass_points(), particles.get_mass_points(), "{:?}", integrator);
+        }
+    }
+
+    /// the buffers of the tasks are merged in the same order however the tasks are stolen
+    #[test]
+    fn symmetric_pairs_repeat_bit_for_bit() {
+        for compensated_summation in [false, true] {
+            let world = || ParWorld::new(random_particles(23, 300, 500.0))
+                .with_compensated_summation(compensated_summation)
+                .with_threads(4)
+                .with_chunk_size(Some(16));
+            let (mut first, mut second) = (world(), world());
+            for frame in 0..5 {
+                first.tick(20.0, NonZeroU16::new(5).unwrap());
+                second.tick(20.0, NonZeroU16::new(5).unwrap());
+                assert_eq!(first.get_mass_points(), second.get_mass_points(), "frame {}", frame);
+            }
+        }
+    }
+
+    #[test]
+    fn worlds_with_pools_of_their_own_tick_side_by_side() {
+        let particles = random_particles(23, 300, 500.0);
+        let mut cpu = CPUWorld::new(particles.clone()).with_deterministic_summation(true);
+        for _ in 0..10 {
+            cpu.tick(20.0, NonZeroU16::new(20).unwrap());
+        }
+        let handles = [1, 3].map(|threads| {
+            let particles = particles.clone();
+            thread::spawn(move || {
+                let mut par = ParWorld::new(particles)
+                    .with_deterministic_summation(true)
+                    .with_threads(threads)
+                    .with_chunk_size(Some(16));
+                assert_eq!(par.pool.as_ref().unwrap().current_num_threads(), threads);
+                for _ in 0..10 {
+                    par.tick(20.0, NonZeroU16::new(20).unwrap());
+                }
+                par.get_mass_points()
+            })
+        });
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), cpu.get_mass_points());
+        }
+    }
+
+    #[test]
+    fn small_worlds_are_a_single_task_like_the_cpu() {
+        ThreadPoolBuilder::new().num_threads(4).build().unwrap().install(|| {
+            assert!(rows_per_task(None, 100) >= 100);
+            assert_eq!(rows_per_task(None, 100_000), 100_000 / (TASKS_PER_THREAD * 4));
+            assert_eq!(rows_per_task(Some(0), 100), 1);
+            assert_eq!(rows_per_task(Some(7), 100), 7);
+        });
+        let particles = random_particles(23, 100, 500.0);
+        let mut cpu = CPUWorld::new(particles.clone());
+        let mut par = ParWorld::new(particles).with_threads(4);
+        for frame in 0..10 {
+            cpu.tick(20.0, NonZeroU16::new(20).unwrap());
+            par.tick(20.0, NonZeroU16::new(20).unwrap());
+            assert_eq!(par.get_mass_points(), cpu.get_mass_points(), "frame {}", frame);
+        }
+    }
+
+    #[test]
+    fn deterministic_summation_matches_the_cpu_bit_for_bit() {
+        let particles = random_particles(23, 100, 500.0);
+        let mut cpu = CPUWorld::new(particles.clone()).with_deterministic_summation(true);
+        // the tasks and the threads they run on differ, the order of the sums doesn't
+        let mut pars = [(1, None), (2, Some(8)), (4, Some(1))].map(|(threads, chunk_size)| {
+            ParWorld::new(particles.clone())
+                .with_deterministic_summation(true)
+                .with_threads(threads)
+                .with_chunk_size(chunk_size)
+        });
+        for frame in 0..50 {
+            cpu.tick(20.0, NonZeroU16::new(20).unwrap());
+            for par in &mut pars {
+                par.tick(20.0, NonZeroU16::new(20).unwrap());
+                assert_eq!(par.get_mass_points(), cpu.get_mass_points(), "frame {}", frame);
+            }
         }
-        mass_points
     }
 }
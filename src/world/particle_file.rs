@@ -0,0 +1,224 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+#[cfg(feature = "serde")]
+use std::io::Read;
+use std::mem;
+use std::path::Path;
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+use crate::{Particle, Vector};
+
+/// columns of every line of a csv particle file, positions and velocities are cartesian
+pub const PARTICLE_COLUMNS: [&str; 5] = ["mass", "x", "y", "vx", "vy"];
+
+/// reads the particles a run starts with from the file at `path`, csv as [`read_csv`] reads it when its extension is
+/// `csv`, and JSON as [`read_json`] reads it when it is `json`
+pub fn read_particle_file<P: AsRef<Path>>(path: P) -> Result<Vec<Particle>, ParticleFileError> {
+    let path = path.as_ref();
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("");
+    match extension {
+        "csv" => read_csv(BufReader::new(File::open(path)?)),
+        #[cfg(feature = "serde")]
+        "json" => read_json(BufReader::new(File::open(path)?)),
+        _ => Err(ParticleFileError::Extension(extension.to_string()))
+    }
+}
+
+/// reads a particle from every line of [`PARTICLE_COLUMNS`] separated by commas, the first of which may be a header
+/// of the columns, blank lines and those starting with `#` are left out
+///
+/// the particles have a radius of 0 and are in group 0, the ids are given by the world
+pub fn read_csv<R: BufRead>(reader: R) -> Result<Vec<Particle>, ParticleFileError> {
+    let mut particles = Vec::new();
+    let mut header = true;
+    for (index, line) in reader.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if mem::take(&mut header) && fields == PARTICLE_COLUMNS {
+            continue
+        }
+        if fields.len() != PARTICLE_COLUMNS.len() {
+            return Err(ParticleFileError::Line(line_number, format!(
+                "expected the {} columns {}, found {}", PARTICLE_COLUMNS.len(), PARTICLE_COLUMNS.join(","), fields.len()
+            )))
+        }
+        let mut values = [0.0; 5];
+        for ((value, field), column) in values.iter_mut().zip(fields).zip(PARTICLE_COLUMNS) {
+            *value = match field.parse::<f32>() {
+                Ok(parsed) if parsed.is_finite() => parsed,
+                _ => return Err(ParticleFileError::Line(line_number, format!("expected a finite number for {}, not `{}`", column, field)))
+            };
+        }
+        let [mass, x, y, vx, vy] = values;
+        particles.push(particle(mass, x, y, vx, vy).map_err(|error| ParticleFileError::Line(line_number, error))?);
+    }
+    Ok(particles)
+}
+
+/// reads a JSON array of particles such as `[{"mass": 1000, "x": 0, "y": 0}, {"mass": 1, "x": 0.5, "y": 0, "vx": 0, "vy": 1.4}]`,
+/// with the keys of [`PARTICLE_COLUMNS`], the velocity being 0 where it is left out
+///
+/// the particles have a radius of 0 and are in group 0, the ids are given by the world
+#[cfg(feature = "serde")]
+pub fn read_json<R: Read>(reader: R) -> Result<Vec<Particle>, ParticleFileError> {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct JsonParticle {
+        mass: f32,
+        x: f32,
+        y: f32,
+        #[serde(default)]
+        vx: f32,
+        #[serde(default)]
+        vy: f32
+    }
+
+    let particles: Vec<JsonParticle> = serde_json::from_reader(reader)?;
+    particles.iter()
+        .enumerate()
+        .map(|(index, JsonParticle { mass, x, y, vx, vy })| {
+            particle(*mass, *x, *y, *vx, *vy).map_err(|error| ParticleFileError::Particle(index, error))
+        })
+        .collect()
+}
+
+/// the particle of a line or an object, which has to have a positive mass, and a finite position and velocity
+fn particle(mass: f32, x: f32, y: f32, vx: f32, vy: f32) -> Result<Particle, String> {
+    if !(mass > 0.0 && mass.is_finite()) {
+        return Err(format!("expected a positive mass, not {}", mass))
+    }
+    if let Some((column, value)) = [("x", x), ("y", y), ("vx", vx), ("vy", vy)].into_iter().find(|(_, value)| !value.is_finite()) {
+        return Err(format!("expected a finite number for {}, not {}", column, value))
+    }
+    Ok(Particle {
+        mass,
+        position: Vector::from_cartesian(x, y),
+        velocity: Vector::from_cartesian(vx, vy),
+        radius: 0.0,
+        fixed: 0,
+        id: 0,
+        group: 0
+    })
+}
+
+/// why a particle file couldn't be read, lines count from 1
+#[derive(Debug)]
+pub enum ParticleFileError {
+    Io(io::Error),
+    /// the extension of the file isn't one of a format that can be read
+    Extension(String),
+    /// a line of a csv file
+    Line(usize, String),
+    /// a particle of a JSON file, by its index
+    Particle(usize, String),
+    #[cfg(feature = "serde")]
+    Json(serde_json::Error)
+}
+
+impl Display for ParticleFileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParticleFileError::Io(error) => write!(f, "{}", error),
+            #[cfg(feature = "serde")]
+            ParticleFileError::Extension(extension) => write!(f, "expected a .csv or .json file, not .{}", extension),
+            #[cfg(not(feature = "serde"))]
+            ParticleFileError::Extension(extension) => write!(f, "expected a .csv file, .json needs the serde feature, not .{}", extension),
+            ParticleFileError::Line(line, error) => write!(f, "line {}: {}", line, error),
+            ParticleFileError::Particle(index, error) => write!(f, "particle {}: {}", index, error),
+            #[cfg(feature = "serde")]
+            ParticleFileError::Json(error) => write!(f, "{}", error)
+        }
+    }
+}
+
+impl Error for ParticleFileError {}
+
+impl From<io::Error> for ParticleFileError {
+    fn from(error: io::Error) -> Self {
+        ParticleFileError::Io(error)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for ParticleFileError {
+    fn from(error: serde_json::Error) -> Self {
+        ParticleFileError::Json(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the message of the error of `csv` at `line`
+    fn csv_error(csv: &str, line: usize) -> String {
+        match read_csv(csv.as_bytes()) {
+            Err(ParticleFileError::Line(error_line, message)) if error_line == line => message,
+            result => panic!("expected an error at line {}, not {:?}", line, result.map(|particles| particles.len()))
+        }
+    }
+
+    #[test]
+    fn csv_particles_are_read_cartesian() {
+        let csv = "# a comment\nmass,x,y,vx,vy\n\n1000, 0, 0, 0, 0\n0.5,3,4,-1,0\n";
+        let particles = read_csv(csv.as_bytes()).unwrap();
+        assert_eq!(particles.len(), 2);
+        assert_eq!(particles[0].mass, 1000.0);
+        let (x, y) = particles[1].position.to_cartesian();
+        let (vx, vy) = particles[1].velocity.to_cartesian();
+        assert!((x - 3.0).abs() < 1e-6 && (y - 4.0).abs() < 1e-6, "at {}, {}", x, y);
+        assert!((vx + 1.0).abs() < 1e-6 && vy.abs() < 1e-6, "moving at {}, {}", vx, vy);
+        // without a header too
+        assert_eq!(read_csv("1,0,0,0,0".as_bytes()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn malformed_csv_lines_are_reported_by_number() {
+        assert!(csv_error("mass,x,y,vx,vy\n1,0,0,0\n", 2).contains("found 4"));
+        assert!(csv_error("1,0,0,0,0\n\n1,zero,0,0,0\n", 3).contains("for x, not `zero`"));
+        assert!(csv_error("1,0,0,0,inf\n", 1).contains("for vy"));
+        assert!(csv_error("1,0,0,0,0\n-1,0,0,0,0\n", 2).contains("positive mass"));
+        assert!(csv_error("0,0,0,0,0\n", 1).contains("positive mass"));
+        assert!(csv_error("NaN,0,0,0,0\n", 1).contains("for mass"));
+    }
+
+    #[test]
+    fn particles_need_a_positive_mass_and_finite_numbers() {
+        assert!(particle(1.0, 0.0, 0.0, 0.0, 0.0).is_ok());
+        for mass in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(particle(mass, 0.0, 0.0, 0.0, 0.0).is_err(), "a mass of {} was let through", mass);
+        }
+        for value in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert_eq!(particle(1.0, value, 0.0, 0.0, 0.0).unwrap_err(), format!("expected a finite number for x, not {}", value));
+            assert!(particle(1.0, 0.0, 0.0, 0.0, value).unwrap_err().contains("for vy"));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_particles_default_to_resting() {
+        let json = r#"[{"mass": 1000, "x": 0, "y": 0}, {"mass": 1, "x": 0.5, "y": 0, "vx": 0, "vy": 1.4}]"#;
+        let particles = read_json(json.as_bytes()).unwrap();
+        assert_eq!(particles.len(), 2);
+        assert_eq!(particles[0].velocity.magnitude, 0.0);
+        let (vx, vy) = particles[1].velocity.to_cartesian();
+        assert!(vx.abs() < 1e-6 && (vy - 1.4).abs() < 1e-6, "moving at {}, {}", vx, vy);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn malformed_json_particles_are_reported_by_index() {
+        let json = r#"[{"mass": 1, "x": 0, "y": 0}, {"mass": 0, "x": 1, "y": 0}]"#;
+        assert!(matches!(read_json(json.as_bytes()), Err(ParticleFileError::Particle(1, message)) if message.contains("positive mass")));
+        // unknown and missing keys
+        assert!(matches!(read_json(r#"[{"mass": 1, "x": 0, "y": 0, "z": 0}]"#.as_bytes()), Err(ParticleFileError::Json(_))));
+        assert!(matches!(read_json(r#"[{"mass": 1, "x": 0}]"#.as_bytes()), Err(ParticleFileError::Json(_))));
+    }
+}
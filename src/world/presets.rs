@@ -0,0 +1,135 @@
+use std::f32::consts::{FRAC_PI_3, TAU};
+use crate::{Particle, Vector};
+
+/// masses of the planets from Mercury to Neptune as fractions of the mass of the sun
+pub const PLANET_MASS_RATIOS: [f32; 8] = [1.660e-7, 2.448e-6, 3.003e-6, 3.227e-7, 9.548e-4, 2.859e-4, 4.366e-5, 5.151e-5];
+/// radii of the orbits of the planets from Mercury to Neptune in astronomical units
+pub const PLANET_ORBITS: [f32; 8] = [0.387, 0.723, 1.0, 1.524, 5.203, 9.537, 19.19, 30.07];
+
+/// the sun at the origin with the 8 planets on circular orbits, scaled so that Neptune orbits at a radius of 1,
+/// each planet a golden angle further around than the one inside it, the sun moving so that the total momentum is zero
+///
+/// the orbits stay circular, Mercury going around the sun about 685 times for every orbit of Neptune, so the substeps
+/// have to be well under a hundredth of the period of Mercury for it to keep its orbit, which is
+/// `TAU * (0.387 / 30.07).powf(1.5) / (g * sun_mass).sqrt()`
+///
+/// the radii, ids and groups are left at 0
+pub fn solar_system(g: f32, sun_mass: f32) -> Vec<Particle> {
+    let golden_angle = TAU * (1.0 - 1.0 / 1.618034);
+    let neptune = PLANET_ORBITS[7];
+    let mut particles = vec![body(sun_mass, (0.0, 0.0), (0.0, 0.0))];
+    let mut momentum = (0.0, 0.0);
+    for (i, (ratio, orbit)) in PLANET_MASS_RATIOS.into_iter().zip(PLANET_ORBITS).enumerate() {
+        let mass = ratio * sun_mass;
+        let radius = orbit / neptune;
+        let speed = f32::sqrt(g * sun_mass / radius);
+        let angle = i as f32 * golden_angle;
+        let (sin, cos) = angle.sin_cos();
+        let velocity = (-sin * speed, cos * speed);
+        momentum = (momentum.0 + mass * velocity.0, momentum.1 + mass * velocity.1);
+        particles.push(body(mass, (cos * radius, sin * radius), velocity));
+    }
+    particles[0].velocity = Vector::from_cartesian(-momentum.0 / sun_mass, -momentum.1 / sun_mass);
+    particles
+}
+
+/// three bodies of `mass` chasing each other along a figure eight, the periodic solution found by Chenciner and
+/// Montgomery, crossing itself at the origin and stretching about `size` along the x axis to either side of it, with
+/// the center of mass resting at the origin
+///
+/// the bodies return to where they started after every [`figure_eight_period`], the orbit is stable, so with short
+/// enough substeps they keep to the eight rather than drifting off of it, after one period in 2000 substeps with
+/// [`Verlet`](crate::world::Integrator::Verlet) every body lands within 0.0001 of `size` of where it started
+///
+/// the radii, ids and groups are left at 0
+pub fn figure_eight(g: f32, mass: f32, size: f32) -> Vec<Particle> {
+    let (x, y) = (0.9700044 * size, -0.24308753 * size);
+    // the velocities of the solution for a gravitational constant, masses and size of 1
+    let speed = f32::sqrt(g * mass / size);
+    let (vx, vy) = (-0.9324074 * speed, -0.86473146 * speed);
+    vec![
+        body(mass, (x, y), (-vx / 2.0, -vy / 2.0)),
+        body(mass, (-x, -y), (-vx / 2.0, -vy / 2.0)),
+        body(mass, (0.0, 0.0), (vx, vy))
+    ]
+}
+
+/// time for the bodies of [`figure_eight`] to go around the eight once
+pub fn figure_eight_period(g: f32, mass: f32, size: f32) -> f32 {
+    6.325914 * f32::sqrt(size.powi(3) / (g * mass))
+}
+
+/// a secondary body on a circular orbit around a primary, `separation` apart, and a small body a thousandth of the
+/// mass of the secondary at their L4 Lagrange point, 60 degrees ahead of the secondary, all going around their center
+/// of mass at the origin together, the secondary starting on the positive x axis
+///
+/// while the secondary is under about 1/25 of the mass of the primary, the small body stays near L4, slowly swinging
+/// around it along the orbit of the secondary, above that it drifts away after a few orbits
+///
+/// the radii, ids and groups are left at 0
+pub fn lagrange_l4(g: f32, primary_mass: f32, secondary_mass: f32, separation: f32) -> Vec<Particle> {
+    let total_mass = primary_mass + secondary_mass;
+    let angular_speed = f32::sqrt(g * total_mass / separation.powi(3));
+    let primary = (-separation * secondary_mass / total_mass, 0.0);
+    let secondary = (separation * primary_mass / total_mass, 0.0);
+    // L4 makes an equilateral triangle with the primary and the secondary
+    let l4 = (primary.0 + separation * FRAC_PI_3.cos(), separation * FRAC_PI_3.sin());
+    // everything turns together, as a rigid body would
+    let orbiting = |mass: f32, (x, y): (f32, f32)| body(mass, (x, y), (-y * angular_speed, x * angular_speed));
+    vec![
+        orbiting(primary_mass, primary),
+        orbiting(secondary_mass, secondary),
+        orbiting(secondary_mass / 1000.0, l4)
+    ]
+}
+
+fn body(mass: f32, (x, y): (f32, f32), (vx, vy): (f32, f32)) -> Particle {
+    Particle {
+        mass,
+        position: Vector::from_cartesian(x, y),
+        velocity: Vector::from_cartesian(vx, vy),
+        radius: 0.0,
+        fixed: 0,
+        id: 0,
+        group: 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU16;
+    use crate::world::Integrator;
+    use crate::world::cpu::CPUWorld;
+    use super::*;
+
+    #[test]
+    fn figure_eight_returns_after_a_period() {
+        let (g, mass, size) = (2.0, 3.0, 0.5);
+        let particles = figure_eight(g, mass, size);
+        let mut world = CPUWorld::new(particles.clone()).with_g(g).with_integrator(Integrator::Verlet);
+        let (energy, momentum) = (world.total_energy(), world.total_momentum());
+        world.tick(figure_eight_period(g, mass, size), NonZeroU16::new(2000).unwrap());
+        for (particle, mass_point) in particles.iter().zip(world.get_mass_points()) {
+            let (x, y) = particle.position.to_cartesian();
+            let apart = f32::hypot(mass_point.position.0 - x, mass_point.position.1 - y);
+            assert!(apart < 1e-4 * size, "a body is {} of the size from where it started", apart / size);
+        }
+        let drift = ((world.total_energy() - energy) / energy).abs();
+        assert!(drift < 1e-5, "the energy drifted by {} of itself", drift);
+        // the center of mass rests at the origin, relative to the momentum of the bodies, which cancels out
+        let scale: f32 = particles.iter().map(|particle| particle.mass * particle.velocity.magnitude).sum();
+        for momentum in [momentum, world.total_momentum()] {
+            assert!(momentum.magnitude < 1e-5 * scale, "the momentum is {} of its scale", momentum.magnitude / scale);
+        }
+    }
+
+    #[test]
+    fn solar_system_rests_at_the_origin() {
+        let particles = solar_system(1.0, 1.0);
+        assert_eq!(particles.len(), 9);
+        let world = CPUWorld::new(particles.clone());
+        assert!(world.total_momentum().magnitude < 1e-6);
+        // Neptune orbits at a radius of 1
+        assert!((particles[8].position.magnitude - 1.0).abs() < 1e-6);
+    }
+}
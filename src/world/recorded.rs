@@ -0,0 +1,175 @@
+use std::collections::VecDeque;
+use std::num::{NonZeroU16, NonZeroUsize};
+use crate::{MassPoint, Particle};
+use crate::world::World;
+use crate::world::checkpoint::Checkpoint;
+
+/// a [`World`] that keeps the state after each of its last ticks, so it can be [`rewind`](Self::rewind)ed,
+/// as [`Checkpoint`]s of the inner world
+///
+/// at most `capacity` states are kept, the oldest are dropped first, the state it was created with counts as one
+pub struct RecordedWorld<W> {
+    world: W,
+    history: VecDeque<Checkpoint>,
+    capacity: NonZeroUsize,
+    frame: usize,
+    time: f32,
+    steps: NonZeroU16
+}
+
+impl<W: World> RecordedWorld<W> {
+    pub fn new(world: W, capacity: NonZeroUsize) -> Self {
+        let mut recorded = Self {
+            world,
+            history: VecDeque::with_capacity(capacity.get()),
+            capacity,
+            frame: 0,
+            time: 0.0,
+            steps: NonZeroU16::new(1).unwrap()
+        };
+        recorded.record();
+        recorded
+    }
+
+    /// ticks since this world was created, less the ones rewound
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// ticks that can currently be rewound
+    pub fn recorded_frames(&self) -> usize {
+        self.history.len() - 1
+    }
+
+    /// restores the state of `frames` ticks ago, or of the oldest recorded tick if there are fewer,
+    /// and forgets every state after it, returns the number of ticks actually rewound
+    pub fn rewind(&mut self, frames: usize) -> usize {
+        let frames = frames.min(self.recorded_frames());
+        self.history.truncate(self.history.len() - frames);
+        let checkpoint = self.history.back().unwrap();
+        self.world.resume(checkpoint);
+        self.frame = checkpoint.frame;
+        frames
+    }
+
+    pub fn inner(&self) -> &W {
+        &self.world
+    }
+
+    pub fn into_inner(self) -> W {
+        self.world
+    }
+
+    fn record(&mut self) {
+        if self.history.len() == self.capacity.get() {
+            self.history.pop_front();
+        }
+        self.history.push_back(self.world.checkpoint(self.frame, self.time, self.steps));
+    }
+
+    /// replaces the latest state, after particles were added or removed between ticks
+    fn record_again(&mut self) {
+        self.history.pop_back();
+        self.record();
+    }
+}
+
+impl<W: World> World for RecordedWorld<W> {
+    fn tick(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        self.tick_with_observer(time, steps, |_| {})
+    }
+
+    fn tick_with_observer<O: FnMut(&[Particle])>(&mut self, time: f32, steps: NonZeroU16, observer: O) -> u16 {
+        let taken = self.world.tick_with_observer(time, steps, observer);
+        self.frame += 1;
+        self.time = time;
+        self.steps = steps;
+        self.record();
+        taken
+    }
+
+    fn tick_reverse(&mut self, time: f32, steps: NonZeroU16) -> u16 {
+        let taken = self.world.tick_reverse(time, steps);
+        self.frame += 1;
+        self.time = time;
+        self.steps = steps;
+        self.record();
+        taken
+    }
+
+    fn get_mass_points(&self) -> Vec<MassPoint> {
+        self.world.get_mass_points()
+    }
+
+    fn get_particles(&self) -> Vec<Particle> {
+        self.world.get_particles()
+    }
+
+    fn total_energy(&self) -> f32 {
+        self.world.total_energy()
+    }
+
+    fn add_particle(&mut self, particle: Particle) -> u32 {
+        let id = self.world.add_particle(particle);
+        self.record_again();
+        id
+    }
+
+    fn remove_particle(&mut self, index: usize) -> Particle {
+        let particle = self.world.remove_particle(index);
+        self.record_again();
+        particle
+    }
+
+    fn checkpoint(&self, frame: usize, time: f32, steps: NonZeroU16) -> Checkpoint {
+        self.world.checkpoint(frame, time, steps)
+    }
+
+    /// also forgets every recorded state, the state of `checkpoint` is the first one of the new history
+    fn resume(&mut self, checkpoint: &Checkpoint) {
+        self.world.resume(checkpoint);
+        self.history.clear();
+        self.frame = checkpoint.frame;
+        self.record();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::{NonZeroU16, NonZeroUsize};
+    use crate::world::World;
+    use crate::world::cpu::CPUWorld;
+    use crate::world::generate::random_particles;
+    use super::RecordedWorld;
+
+    #[test]
+    fn rewound_ticks_repeat_exactly() {
+        let steps = NonZeroU16::new(20).unwrap();
+        let mut world = RecordedWorld::new(CPUWorld::new(random_particles(23, 50, 500.0)), NonZeroUsize::new(5).unwrap());
+        let mut frames = Vec::new();
+        for _ in 0..10 {
+            world.tick(20.0, steps);
+            frames.push(world.get_mass_points());
+        }
+        assert_eq!(world.recorded_frames(), 4);
+        assert_eq!(world.rewind(3), 3);
+        assert_eq!(world.frame(), 7);
+        assert_eq!(world.get_mass_points(), frames[6]);
+        for frame in &frames[7..] {
+            world.tick(20.0, steps);
+            assert_eq!(&world.get_mass_points(), frame);
+        }
+    }
+
+    #[test]
+    fn rewinds_no_further_than_the_capacity() {
+        let steps = NonZeroU16::new(1).unwrap();
+        let mut world = RecordedWorld::new(CPUWorld::new(random_particles(23, 10, 500.0)), NonZeroUsize::new(3).unwrap());
+        for _ in 0..5 {
+            world.tick(20.0, steps);
+        }
+        assert_eq!(world.rewind(10), 2);
+        assert_eq!(world.frame(), 3);
+        assert_eq!(world.rewind(1), 0);
+    }
+}
@@ -0,0 +1,170 @@
+use std::ops::Range;
+use wide::{f32x8, CmpEq};
+use crate::{Particle, Vector};
+use crate::world::Boundary;
+use crate::world::force::ForceModel;
+use crate::world::store::ParticleStore;
+
+/// interaction partners processed at once
+const LANES: usize = 8;
+
+/// positions and masses of the particles as separate arrays, so that consecutive particles fill the lanes of a vector
+///
+/// this is only built for the duration of a force computation, which is cheap next to the pairs themselves, out of
+/// whole [`Particle`]s or the mass and position columns of a [`ParticleStore`]
+struct Columns {
+    x: Vec<f32>,
+    y: Vec<f32>,
+    mass: Vec<f32>
+}
+
+impl Columns {
+    fn new(particles: &[Particle]) -> Self {
+        Self::of(particles.iter().map(|particle| (particle.mass, particle.position)))
+    }
+
+    fn of_store(particles: &ParticleStore) -> Self {
+        Self::of(particles.masses().iter().copied().zip(particles.positions().iter().copied()))
+    }
+
+    fn of(particles: impl ExactSizeIterator<Item = (f32, Vector)>) -> Self {
+        let mut columns = Self {
+            x: Vec::with_capacity(particles.len()),
+            y: Vec::with_capacity(particles.len()),
+            mass: Vec::with_capacity(particles.len())
+        };
+        for (mass, position) in particles {
+            let (x, y) = position.to_cartesian();
+            columns.x.push(x);
+            columns.y.push(y);
+            columns.mass.push(mass);
+        }
+        columns
+    }
+}
+
+/// vectorized version of the pairwise force computation of [`CPUWorld`](crate::world::cpu::CPUWorld),
+/// indexed the same as `particles`
+///
+/// every unordered pair is visited once, the partners of each particle are processed [`LANES`] at a time
+/// with a scalar loop for the remainder, the accelerations are summed in cartesian coordinates, so results
+/// only match the scalar computation up to rounding
+pub fn compute_accelerations<F: ForceModel>(particles: &[Particle], force_model: &F, softening: f32, boundary: Boundary) -> Vec<Vector> {
+    let columns = Columns::new(particles);
+    let (ax, ay) = compute_rows(&columns, 0..particles.len(), force_model, softening, boundary);
+    to_vectors(ax, ay)
+}
+
+/// [`compute_accelerations`] of the particles of `particles`, without taking them out of the store
+pub fn compute_store_accelerations<F: ForceModel>(particles: &ParticleStore, force_model: &F, softening: f32, boundary: Boundary) -> Vec<Vector> {
+    let columns = Columns::of_store(particles);
+    let (ax, ay) = compute_rows(&columns, 0..particles.len(), force_model, softening, boundary);
+    to_vectors(ax, ay)
+}
+
+/// parallel version of [`compute_accelerations`] for [`ParWorld`](crate::world::par::ParWorld), each task computes
+/// the pairs of up to `rows` particles into buffers of its own, which are then added up in a fixed order,
+/// so the result only depends on `rows`, and is that of [`compute_accelerations`] when a single task computes every row
+pub fn par_compute_accelerations<F: ForceModel>(particles: &[Particle], rows: usize, force_model: &F, softening: f32, boundary: Boundary) -> Vec<Vector> {
+    let columns = Columns::new(particles);
+    let (ax, ay) = split_rows(&columns, 0..particles.len(), rows, force_model, softening, boundary);
+    to_vectors(ax, ay)
+}
+
+/// [`par_compute_accelerations`] of the particles of `particles`, without taking them out of the store
+pub fn par_compute_store_accelerations<F: ForceModel>(particles: &ParticleStore, rows: usize, force_model: &F, softening: f32, boundary: Boundary) -> Vec<Vector> {
+    let columns = Columns::of_store(particles);
+    let (ax, ay) = split_rows(&columns, 0..particles.len(), rows, force_model, softening, boundary);
+    to_vectors(ax, ay)
+}
+
+fn split_rows<F: ForceModel>(columns: &Columns, range: Range<usize>, rows: usize, force_model: &F, softening: f32, boundary: Boundary) -> (Vec<f32>, Vec<f32>) {
+    if range.len() <= rows {
+        return compute_rows(columns, range, force_model, softening, boundary)
+    }
+    let mid = (range.start + range.end) / 2;
+    let ((mut ax, mut ay), (hi_ax, hi_ay)) = rayon::join(
+        || split_rows(columns, range.start..mid, rows, force_model, softening, boundary),
+        || split_rows(columns, mid..range.end, rows, force_model, softening, boundary)
+    );
+    for (a, b) in ax.iter_mut().zip(hi_ax) {
+        *a += b;
+    }
+    for (a, b) in ay.iter_mut().zip(hi_ay) {
+        *a += b;
+    }
+    (ax, ay)
+}
+
+/// cartesian accelerations of every particle caused by the pairs whose lower index is in `rows`
+fn compute_rows<F: ForceModel>(columns: &Columns, rows: Range<usize>, force_model: &F, softening: f32, boundary: Boundary) -> (Vec<f32>, Vec<f32>) {
+    let particles_len = columns.x.len();
+    let mut ax = vec![0.0; particles_len];
+    let mut ay = vec![0.0; particles_len];
+    let softening_sq = softening * softening;
+    for i in rows {
+        let (xi, yi, mi) = (columns.x[i], columns.y[i], columns.mass[i]);
+        let (mut axi, mut ayi) = (f32x8::splat(0.0), f32x8::splat(0.0));
+        let mut j = i + 1;
+        while j + LANES <= particles_len {
+            let lanes = j..j + LANES;
+            let x = f32x8::new(columns.x[lanes.clone()].try_into().unwrap());
+            let y = f32x8::new(columns.y[lanes.clone()].try_into().unwrap());
+            let mj: [f32; LANES] = columns.mass[lanes.clone()].try_into().unwrap();
+            let (dx, dy) = displacement(boundary, x - f32x8::splat(xi), y - f32x8::splat(yi));
+            let distance_sq = dx * dx + dy * dy;
+            let r_sq = (distance_sq + f32x8::splat(softening_sq)).to_array();
+            let f = f32x8::new(std::array::from_fn(|k| force_model.force(mi, mj[k], r_sq[k])));
+            // force over distance, zero for particles on top of each other or infinitely strong forces
+            let skip = distance_sq.cmp_eq(f32x8::splat(0.0)) | f.is_inf();
+            let f_over_r = skip.blend(f32x8::splat(0.0), f / distance_sq.sqrt());
+            let (fx, fy) = (f_over_r * dx, f_over_r * dy);
+            // f = ma
+            axi += fx / f32x8::splat(mi);
+            ayi += fy / f32x8::splat(mi);
+            let mj = f32x8::new(mj);
+            let axj = f32x8::new(ax[lanes.clone()].try_into().unwrap()) - fx / mj;
+            let ayj = f32x8::new(ay[lanes.clone()].try_into().unwrap()) - fy / mj;
+            ax[lanes.clone()].copy_from_slice(axj.as_array_ref());
+            ay[lanes].copy_from_slice(ayj.as_array_ref());
+            j += LANES;
+        }
+        let (mut axi, mut ayi) = (axi.reduce_add(), ayi.reduce_add());
+        for j in j..particles_len {
+            let (dx, dy) = boundary.displacement((columns.x[j] - xi, columns.y[j] - yi));
+            let distance_sq = dx * dx + dy * dy;
+            let f = force_model.force(mi, columns.mass[j], distance_sq + softening_sq);
+            if distance_sq == 0.0 || f.is_infinite() {
+                continue
+            }
+            let f_over_r = f / f32::sqrt(distance_sq);
+            // f = ma
+            axi += f_over_r * dx / mi;
+            ayi += f_over_r * dy / mi;
+            ax[j] -= f_over_r * dx / columns.mass[j];
+            ay[j] -= f_over_r * dy / columns.mass[j];
+        }
+        ax[i] += axi;
+        ay[i] += ayi;
+    }
+    (ax, ay)
+}
+
+fn to_vectors(ax: Vec<f32>, ay: Vec<f32>) -> Vec<Vector> {
+    ax.into_iter()
+        .zip(ay)
+        .map(|(x, y)| Vector::from_cartesian(x, y))
+        .collect()
+}
+
+/// lane wise [`Boundary::displacement`]
+#[inline(always)]
+fn displacement(boundary: Boundary, dx: f32x8, dy: f32x8) -> (f32x8, f32x8) {
+    match boundary {
+        Boundary::Open | Boundary::Reflective { .. } => (dx, dy),
+        Boundary::Periodic { width, height } => {
+            let (width, height) = (f32x8::splat(width), f32x8::splat(height));
+            (dx - width * (dx / width).round(), dy - height * (dy / height).round())
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::io::{Read, Write};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use crate::Particle;
+use crate::world::{AdaptiveSteps, Boundary, Collisions, Escape, Integrator};
+use crate::world::field::Field;
+
+/// the particles and settings of a [`CPUWorld`](crate::world::cpu::CPUWorld) or [`ParWorld`](crate::world::par::ParWorld),
+/// written and read as JSON by their `save_snapshot` and `from_snapshot`
+///
+/// particles are stored exactly, ids included, so a world loaded from a snapshot continues
+/// exactly like the one that saved it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot<F> {
+    pub particles: Vec<Particle>,
+    /// see [`Particle::id`]
+    pub next_id: u32,
+    pub integrator: Integrator,
+    pub force_model: F,
+    pub softening: f32,
+    pub drag: f32,
+    pub boundary: Boundary,
+    pub barnes_hut_theta: Option<f32>,
+    pub collisions: Collisions,
+    pub adaptive_steps: Option<AdaptiveSteps>,
+    pub fields: Vec<Field>,
+    pub escape: Option<Escape>,
+    pub escaped: usize,
+    pub compensated_summation: bool,
+    /// left out by snapshots written before it existed
+    #[serde(default)]
+    pub deterministic_summation: bool
+}
+
+impl<F: Serialize> Snapshot<F> {
+    pub fn write(&self, writer: impl Write) -> serde_json::Result<()> {
+        serde_json::to_writer_pretty(writer, self)
+    }
+}
+
+impl<F: DeserializeOwned> Snapshot<F> {
+    pub fn read(reader: impl Read) -> serde_json::Result<Self> {
+        serde_json::from_reader(reader)
+    }
+}
@@ -0,0 +1,188 @@
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use crate::{MassPoint, Particle, Vector};
+use crate::world::{drag_factor, Bodies, Boundary};
+
+/// particles stored as one array per component (structure of arrays), the storage of
+/// [`CPUWorld`](crate::world::cpu::CPUWorld) and [`ParWorld`](crate::world::par::ParWorld)
+///
+/// reading a single component of every particle, like [`mass_points`](Self::mass_points) does,
+/// only touches the memory of that component, the integrators step the velocity and position columns in place
+/// and the forces between particles are computed from the mass and position columns, whole [`Particle`]s are only
+/// taken out of the store for the settings of a world that work on them, such as collisions
+#[derive(Clone, Debug, Default)]
+pub struct ParticleStore {
+    mass: Vec<f32>,
+    position: Vec<Vector>,
+    velocity: Vec<Vector>,
+    radius: Vec<f32>,
+    fixed: Vec<u32>,
+    id: Vec<u32>,
+    group: Vec<u32>
+}
+
+impl ParticleStore {
+    pub fn len(&self) -> usize {
+        self.mass.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mass.is_empty()
+    }
+
+    pub fn get(&self, i: usize) -> Particle {
+        Particle {
+            mass: self.mass[i],
+            position: self.position[i],
+            velocity: self.velocity[i],
+            radius: self.radius[i],
+            fixed: self.fixed[i],
+            id: self.id[i],
+            group: self.group[i]
+        }
+    }
+
+    pub fn push(&mut self, particle: Particle) {
+        self.mass.push(particle.mass);
+        self.position.push(particle.position);
+        self.velocity.push(particle.velocity);
+        self.radius.push(particle.radius);
+        self.fixed.push(particle.fixed);
+        self.id.push(particle.id);
+        self.group.push(particle.group);
+    }
+
+    /// removes the particle at `i`, the last particle takes its place
+    pub fn swap_remove(&mut self, i: usize) -> Particle {
+        Particle {
+            mass: self.mass.swap_remove(i),
+            position: self.position.swap_remove(i),
+            velocity: self.velocity.swap_remove(i),
+            radius: self.radius.swap_remove(i),
+            fixed: self.fixed.swap_remove(i),
+            id: self.id.swap_remove(i),
+            group: self.group.swap_remove(i)
+        }
+    }
+
+    /// points every velocity the other way
+    pub fn reverse_velocities(&mut self) {
+        for velocity in &mut self.velocity {
+            *velocity = velocity.scale(-1.0);
+        }
+    }
+
+    pub fn masses(&self) -> &[f32] {
+        &self.mass
+    }
+
+    pub fn positions(&self) -> &[Vector] {
+        &self.position
+    }
+
+    /// scales the velocity of every moving particle for linear drag over a substep of length `time`, see
+    /// [`CPUWorld::with_drag`](crate::world::cpu::CPUWorld::with_drag)
+    pub fn damp(&mut self, drag: f32, time: f32) {
+        if let Some(factor) = drag_factor(drag, time) {
+            for (velocity, fixed) in self.velocity.iter_mut().zip(&self.fixed) {
+                if *fixed == 0 {
+                    *velocity = velocity.scale(factor);
+                }
+            }
+        }
+    }
+
+    /// [`Boundary::apply`] for the position and velocity columns
+    pub fn apply_boundary(&mut self, boundary: Boundary) {
+        if boundary == Boundary::Open {
+            return
+        }
+        for ((position, velocity), fixed) in self.position.iter_mut().zip(&mut self.velocity).zip(&self.fixed) {
+            if *fixed == 0 {
+                boundary.apply_to(position, velocity);
+            }
+        }
+    }
+
+    /// every particle, put back together
+    pub fn iter(&self) -> impl Iterator<Item = Particle> + '_ {
+        (0..self.len()).map(|i| self.get(i))
+    }
+
+    pub fn to_vec(&self) -> Vec<Particle> {
+        self.iter().collect()
+    }
+
+    /// reads only the masses, positions, velocities, ids and groups
+    pub fn mass_points(&self) -> Vec<MassPoint> {
+        self.mass.iter()
+            .zip(&self.position)
+            .zip(&self.velocity)
+            .zip(&self.id)
+            .zip(&self.group)
+            .map(|((((mass, position), velocity), id), group)| MassPoint {
+                mass: *mass,
+                position: position.to_cartesian(),
+                velocity: velocity.to_cartesian(),
+                id: *id,
+                group: *group
+            })
+            .collect()
+    }
+
+    /// parallel version of [`mass_points`](Self::mass_points)
+    pub fn par_mass_points(&self) -> Vec<MassPoint> {
+        self.mass.par_iter()
+            .zip(&self.position)
+            .zip(&self.velocity)
+            .zip(&self.id)
+            .zip(&self.group)
+            .map(|((((mass, position), velocity), id), group)| MassPoint {
+                mass: *mass,
+                position: position.to_cartesian(),
+                velocity: velocity.to_cartesian(),
+                id: *id,
+                group: *group
+            })
+            .collect()
+    }
+}
+
+impl Bodies for ParticleStore {
+    type Vector = Vector;
+
+    fn len(&self) -> usize {
+        ParticleStore::len(self)
+    }
+
+    fn velocity(&self, i: usize) -> Vector {
+        self.velocity[i]
+    }
+
+    fn is_fixed(&self, i: usize) -> bool {
+        self.fixed[i] != 0
+    }
+
+    fn kick(&mut self, i: usize, acceleration: &Vector, time: f32) {
+        self.velocity[i].step(acceleration, time);
+    }
+
+    fn drift(&mut self, i: usize, velocity: &Vector, time: f32) {
+        self.position[i].step(velocity, time);
+    }
+}
+
+impl From<Vec<Particle>> for ParticleStore {
+    fn from(particles: Vec<Particle>) -> Self {
+        particles.into_iter().collect()
+    }
+}
+
+impl FromIterator<Particle> for ParticleStore {
+    fn from_iter<I: IntoIterator<Item = Particle>>(particles: I) -> Self {
+        let mut store = Self::default();
+        for particle in particles {
+            store.push(particle);
+        }
+        store
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use crate::MassPoint;
+
+/// writes the mass points of every frame of a run as csv, a row per particle per frame, as the frames come rather
+/// than once the run is done, gzipped when the path ends in `.csv.gz`
+///
+/// [`write_frame`](Self::write_frame) keeps the first error and drops the frames after it,
+/// [`finish`](Self::finish) returns it
+pub struct TrajectoryWriter {
+    writer: csv::Writer<Output>,
+    frames: usize,
+    error: Option<csv::Error>
+}
+
+/// the columns of every row, `particle_index` being the index of the particle in its frame, see [`Particle::id`](crate::Particle::id)
+/// for `id`
+pub const TRAJECTORY_COLUMNS: [&str; 8] = ["frame", "particle_index", "id", "mass", "x", "y", "vx", "vy"];
+
+impl TrajectoryWriter {
+    /// creates the file at `path`, replacing it if there is one, and writes the header
+    pub fn create<P: AsRef<Path>>(path: P) -> csv::Result<Self> {
+        let gzip = path.as_ref().to_string_lossy().ends_with(".csv.gz");
+        let file = BufWriter::new(File::create(path)?);
+        let output = match gzip {
+            true => Output::Gzip(GzEncoder::new(file, Compression::default())),
+            false => Output::Plain(file)
+        };
+        let mut writer = csv::Writer::from_writer(output);
+        writer.write_record(TRAJECTORY_COLUMNS)?;
+        Ok(Self { writer, frames: 0, error: None })
+    }
+
+    /// writes a row for every one of `mass_points`, those of frame `frame`
+    pub fn write_frame(&mut self, frame: usize, mass_points: &[MassPoint]) {
+        if self.error.is_some() {
+            return
+        }
+        for (index, mass_point) in mass_points.iter().enumerate() {
+            // shortest representations that read back as the same f32, in full rather than with an exponent
+            let record = [
+                frame.to_string(),
+                index.to_string(),
+                mass_point.id.to_string(),
+                mass_point.mass.to_string(),
+                mass_point.position.0.to_string(),
+                mass_point.position.1.to_string(),
+                mass_point.velocity.0.to_string(),
+                mass_point.velocity.1.to_string()
+            ];
+            if let Err(error) = self.writer.write_record(&record) {
+                self.error = Some(error);
+                return
+            }
+        }
+        self.frames += 1;
+    }
+
+    /// the number of frames written, or the first error writing them, the file is complete once this returns
+    pub fn finish(self) -> csv::Result<usize> {
+        if let Some(error) = self.error {
+            return Err(error)
+        }
+        let output = self.writer.into_inner().map_err(|error| error.into_error())?;
+        output.finish()?;
+        Ok(self.frames)
+    }
+}
+
+/// the file written by a [`TrajectoryWriter`]
+enum Output {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>)
+}
+
+impl Output {
+    /// flushes everything, with the end of the gzip stream
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Output::Plain(mut file) => file.flush(),
+            Output::Gzip(encoder) => encoder.finish()?.flush()
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Plain(file) => file.write(buf),
+            Output::Gzip(encoder) => encoder.write(buf)
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Plain(file) => file.flush(),
+            Output::Gzip(encoder) => encoder.flush()
+        }
+    }
+}
+
+/// writes the positions of every frame of a run as a NumPy `.npy` file of little-endian `float32`s of shape
+/// `(frames, particles, 2)`, as the frames come, and the masses of the particles of the first frame as one of shape
+/// `(particles,)`, both readable with `numpy.load`
+///
+/// the particles are those of the first frame, in its order, each found in later frames by its
+/// [`id`](crate::Particle::id), so that they stay in their column once others are removed or merged, a particle
+/// missing from a frame has NaN for its position, and particles added after the first frame are left out
+///
+/// [`write_frame`](Self::write_frame) keeps the first error and drops the frames after it,
+/// [`finish`](Self::finish) returns it
+pub struct NpyTrajectoryWriter {
+    positions: BufWriter<File>,
+    masses_path: PathBuf,
+    /// column of every particle of the first frame by its id, and their masses, empty before the first frame
+    columns: HashMap<u32, usize>,
+    masses: Vec<f32>,
+    frames: usize,
+    error: Option<io::Error>
+}
+
+/// length of every header written by [`NpyTrajectoryWriter`], room for the shape of any run, so that the header of
+/// the positions can be written again once the number of frames is known
+const NPY_HEADER_LENGTH: usize = 128;
+
+impl NpyTrajectoryWriter {
+    /// creates the file of the positions at `positions_path`, replacing it if there is one, that of the masses is
+    /// written by [`finish`](Self::finish)
+    pub fn create<P: AsRef<Path>, M: AsRef<Path>>(positions_path: P, masses_path: M) -> io::Result<Self> {
+        let mut positions = BufWriter::new(File::create(positions_path)?);
+        positions.write_all(&npy_header(&[0, 0, 2]))?;
+        Ok(Self {
+            positions,
+            masses_path: masses_path.as_ref().to_path_buf(),
+            columns: HashMap::new(),
+            masses: Vec::new(),
+            frames: 0,
+            error: None
+        })
+    }
+
+    /// appends the positions of `mass_points` as the next frame
+    pub fn write_frame(&mut self, mass_points: &[MassPoint]) {
+        if self.error.is_some() {
+            return
+        }
+        if self.frames == 0 {
+            self.columns = mass_points.iter().enumerate().map(|(column, mass_point)| (mass_point.id, column)).collect();
+            self.masses = mass_points.iter().map(|mass_point| mass_point.mass).collect();
+        }
+        let mut row = vec![f32::NAN; self.masses.len() * 2];
+        for mass_point in mass_points {
+            if let Some(&column) = self.columns.get(&mass_point.id) {
+                row[column * 2] = mass_point.position.0;
+                row[column * 2 + 1] = mass_point.position.1;
+            }
+        }
+        let bytes: Vec<u8> = row.iter().flat_map(|value| value.to_le_bytes()).collect();
+        match self.positions.write_all(&bytes) {
+            Ok(()) => self.frames += 1,
+            Err(error) => self.error = Some(error)
+        }
+    }
+
+    /// writes the shape of the positions and the masses, returns the number of frames written, or the first error
+    /// writing them, the files are complete once this returns
+    pub fn finish(mut self) -> io::Result<usize> {
+        if let Some(error) = self.error {
+            return Err(error)
+        }
+        self.positions.seek(SeekFrom::Start(0))?;
+        self.positions.write_all(&npy_header(&[self.frames, self.masses.len(), 2]))?;
+        self.positions.flush()?;
+        let mut masses = npy_header(&[self.masses.len()]);
+        masses.extend(self.masses.iter().flat_map(|mass| mass.to_le_bytes()));
+        fs::write(&self.masses_path, masses)?;
+        Ok(self.frames)
+    }
+}
+
+/// header of a version 1.0 `.npy` file of little-endian `float32`s of `shape` in C order, padded with spaces to
+/// [`NPY_HEADER_LENGTH`]
+fn npy_header(shape: &[usize]) -> Vec<u8> {
+    let shape = match shape {
+        [length] => format!("({},)", length),
+        _ => format!("({})", shape.iter().map(usize::to_string).collect::<Vec<_>>().join(", "))
+    };
+    let dictionary = format!("{{'descr': '<f4', 'fortran_order': False, 'shape': {}, }}", shape);
+    // the magic string, the version and the length of the dictionary come before it, and a newline ends it
+    let padded_length = NPY_HEADER_LENGTH - 10;
+    let mut header = b"\x93NUMPY\x01\x00".to_vec();
+    header.extend((padded_length as u16).to_le_bytes());
+    header.extend(format!("{:<1$}\n", dictionary, padded_length - 1).bytes());
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::fs::File;
+    use std::io::Read;
+    use std::path::PathBuf;
+    use std::process;
+    use flate2::read::GzDecoder;
+    use crate::MassPoint;
+    use super::{NpyTrajectoryWriter, TrajectoryWriter, NPY_HEADER_LENGTH, TRAJECTORY_COLUMNS};
+
+    /// a path in the temporary directory no other test writes to
+    fn temporary_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("newtonian_gravity_{}_{}", process::id(), name))
+    }
+
+    /// 3 frames of 2 particles moving apart, with values that don't have a short decimal representation
+    fn frames() -> Vec<Vec<MassPoint>> {
+        (0..3).map(|frame| {
+            let t = frame as f32 / 3.0;
+            vec![
+                MassPoint { mass: 1.0 / 3.0, position: (t, -t / 7.0), velocity: (0.1, -1e-7), id: 4, group: 0 },
+                MassPoint { mass: 2.5e6, position: (-t * 1e5, 0.0), velocity: (-t, 12.5), id: 9, group: 1 }
+            ]
+        }).collect()
+    }
+
+    fn write(path: &PathBuf) {
+        let mut writer = TrajectoryWriter::create(path).unwrap();
+        for (frame, mass_points) in frames().iter().enumerate() {
+            writer.write_frame(frame, mass_points);
+        }
+        assert_eq!(writer.finish().unwrap(), 3);
+    }
+
+    /// the rows of `csv`, checked against [`frames`]
+    fn check_rows(csv: &str) {
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        assert_eq!(reader.headers().unwrap(), TRAJECTORY_COLUMNS.as_slice());
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        let expected: Vec<(usize, usize, MassPoint)> = frames().into_iter()
+            .enumerate()
+            .flat_map(|(frame, mass_points)| mass_points.into_iter().enumerate().map(move |(index, mass_point)| (frame, index, mass_point)))
+            .collect();
+        assert_eq!(rows.len(), expected.len());
+        for (row, (frame, index, mass_point)) in rows.iter().zip(expected) {
+            assert_eq!((row[0].parse::<usize>().unwrap(), row[1].parse::<usize>().unwrap(), row[2].parse::<u32>().unwrap()), (frame, index, mass_point.id));
+            let values: Vec<f32> = (3..8).map(|column| row[column].parse().unwrap()).collect();
+            // exactly, the values are written as the shortest decimals that read back the same
+            assert_eq!(values, [mass_point.mass, mass_point.position.0, mass_point.position.1, mass_point.velocity.0, mass_point.velocity.1]);
+        }
+    }
+
+    #[test]
+    fn csv_trajectories_read_back_exactly() {
+        let path = temporary_path("trajectory.csv");
+        write(&path);
+        let csv = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        check_rows(&csv);
+    }
+
+    #[test]
+    fn gzipped_trajectories_read_back_exactly() {
+        let path = temporary_path("trajectory.csv.gz");
+        write(&path);
+        let mut csv = String::new();
+        GzDecoder::new(File::open(&path).unwrap()).read_to_string(&mut csv).unwrap();
+        fs::remove_file(&path).unwrap();
+        check_rows(&csv);
+    }
+
+    /// the dictionary of the header of `npy` and the float32s after it, checking the rest of the header
+    fn read_npy(npy: &[u8]) -> (&str, Vec<f32>) {
+        assert_eq!(&npy[..8], b"\x93NUMPY\x01\x00");
+        let header_length = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        // numpy aligns the data to 64 bytes
+        assert_eq!(10 + header_length, NPY_HEADER_LENGTH);
+        assert_eq!(NPY_HEADER_LENGTH % 64, 0);
+        assert_eq!(npy[NPY_HEADER_LENGTH - 1], b'\n');
+        let dictionary = std::str::from_utf8(&npy[10..NPY_HEADER_LENGTH]).unwrap().trim_end();
+        let values = npy[NPY_HEADER_LENGTH..].chunks_exact(4).map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap())).collect();
+        (dictionary, values)
+    }
+
+    #[test]
+    fn npy_trajectories_keep_the_particles_of_the_first_frame_in_their_columns() {
+        let [first, second, third]: [Vec<MassPoint>; 3] = frames().try_into().unwrap();
+        // the second particle is gone in the second frame, and the third frame is reordered with a particle added
+        let second = vec![second[0]];
+        let added = MassPoint { id: 12, position: (5.0, 5.0), ..third[0] };
+        let third = vec![third[1], added, third[0]];
+        let (positions_path, masses_path) = (temporary_path("positions.npy"), temporary_path("masses.npy"));
+        let mut writer = NpyTrajectoryWriter::create(&positions_path, &masses_path).unwrap();
+        for mass_points in [&first, &second, &third] {
+            writer.write_frame(mass_points);
+        }
+        assert_eq!(writer.finish().unwrap(), 3);
+        let (positions, masses) = (fs::read(&positions_path).unwrap(), fs::read(&masses_path).unwrap());
+        fs::remove_file(&positions_path).unwrap();
+        fs::remove_file(&masses_path).unwrap();
+
+        let (dictionary, values) = read_npy(&positions);
+        assert_eq!(dictionary, "{'descr': '<f4', 'fortran_order': False, 'shape': (3, 2, 2), }");
+        let expected = [first[0], first[1], second[0], first[1], third[2], third[0]].map(|mass_point| mass_point.position);
+        for (i, (pair, (x, y))) in values.chunks_exact(2).zip(expected).enumerate() {
+            if i == 3 {
+                assert!(pair.iter().all(|value| value.is_nan()), "{:?} rather than NaN for the missing particle", pair);
+            } else {
+                assert_eq!(pair, [x, y]);
+            }
+        }
+        let (dictionary, values) = read_npy(&masses);
+        assert_eq!(dictionary, "{'descr': '<f4', 'fortran_order': False, 'shape': (2,), }");
+        assert_eq!(values, [first[0].mass, first[1].mass]);
+    }
+}
@@ -0,0 +1,129 @@
+use std::f32::consts::TAU;
+use crate::{Particle, Vector};
+
+/// two bodies on a closed Kepler orbit around their common center of mass, which sits still at the origin,
+/// along with the exact positions of both bodies at any time, as ground truth for the integrators
+///
+/// after one full [`period`](Self::period) in 1000 substeps, at an eccentricity of 0.5 and masses of
+/// 10000 and 100 and a semi-major axis of 0.5, the lighter body lands within these fractions of the semi-major axis
+/// of where it started:
+/// - [`Euler`](crate::world::Integrator::Euler): 0.01
+/// - [`Verlet`](crate::world::Integrator::Verlet): 0.005
+/// - [`RK4`](crate::world::Integrator::RK4): 0.001, mostly rounding error of `f32` positions
+/// - [`Yoshida`](crate::world::Integrator::Yoshida): 0.001, as with RK4
+///
+/// errors grow quickly with the eccentricity, as the closest approach needs much shorter substeps
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct KeplerOrbit {
+    pub g: f32,
+    pub masses: (f32, f32),
+    pub semi_major_axis: f32,
+    /// 0.0 is circular, must be less than 1.0
+    pub eccentricity: f32
+}
+
+impl KeplerOrbit {
+    /// the two bodies at periapsis, the lighter one on the positive x axis moving towards positive y,
+    /// their radii, ids and groups are left at 0
+    pub fn particles(&self) -> Vec<Particle> {
+        let (a, b) = self.positions(0.0);
+        let (m_a, m_b) = self.masses;
+        let total_mass = m_a + m_b;
+        // vis-viva at periapsis, split between the bodies so the total momentum is zero
+        let periapsis = self.semi_major_axis * (1.0 - self.eccentricity);
+        let speed = f32::sqrt(self.g * total_mass * (2.0 / periapsis - 1.0 / self.semi_major_axis));
+        let body = |mass: f32, (x, y): (f32, f32), speed: f32| Particle {
+            mass,
+            position: Vector::from_cartesian(x, y),
+            velocity: Vector::from_cartesian(0.0, speed),
+            radius: 0.0,
+            fixed: 0,
+            id: 0,
+            group: 0
+        };
+        vec![
+            body(m_a, a, -speed * m_b / total_mass),
+            body(m_b, b, speed * m_a / total_mass)
+        ]
+    }
+
+    /// time to complete one orbit
+    pub fn period(&self) -> f32 {
+        TAU * f32::sqrt(self.semi_major_axis.powi(3) / (self.g * (self.masses.0 + self.masses.1)))
+    }
+
+    /// exact positions of both bodies, in the order of [`particles`](Self::particles), `time` after periapsis
+    pub fn positions(&self, time: f32) -> ((f32, f32), (f32, f32)) {
+        let e = self.eccentricity;
+        let mean_anomaly = (TAU * time / self.period()).rem_euclid(TAU);
+        let eccentric_anomaly = solve_kepler(mean_anomaly, e);
+        // position of the second body relative to the first
+        let x = self.semi_major_axis * (f32::cos(eccentric_anomaly) - e);
+        let y = self.semi_major_axis * f32::sqrt(1.0 - e * e) * f32::sin(eccentric_anomaly);
+        let (m_a, m_b) = self.masses;
+        let total_mass = m_a + m_b;
+        (
+            (-x * m_b / total_mass, -y * m_b / total_mass),
+            (x * m_a / total_mass, y * m_a / total_mass)
+        )
+    }
+}
+
+/// solves Keplers equation `M = E - e * sin(E)` for the eccentric anomaly `E` with Newtons method
+fn solve_kepler(mean_anomaly: f32, eccentricity: f32) -> f32 {
+    // the mean anomaly is a poor first guess for very eccentric orbits
+    let mut eccentric_anomaly = if eccentricity > 0.8 { TAU / 2.0 } else { mean_anomaly };
+    for _ in 0..32 {
+        let error = eccentric_anomaly - eccentricity * f32::sin(eccentric_anomaly) - mean_anomaly;
+        if error.abs() < 1e-7 {
+            break
+        }
+        eccentric_anomaly -= error / (1.0 - eccentricity * f32::cos(eccentric_anomaly));
+    }
+    eccentric_anomaly
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU16;
+    use crate::world::{G, Integrator};
+    use crate::world::cpu::CPUWorld;
+    use super::KeplerOrbit;
+
+    const ORBIT: KeplerOrbit = KeplerOrbit {
+        g: G,
+        masses: (10000.0, 100.0),
+        semi_major_axis: 0.5,
+        eccentricity: 0.5
+    };
+
+    /// how far the lighter body is from where it started after a period in 1000 substeps, as a fraction of the
+    /// semi-major axis
+    fn error_after_orbit(integrator: Integrator) -> f32 {
+        let mut world = CPUWorld::new(ORBIT.particles()).with_integrator(integrator).with_g(ORBIT.g);
+        world.tick(ORBIT.period(), NonZeroU16::new(1000).unwrap());
+        let (x, y) = world.get_mass_points()[1].position;
+        let (_, (expected_x, expected_y)) = ORBIT.positions(ORBIT.period());
+        f32::hypot(x - expected_x, y - expected_y) / ORBIT.semi_major_axis
+    }
+
+    /// the tolerances documented on [`KeplerOrbit`]
+    #[test]
+    fn kepler_orbit_error_bounds() {
+        for (integrator, tolerance) in [(Integrator::Euler, 0.01), (Integrator::Verlet, 0.005), (Integrator::RK4, 0.001), (Integrator::Yoshida, 0.001)] {
+            let error = error_after_orbit(integrator);
+            assert!(error < tolerance, "{:?} ended {} of the semi-major axis away", integrator, error);
+        }
+    }
+
+    #[test]
+    fn exact_positions_return_after_a_period() {
+        let (a, b) = ORBIT.positions(0.0);
+        let (a_after, b_after) = ORBIT.positions(ORBIT.period());
+        assert!(f32::hypot(a.0 - a_after.0, a.1 - a_after.1) < 1e-6);
+        assert!(f32::hypot(b.0 - b_after.0, b.1 - b_after.1) < 1e-6);
+        // periapsis of the lighter body, the center of mass being at the origin
+        let periapsis = ORBIT.semi_major_axis * (1.0 - ORBIT.eccentricity) * 10000.0 / 10100.0;
+        assert!((b.0 - periapsis).abs() < 1e-6 && b.1 == 0.0);
+    }
+}
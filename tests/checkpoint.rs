@@ -0,0 +1,69 @@
+//! kills a run of the binary once it has written a checkpoint, resumes it, and compares the trajectory of the resumed
+//! run with that of a run that wasn't interrupted
+
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use newtonian_gravity::world::checkpoint::Checkpoint;
+
+/// `simulate` of 20 particles in 5 substeps a frame on the cpu into the 200 by 200 `<dir>/<name>.gif`, with the trajectory of every
+/// frame in `<dir>/<name>.csv`
+fn simulate(dir: &Path, name: &str, frames: usize, extra: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_newtonian_gravity"));
+    command.current_dir(dir)
+        .args(["simulate", "--backend", "cpu", "--particles", "20", "--steps", "5", "--size", "200x200", "--trajectory", "csv", "--out"])
+        .arg(dir.join(format!("{}.gif", name)))
+        .args(["--frames", &frames.to_string()])
+        .args(extra)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    command
+}
+
+/// the rows of a trajectory csv, without the header
+fn rows(path: PathBuf) -> Vec<String> {
+    fs::read_to_string(&path)
+        .unwrap_or_else(|error| panic!("unable to read {}: {}", path.display(), error))
+        .lines()
+        .skip(1)
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn killed_runs_resume_where_they_left_off() {
+    let dir = std::env::temp_dir().join(format!("newtonian_gravity_checkpoint_{}", std::process::id()));
+    // a failed run of a process with the same id leaves its checkpoint behind
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let checkpoint_path = dir.join("killed.ckpt");
+
+    // far more frames than it gets to before it is killed
+    let mut killed = simulate(&dir, "killed", 100_000, &["--checkpoint-interval", "2"]).spawn().unwrap();
+    let started = Instant::now();
+    while !checkpoint_path.exists() {
+        assert!(started.elapsed() < Duration::from_secs(120), "no checkpoint was written");
+        thread::sleep(Duration::from_millis(10));
+    }
+    killed.kill().unwrap();
+    killed.wait().unwrap();
+    let frame = Checkpoint::read(BufReader::new(File::open(&checkpoint_path).unwrap())).unwrap().frame;
+    assert!(frame < 12, "the run got to frame {} before it was killed", frame);
+
+    let resumed = simulate(&dir, "killed", 12, &["--resume"]).status().unwrap();
+    assert!(resumed.success());
+    let straight = simulate(&dir, "straight", 12, &[]).status().unwrap();
+    assert!(straight.success());
+
+    // the frames before the checkpoint are those of the uninterrupted run, as the killed run simulated the same ones
+    let straight = rows(dir.join("straight.csv"));
+    let resumed = rows(dir.join(format!("killed_from_{}.csv", frame)));
+    let particles = straight.len() / 12;
+    assert_eq!(resumed.len(), (12 - frame) * particles);
+    assert_eq!(resumed, straight[frame * particles..]);
+    assert!(dir.join(format!("killed_from_{}.gif", frame)).exists());
+    fs::remove_dir_all(dir).unwrap();
+}
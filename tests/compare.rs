@@ -0,0 +1,52 @@
+//! runs `compare` of the binary on a device that doesn't exist, from a directory of its own, so that the gpu gif is
+//! simulated with ParWorld instead whether or not the machine has a device vulkan can run on
+
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::process::Command;
+use image::{AnimationDecoder, RgbaImage};
+use image::codecs::gif::GifDecoder;
+
+fn frames(path: PathBuf) -> Vec<RgbaImage> {
+    let gif = File::open(&path).unwrap_or_else(|error| panic!("unable to open {}: {}", path.display(), error));
+    GifDecoder::new(gif).unwrap().into_frames().map(|frame| frame.unwrap().into_buffer()).collect()
+}
+
+#[test]
+fn compare_falls_back_to_par_without_the_device() {
+    let dir = std::env::temp_dir().join(format!("newtonian_gravity_compare_{}", std::process::id()));
+    // a failed run of a process with the same id leaves its output behind
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_newtonian_gravity"))
+        .current_dir(&dir)
+        .args(["compare", "--particles", "10", "--frames", "3", "--size", "fit", "--device", "no such device"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("simulating output/gpu.gif with ParWorld instead"));
+
+    let output_dir = dir.join("output");
+    let par = frames(output_dir.join("par.gif"));
+    assert_eq!(par.len(), 3);
+    assert_eq!(frames(output_dir.join("gpu.gif")), par);
+    // the blue channel of the gpu duplicates the green one of par
+    let merged = frames(output_dir.join("merged.gif"));
+    assert_eq!(merged.len(), 3);
+    for frame in &merged {
+        assert!(frame.pixels().all(|pixel| pixel[1] == pixel[2]));
+    }
+    assert!(merged[0].pixels().any(|pixel| pixel[1] > 0), "nothing was drawn");
+    // a row for every frame, those of par and the gpu, which is par again, are the same
+    let divergence = fs::read_to_string(output_dir.join("divergence.csv")).unwrap();
+    let mut lines = divergence.lines();
+    assert_eq!(lines.next(), Some("frame,cpu_vs_par_rms,cpu_vs_par_max,cpu_vs_gpu_rms,cpu_vs_gpu_max,par_vs_gpu_rms,par_vs_gpu_max"));
+    let rows: Vec<Vec<f32>> = lines.map(|line| line.split(',').map(|value| value.parse().unwrap()).collect()).collect();
+    assert_eq!(rows.iter().map(|row| row[0]).collect::<Vec<_>>(), [0.0, 1.0, 2.0]);
+    for row in &rows {
+        assert_eq!(row[1..], [row[1], row[2], row[1], row[2], 0.0, 0.0]);
+        assert!(row[1] <= row[2] && row[2] >= 0.0, "{:?}", row);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
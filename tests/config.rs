@@ -0,0 +1,56 @@
+//! runs the binary on the example scenario, scenarios/orbit.toml, and on a scenario with a misspelled key, from a
+//! directory of their own so that the gifs they write don't land in the repository
+
+#![cfg(feature = "serde")]
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use image::AnimationDecoder;
+use image::codecs::gif::GifDecoder;
+
+/// an empty directory in the temporary one for `name` alone
+fn dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("newtonian_gravity_config_{}_{}", name, std::process::id()));
+    // a failed run of a process with the same id leaves its gifs behind
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// `newtonian_gravity run <scenario>` in `dir`
+fn run(dir: &Path, scenario: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_newtonian_gravity"))
+        .current_dir(dir)
+        .arg("run")
+        .arg(scenario)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn the_example_scenario_produces_a_gif() {
+    let dir = dir("orbit");
+    let scenario = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios/orbit.toml");
+    let output = run(&dir, &scenario);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let gif = File::open(dir.join("output/orbit.gif")).unwrap();
+    let frames = GifDecoder::new(gif).unwrap().into_frames().collect_frames().unwrap();
+    assert_eq!(frames.len(), 120);
+    assert_eq!(frames[0].buffer().dimensions(), (500, 500));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn unknown_keys_are_errors_with_their_path() {
+    let dir = dir("unknown");
+    let scenario = dir.join("misspelled.toml");
+    fs::write(&scenario, "out = \"output/misspelled.gif\"\n\n[simulation]\nframes = 2\nsoftenning = 0.1\n\n[scenario]\ngenerator = \"kepler\"\n").unwrap();
+    let output = run(&dir, &scenario);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown field `softenning`") && stderr.contains("for key `simulation`"), "{}", stderr);
+    assert!(!dir.join("output/misspelled.gif").exists());
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,33 @@
+use std::num::NonZeroU16;
+use newtonian_gravity::MassPoint;
+use newtonian_gravity::world::World;
+use newtonian_gravity::world::cpu::CPUWorld;
+use newtonian_gravity::world::generate::random_particles;
+use newtonian_gravity::world::par::ParWorld;
+
+fn ticked<W: World>(mut world: W) -> Vec<MassPoint> {
+    for _ in 0..10 {
+        world.tick(20.0, NonZeroU16::new(20).unwrap());
+    }
+    world.get_mass_points()
+}
+
+fn distance((ax, ay): (f32, f32), (bx, by): (f32, f32)) -> f32 {
+    f32::sqrt((ax - bx).powi(2) + (ay - by).powi(2))
+}
+
+#[test]
+fn cpu_and_par_worlds_agree() {
+    let particles = random_particles(23, 100, 500.0);
+    let cpu = ticked(CPUWorld::new(particles.clone()));
+    let par = ticked(ParWorld::new(particles.clone()));
+    // the par world sums in a different order, but both keep the order and the ids of the particles
+    assert_eq!(cpu.len(), particles.len());
+    assert_eq!(par.len(), particles.len());
+    for ((a, b), particle) in cpu.iter().zip(&par).zip(&particles) {
+        assert_eq!((a.id, a.mass), (b.id, b.mass));
+        let moved = distance(particle.position.to_cartesian(), a.position);
+        let apart = distance(a.position, b.position);
+        assert!(apart <= 1e-3 * moved + 1e-6, "particle {} is {} apart after moving {}", a.id, apart, moved);
+    }
+}
@@ -0,0 +1,28 @@
+use std::num::NonZeroU16;
+use newtonian_gravity::world::Integrator;
+use newtonian_gravity::world::cpu::CPUWorld;
+use newtonian_gravity::world::particle_file::read_particle_file;
+
+/// the run of the comment at the top of scenarios/solar.csv, in which the outermost planet goes around once
+#[test]
+fn solar_fixture_keeps_its_orbits() {
+    let particles = read_particle_file(concat!(env!("CARGO_MANIFEST_DIR"), "/scenarios/solar.csv")).unwrap();
+    assert_eq!(particles.len(), 5);
+    let start: Vec<(f32, f32)> = particles.iter().map(|particle| particle.position.to_cartesian()).collect();
+    let mut world = CPUWorld::new(particles).with_g(0.001).with_integrator(Integrator::Verlet);
+    for _ in 0..225 {
+        world.tick(0.02, NonZeroU16::new(20).unwrap());
+    }
+    let end = world.get_mass_points();
+    assert_eq!(end.len(), 5);
+    let sun = end[0].position;
+    for (planet, &(x, y)) in end.iter().zip(&start).skip(1) {
+        let radius = f32::hypot(planet.position.0 - sun.0, planet.position.1 - sun.1);
+        let start_radius = f32::hypot(x, y);
+        assert!((radius - start_radius).abs() < 0.02 * start_radius, "planet {} moved from an orbit of {} to {}", planet.id, start_radius, radius);
+    }
+    // the outermost planet is back about where it started
+    let (x, y) = start[4];
+    let apart = f32::hypot(end[4].position.0 - x, end[4].position.1 - y);
+    assert!(apart < 0.05, "the outermost planet is {} from where it started", apart);
+}
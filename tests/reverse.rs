@@ -0,0 +1,67 @@
+//! gravity is time-reversible, so running a world backwards for as long as it ran forwards brings it back to where
+//! it started, save for the rounding of every substep, which a symplectic integrator keeps from piling up
+//!
+//! the figure eight of three bodies is run forwards and backwards for 100 frames, a period in all, and every body has
+//! to land within [`TOLERANCE`] of the size of the eight of where it started
+
+use std::num::NonZeroU16;
+use newtonian_gravity::{MassPoint, Particle};
+use newtonian_gravity::world::{Integrator, World};
+use newtonian_gravity::world::cpu::CPUWorld;
+use newtonian_gravity::world::gpu::GPUWorld;
+use newtonian_gravity::world::par::ParWorld;
+use newtonian_gravity::world::presets::{figure_eight, figure_eight_period};
+
+const FRAMES: usize = 100;
+// measured at about 1.1e-4 with Verlet and 6e-5 with Yoshida, in f32 the rounding of the positions alone is about 1e-7
+const TOLERANCE: f32 = 5e-4;
+
+fn particles() -> Vec<Particle> {
+    figure_eight(1.0, 1.0, 1.0)
+}
+
+/// the largest distance of a body from where it started after running forwards and backwards
+fn round_trip_error<W: World>(mut world: W) -> f32 {
+    let start = world.get_mass_points();
+    let time = figure_eight_period(1.0, 1.0, 1.0) / FRAMES as f32;
+    let steps = NonZeroU16::new(20).unwrap();
+    for _ in 0..FRAMES {
+        world.tick(time, steps);
+    }
+    for _ in 0..FRAMES {
+        world.tick_reverse(time, steps);
+    }
+    let end: Vec<MassPoint> = world.get_mass_points();
+    start.iter()
+        .zip(&end)
+        .map(|(a, b)| f32::sqrt((a.position.0 - b.position.0).powi(2) + (a.position.1 - b.position.1).powi(2)))
+        .fold(0.0, f32::max)
+}
+
+#[test]
+fn cpu_world_runs_back_to_the_start() {
+    for integrator in [Integrator::Verlet, Integrator::Yoshida] {
+        let error = round_trip_error(CPUWorld::new(particles()).with_g(1.0).with_integrator(integrator));
+        assert!(error < TOLERANCE, "{:?} ended {} from the start", integrator, error);
+    }
+}
+
+#[test]
+fn par_world_runs_back_to_the_start() {
+    for integrator in [Integrator::Verlet, Integrator::Yoshida] {
+        let error = round_trip_error(ParWorld::new(particles()).with_g(1.0).with_integrator(integrator));
+        assert!(error < TOLERANCE, "{:?} ended {} from the start", integrator, error);
+    }
+}
+
+#[test]
+fn gpu_world_runs_back_to_the_start() {
+    if GPUWorld::list_devices().is_empty() {
+        eprintln!("skipping the gpu, there is no device vulkan can run on");
+        return
+    }
+    for integrator in [Integrator::Verlet, Integrator::Yoshida] {
+        let error = round_trip_error(GPUWorld::new(particles()).unwrap().with_g(1.0).with_integrator(integrator));
+        assert!(error < TOLERANCE, "{:?} ended {} from the start", integrator, error);
+    }
+}